@@ -0,0 +1,76 @@
+/// 现金账户相关的 Tauri 命令
+/// 入金、出金、利息录入及余额查询
+
+use crate::{not_found, business_error, error::Result};
+use crate::db::{AuditGroup, CashRepository, PortfolioRepository};
+use crate::models::CashTransaction;
+use crate::models::cash::{CashTransactionType, CreateCashTransactionRequest};
+use crate::db::Database;
+use tauri::State;
+
+/// 录入现金流水（入金 / 出金 / 利息）
+#[tauri::command]
+pub async fn add_cash_transaction(db: State<'_, Database>, request: CreateCashTransactionRequest) -> Result<CashTransaction> {
+    let mut conn = db.acquire().await?;
+    let txn = request.into_transaction()?;
+
+    let tx = conn.transaction()?;
+    let portfolio = PortfolioRepository::ensure_exists(&tx, &txn.portfolio)?;
+    if portfolio.archived {
+        return Err(business_error!("投资组合 {} 已归档，不能录入现金流水", portfolio.name));
+    }
+
+    // 出金不能超过可用现金
+    if txn.txn_type == CashTransactionType::Withdraw {
+        let balance = CashRepository::balance(&tx, &txn.portfolio)?;
+        if balance + txn.amount < 0.0 {
+            return Err(business_error!(
+                "可用现金不足：当前余额 ¥{:.2}，出金 ¥{:.2}",
+                balance,
+                -txn.amount
+            ));
+        }
+    }
+
+    CashRepository::create(&tx, &txn)?;
+    AuditGroup::new("add_cash_transaction").record_cash(&tx, None, Some(&txn))?;
+    tx.commit()?;
+
+    println!("✅ 现金流水录入成功：{} {} ¥{:.2}", txn.portfolio, txn.txn_type.as_str(), txn.amount);
+
+    Ok(txn)
+}
+
+/// 获取指定组合的现金流水
+#[tauri::command]
+pub async fn get_cash_transactions(db: State<'_, Database>, portfolio: String) -> Result<Vec<CashTransaction>> {
+    let conn = db.acquire().await?;
+    CashRepository::find_by_portfolio(&conn, &portfolio)
+}
+
+/// 获取指定组合的可用现金余额
+#[tauri::command]
+pub async fn get_cash_balance(db: State<'_, Database>, portfolio: String) -> Result<f64> {
+    let conn = db.acquire().await?;
+    CashRepository::balance(&conn, &portfolio)
+}
+
+/// 删除手工录入的现金流水（买卖流水随持仓记录维护，不能单独删除）
+#[tauri::command]
+pub async fn delete_cash_transaction(db: State<'_, Database>, id: String) -> Result<()> {
+    let mut conn = db.acquire().await?;
+    let tx = conn.transaction()?;
+
+    let txn = CashRepository::find_by_id(&tx, &id)?
+        .ok_or_else(|| not_found!("找不到 ID 为 {} 的现金流水", id))?;
+
+    if !txn.txn_type.is_manual() {
+        return Err(business_error!("买卖流水随持仓记录维护，不能单独删除"));
+    }
+
+    AuditGroup::new("delete_cash_transaction").record_cash(&tx, Some(&txn), None)?;
+    CashRepository::delete(&tx, &id)?;
+    tx.commit()?;
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+/// 公司行为相关的 Tauri 命令
+/// 分红、送转、拆股、配股的录入与查询
+
+use crate::error::Result;
+use crate::db::{AuditGroup, CorporateActionService, FxService, PortfolioRepository};
+use crate::models::{CorporateAction, Symbol};
+use crate::models::corporate_action::{
+    CorporateActionEntry, CorporateActionResult, RecordCorporateActionRequest,
+};
+use crate::db::Database;
+use tauri::State;
+
+/// 录入公司行为，并自动调整受影响的持仓和现金
+#[tauri::command]
+pub async fn record_corporate_action(db: State<'_, Database>, request: RecordCorporateActionRequest) -> Result<CorporateActionResult> {
+    let action = CorporateAction::from(request);
+
+    // 外币持仓的现金需要折算为各组合的基础货币，获取汇率需要等待网络请求，在事务之前完成
+    let currency = Symbol::parse(&action.code)?.currency();
+    let mut pairs: Vec<(String, String)> = Vec::new();
+    for portfolio in PortfolioRepository::find_all(&*db.acquire().await?, true)? {
+        let pair = (currency.to_string(), portfolio.base_currency);
+        if pair.0 != pair.1 && !pairs.contains(&pair) {
+            pairs.push(pair);
+        }
+    }
+    let fx_rates = FxService::fetch_configured_rates(&db, &pairs).await?;
+
+    let mut conn = db.acquire().await?;
+
+    // 持仓调整、现金流水和明细在同一事务中写入，并记录审计以便撤销
+    let tx = conn.transaction()?;
+    let audit = AuditGroup::new("record_corporate_action");
+    let entries = CorporateActionService::apply(&tx, &audit, &action, &fx_rates)?;
+    tx.commit()?;
+
+    println!(
+        "✅ 公司行为录入成功：{} {} {}，调整 {} 笔持仓",
+        action.code,
+        action.action_type.as_str(),
+        action.ex_date,
+        entries.len()
+    );
+
+    Ok(CorporateActionResult { action, entries })
+}
+
+/// 获取公司行为列表（可按股票代码过滤）
+#[tauri::command]
+pub async fn get_corporate_actions(db: State<'_, Database>, code: Option<String>) -> Result<Vec<CorporateAction>> {
+    let conn = db.acquire().await?;
+    CorporateActionService::find_actions(&conn, code.as_deref())
+}
+
+/// 获取公司行为对各持仓的调整明细
+#[tauri::command]
+pub async fn get_corporate_action_entries(db: State<'_, Database>, action_id: String) -> Result<Vec<CorporateActionEntry>> {
+    let conn = db.acquire().await?;
+    CorporateActionService::find_entries(&conn, &action_id)
+}
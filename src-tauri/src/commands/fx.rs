@@ -0,0 +1,46 @@
+/// 汇率相关的 Tauri 命令
+/// 港股、美股等外币持仓按汇率换算为组合的基础货币
+
+use crate::{not_found, error::Result};
+use crate::db::{Database, FxRateRepository};
+use crate::models::FxRate;
+use crate::models::fx::{normalize_currency, FxRateSource};
+use tauri::State;
+
+/// 设置手动汇率（1 单位 from_currency 兑换 rate 单位 to_currency，优先于实时汇率）
+#[tauri::command]
+pub async fn set_fx_rate(
+    db: State<'_, Database>,
+    from_currency: String,
+    to_currency: String,
+    rate: f64,
+) -> Result<FxRate> {
+    let fx_rate = FxRate::new(&from_currency, &to_currency, rate, FxRateSource::Manual)?;
+
+    let conn = db.acquire().await?;
+    FxRateRepository::upsert(&conn, &fx_rate)?;
+
+    println!("✅ 手动汇率已保存：1 {} = {} {}", fx_rate.from_currency, fx_rate.rate, fx_rate.to_currency);
+
+    Ok(fx_rate)
+}
+
+/// 获取所有汇率（手动汇率及缓存的实时汇率）
+#[tauri::command]
+pub async fn get_fx_rates(db: State<'_, Database>) -> Result<Vec<FxRate>> {
+    let conn = db.acquire().await?;
+    FxRateRepository::find_all(&conn)
+}
+
+/// 删除手动汇率，之后该币种对恢复使用实时汇率
+#[tauri::command]
+pub async fn delete_fx_rate(db: State<'_, Database>, from_currency: String, to_currency: String) -> Result<()> {
+    let from_currency = normalize_currency(&from_currency)?;
+    let to_currency = normalize_currency(&to_currency)?;
+
+    let conn = db.acquire().await?;
+    if !FxRateRepository::delete_manual(&conn, &from_currency, &to_currency)? {
+        return Err(not_found!("找不到 {}/{} 的手动汇率", from_currency, to_currency));
+    }
+    Ok(())
+}
@@ -0,0 +1,27 @@
+/// 操作历史相关的 Tauri 命令
+/// 撤销最近一次操作、查看持仓记录的修改历史
+
+use crate::error::Result;
+use crate::db::AuditService;
+use crate::models::audit::{PositionEvent, UndoResult};
+use crate::db::Database;
+use tauri::State;
+
+/// 撤销最近一次操作（整组恢复，例如减仓会删除子记录并恢复原记录数量）
+#[tauri::command]
+pub async fn undo_last_action(db: State<'_, Database>) -> Result<UndoResult> {
+    let mut conn = db.acquire().await?;
+
+    let tx = conn.transaction()?;
+    let result = AuditService::undo_last(&tx)?;
+    tx.commit()?;
+
+    Ok(result)
+}
+
+/// 获取持仓记录的修改历史（含减仓子记录、关联现金流水和公司行为明细）
+#[tauri::command]
+pub async fn get_position_history(db: State<'_, Database>, id: String) -> Result<Vec<PositionEvent>> {
+    let conn = db.acquire().await?;
+    AuditService::find_history(&conn, &id)
+}
@@ -0,0 +1,54 @@
+/// 手动价格相关的 Tauri 命令
+/// 为停牌股票、私募基金、理财产品等获取不到行情的持仓录入价格
+
+use crate::{not_found, error::Result};
+use crate::db::{Database, ManualPriceRepository};
+use crate::models::quote::ManualPrice;
+use tauri::State;
+
+/// 设置手动价格（同一代码已有手动价格时覆盖）
+///
+/// 手动价格不记录操作审计，不能通过撤销恢复
+///
+/// 参数：
+/// - pinned: 是否固定使用手动价格（默认 false，只在没有实时行情时使用）
+#[tauri::command]
+pub async fn set_manual_price(
+    db: State<'_, Database>,
+    code: String,
+    price: f64,
+    date: String,
+    pinned: Option<bool>,
+) -> Result<ManualPrice> {
+    let manual_price = ManualPrice::new(code, price, date, pinned.unwrap_or(false))?;
+
+    let conn = db.acquire().await?;
+    ManualPriceRepository::upsert(&conn, &manual_price)?;
+
+    println!(
+        "✅ 手动价格已保存：{} ¥{} ({}){}",
+        manual_price.code,
+        manual_price.price,
+        manual_price.price_date,
+        if manual_price.pinned { " [固定]" } else { "" }
+    );
+
+    Ok(manual_price)
+}
+
+/// 获取所有手动价格
+#[tauri::command]
+pub async fn get_manual_prices(db: State<'_, Database>) -> Result<Vec<ManualPrice>> {
+    let conn = db.acquire().await?;
+    ManualPriceRepository::find_all(&conn)
+}
+
+/// 删除手动价格，之后该代码恢复使用实时行情
+#[tauri::command]
+pub async fn delete_manual_price(db: State<'_, Database>, code: String) -> Result<()> {
+    let conn = db.acquire().await?;
+    if !ManualPriceRepository::delete(&conn, code.trim())? {
+        return Err(not_found!("找不到代码为 {} 的手动价格", code));
+    }
+    Ok(())
+}
@@ -2,6 +2,14 @@
 /// 包含所有 Tauri 命令的实现
 
 pub mod position;
+pub mod settings;
+pub mod portfolio;
+pub mod cash;
+pub mod corporate_action;
+pub mod history;
+pub mod manual_price;
+pub mod fx;
+pub mod snapshot;
 
 // 重新导出所有命令
 pub use position::*;
\ No newline at end of file
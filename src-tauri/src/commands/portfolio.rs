@@ -0,0 +1,131 @@
+/// 投资组合设置相关的 Tauri 命令
+/// 创建、修改、重命名、归档投资组合
+
+use crate::{not_found, business_error, error::Result};
+use crate::db::{PortfolioRepository, PositionRepository};
+use crate::models::Portfolio;
+use crate::models::portfolio::{CreatePortfolioRequest, UpdatePortfolioRequest};
+use crate::db::Database;
+use tauri::State;
+
+/// 获取所有投资组合设置
+///
+/// 参数：
+/// - include_archived: 是否包含已归档组合（默认不包含）
+#[tauri::command]
+pub async fn get_portfolio_settings(db: State<'_, Database>, include_archived: Option<bool>) -> Result<Vec<Portfolio>> {
+    let conn = db.acquire().await?;
+    PortfolioRepository::find_all(&conn, include_archived.unwrap_or(false))
+}
+
+/// 创建投资组合
+#[tauri::command]
+pub async fn create_portfolio(db: State<'_, Database>, request: CreatePortfolioRequest) -> Result<Portfolio> {
+    let conn = db.acquire().await?;
+    let portfolio = Portfolio::from(request);
+
+    if PortfolioRepository::find_by_name(&conn, &portfolio.name)?.is_some() {
+        return Err(business_error!("投资组合 {} 已存在", portfolio.name));
+    }
+
+    PortfolioRepository::create(&conn, &portfolio)?;
+
+    println!("✅ 创建投资组合成功：{}，满仓金额 ¥{}", portfolio.name, portfolio.full_position);
+
+    Ok(portfolio)
+}
+
+/// 修改投资组合设置（满仓金额、基础货币、描述、业绩比较基准）
+///
+/// 已有持仓记录的组合不能修改基础货币：买入汇率是按原基础货币记录的，改为新的基础货币后成本和汇兑盈亏都会出错
+#[tauri::command]
+pub async fn update_portfolio(db: State<'_, Database>, request: UpdatePortfolioRequest) -> Result<Portfolio> {
+    let conn = db.acquire().await?;
+
+    let mut portfolio = PortfolioRepository::find_by_name(&conn, &request.name)?
+        .ok_or_else(|| not_found!("找不到投资组合 {}", request.name))?;
+
+    let changes_base_currency = request
+        .base_currency
+        .as_deref()
+        .is_some_and(|base_currency| !base_currency.eq_ignore_ascii_case(&portfolio.base_currency));
+    if changes_base_currency && !PositionRepository::find_by_portfolio(&conn, &portfolio.name)?.is_empty() {
+        return Err(business_error!(
+            "投资组合 {} 已有持仓记录，不能修改基础货币（买入汇率按 {} 记录）",
+            portfolio.name, portfolio.base_currency
+        ));
+    }
+
+    request.apply_to(&mut portfolio);
+    PortfolioRepository::update(&conn, &portfolio)?;
+
+    Ok(portfolio)
+}
+
+/// 重命名投资组合，并同步更新所有持仓记录的 portfolio 字段
+///
+/// 组合设置不记录操作审计，重命名不能通过撤销恢复（可再次重命名改回）
+#[tauri::command]
+pub async fn rename_portfolio(db: State<'_, Database>, old_name: String, new_name: String) -> Result<Portfolio> {
+    let mut conn = db.acquire().await?;
+    let new_name = new_name.trim().to_string();
+
+    if PortfolioRepository::find_by_name(&conn, &old_name)?.is_none() {
+        return Err(not_found!("找不到投资组合 {}", old_name));
+    }
+    if old_name == new_name {
+        return Err(business_error!("新名称与原名称相同"));
+    }
+    if PortfolioRepository::find_by_name(&conn, &new_name)?.is_some() {
+        return Err(business_error!("投资组合 {} 已存在", new_name));
+    }
+
+    let tx = conn.transaction()?;
+    let affected_positions = PortfolioRepository::rename(&tx, &old_name, &new_name)?;
+    tx.commit()?;
+
+    println!(
+        "✅ 投资组合重命名成功：{} -> {}，更新 {} 条持仓记录",
+        old_name, new_name, affected_positions
+    );
+
+    PortfolioRepository::find_by_name(&conn, &new_name)?
+        .ok_or_else(|| not_found!("找不到投资组合 {}", new_name))
+}
+
+/// 归档投资组合（组合中不能有未平仓的持仓）
+#[tauri::command]
+pub async fn archive_portfolio(db: State<'_, Database>, name: String) -> Result<()> {
+    let conn = db.acquire().await?;
+
+    if PortfolioRepository::find_by_name(&conn, &name)?.is_none() {
+        return Err(not_found!("找不到投资组合 {}", name));
+    }
+
+    let open_positions = PortfolioRepository::count_open_positions(&conn, &name)?;
+    if open_positions > 0 {
+        return Err(business_error!(
+            "投资组合 {} 仍有 {} 条持仓记录，请先平仓后再归档",
+            name,
+            open_positions
+        ));
+    }
+
+    PortfolioRepository::set_archived(&conn, &name, true)?;
+
+    println!("✅ 投资组合已归档：{}", name);
+
+    Ok(())
+}
+
+/// 取消归档投资组合
+#[tauri::command]
+pub async fn unarchive_portfolio(db: State<'_, Database>, name: String) -> Result<()> {
+    let conn = db.acquire().await?;
+
+    if !PortfolioRepository::set_archived(&conn, &name, false)? {
+        return Err(not_found!("找不到投资组合 {}", name));
+    }
+
+    Ok(())
+}
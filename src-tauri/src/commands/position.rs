@@ -1,204 +1,89 @@
 /// 持仓相关的 Tauri 命令
 /// 处理前端调用，调用数据访问层
 
-use crate::{not_found, invalid_input, error::{AppError, Result}};
+use crate::{not_found, invalid_input, business_error, error::Result};
 use crate::db::position_repo::PositionRepository;
-use crate::db::{QuoteService, PortfolioService, ClosedTradeService};
-use crate::models::position::{Position, CreatePositionRequest, PortfolioSummary, ClosedTradesSummary};
-use crate::models::{PortfolioProfitLoss};
-use rusqlite::{Connection, params};
-use std::path::PathBuf;
-
-/// 获取数据库路径
-/// 使用平台特定的应用数据目录，确保符合各平台的标准规范
-fn get_db_path() -> PathBuf {
-    let app_data_dir = if cfg!(windows) {
-        // Windows: 使用 %APPDATA%\InvestmentTracker
-        // 如果 APPDATA 不存在，回退到 USERPROFILE\AppData\Roaming\InvestmentTracker
-        let appdata = std::env::var("APPDATA")
-            .or_else(|_| {
-                std::env::var("USERPROFILE")
-                    .map(|home| format!("{}\\AppData\\Roaming", home))
-            })
-            .unwrap_or_else(|_| ".".to_string());
-        PathBuf::from(appdata).join("InvestmentTracker")
-    } else if cfg!(target_os = "macos") {
-        // macOS: 使用 ~/Library/Application Support/InvestmentTracker
-        // 优先使用 HOME，如果不存在则回退到 USERPROFILE（某些特殊环境可能只有 USERPROFILE）
-        let home = std::env::var("HOME")
-            .or_else(|_| std::env::var("USERPROFILE"))
-            .unwrap_or_else(|_| ".".to_string());
-        PathBuf::from(home)
-            .join("Library")
-            .join("Application Support")
-            .join("InvestmentTracker")
-    } else {
-        // Linux 和其他 Unix 系统: 使用 ~/.local/share/InvestmentTracker
-        let home = std::env::var("HOME")
-            .unwrap_or_else(|_| ".".to_string());
-        PathBuf::from(home)
-            .join(".local")
-            .join("share")
-            .join("InvestmentTracker")
-    };
-    
-    // 确保目录存在
-    if let Err(e) = std::fs::create_dir_all(&app_data_dir) {
-        eprintln!("无法创建应用数据目录: {:?}, 错误: {}", app_data_dir, e);
-    }
-    
-    app_data_dir.join("positions.db")
-}
+use crate::db::{Database, QuoteService, PortfolioService, ClosedTradeService, SettingsRepository, PortfolioRepository, CashRepository, CorporateActionService, TradeService, IdempotencyRepository, AuditGroup, FxService, RiskService};
+use crate::models::position::{Position, CreatePositionRequest, UpdatePositionRequest, PortfolioSummary, ClosedTradesSummary, ClosedTradeFilter};
+use crate::models::{ProfitLossView, CashTransaction, RiskMetrics, RiskQuery};
+use crate::models::trade::{SellSharesRequest, SellSharesResult};
+use crate::models::quote::QuoteOutcome;
+use std::collections::HashMap;
+use tauri::State;
 
-/// 获取数据库连接
-fn get_db_connection() -> Result<Connection> {
-    let db_path = get_db_path();
-    println!("[DB Connection] 数据库路径: {:?}", db_path);
-
-    // 确保目录存在
-    if let Some(parent) = db_path.parent() {
-        println!("[DB Connection] 检查并创建数据库目录: {:?}", parent);
-        match std::fs::create_dir_all(parent) {
-            Ok(_) => {
-                println!("[DB Connection] ✅ 数据库目录创建成功");
-            }
-            Err(e) => {
-                let err_msg = format!("创建数据库目录失败: {:?}, 错误: {}", parent, e);
-                eprintln!("[DB Connection] ❌ {}", err_msg);
-                return Err(AppError::Database(err_msg));
-            }
-        }
-    } else {
-        eprintln!("[DB Connection] ⚠️  无法获取数据库路径的父目录");
+/// 保存新的持仓记录
+///
+/// 参数：
+/// - request: 持仓信息
+/// - idempotency_key: 幂等键（可选，前端重试同一请求时传入相同的值，避免重复买入）
+#[tauri::command]
+pub async fn save_position(db: State<'_, Database>, request: CreatePositionRequest, idempotency_key: Option<String>) -> Result<Position> {
+    // 代码必须能确定所属市场（号段无法确定时需要加前缀）
+    let symbol = request.symbol()?;
+    println!("[Command] save_position: {} 识别为 {} 市场", request.code, symbol.market.as_str());
+
+    // 重试的请求直接返回首次执行的结果，不再获取汇率（事务中会再次检查幂等键）
+    if let Some(saved) = IdempotencyRepository::find_result(&*db.acquire().await?, "save_position", idempotency_key.as_deref())? {
+        return Ok(saved);
     }
 
+    // 外币持仓记录买入时的汇率，用于折算买入扣款以及区分价格盈亏和汇兑盈亏
+    let buy_fx_rate = fetch_trade_rate(&db, symbol.currency(), &request.portfolio).await?;
+
     // 连接到数据库
-    println!("[DB Connection] 正在连接数据库...");
-    let conn = match Connection::open(&db_path) {
-        Ok(conn) => {
-            println!("[DB Connection] ✅ 数据库连接成功");
-            conn
-        }
-        Err(e) => {
-            let err_msg = format!("连接数据库失败: {:?}, 错误: {}", db_path, e);
-            eprintln!("[DB Connection] ❌ {}", err_msg);
-            return Err(AppError::Database(err_msg));
+    let mut conn = db.acquire().await?;
+    let audit = AuditGroup::new("save_position");
+
+    IdempotencyRepository::run_in_transaction(&mut conn, &audit, idempotency_key.as_deref(), |tx| {
+        // 未填写实际费用时，按费用配置估算买入费用
+        let explicit_fee = request.buy_fee;
+
+        // 转换为 Position 模型
+        let mut position = Position::from(request);
+        position.buy_fx_rate = buy_fx_rate;
+        if explicit_fee.is_none() {
+            let fee_config = SettingsRepository::get_fee_config(tx)?;
+            position.buy_fee = fee_config.calculate_buy_fee(&position.code, position.buy_price, position.quantity);
         }
-    };
 
-    // 检查表是否存在
-    println!("[DB Connection] 检查表是否存在...");
-    let table_exists: bool = match conn.query_row(
-        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='positions'",
-        [],
-        |row| row.get::<_, i32>(0)
-    ) {
-        Ok(count) => count > 0,
-        Err(e) => {
-            eprintln!("[DB Connection] ⚠️  检查表是否存在时出错: {}", e);
-            false
-        }
-    };
+        // 验证数据
+        position.validate()?;
 
-    if !table_exists {
-        // 如果是全新数据库，先创建新表结构（包含所有最新字段）
-        println!("[DB Connection] 表不存在，创建新表结构...");
-        match conn.execute(
-            "CREATE TABLE positions (
-                id TEXT PRIMARY KEY,
-                code TEXT NOT NULL,
-                name TEXT NOT NULL,
-                buy_price REAL NOT NULL,
-                buy_date TEXT NOT NULL,
-                quantity INTEGER NOT NULL,
-                status TEXT NOT NULL DEFAULT 'POSITION',
-                portfolio TEXT,
-                sell_price REAL,
-                sell_date TEXT,
-                parent_id TEXT
-            )",
-            [],
-        ) {
-            Ok(_) => {
-                println!("[DB Connection] ✅ 表结构创建成功");
-            }
-            Err(e) => {
-                let err_msg = format!("创建表失败: {}", e);
-                eprintln!("[DB Connection] ❌ {}", err_msg);
-                return Err(AppError::Database(err_msg));
-            }
+        // 确保组合设置存在，已归档的组合不允许新增持仓
+        let portfolio = PortfolioRepository::ensure_exists(tx, &position.portfolio)?;
+        if portfolio.archived {
+            return Err(business_error!("投资组合 {} 已归档，不能新增持仓", portfolio.name));
         }
-    } else {
-        println!("[DB Connection] 表已存在，跳过表创建");
-    }
 
-    // 执行所有数据库迁移（自动处理版本升级）
-    // 注意：对于全新数据库，迁移会被跳过；对于已有数据库，迁移会添加缺失的字段
-    println!("[DB Connection] 开始执行数据库迁移...");
-    match crate::migration::run_migrations(&conn) {
-        Ok(_) => {
-            println!("[DB Connection] ✅ 数据库迁移完成");
-        }
-        Err(e) => {
-            let err_msg = format!("数据库迁移失败: {}", e);
-            eprintln!("[DB Connection] ❌ {}", err_msg);
-            return Err(AppError::Database(err_msg));
-        }
-    }
+        // 保存到数据库，并从组合现金中扣除买入金额
+        let id = PositionRepository::create(tx, &position)?;
+        audit.record_position(tx, None, Some(&position))?;
+        let txn = CashTransaction::for_buy(&position);
+        CashRepository::create(tx, &txn)?;
+        audit.record_cash(tx, None, Some(&txn))?;
 
-    // 创建索引
-    println!("[DB Connection] 创建索引...");
-    let indexes = vec![
-        ("idx_code", "CREATE INDEX IF NOT EXISTS idx_code ON positions(code)"),
-        ("idx_status", "CREATE INDEX IF NOT EXISTS idx_status ON positions(status)"),
-        ("idx_parent_id", "CREATE INDEX IF NOT EXISTS idx_parent_id ON positions(parent_id)"),
-    ];
-
-    for (name, sql) in indexes {
-        match conn.execute(sql, []) {
-            Ok(_) => {
-                println!("[DB Connection] ✅ 索引 {} 创建成功", name);
-            }
-            Err(e) => {
-                let err_msg = format!("创建索引 {} 失败: {}", name, e);
-                eprintln!("[DB Connection] ❌ {}", err_msg);
-                return Err(AppError::Database(err_msg));
-            }
-        }
-    }
+        // 设置生成的 ID
+        let mut saved_position = position;
+        saved_position.id = id;
 
-    println!("[DB Connection] ✅ 数据库初始化完成");
-    Ok(conn)
+        Ok(saved_position)
+    })
 }
 
-/// 保存新的持仓记录
-#[tauri::command]
-pub async fn save_position(request: CreatePositionRequest) -> Result<Position> {
-    // 连接到数据库
-    let conn = get_db_connection()?;
-
-    // 转换为 Position 模型
-    let position = Position::from(request);
-
-    // 验证数据
-    position.validate()?;
-
-    // 保存到数据库
-    let id = PositionRepository::create(&conn, &position)?;
-
-    // 设置生成的 ID
-    let mut saved_position = position;
-    saved_position.id = id;
-
-    Ok(saved_position)
+/// 获取交易币种到组合基础货币的成交汇率（币种相同时为 None）
+///
+/// 获取汇率需要等待网络请求，在事务之前完成
+async fn fetch_trade_rate(db: &Database, currency: &str, portfolio: &str) -> Result<Option<f64>> {
+    let base_currency = PortfolioRepository::base_currency(&*db.acquire().await?, portfolio)?;
+    FxService::fetch_trade_rate(db, currency, &base_currency).await
 }
 
 /// 获取所有持仓记录
 #[tauri::command]
-pub async fn get_positions() -> Result<Vec<Position>> {
+pub async fn get_positions(db: State<'_, Database>) -> Result<Vec<Position>> {
     println!("[Command] get_positions: 开始获取持仓记录...");
     
-    let conn = match get_db_connection() {
+    let conn = match db.acquire().await {
         Ok(conn) => {
             println!("[Command] get_positions: 数据库连接成功");
             conn
@@ -223,16 +108,16 @@ pub async fn get_positions() -> Result<Vec<Position>> {
 
 /// 获取指定代码的所有记录
 #[tauri::command]
-pub async fn get_position_records(code: String) -> Result<Vec<Position>> {
-    let conn = get_db_connection()?;
+pub async fn get_position_records(db: State<'_, Database>, code: String) -> Result<Vec<Position>> {
+    let conn = db.acquire().await?;
     let positions = PositionRepository::find_by_code(&conn, &code)?;
     Ok(positions)
 }
 
 /// 获取所有持仓中的代码列表
 #[tauri::command]
-pub async fn get_codes_in_position() -> Result<Vec<String>> {
-    let conn = get_db_connection()?;
+pub async fn get_codes_in_position(db: State<'_, Database>) -> Result<Vec<String>> {
+    let conn = db.acquire().await?;
     let codes = PositionRepository::get_distinct_codes_in_position(&conn)?;
     Ok(codes)
 }
@@ -243,368 +128,418 @@ pub async fn get_codes_in_position() -> Result<Vec<String>> {
 /// - id: 持仓记录ID
 /// - sell_price: 卖出价格
 /// - sell_date: 卖出日期 (YYYY-MM-DD)
+/// - sell_fee: 实际卖出费用（可选，不填时按费用配置计算）
+/// - idempotency_key: 幂等键（可选，前端重试时传入相同的值，避免重复卖出）
 #[tauri::command]
 pub async fn close_position(
+    db: State<'_, Database>,
     id: String,
     sell_price: f64,
     sell_date: String,
+    sell_fee: Option<f64>,
+    idempotency_key: Option<String>,
 ) -> Result<()> {
-    let conn = get_db_connection()?;
-
-    // 检查记录是否存在
-    if !PositionRepository::exists(&conn, &id)? {
-        return Err(not_found!("找不到 ID 为 {} 的持仓记录", id));
+    if let Some(done) = IdempotencyRepository::find_result(&*db.acquire().await?, "close_position", idempotency_key.as_deref())? {
+        return Ok(done);
     }
 
-    // 验证参数
-    if sell_price <= 0.0 {
-        return Err(invalid_input!("卖出价格必须大于0"));
-    }
+    // 在获取汇率和开启事务之前校验卖出参数
+    TradeService::validate_sell(sell_price, sell_fee)?;
+    let position = PositionRepository::find_by_id(&*db.acquire().await?, &id)?
+        .ok_or_else(|| not_found!("找不到 ID 为 {} 的持仓记录", id))?;
+    TradeService::validate_sell_date(&sell_date, &position.buy_date)?;
 
-    // 执行平仓并记录卖出信息
-    conn.execute(
-        "UPDATE positions SET status = 'CLOSE', sell_price = ?, sell_date = ? WHERE id = ?",
-        params![sell_price, sell_date, id],
-    )?;
+    // 外币持仓的卖出回款按卖出时汇率折算为组合基础货币
+    let fx_rate = fetch_trade_rate(&db, &position.currency, &position.portfolio).await?;
 
-    println!("✅ 平仓成功：ID={}, 卖出价=¥{}, 日期={}", id, sell_price, sell_date);
+    let mut conn = db.acquire().await?;
+    let audit = AuditGroup::new("close_position");
 
-    Ok(())
+    IdempotencyRepository::run_in_transaction(&mut conn, &audit, idempotency_key.as_deref(), |tx| {
+        // 检查记录是否存在
+        let position = PositionRepository::find_by_id(tx, &id)?
+            .ok_or_else(|| not_found!("找不到 ID 为 {} 的持仓记录", id))?;
+
+        // 已平仓的记录不能重复平仓
+        if position.status != "POSITION" {
+            return Err(business_error!("该记录已平仓，不能重复平仓"));
+        }
+
+        let sell_fee = TradeService::resolve_sell_fee(tx, &position.code, sell_price, position.quantity, sell_fee)?;
+
+        // 执行平仓并记录卖出信息，卖出回款计入组合现金
+        TradeService::close_lot(tx, &audit, &position, sell_price, &sell_date, sell_fee, fx_rate)?;
+
+        println!("✅ 平仓成功：ID={}, 卖出价=¥{}, 费用=¥{}, 日期={}", id, sell_price, sell_fee, sell_date);
+
+        Ok(())
+    })
 }
 
 /// 减仓（部分卖出）
 /// 
 /// 操作逻辑：
 /// 1. 验证减仓数量必须小于持有数量（否则应使用平仓）
-/// 2. 创建新的已卖出记录（status=CLOSE，parent_id=原ID）
-/// 3. 更新原持仓记录的数量（quantity 减少）
+/// 2. 创建新的已卖出记录（status=CLOSE，parent_id=原ID），按数量比例分摊买入费用
+/// 3. 更新原持仓记录的数量（quantity 减少）及剩余买入费用
+///
+/// 以上步骤在同一事务中执行；传入幂等键时，重试请求不会重复减仓
 #[tauri::command]
 pub async fn reduce_position(
+    db: State<'_, Database>,
     id: String,
     reduce_quantity: i32,
     sell_price: f64,
     sell_date: String,
+    sell_fee: Option<f64>,
+    idempotency_key: Option<String>,
 ) -> Result<()> {
-    let conn = get_db_connection()?;
+    if let Some(done) = IdempotencyRepository::find_result(&*db.acquire().await?, "reduce_position", idempotency_key.as_deref())? {
+        return Ok(done);
+    }
 
-    // 1. 获取原持仓记录
-    let position = PositionRepository::find_by_id(&conn, &id)?
+    // 在获取汇率和开启事务之前校验卖出参数
+    TradeService::validate_sell(sell_price, sell_fee)?;
+    let position = PositionRepository::find_by_id(&*db.acquire().await?, &id)?
         .ok_or_else(|| not_found!("找不到 ID 为 {} 的持仓记录", id))?;
+    TradeService::validate_sell_date(&sell_date, &position.buy_date)?;
 
-    // 2. 验证状态必须是 POSITION
-    if position.status != "POSITION" {
-        return Err(invalid_input!("只能对持仓中的记录进行减仓操作"));
-    }
+    // 外币持仓的卖出回款按卖出时汇率折算为组合基础货币
+    let fx_rate = fetch_trade_rate(&db, &position.currency, &position.portfolio).await?;
 
-    // 3. 验证减仓数量
-    if reduce_quantity <= 0 {
-        return Err(invalid_input!("减仓数量必须大于0"));
-    }
-    if reduce_quantity >= position.quantity {
-        return Err(invalid_input!(
-            "减仓数量({})必须小于持有数量({})，如需全部卖出请使用平仓功能",
-            reduce_quantity,
-            position.quantity
-        ));
-    }
+    let mut conn = db.acquire().await?;
+    let audit = AuditGroup::new("reduce_position");
+
+    IdempotencyRepository::run_in_transaction(&mut conn, &audit, idempotency_key.as_deref(), |tx| {
+        // 1. 获取原持仓记录
+        let position = PositionRepository::find_by_id(tx, &id)?
+            .ok_or_else(|| not_found!("找不到 ID 为 {} 的持仓记录", id))?;
+
+        // 2. 验证状态必须是 POSITION
+        if position.status != "POSITION" {
+            return Err(invalid_input!("只能对持仓中的记录进行减仓操作"));
+        }
+
+        // 3. 验证减仓数量
+        if reduce_quantity <= 0 {
+            return Err(invalid_input!("减仓数量必须大于0"));
+        }
+        if reduce_quantity >= position.quantity {
+            return Err(invalid_input!(
+                "减仓数量({})必须小于持有数量({})，如需全部卖出请使用平仓功能",
+                reduce_quantity,
+                position.quantity
+            ));
+        }
 
-    // 4. 验证卖出价格
-    if sell_price <= 0.0 {
-        return Err(invalid_input!("卖出价格必须大于0"));
+        // 4. 确定卖出费用
+        let sell_fee = TradeService::resolve_sell_fee(tx, &position.code, sell_price, reduce_quantity, sell_fee)?;
+
+        // 5. 创建已卖出记录（parent_id 指向原记录），更新原持仓数量，回款计入组合现金
+        TradeService::reduce_lot(tx, &audit, &position, reduce_quantity, sell_price, &sell_date, sell_fee, fx_rate)?;
+        let remaining_quantity = position.quantity - reduce_quantity;
+
+        println!(
+            "✅ 减仓成功：ID={}, 卖出{}股@¥{}, 剩余{}股",
+            id, reduce_quantity, sell_price, remaining_quantity
+        );
+
+        Ok(())
+    })
+}
+
+/// 按股票卖出（跨批次）
+///
+/// 按匹配策略（先进先出 / 后进先出 / 成本最高优先 / 平均成本）将卖出数量拆分到各持仓批次，
+/// 所有批次在同一事务中更新，任一步失败则全部回滚；传入幂等键时，重试请求不会重复卖出
+#[tauri::command]
+pub async fn sell_shares(db: State<'_, Database>, request: SellSharesRequest, idempotency_key: Option<String>) -> Result<SellSharesResult> {
+    if let Some(done) = IdempotencyRepository::find_result(&*db.acquire().await?, "sell_shares", idempotency_key.as_deref())? {
+        return Ok(done);
     }
 
-    // 5. 生成新记录ID（已卖出部分）
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let sold_id = format!("{}-sold-{}", id, timestamp);
-
-    // 6. 创建已卖出记录（新记录）
-    conn.execute(
-        "INSERT INTO positions (id, code, name, quantity, buy_price, buy_date, status, portfolio, sell_price, sell_date, parent_id)
-         VALUES (?, ?, ?, ?, ?, ?, 'CLOSE', ?, ?, ?, ?)",
-        params![
-            sold_id,
-            position.code,
-            position.name,
-            reduce_quantity,
-            position.buy_price,
-            position.buy_date,
-            position.portfolio,
-            sell_price,
-            sell_date,
-            id, // parent_id 指向原记录
-        ],
-    )?;
-
-    // 7. 更新原持仓数量
-    let remaining_quantity = position.quantity - reduce_quantity;
-    conn.execute(
-        "UPDATE positions SET quantity = ? WHERE id = ?",
-        params![remaining_quantity, id],
-    )?;
+    // 外币持仓的卖出回款按卖出时汇率折算为组合基础货币
+    let lots = TradeService::find_open_lots(&*db.acquire().await?, &request.portfolio, &request.code)?;
+    let fx_rate = match lots.first() {
+        Some(lot) => fetch_trade_rate(&db, &lot.currency, &request.portfolio).await?,
+        None => None,
+    };
+
+    let mut conn = db.acquire().await?;
+    let audit = AuditGroup::new("sell_shares");
+
+    let result = IdempotencyRepository::run_in_transaction(&mut conn, &audit, idempotency_key.as_deref(), |tx| {
+        TradeService::sell_shares(tx, &audit, &request, fx_rate)
+    })?;
 
     println!(
-        "✅ 减仓成功：ID={}, 卖出{}股@¥{}, 剩余{}股",
-        id, reduce_quantity, sell_price, remaining_quantity
+        "✅ 卖出成功：{} {} 共{}股@¥{}，涉及{}个批次（{:?}）",
+        result.portfolio,
+        result.code,
+        result.quantity,
+        result.sell_price,
+        result.lots.len(),
+        result.policy
     );
 
-    Ok(())
+    Ok(result)
+}
+
+/// 重新打开已平仓记录（撤销卖出）
+///
+/// 减仓产生的子记录会合并回原持仓记录；原记录已平仓时一并重新打开，已删除时按原 ID 重建
+#[tauri::command]
+pub async fn reopen_position(db: State<'_, Database>, id: String) -> Result<Position> {
+    let mut conn = db.acquire().await?;
+
+    let tx = conn.transaction()?;
+    let position = TradeService::reopen_position(&tx, &AuditGroup::new("reopen_position"), &id)?;
+    tx.commit()?;
+
+    println!("✅ 重新打开成功：ID={}, 当前持有{}股", position.id, position.quantity);
+
+    Ok(position)
+}
+
+/// 修改持仓记录（修正录入错误）
+///
+/// 保留记录 ID，修改同步到减仓产生的子记录，买入扣款等现金流水随之调整
+#[tauri::command]
+pub async fn update_position(db: State<'_, Database>, request: UpdatePositionRequest) -> Result<Position> {
+    // 交易币种或组合基础货币变化时按当前汇率重新记录买入汇率，获取汇率在事务之前完成
+    let changed = {
+        let conn = db.acquire().await?;
+        let before = PositionRepository::find_by_id(&conn, &request.id)?
+            .ok_or_else(|| not_found!("找不到 ID 为 {} 的持仓记录", request.id))?;
+        let mut updated = before.clone();
+        request.clone().apply_to(&mut updated)?;
+        TradeService::trade_currency_changed(&conn, &before, &updated)?.then_some(updated)
+    };
+    let buy_fx_rate = match changed {
+        Some(updated) => fetch_trade_rate(&db, &updated.currency, &updated.portfolio).await?,
+        None => None,
+    };
+
+    let mut conn = db.acquire().await?;
+
+    let tx = conn.transaction()?;
+    let position = TradeService::update_position(&tx, &AuditGroup::new("update_position"), request, buy_fx_rate)?;
+    tx.commit()?;
+
+    println!("✅ 修改持仓成功：ID={}, {} {}", position.id, position.code, position.name);
+
+    Ok(position)
 }
 
 /// 删除持仓记录
 #[tauri::command]
-pub async fn delete_position(id: String) -> Result<()> {
-    let conn = get_db_connection()?;
+pub async fn delete_position(db: State<'_, Database>, id: String) -> Result<()> {
+    let mut conn = db.acquire().await?;
+    let tx = conn.transaction()?;
 
     // 检查记录是否存在
-    if !PositionRepository::exists(&conn, &id)? {
-        return Err(not_found!("找不到 ID 为 {} 的持仓记录", id));
+    let position = PositionRepository::find_by_id(&tx, &id)?
+        .ok_or_else(|| not_found!("找不到 ID 为 {} 的持仓记录", id))?;
+
+    // 删除前记录快照，以便撤销
+    let audit = AuditGroup::new("delete_position");
+    audit.record_position(&tx, Some(&position), None)?;
+    for txn in CashRepository::find_by_position(&tx, &id)? {
+        audit.record_cash(&tx, Some(&txn), None)?;
+    }
+    for entry in CorporateActionService::find_entries_by_position(&tx, &id)? {
+        audit.record_corporate_action_entry(&tx, Some(&entry), None)?;
     }
 
-    // 删除记录
-    PositionRepository::delete(&conn, &id)?;
+    // 删除记录及其关联的现金流水和公司行为明细
+    PositionRepository::delete(&tx, &id)?;
+    CashRepository::delete_by_position(&tx, &id)?;
+    CorporateActionService::delete_entries_by_position(&tx, &id)?;
 
+    tx.commit()?;
     Ok(())
 }
 
 /// 获取指定代码的持仓统计信息
 #[tauri::command]
-pub async fn get_position_stats(code: String) -> Result<crate::db::position_repo::PositionStats> {
-    let conn = get_db_connection()?;
+pub async fn get_position_stats(db: State<'_, Database>, code: String) -> Result<crate::db::position_repo::PositionStats> {
+    let conn = db.acquire().await?;
     let stats = PositionRepository::get_position_stats_by_code(&conn, &code)?;
     Ok(stats)
 }
 
 /// 获取投资组合汇总
 #[tauri::command]
-pub async fn get_portfolio_summary(portfolio: String) -> Result<PortfolioSummary> {
-    let conn = get_db_connection()?;
+pub async fn get_portfolio_summary(db: State<'_, Database>, portfolio: String) -> Result<PortfolioSummary> {
+    let conn = db.acquire().await?;
     let summary = PositionRepository::get_portfolio_summary(&conn, &portfolio)?;
     Ok(summary)
 }
 
 /// 获取所有投资���合汇总
 #[tauri::command]
-pub async fn get_all_portfolio_summaries() -> Result<Vec<PortfolioSummary>> {
-    let conn = get_db_connection()?;
+pub async fn get_all_portfolio_summaries(db: State<'_, Database>) -> Result<Vec<PortfolioSummary>> {
+    let conn = db.acquire().await?;
     let summaries = PositionRepository::get_all_portfolio_summaries(&conn)?;
     Ok(summaries)
 }
 
-/// 获取所有投资组合列表
+/// 获取所有投资组合列表（不含已归档组合）
 #[tauri::command]
-pub async fn get_portfolios() -> Result<Vec<String>> {
-    let conn = get_db_connection()?;
-    let portfolios = PositionRepository::get_distinct_portfolios(&conn)?;
+pub async fn get_portfolios(db: State<'_, Database>) -> Result<Vec<String>> {
+    let conn = db.acquire().await?;
+    let portfolios = PortfolioRepository::find_all(&conn, false)?
+        .into_iter()
+        .map(|p| p.name)
+        .collect();
     Ok(portfolios)
 }
 
 /// 获取指定投资组合中的所有持仓
 #[tauri::command]
-pub async fn get_portfolio_positions(portfolio: String) -> Result<Vec<Position>> {
-    let conn = get_db_connection()?;
+pub async fn get_portfolio_positions(db: State<'_, Database>, portfolio: String) -> Result<Vec<Position>> {
+    let conn = db.acquire().await?;
     let positions = PositionRepository::get_portfolio_positions(&conn, &portfolio)?;
     Ok(positions)
 }
 
-/// 获取数据库连接
-
 /// 重置数据库（仅用于测试）
 #[tauri::command]
-pub async fn reset_database() -> Result<()> {
-    let conn = get_db_connection()?;
+pub async fn reset_database(db: State<'_, Database>) -> Result<()> {
+    let mut conn = db.acquire().await?;
+    let tx = conn.transaction()?;
+
+    // 删除前记录所有数据的快照，以便撤销
+    let audit = AuditGroup::new("reset_database");
+    for position in PositionRepository::find_all(&tx)? {
+        audit.record_position(&tx, Some(&position), None)?;
+    }
+    for txn in CashRepository::find_all(&tx)? {
+        audit.record_cash(&tx, Some(&txn), None)?;
+    }
+    for action in CorporateActionService::find_actions(&tx, None)? {
+        audit.record_corporate_action(&tx, Some(&action), None)?;
+    }
+    for entry in CorporateActionService::find_all_entries(&tx)? {
+        audit.record_corporate_action_entry(&tx, Some(&entry), None)?;
+    }
 
-    // 删除所有数据
-    conn.execute("DELETE FROM positions", [])?;
+    // 删除所有数据（审计记录保留）
+    tx.execute("DELETE FROM positions", [])?;
+    tx.execute("DELETE FROM cash_transactions", [])?;
+    tx.execute("DELETE FROM corporate_action_entries", [])?;
+    tx.execute("DELETE FROM corporate_actions", [])?;
+    tx.execute("DELETE FROM idempotency_keys", [])?;
 
     // 重置自增序列（如果有的话）
     // SQLite 使用 TEXT 主键，不需要重置序列
 
+    tx.commit()?;
     Ok(())
 }
 
 /// 获取单只股票的名称和价格（用于表单自动填充）
 #[tauri::command]
-pub async fn fetch_stock_name(code: String) -> Result<serde_json::Value> {
+pub async fn fetch_stock_name(db: State<'_, Database>, code: String) -> Result<serde_json::Value> {
+    let providers = QuoteService::configured_providers(&*db.acquire().await?)?;
+
     // 获取单只股票的实时数据
-    match QuoteService::fetch_real_quotes(vec![code.clone()]).await {
-        Ok(quotes) => {
-            // 查找匹配的价格数据
-            if let Some(quote) = quotes.values().next() {
-                Ok(serde_json::json!({
-                    "code": quote.code.clone(),
-                    "name": quote.name.clone(),
-                    "price": quote.real_price
-                }))
-            } else {
-                // 获取失败，返回原始代码
-                Ok(serde_json::json!({
-                    "code": code.clone(),
-                    "name": code,
-                    "price": null
-                }))
-            }
-        }
-        Err(_) => {
-            // 获取失败，返回原始代码
-            Ok(serde_json::json!({
-                "code": code.clone(),
-                "name": code,
-                "price": null
-            }))
-        }
+    let outcome = QuoteService::fetch_quotes(&providers, std::slice::from_ref(&code)).await.remove(&code);
+
+    match outcome {
+        Some(QuoteOutcome::Ok { quote }) => Ok(serde_json::json!({
+            "code": quote.code,
+            "name": quote.name,
+            "price": quote.real_price
+        })),
+        // 停牌时仍能取到名称
+        Some(QuoteOutcome::Suspended { name }) => Ok(serde_json::json!({
+            "code": code,
+            "name": name,
+            "price": null
+        })),
+        // 获取失败，返回原始代码
+        _ => Ok(serde_json::json!({
+            "code": code.clone(),
+            "name": code,
+            "price": null
+        })),
     }
 }
 
+/// 批量获取行情，逐只返回获取结果（成功、停牌、代码不存在或网络错误）
+///
+/// 优先使用有效期内的缓存，获取失败时返回最后已知价格（is_stale = true）
+#[tauri::command]
+pub async fn get_quotes(db: State<'_, Database>, codes: Vec<String>) -> Result<HashMap<String, QuoteOutcome>> {
+    QuoteService::fetch_configured_quotes(&db, &codes).await
+}
+
 /// 获取所有投资组合的完整盈亏视图（带实时价格）
 /// 对应 Java 版本的 PortfolioService.show()
+///
+/// 计算规则见 PortfolioService::build_profit_loss_view
 #[tauri::command]
-pub async fn get_portfolio_profit_loss_view(use_mock: Option<bool>) -> Result<Vec<PortfolioProfitLoss>> {
+pub async fn get_portfolio_profit_loss_view(db: State<'_, Database>, use_mock: Option<bool>) -> Result<ProfitLossView> {
     println!("[Command] get_portfolio_profit_loss_view: 开始获取投资组合盈亏视图...");
-    
-    let conn = match get_db_connection() {
-        Ok(conn) => {
-            println!("[Command] get_portfolio_profit_loss_view: 数据库连接成功");
-            conn
-        }
-        Err(e) => {
-            eprintln!("[Command] get_portfolio_profit_loss_view: 数据库连接失败: {}", e);
-            return Err(e);
-        }
-    };
-
-    // 获取所有未平仓的持仓
-    println!("[Command] get_portfolio_profit_loss_view: 开始查询持仓记录...");
-    let positions = match PositionRepository::find_positions(&conn) {
-        Ok(positions) => {
-            println!("[Command] get_portfolio_profit_loss_view: 查询到 {} 条持仓记录", positions.len());
-            positions
-        }
-        Err(e) => {
-            eprintln!("[Command] get_portfolio_profit_loss_view: 查询持仓记录失败: {}", e);
-            return Err(e);
-        }
-    };
-    
-    let positions: Vec<Position> = positions.into_iter()
-        .filter(|p| p.status == "POSITION")
-        .collect();
-    
-    println!("[Command] get_portfolio_profit_loss_view: 过滤后剩余 {} 条未平仓持仓", positions.len());
-
-    // 如果没有持仓，返回空列表
-    if positions.is_empty() {
-        return Ok(vec![]);
-    }
-
-    // 获取所有需要的股票代码
-    let codes: Vec<String> = positions.iter()
-        .map(|p| p.code.clone())
-        .collect::<std::collections::HashSet<_>>()
-        .into_iter()
-        .collect();
-
-    println!("📦 获取持仓的股票代码列表 (共{}只):", codes.len());
-    for code in &codes {
-        println!("   - {}", code);
-    }
-
-    // 获取价格 - 智能降级策略
-    let quotes = if use_mock.unwrap_or(false) {
-        // 强制使用模拟数据
-        println!("使用模拟数据（用户指定）");
-        QuoteService::mock_quotes(codes)
-    } else {
-        // 尝试实时价格，失败时自动降级
-        println!("尝试获取实时价格...");
-        match QuoteService::fetch_real_quotes(codes.clone()).await {
-            Ok(mut real_quotes) => {
-                println!("实时价格获取成功，共{}只股票", real_quotes.len());
-                println!("🔍 获取到的价格数据映射:");
-                for (code, quote) in &real_quotes {
-                    println!("   {} => {} (¥{})", code, quote.name, quote.real_price);
-                }
-
-                // 检查是否所有股票都有价格
-                if real_quotes.len() == codes.len() {
-                    println!("✅ 所有股票价格获取成功");
-                    real_quotes
-                } else {
-                    println!("⚠️  部分股票价格获取失败");
-                    println!("   预期: {}只，实际: {}只", codes.len(), real_quotes.len());
-
-                    // 只为失败的股票生成模拟数据
-                    let failed_codes: Vec<String> = codes.iter()
-                        .filter(|code| !real_quotes.contains_key(*code))
-                        .cloned()
-                        .collect();
-
-                    println!("   失败的股票代码:");
-                    for code in &failed_codes {
-                        println!("     - {}", code);
-                    }
-
-                    // 为失败的股票生成模拟数据
-                    let mock_quotes = QuoteService::mock_quotes(failed_codes);
-
-                    // 合并真实数据和模拟数据（保留真实数据优先）
-                    for (code, mock_quote) in mock_quotes {
-                        if !real_quotes.contains_key(&code) {
-                            println!("   🔧 为 {} 添加模拟数据", code);
-                            real_quotes.insert(code, mock_quote);
-                        }
-                    }
-
-                    real_quotes
-                }
-            }
-            Err(e) => {
-                println!("❌ 实时价格获取失败: {}，降级到模拟数据", e);
-                QuoteService::mock_quotes(codes)
-            }
-        }
-    };
+    PortfolioService::build_profit_loss_view(&db, use_mock.unwrap_or(false)).await
+}
 
-    // 聚合计算
-    println!("[Command] get_portfolio_profit_loss_view: 开始聚合计算...");
-    let result = match PortfolioService::aggregate_positions(positions, &quotes) {
-        Ok(data) => {
-            println!("[Command] get_portfolio_profit_loss_view: 聚合计算成功，共 {} 个投资组合", data.len());
-            println!("📊 聚合后的投资组合数据:");
-            for portfolio in &data {
-                println!("  投资组合: {}", portfolio.portfolio);
-                for target in &portfolio.target_profit_losses {
-                    println!("    股票: {} {} (当前价: ¥{})", target.code, target.name, target.real_price);
-                }
-            }
-            data
-        }
-        Err(e) => {
-            eprintln!("[Command] get_portfolio_profit_loss_view: 聚合计算失败: {}", e);
-            return Err(e);
-        }
-    };
+/// 获取投资组合的风险指标
+///
+/// 参数：
+/// - portfolio: 组合名称
+/// - query: 可选的日期范围、基准指数（默认沪深300）和无风险利率（默认 2%）
+///
+/// 根据已保存的组合快照计算年化波动率、最大回撤、夏普/索提诺比率和相对基准的 Beta / Alpha
+#[tauri::command]
+pub async fn get_portfolio_risk(
+    db: State<'_, Database>,
+    portfolio: String,
+    query: Option<RiskQuery>,
+) -> Result<RiskMetrics> {
+    println!("[Command] get_portfolio_risk: 组合 {}", portfolio);
+    let conn = db.acquire().await?;
+    RiskService::portfolio_risk(&conn, &portfolio, &query.unwrap_or_default())
+}
 
-    println!("[Command] get_portfolio_profit_loss_view: 成功完成");
-    Ok(result)
+/// 获取个股的风险指标
+///
+/// 参数：
+/// - code: 股票代码
+/// - query: 可选的日期范围、基准指数（默认沪深300）和无风险利率（默认 2%）
+///
+/// 根据已保存的每日收盘价计算，指标含义同 get_portfolio_risk
+#[tauri::command]
+pub async fn get_stock_risk(
+    db: State<'_, Database>,
+    code: String,
+    query: Option<RiskQuery>,
+) -> Result<RiskMetrics> {
+    println!("[Command] get_stock_risk: {}", code);
+    let conn = db.acquire().await?;
+    RiskService::stock_risk(&conn, &code, &query.unwrap_or_default())
 }
 
 /// 获取已平仓交易统计
 ///
+/// 参数：
+/// - filter: 可选的筛选条件（组合、股票代码、买入/卖出日期范围），不传时统计全部已平仓交易
+///
 /// 返回：
-/// - ClosedTradesSummary: 包含所有已平仓交易列表和总统计
+/// - ClosedTradesSummary: 包含符合条件的已平仓交易列表、总统计，以及按组合/股票/月份/年份的分组统计
 #[tauri::command]
-pub async fn get_closed_trades_summary() -> Result<ClosedTradesSummary> {
+pub async fn get_closed_trades_summary(
+    db: State<'_, Database>,
+    filter: Option<ClosedTradeFilter>,
+) -> Result<ClosedTradesSummary> {
     println!("⏳ [Command] 开始获取已平仓交易统计...");
 
     // 连接到数据库
-    let conn = get_db_connection()?;
+    let conn = db.acquire().await?;
 
     // 获取已平仓交易统计
-    let summary = ClosedTradeService::get_closed_trades_summary(&conn)?;
+    let summary = ClosedTradeService::get_closed_trades_summary(&conn, &filter.unwrap_or_default())?;
 
     println!("✅ [Command] 已平仓交易统计获取成功");
     println!("   - 总交易笔数: {}", summary.statistics.total_trades);
@@ -0,0 +1,54 @@
+/// 应用设置相关的 Tauri 命令
+/// 包含交易费用配置、行情配置的读取与保存，以及数据库迁移报告
+
+use crate::error::Result;
+use crate::db::SettingsRepository;
+use crate::models::FeeConfig;
+use crate::models::quote::QuoteConfig;
+use crate::db::Database;
+use crate::migration::MigrationReport;
+use tauri::State;
+
+/// 获取交易费用配置（未配置时返回默认值）
+#[tauri::command]
+pub async fn get_fee_config(db: State<'_, Database>) -> Result<FeeConfig> {
+    let conn = db.acquire().await?;
+    SettingsRepository::get_fee_config(&conn)
+}
+
+/// 更新交易费用配置
+///
+/// 仅影响之后新增的买入/卖出记录，已保存的费用不会被重新计算
+#[tauri::command]
+pub async fn update_fee_config(db: State<'_, Database>, config: FeeConfig) -> Result<FeeConfig> {
+    let conn = db.acquire().await?;
+    SettingsRepository::save_fee_config(&conn, &config)?;
+
+    println!("✅ 交易费用配置已更新: {:?}", config);
+
+    Ok(config)
+}
+
+/// 获取行情配置（数据源优先级，未配置时返回默认值）
+#[tauri::command]
+pub async fn get_quote_config(db: State<'_, Database>) -> Result<QuoteConfig> {
+    let conn = db.acquire().await?;
+    SettingsRepository::get_quote_config(&conn)
+}
+
+/// 更新行情配置
+#[tauri::command]
+pub async fn update_quote_config(db: State<'_, Database>, config: QuoteConfig) -> Result<QuoteConfig> {
+    let conn = db.acquire().await?;
+    SettingsRepository::save_quote_config(&conn, &config)?;
+
+    println!("✅ 行情配置已更新: {:?}", config);
+
+    Ok(config)
+}
+
+/// 获取应用启动时的数据库迁移报告
+#[tauri::command]
+pub async fn get_migration_report(db: State<'_, Database>) -> Result<MigrationReport> {
+    Ok(db.migration_report().clone())
+}
@@ -0,0 +1,75 @@
+/// 组合快照相关的 Tauri 命令
+/// 记录每日快照并查询组合的权益曲线
+
+use crate::{invalid_input, error::Result};
+use crate::db::{BenchmarkService, Database, DailyPriceRepository, QuoteService, SnapshotRepository, SnapshotService};
+use crate::models::{BenchmarkComparison, DailyPrice, PortfolioSnapshot, RiskQuery};
+use tauri::State;
+
+/// 按当前行情记录今天的组合快照和持仓收盘价（今天已有快照时覆盖，周末和节假日不记录）
+#[tauri::command]
+pub async fn record_portfolio_snapshots(db: State<'_, Database>) -> Result<Vec<PortfolioSnapshot>> {
+    SnapshotService::record_today(&db).await
+}
+
+/// 获取组合在日期范围内（含首尾）的每日快照，按日期升序（权益曲线）
+///
+/// 参数：
+/// - from / to: 开始、结束日期 (YYYY-MM-DD)
+#[tauri::command]
+pub async fn get_portfolio_history(
+    db: State<'_, Database>,
+    portfolio: String,
+    from: String,
+    to: String,
+) -> Result<Vec<PortfolioSnapshot>> {
+    validate_date_range(&from, &to)?;
+
+    let conn = db.acquire().await?;
+    SnapshotRepository::find_range(&conn, &portfolio, &from, &to)
+}
+
+/// 获取股票在日期范围内（含首尾）的每日收盘价，按日期升序
+#[tauri::command]
+pub async fn get_daily_prices(
+    db: State<'_, Database>,
+    code: String,
+    from: String,
+    to: String,
+) -> Result<Vec<DailyPrice>> {
+    validate_date_range(&from, &to)?;
+
+    let conn = db.acquire().await?;
+    DailyPriceRepository::find_range(&conn, &QuoteService::format_stock_code(&code), &from, &to)
+}
+
+/// 对比组合与业绩比较基准的累计收益率
+///
+/// 参数：
+/// - portfolio: 组合名称
+/// - query: 可选的日期范围和基准代码（不填时使用组合设置的基准，默认沪深300），无风险利率不使用
+///
+/// 返回组合和基准在同一日期序列上的累计收益率、超额收益和跟踪误差，
+/// 基准的历史收盘价在本地有缺口时从行情数据源获取并保存
+#[tauri::command]
+pub async fn get_benchmark_comparison(
+    db: State<'_, Database>,
+    portfolio: String,
+    query: Option<RiskQuery>,
+) -> Result<BenchmarkComparison> {
+    println!("[Command] get_benchmark_comparison: 组合 {}", portfolio);
+    BenchmarkService::compare(&db, &portfolio, &query.unwrap_or_default()).await
+}
+
+/// 验证日期范围：格式为 YYYY-MM-DD，且开始日期不晚于结束日期
+fn validate_date_range(from: &str, to: &str) -> Result<()> {
+    for date in [from, to] {
+        if chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+            return Err(invalid_input!("日期格式错误，应为 YYYY-MM-DD: {}", date));
+        }
+    }
+    if from > to {
+        return Err(invalid_input!("开始日期不能晚于结束日期"));
+    }
+    Ok(())
+}
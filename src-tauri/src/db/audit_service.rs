@@ -0,0 +1,359 @@
+/// 操作审计服务
+/// 负责写入 position_events 审计记录、查询修改历史以及撤销最近一次操作
+
+use rusqlite::{Connection, params, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+use crate::{not_found, business_error, error::Result};
+use crate::db::{CashRepository, CorporateActionService, IdempotencyRepository};
+use crate::db::idempotency_repo::IdempotencyRecord;
+use crate::db::position_repo::PositionRepository;
+use crate::models::{CashTransaction, CorporateAction, Position};
+use crate::models::audit::{AuditEntityType, PositionEvent, UndoResult};
+use crate::models::corporate_action::CorporateActionEntry;
+
+/// 一次命令对应的审计分组
+///
+/// 同一分组内的所有记录在撤销时一起恢复
+pub struct AuditGroup {
+    group_id: String,
+    action: String,
+    undo_of: Option<String>,
+}
+
+impl AuditGroup {
+    /// 创建新的审计分组（action 使用命令名）
+    pub fn new(action: &str) -> Self {
+        Self {
+            group_id: uuid::Uuid::new_v4().to_string(),
+            action: action.to_string(),
+            undo_of: None,
+        }
+    }
+
+    /// 操作名称（与命令名一致）
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    /// 记录持仓记录的变更（before 为空表示新建，after 为空表示删除）
+    pub fn record_position(&self, conn: &Connection, before: Option<&Position>, after: Option<&Position>) -> Result<()> {
+        let id = before.or(after).map(|p| p.id.clone()).unwrap_or_default();
+        self.record(conn, AuditEntityType::Position, &id, before, after)
+    }
+
+    /// 记录现金流水的变更
+    pub fn record_cash(&self, conn: &Connection, before: Option<&CashTransaction>, after: Option<&CashTransaction>) -> Result<()> {
+        let id = before.or(after).map(|t| t.id.clone()).unwrap_or_default();
+        self.record(conn, AuditEntityType::CashTransaction, &id, before, after)
+    }
+
+    /// 记录公司行为的变更
+    pub fn record_corporate_action(&self, conn: &Connection, before: Option<&CorporateAction>, after: Option<&CorporateAction>) -> Result<()> {
+        let id = before.or(after).map(|a| a.id.clone()).unwrap_or_default();
+        self.record(conn, AuditEntityType::CorporateAction, &id, before, after)
+    }
+
+    /// 记录公司行为明细的变更
+    pub fn record_corporate_action_entry(
+        &self,
+        conn: &Connection,
+        before: Option<&CorporateActionEntry>,
+        after: Option<&CorporateActionEntry>,
+    ) -> Result<()> {
+        let id = before.or(after).map(|e| e.id.clone()).unwrap_or_default();
+        self.record(conn, AuditEntityType::CorporateActionEntry, &id, before, after)
+    }
+
+    /// 记录幂等键的变更
+    pub fn record_idempotency_key(
+        &self,
+        conn: &Connection,
+        before: Option<&IdempotencyRecord>,
+        after: Option<&IdempotencyRecord>,
+    ) -> Result<()> {
+        let id = before.or(after).map(|r| r.key.clone()).unwrap_or_default();
+        self.record(conn, AuditEntityType::IdempotencyKey, &id, before, after)
+    }
+
+    fn record<T: Serialize>(
+        &self,
+        conn: &Connection,
+        entity_type: AuditEntityType,
+        entity_id: &str,
+        before: Option<&T>,
+        after: Option<&T>,
+    ) -> Result<()> {
+        let before = before.map(serde_json::to_value).transpose()?;
+        let after = after.map(serde_json::to_value).transpose()?;
+        self.record_value(conn, entity_type, entity_id, before.as_ref(), after.as_ref())
+    }
+
+    fn record_value(
+        &self,
+        conn: &Connection,
+        entity_type: AuditEntityType,
+        entity_id: &str,
+        before: Option<&Value>,
+        after: Option<&Value>,
+    ) -> Result<()> {
+        conn.execute(
+            "INSERT INTO position_events (group_id, action, entity_type, entity_id, before_json, after_json, undo_of, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, datetime('now', 'localtime'))",
+            params![
+                &self.group_id,
+                &self.action,
+                entity_type.as_str(),
+                entity_id,
+                before.map(|v| v.to_string()),
+                after.map(|v| v.to_string()),
+                &self.undo_of,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+/// 操作审计服务
+pub struct AuditService;
+
+impl AuditService {
+    /// 辅助方法：从数据库行映射到 PositionEvent 对象
+    fn map_row_to_event(row: &rusqlite::Row) -> rusqlite::Result<PositionEvent> {
+        let entity_type: String = row.get(3)?;
+        let parse_json = |index: usize| -> rusqlite::Result<Option<Value>> {
+            let raw: Option<String> = row.get(index)?;
+            raw.map(|text| serde_json::from_str(&text)).transpose().map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e))
+            })
+        };
+
+        Ok(PositionEvent {
+            id: row.get(0)?,
+            group_id: row.get(1)?,
+            action: row.get(2)?,
+            entity_type: AuditEntityType::parse(&entity_type).ok_or_else(|| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    3,
+                    rusqlite::types::Type::Text,
+                    format!("未知的审计数据类型: {}", entity_type).into(),
+                )
+            })?,
+            entity_id: row.get(4)?,
+            before: parse_json(5)?,
+            after: parse_json(6)?,
+            undo_of: row.get(7)?,
+            created_at: row.get(8)?,
+        })
+    }
+
+    /// 查询持仓记录的修改历史（按时间顺序）
+    ///
+    /// 包括该记录本身、减仓产生的子记录，以及关联的现金流水和公司行为明细
+    pub fn find_history(conn: &Connection, position_id: &str) -> Result<Vec<PositionEvent>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, group_id, action, entity_type, entity_id, before_json, after_json, undo_of, created_at
+             FROM position_events
+             WHERE entity_id = ?1
+                OR (entity_type = 'POSITION'
+                    AND json_extract(COALESCE(after_json, before_json), '$.parent_id') = ?1)
+                OR (entity_type IN ('CASH_TRANSACTION', 'CORPORATE_ACTION_ENTRY')
+                    AND json_extract(COALESCE(after_json, before_json), '$.position_id') = ?1)
+             ORDER BY id"
+        )?;
+
+        let events = stmt.query_map([position_id], Self::map_row_to_event)?
+            .collect::<rusqlite::Result<Vec<PositionEvent>>>()?;
+
+        Ok(events)
+    }
+
+    /// 撤销最近一次尚未撤销的操作
+    ///
+    /// 按记录顺序倒序恢复该分组内所有数据的操作前快照，
+    /// 恢复过程本身也会作为一次新的审计分组追加记录（undo_of 指向被撤销的分组）
+    ///
+    /// 如果数据在之后被其他未记录的操作修改过（当前状态与快照不一致），拒绝撤销
+    ///
+    /// 审计范围为持仓记录、现金流水（含手工录入的入金、出金、利息）、公司行为及其明细和幂等键。
+    /// 组合设置（含重命名组合）和手动价格属于设置类数据，不记录审计，不能撤销；
+    /// 重命名组合后，改名前记录的操作因快照中的组合名称不一致而拒绝撤销
+    ///
+    /// 注意：调用方应在事务中执行
+    pub fn undo_last(conn: &Connection) -> Result<UndoResult> {
+        let last_group: Option<(String, String)> = conn.query_row(
+            "SELECT e.group_id, e.action
+             FROM position_events e
+             WHERE e.undo_of IS NULL
+               AND NOT EXISTS (SELECT 1 FROM position_events u WHERE u.undo_of = e.group_id)
+             ORDER BY e.id DESC
+             LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional()?;
+
+        let (group_id, action) = last_group.ok_or_else(|| not_found!("没有可以撤销的操作"))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, group_id, action, entity_type, entity_id, before_json, after_json, undo_of, created_at
+             FROM position_events
+             WHERE group_id = ?
+             ORDER BY id DESC"
+        )?;
+        let events = stmt.query_map([&group_id], Self::map_row_to_event)?
+            .collect::<rusqlite::Result<Vec<PositionEvent>>>()?;
+
+        let undo_group = AuditGroup {
+            group_id: uuid::Uuid::new_v4().to_string(),
+            action: "undo_last_action".to_string(),
+            undo_of: Some(group_id.clone()),
+        };
+
+        for event in &events {
+            let current = Self::load_current(conn, event.entity_type, &event.entity_id)?;
+            if current != event.after {
+                return Err(business_error!(
+                    "{} {} 已被后续操作修改，无法撤销 {}",
+                    event.entity_type.as_str(),
+                    event.entity_id,
+                    action
+                ));
+            }
+
+            Self::restore(conn, event.entity_type, &event.entity_id, event.before.as_ref())?;
+            undo_group.record_value(
+                conn,
+                event.entity_type,
+                &event.entity_id,
+                event.after.as_ref(),
+                event.before.as_ref(),
+            )?;
+        }
+
+        println!("↩️  已撤销操作 {}（{}），恢复 {} 条数据", action, group_id, events.len());
+
+        Ok(UndoResult {
+            group_id,
+            action,
+            restored: events.len(),
+        })
+    }
+
+    /// 读取数据当前状态的快照
+    fn load_current(conn: &Connection, entity_type: AuditEntityType, id: &str) -> Result<Option<Value>> {
+        let value = match entity_type {
+            AuditEntityType::Position => PositionRepository::find_by_id(conn, id)?
+                .map(serde_json::to_value).transpose()?,
+            AuditEntityType::CashTransaction => CashRepository::find_by_id(conn, id)?
+                .map(serde_json::to_value).transpose()?,
+            AuditEntityType::CorporateAction => CorporateActionService::find_action_by_id(conn, id)?
+                .map(serde_json::to_value).transpose()?,
+            AuditEntityType::CorporateActionEntry => CorporateActionService::find_entry_by_id(conn, id)?
+                .map(serde_json::to_value).transpose()?,
+            AuditEntityType::IdempotencyKey => IdempotencyRepository::find(conn, id)?
+                .map(serde_json::to_value).transpose()?,
+        };
+        Ok(value)
+    }
+
+    /// 将数据恢复为指定快照（快照为空表示删除）
+    fn restore(conn: &Connection, entity_type: AuditEntityType, id: &str, state: Option<&Value>) -> Result<()> {
+        let Some(state) = state else {
+            let (table, key_column) = match entity_type {
+                AuditEntityType::Position => ("positions", "id"),
+                AuditEntityType::CashTransaction => ("cash_transactions", "id"),
+                AuditEntityType::CorporateAction => ("corporate_actions", "id"),
+                AuditEntityType::CorporateActionEntry => ("corporate_action_entries", "id"),
+                AuditEntityType::IdempotencyKey => ("idempotency_keys", "key"),
+            };
+            conn.execute(&format!("DELETE FROM {} WHERE {} = ?", table, key_column), [id])?;
+            return Ok(());
+        };
+
+        match entity_type {
+            AuditEntityType::Position => {
+                PositionRepository::upsert(conn, &serde_json::from_value(state.clone())?)
+            }
+            AuditEntityType::CashTransaction => {
+                CashRepository::upsert(conn, &serde_json::from_value(state.clone())?)
+            }
+            AuditEntityType::CorporateAction => {
+                CorporateActionService::upsert_action(conn, &serde_json::from_value(state.clone())?)
+            }
+            AuditEntityType::CorporateActionEntry => {
+                CorporateActionService::upsert_entry(conn, &serde_json::from_value(state.clone())?)
+            }
+            AuditEntityType::IdempotencyKey => {
+                IdempotencyRepository::upsert(conn, &serde_json::from_value(state.clone())?)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::db::TradeService;
+
+    #[test]
+    fn test_undo_reduce_merges_child_back() {
+        let database = Database::open_in_memory().unwrap();
+        let conn = database.get().unwrap();
+
+        let mut position = Position::new("600519".to_string(), "贵州茅台".to_string(), 100.0, "2025-01-02".to_string(), 300, "组合A".to_string());
+        position.buy_fee = 7.5;
+        let created = AuditGroup::new("save_position");
+        PositionRepository::create(&conn, &position).unwrap();
+        created.record_position(&conn, None, Some(&position)).unwrap();
+
+        let reduced = AuditGroup::new("reduce_position");
+        let sold_id = TradeService::reduce_lot(&conn, &reduced, &position, 100, 110.0, "2025-02-01", 5.0, None).unwrap();
+        assert_eq!(PositionRepository::find_by_id(&conn, &position.id).unwrap().unwrap().quantity, 200);
+        assert_eq!(AuditService::find_history(&conn, &position.id).unwrap().len(), 3);
+
+        // 撤销减仓：子记录和回款删除，原记录数量和费用恢复
+        let undone = AuditService::undo_last(&conn).unwrap();
+        assert_eq!(undone.action, "reduce_position");
+        assert_eq!(undone.restored, 3);
+        let restored = PositionRepository::find_by_id(&conn, &position.id).unwrap().unwrap();
+        assert_eq!(restored.quantity, 300);
+        assert_eq!(restored.buy_fee, 7.5);
+        assert!(PositionRepository::find_by_id(&conn, &sold_id).unwrap().is_none());
+        assert_eq!(CashRepository::balance(&conn, "组合A").unwrap(), 0.0);
+
+        // 再次撤销：撤销最初的买入
+        assert_eq!(AuditService::undo_last(&conn).unwrap().action, "save_position");
+        assert!(PositionRepository::find_by_id(&conn, &position.id).unwrap().is_none());
+        assert!(AuditService::undo_last(&conn).is_err());
+    }
+
+    #[test]
+    fn test_undo_removes_idempotency_key() {
+        let database = Database::open_in_memory().unwrap();
+        let mut conn = database.get().unwrap();
+        let position = Position::new("600519".to_string(), "贵州茅台".to_string(), 100.0, "2025-01-02".to_string(), 100, "组合A".to_string());
+
+        let save = |conn: &mut Connection| {
+            let audit = AuditGroup::new("save_position");
+            IdempotencyRepository::run_in_transaction(conn, &audit, Some("k1"), |tx| {
+                let mut position = position.clone();
+                position.id = uuid::Uuid::new_v4().to_string();
+                PositionRepository::create(tx, &position)?;
+                audit.record_position(tx, None, Some(&position))?;
+                Ok(position.id)
+            })
+        };
+
+        let first = save(&mut conn).unwrap();
+        assert_eq!(save(&mut conn).unwrap(), first);
+
+        // 撤销买入后幂等键一并删除，使用同一幂等键重新提交会再次执行
+        let undone = AuditService::undo_last(&conn).unwrap();
+        assert_eq!(undone.restored, 2);
+        assert!(IdempotencyRepository::find(&conn, "k1").unwrap().is_none());
+        let second = save(&mut conn).unwrap();
+        assert_ne!(second, first);
+        assert!(PositionRepository::find_by_id(&conn, &second).unwrap().is_some());
+    }
+}
@@ -0,0 +1,119 @@
+/// 业绩基准对比服务
+/// 对比组合与业绩比较基准的累计收益率，基准的历史收盘价从行情数据源获取后保存在 daily_prices 表
+
+use crate::db::{Database, DailyPriceRepository, PortfolioRepository, QuoteService, RiskService};
+use crate::error::Result;
+use crate::models::benchmark::{BenchmarkComparison, MAX_CLOSE_AGE_DAYS};
+use crate::models::portfolio::DEFAULT_BASE_CURRENCY;
+use crate::models::returns::days_between;
+use crate::models::risk::{RiskQuery, DEFAULT_BENCHMARK};
+use chrono::{Duration, NaiveDate};
+use std::collections::BTreeMap;
+
+/// 业绩基准对比服务
+pub struct BenchmarkService;
+
+impl BenchmarkService {
+    /// 对比组合与基准在日期范围内的累计收益率
+    ///
+    /// 基准优先使用参数指定的代码，其次是组合设置的业绩比较基准，都没有时使用沪深300。
+    /// 本地收盘价有缺口时先从行情数据源补全，获取失败时使用已保存的收盘价并标记为不完整
+    pub async fn compare(database: &Database, portfolio: &str, query: &RiskQuery) -> Result<BenchmarkComparison> {
+        query.validate()?;
+        let (from, to) = query.date_range();
+
+        let (benchmark, returns) = {
+            let conn = database.acquire().await?;
+            let settings = PortfolioRepository::find_by_name(&conn, portfolio)?;
+            let base_currency = settings
+                .as_ref()
+                .map(|p| p.base_currency.clone())
+                .unwrap_or_else(|| DEFAULT_BASE_CURRENCY.to_string());
+            let benchmark = query
+                .benchmark
+                .clone()
+                .or_else(|| settings.and_then(|p| p.benchmark))
+                .unwrap_or_else(|| DEFAULT_BENCHMARK.to_string());
+
+            let returns = RiskService::portfolio_returns(&conn, portfolio, &base_currency, from, to)?;
+            (QuoteService::format_stock_code(&benchmark), returns)
+        };
+
+        let (Some(first), Some(last)) = (returns.first(), returns.last()) else {
+            println!("[BenchmarkService] 组合 {} 在 {} ~ {} 没有足够的快照", portfolio, from, to);
+            return Ok(BenchmarkComparison::compute(portfolio, &benchmark, &returns, &BTreeMap::new()));
+        };
+        let (start, end) = (first.start_date.clone(), last.end_date.clone());
+
+        let history_complete = match Self::ensure_history(database, &benchmark, &start, &end).await {
+            Ok(()) => true,
+            Err(e) => {
+                println!("⚠️ 获取基准 {} 的历史收盘价失败，使用本地已保存的收盘价: {}", benchmark, e);
+                false
+            }
+        };
+
+        let benchmark_closes: BTreeMap<String, f64> =
+            DailyPriceRepository::find_range(&*database.acquire().await?, &benchmark, &days_before(&start, MAX_CLOSE_AGE_DAYS), &end)?
+                .into_iter()
+                .map(|price| (price.price_date, price.close))
+                .collect();
+
+        let mut comparison = BenchmarkComparison::compute(portfolio, &benchmark, &returns, &benchmark_closes);
+        comparison.is_partial |= !history_complete;
+        Ok(comparison)
+    }
+
+    /// 本地保存的收盘价有缺口时，从行情数据源获取基准的历史收盘价并保存到 daily_prices 表
+    ///
+    /// 范围起点之前、相邻两个收盘价之间、最后一个收盘价到范围终点，间隔超过 MAX_CLOSE_AGE_DAYS 天视为缺口
+    pub async fn ensure_history(database: &Database, code: &str, from: &str, to: &str) -> Result<()> {
+        let fetch_from = days_before(from, MAX_CLOSE_AGE_DAYS);
+        let stored: Vec<String> = DailyPriceRepository::find_range(&*database.acquire().await?, code, &fetch_from, to)?
+            .into_iter()
+            .map(|price| price.price_date)
+            .collect();
+
+        if !has_gap(&stored, from, to) {
+            println!("💾 {} 使用本地保存的 {} 个收盘价", code, stored.len());
+            return Ok(());
+        }
+
+        let providers = QuoteService::configured_providers(&*database.acquire().await?)?;
+        let prices = QuoteService::fetch_history(&providers, code, &fetch_from, to).await?;
+
+        let mut conn = database.acquire().await?;
+        let tx = conn.transaction()?;
+        for price in &prices {
+            DailyPriceRepository::upsert(&tx, price)?;
+        }
+        tx.commit()?;
+        println!("✅ 已保存 {} 的 {} 个历史收盘价（{} ~ {}）", code, prices.len(), fetch_from, to);
+
+        Ok(())
+    }
+}
+
+/// 已保存的收盘价日期（升序）在范围内是否有缺口
+fn has_gap(dates: &[String], from: &str, to: &str) -> bool {
+    let Some(mut previous) = dates.iter().rev().find(|date| date.as_str() <= from).map(String::as_str) else {
+        return true;
+    };
+
+    let later = dates.iter().map(String::as_str).filter(|date| *date > from && *date <= to);
+    for date in later.chain(std::iter::once(to)) {
+        if days_between(previous, date).map_or(true, |days| days > MAX_CLOSE_AGE_DAYS) {
+            return true;
+        }
+        previous = date;
+    }
+
+    false
+}
+
+/// YYYY-MM-DD 日期往前推若干天，日期无法解析时原样返回
+fn days_before(date: &str, days: i64) -> String {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|date| (date - Duration::days(days)).format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|_| date.to_string())
+}
@@ -0,0 +1,217 @@
+/// 现金流水数据访问层
+/// 负责 cash_transactions 表的读写及余额统计
+
+use rusqlite::{Connection, params, OptionalExtension};
+use std::collections::HashMap;
+use crate::{db_error, error::Result};
+use crate::models::cash::{CashTransaction, CashTransactionType};
+
+/// 现金流水数据仓库
+pub struct CashRepository;
+
+impl CashRepository {
+    /// 辅助方法：从数据库行映射到 CashTransaction 对象
+    fn map_row_to_transaction(row: &rusqlite::Row) -> rusqlite::Result<CashTransaction> {
+        let txn_type: String = row.get(2)?;
+        Ok(CashTransaction {
+            id: row.get(0)?,
+            portfolio: row.get(1)?,
+            txn_type: CashTransactionType::parse(&txn_type).ok_or_else(|| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    2,
+                    rusqlite::types::Type::Text,
+                    format!("未知的现金流水类型: {}", txn_type).into(),
+                )
+            })?,
+            amount: row.get(3)?,
+            txn_date: row.get(4)?,
+            position_id: row.get(5)?,
+            note: row.get(6)?,
+            fx_rate: row.get(7)?,
+        })
+    }
+
+    /// 保存现金流水
+    pub fn create(conn: &Connection, txn: &CashTransaction) -> Result<String> {
+        let affected_rows = conn.execute(
+            "INSERT INTO cash_transactions (id, portfolio, txn_type, amount, txn_date, position_id, note, fx_rate, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, datetime('now', 'localtime'))",
+            params![
+                &txn.id,
+                &txn.portfolio,
+                txn.txn_type.as_str(),
+                txn.amount,
+                &txn.txn_date,
+                &txn.position_id,
+                &txn.note,
+                txn.fx_rate,
+            ],
+        )?;
+
+        if affected_rows == 0 {
+            return Err(db_error!("保存现金流水失败"));
+        }
+
+        Ok(txn.id.clone())
+    }
+
+    /// 按完整快照写入现金流水（存在则覆盖，用于撤销操作时恢复数据）
+    pub fn upsert(conn: &Connection, txn: &CashTransaction) -> Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO cash_transactions (id, portfolio, txn_type, amount, txn_date, position_id, note, fx_rate, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, datetime('now', 'localtime'))",
+            params![
+                &txn.id,
+                &txn.portfolio,
+                txn.txn_type.as_str(),
+                txn.amount,
+                &txn.txn_date,
+                &txn.position_id,
+                &txn.note,
+                txn.fx_rate,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 修改现金流水（组合、金额、日期、备注、汇率）
+    pub fn update(conn: &Connection, txn: &CashTransaction) -> Result<bool> {
+        let affected_rows = conn.execute(
+            "UPDATE cash_transactions SET portfolio = ?, amount = ?, txn_date = ?, note = ?, fx_rate = ? WHERE id = ?",
+            params![&txn.portfolio, txn.amount, &txn.txn_date, &txn.note, txn.fx_rate, &txn.id],
+        )?;
+        Ok(affected_rows > 0)
+    }
+
+    /// 获取所有现金流水
+    pub fn find_all(conn: &Connection) -> Result<Vec<CashTransaction>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, portfolio, txn_type, amount, txn_date, position_id, note, fx_rate
+             FROM cash_transactions
+             ORDER BY txn_date, created_at"
+        )?;
+
+        let transactions = stmt.query_map([], Self::map_row_to_transaction)?
+            .collect::<rusqlite::Result<Vec<CashTransaction>>>()?;
+
+        Ok(transactions)
+    }
+
+    /// 获取与指定持仓记录关联的现金流水
+    pub fn find_by_position(conn: &Connection, position_id: &str) -> Result<Vec<CashTransaction>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, portfolio, txn_type, amount, txn_date, position_id, note, fx_rate
+             FROM cash_transactions
+             WHERE position_id = ?
+             ORDER BY txn_date, created_at"
+        )?;
+
+        let transactions = stmt.query_map([position_id], Self::map_row_to_transaction)?
+            .collect::<rusqlite::Result<Vec<CashTransaction>>>()?;
+
+        Ok(transactions)
+    }
+
+    /// 获取指定组合的所有现金流水（按日期倒序）
+    pub fn find_by_portfolio(conn: &Connection, portfolio: &str) -> Result<Vec<CashTransaction>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, portfolio, txn_type, amount, txn_date, position_id, note, fx_rate
+             FROM cash_transactions
+             WHERE portfolio = ?
+             ORDER BY txn_date DESC, created_at DESC"
+        )?;
+
+        let transactions = stmt.query_map([portfolio], Self::map_row_to_transaction)?
+            .collect::<rusqlite::Result<Vec<CashTransaction>>>()?;
+
+        Ok(transactions)
+    }
+
+    /// 根据 ID 查找现金流水
+    pub fn find_by_id(conn: &Connection, id: &str) -> Result<Option<CashTransaction>> {
+        let txn = conn.query_row(
+            "SELECT id, portfolio, txn_type, amount, txn_date, position_id, note, fx_rate
+             FROM cash_transactions
+             WHERE id = ?",
+            [id],
+            Self::map_row_to_transaction,
+        ).optional()?;
+
+        Ok(txn)
+    }
+
+    /// 计算指定组合的现金余额
+    pub fn balance(conn: &Connection, portfolio: &str) -> Result<f64> {
+        let balance: f64 = conn.query_row(
+            "SELECT COALESCE(SUM(amount), 0) FROM cash_transactions WHERE portfolio = ?",
+            [portfolio],
+            |row| row.get(0),
+        )?;
+        Ok(balance)
+    }
+
+    /// 计算所有组合的现金余额（portfolio -> balance）
+    pub fn balances(conn: &Connection) -> Result<HashMap<String, f64>> {
+        let mut stmt = conn.prepare(
+            "SELECT portfolio, SUM(amount) FROM cash_transactions GROUP BY portfolio"
+        )?;
+
+        let balances = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+        })?
+        .collect::<rusqlite::Result<HashMap<String, f64>>>()?;
+
+        Ok(balances)
+    }
+
+    /// 删除单条现金流水
+    pub fn delete(conn: &Connection, id: &str) -> Result<bool> {
+        let affected_rows = conn.execute("DELETE FROM cash_transactions WHERE id = ?", [id])?;
+        Ok(affected_rows > 0)
+    }
+
+    /// 删除与指定持仓记录关联的所有流水（删除持仓时同步清理）
+    pub fn delete_by_position(conn: &Connection, position_id: &str) -> Result<usize> {
+        let affected_rows = conn.execute(
+            "DELETE FROM cash_transactions WHERE position_id = ?",
+            [position_id],
+        )?;
+        Ok(affected_rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Position;
+
+    #[test]
+    fn test_balance_follows_buy_and_sell_legs() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::migration::migrate_v5_to_v6(&conn).unwrap();
+        crate::migration::migrate_v19_to_v20(&conn).unwrap();
+
+        let deposit = CashTransaction::new("组合A".to_string(), CashTransactionType::Deposit, 20000.0, "2025-01-01".to_string());
+        CashRepository::create(&conn, &deposit).unwrap();
+
+        let mut position = Position::new(
+            "600519".to_string(),
+            "贵州茅台".to_string(),
+            100.0,
+            "2025-01-02".to_string(),
+            100,
+            "组合A".to_string(),
+        );
+        position.buy_fee = 5.1;
+        CashRepository::create(&conn, &CashTransaction::for_buy(&position)).unwrap();
+        assert!((CashRepository::balance(&conn, "组合A").unwrap() - 9994.9).abs() < 1e-9);
+
+        let sell = CashTransaction::for_sell("组合A".to_string(), position.id.clone(), 110.0, 100, 10.6, "2025-02-01".to_string(), None);
+        CashRepository::create(&conn, &sell).unwrap();
+        assert!((CashRepository::balance(&conn, "组合A").unwrap() - 20984.3).abs() < 1e-9);
+
+        // 删除持仓时，关联的买卖流水一并删除
+        assert_eq!(CashRepository::delete_by_position(&conn, &position.id).unwrap(), 2);
+        assert_eq!(CashRepository::balances(&conn).unwrap().get("组合A"), Some(&20000.0));
+    }
+}
@@ -1,47 +1,53 @@
 /// 已平仓交易统计服务
 /// 负责查询和统计所有已平仓的交易记录
 
-use crate::models::position::{ClosedTrade, ClosedTradesSummary, ClosedTradesStatistics, Position};
+use crate::models::position::{
+    ClosedTrade, ClosedTradeFilter, ClosedTradeGroup, ClosedTradesSummary, ClosedTradesStatistics,
+    HoldingDayBucket,
+};
+use crate::models::returns::DAYS_PER_YEAR;
+use crate::db::position_repo::{PositionRepository, POSITION_COLUMNS};
+use crate::db::{CorporateActionService, QuoteService};
 use crate::error::Result;
 use rusqlite::Connection;
+use std::collections::BTreeMap;
 
 /// 已平仓交易服务
 pub struct ClosedTradeService;
 
 impl ClosedTradeService {
-    /// 获取所有已平仓交易及总统计
-    pub fn get_closed_trades_summary(conn: &Connection) -> Result<ClosedTradesSummary> {
-        println!("[ClosedTradeService] 开始查询已平仓交易...");
-
-        // 查询所有已平仓记录，按卖出时间倒序排列
-        let mut stmt = conn.prepare(
-            "SELECT id, code, name, buy_price, buy_date, quantity, status, portfolio,
-                    sell_price, sell_date, parent_id
+    /// 获取符合条件的已平仓交易、总统计及分组统计
+    pub fn get_closed_trades_summary(
+        conn: &Connection,
+        filter: &ClosedTradeFilter,
+    ) -> Result<ClosedTradesSummary> {
+        filter.validate()?;
+        println!("[ClosedTradeService] 开始查询已平仓交易... 条件: {:?}", filter);
+
+        // 查询符合条件的已平仓记录，按卖出时间倒序排列
+        let (where_clause, params) = filter.build_where_clause();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
              FROM positions
-             WHERE status = 'CLOSE'
-             ORDER BY sell_date DESC"
-        )?;
-
-        let positions = stmt.query_map([], |row| {
-            Ok(Position {
-                id: row.get(0)?,
-                code: row.get(1)?,
-                name: row.get(2)?,
-                buy_price: row.get(3)?,
-                buy_date: row.get(4)?,
-                quantity: row.get(5)?,
-                status: row.get(6)?,
-                portfolio: row.get(7)?,
-                sell_price: row.get(8)?,
-                sell_date: row.get(9)?,
-                parent_id: row.get(10)?,
-            })
-        })?;
+             {}
+             ORDER BY sell_date DESC",
+            POSITION_COLUMNS,
+            where_clause
+        ))?;
 
-        // 转换为 ClosedTrade 列表
-        let mut trades: Vec<ClosedTrade> = positions
+        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p as &dyn rusqlite::ToSql).collect();
+        let positions = stmt.query_map(param_refs.as_slice(), PositionRepository::map_row_to_position)?;
+        let dividends = CorporateActionService::dividend_income_by_position(conn)?;
+        let target_code = filter.code.as_deref().map(QuoteService::format_stock_code);
+
+        // 转换为 ClosedTrade 列表（计入持有期间的分红），股票代码按规范化后的代码筛选
+        let trades: Vec<ClosedTrade> = positions
             .filter_map(|pos| pos.ok())
-            .map(|pos| ClosedTrade::from(pos))
+            .filter(|pos| target_code.as_ref().map_or(true, |code| QuoteService::format_stock_code(&pos.code) == *code))
+            .map(|pos| {
+                let dividend_income = dividends.get(&pos.id).copied().unwrap_or(0.0);
+                ClosedTrade::from(pos).with_dividend_income(dividend_income)
+            })
             .collect();
 
         println!("[ClosedTradeService] 查询到 {} 笔已平仓交易", trades.len());
@@ -49,27 +55,68 @@ impl ClosedTradeService {
         // 计算总统计
         let statistics = Self::calculate_statistics(&trades);
 
+        println!("[Statistics] 总计: {} 笔, 盈利: {} 笔, 亏损: {} 笔",
+                 statistics.total_trades, statistics.profitable_trades, statistics.loss_trades);
+        println!("[Statistics] 成功率: {:.2}%, 总盈亏: ¥{:.2}",
+                 statistics.win_rate * 100.0, statistics.total_profit_loss);
+
+        // 分组统计
+        let by_portfolio = Self::group_statistics(&trades, |t| (t.portfolio.clone(), t.portfolio.clone()));
+        let by_code = Self::group_statistics(&trades, |t| (QuoteService::format_stock_code(&t.code), t.name.clone()));
+        let by_month = Self::group_statistics(&trades, |t| {
+            let month = t.sell_date.get(..7).unwrap_or(&t.sell_date).to_string();
+            (month.clone(), month)
+        });
+        let by_year = Self::group_statistics(&trades, |t| {
+            let year = t.sell_date.get(..4).unwrap_or(&t.sell_date).to_string();
+            (year.clone(), year)
+        });
+
         Ok(ClosedTradesSummary {
             trades,
             statistics,
+            by_portfolio,
+            by_code,
+            by_month,
+            by_year,
         })
     }
 
+    /// 按分组键分组计算统计，分组按键升序排列
+    ///
+    /// key_fn 返回 (分组键, 显示名称)，同一分组取第一次出现的显示名称
+    fn group_statistics<F>(trades: &[ClosedTrade], key_fn: F) -> Vec<ClosedTradeGroup>
+    where
+        F: Fn(&ClosedTrade) -> (String, String),
+    {
+        let mut groups: BTreeMap<String, (String, Vec<ClosedTrade>)> = BTreeMap::new();
+        for trade in trades {
+            let (key, name) = key_fn(trade);
+            groups
+                .entry(key)
+                .or_insert_with(|| (name, Vec::new()))
+                .1
+                .push(trade.clone());
+        }
+
+        groups
+            .into_iter()
+            .map(|(key, (name, trades))| ClosedTradeGroup {
+                key,
+                name,
+                statistics: Self::calculate_statistics(&trades),
+            })
+            .collect()
+    }
+
     /// 计算总统计
     fn calculate_statistics(trades: &[ClosedTrade]) -> ClosedTradesStatistics {
         let total_trades = trades.len();
 
         if total_trades == 0 {
             return ClosedTradesStatistics {
-                total_trades: 0,
-                profitable_trades: 0,
-                loss_trades: 0,
-                win_rate: 0.0,
-                total_profit_loss: 0.0,
-                average_profit_loss_rate: 0.0,
-                max_profit: 0.0,
-                max_loss: 0.0,
-                average_holding_days: 0.0,
+                holding_day_histogram: HoldingDayBucket::default_buckets(),
+                ..Default::default()
             };
         }
 
@@ -78,22 +125,14 @@ impl ClosedTradeService {
         let loss_trades = trades.iter().filter(|t| t.profit_loss < 0.0).count();
 
         // 成功率
-        let win_rate = if total_trades > 0 {
-            profitable_trades as f64 / total_trades as f64
-        } else {
-            0.0
-        };
+        let win_rate = profitable_trades as f64 / total_trades as f64;
 
         // 总盈亏
         let total_profit_loss: f64 = trades.iter().map(|t| t.profit_loss).sum();
 
         // 平均盈亏率
         let total_rate: f64 = trades.iter().map(|t| t.profit_loss_rate).sum();
-        let average_profit_loss_rate = if total_trades > 0 {
-            total_rate / total_trades as f64
-        } else {
-            0.0
-        };
+        let average_profit_loss_rate = total_rate / total_trades as f64;
 
         // 最大盈利/亏损
         let max_profit = trades
@@ -108,16 +147,45 @@ impl ClosedTradeService {
 
         // 平均持有天数
         let total_days: i32 = trades.iter().map(|t| t.holding_days).sum();
-        let average_holding_days = if total_trades > 0 {
-            total_days as f64 / total_trades as f64
+        let average_holding_days = total_days as f64 / total_trades as f64;
+
+        // 资金加权收益率：按买入成本加权，大额交易的权重更高
+        let total_cost: f64 = trades.iter().map(|t| t.cost()).sum();
+        let capital_weighted_return = if total_cost != 0.0 {
+            total_profit_loss / total_cost
         } else {
             0.0
         };
 
-        println!("[Statistics] 总计: {} 笔, 盈利: {} 笔, 亏损: {} 笔",
-                 total_trades, profitable_trades, loss_trades);
-        println!("[Statistics] 成功率: {:.2}%, 总盈亏: ¥{:.2}",
-                 win_rate * 100.0, total_profit_loss);
+        // 资金时间加权的年化收益率：资金占用按 成本 × 持有天数 计算
+        let capital_days: f64 = trades
+            .iter()
+            .map(|t| t.cost() * t.holding_days.max(0) as f64)
+            .sum();
+        let capital_weighted_annualized_return = (capital_days > 0.0)
+            .then(|| total_profit_loss / capital_days * DAYS_PER_YEAR);
+
+        // 平均盈利 / 平均亏损 / 盈亏比因子
+        let gross_profit: f64 = trades.iter().map(|t| t.profit_loss).filter(|pl| *pl > 0.0).sum();
+        let gross_loss: f64 = trades.iter().map(|t| t.profit_loss).filter(|pl| *pl < 0.0).sum();
+        let average_win = if profitable_trades > 0 {
+            gross_profit / profitable_trades as f64
+        } else {
+            0.0
+        };
+        let average_loss = if loss_trades > 0 {
+            gross_loss / loss_trades as f64
+        } else {
+            0.0
+        };
+        let profit_factor = (gross_loss < 0.0).then(|| gross_profit / -gross_loss);
+
+        // 期望盈亏：持平交易不计入盈利或亏损
+        let loss_rate = loss_trades as f64 / total_trades as f64;
+        let expectancy = win_rate * average_win + loss_rate * average_loss;
+
+        let (longest_win_streak, longest_loss_streak) = Self::longest_streaks(trades);
+        let holding_day_histogram = Self::holding_day_histogram(trades);
 
         ClosedTradesStatistics {
             total_trades,
@@ -129,6 +197,183 @@ impl ClosedTradeService {
             max_profit,
             max_loss,
             average_holding_days,
+            capital_weighted_return,
+            capital_weighted_annualized_return,
+            profit_factor,
+            average_win,
+            average_loss,
+            expectancy,
+            longest_win_streak,
+            longest_loss_streak,
+            holding_day_histogram,
         }
     }
+
+    /// 按卖出日期先后计算最长连续盈利 / 亏损笔数，持平交易会中断连续
+    fn longest_streaks(trades: &[ClosedTrade]) -> (usize, usize) {
+        let mut ordered: Vec<&ClosedTrade> = trades.iter().collect();
+        ordered.sort_by(|a, b| {
+            a.sell_date
+                .cmp(&b.sell_date)
+                .then_with(|| a.buy_date.cmp(&b.buy_date))
+        });
+
+        let (mut wins, mut losses) = (0, 0);
+        let (mut longest_win, mut longest_loss) = (0, 0);
+        for trade in ordered {
+            if trade.profit_loss > 0.0 {
+                wins += 1;
+                losses = 0;
+            } else if trade.profit_loss < 0.0 {
+                losses += 1;
+                wins = 0;
+            } else {
+                wins = 0;
+                losses = 0;
+            }
+            longest_win = longest_win.max(wins);
+            longest_loss = longest_loss.max(losses);
+        }
+
+        (longest_win, longest_loss)
+    }
+
+    /// 按持有天数区间统计交易笔数和盈亏
+    fn holding_day_histogram(trades: &[ClosedTrade]) -> Vec<HoldingDayBucket> {
+        let mut buckets = HoldingDayBucket::default_buckets();
+        for trade in trades {
+            if let Some(bucket) = buckets.iter_mut().find(|b| b.contains(trade.holding_days)) {
+                bucket.trades += 1;
+                if trade.profit_loss > 0.0 {
+                    bucket.profitable_trades += 1;
+                }
+                bucket.total_profit_loss += trade.profit_loss;
+            }
+        }
+        buckets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(buy_date: &str, sell_date: &str, buy_price: f64, sell_price: f64) -> ClosedTrade {
+        let mut position = crate::models::Position::new(
+            "sh600000".to_string(),
+            "浦发银行".to_string(),
+            buy_price,
+            buy_date.to_string(),
+            100,
+            "默认".to_string(),
+        );
+        position.sell_price = Some(sell_price);
+        position.sell_date = Some(sell_date.to_string());
+        ClosedTrade::from(position)
+    }
+
+    #[test]
+    fn test_statistics_weight_by_capital_and_time() {
+        let trades = vec![
+            // 5 天赚 10%
+            trade("2024-01-01", "2024-01-06", 10.0, 11.0),
+            // 2 年赚 10%
+            trade("2022-01-01", "2024-01-01", 10.0, 11.0),
+            // 两笔连续亏损
+            trade("2024-02-01", "2024-03-01", 20.0, 18.0),
+            trade("2024-03-01", "2024-04-30", 20.0, 19.0),
+        ];
+        assert!(trades[0].annualized_return.unwrap() > trades[1].annualized_return.unwrap());
+
+        let stats = ClosedTradeService::calculate_statistics(&trades);
+        // 盈利 100 + 100，亏损 -200 - 100，成本 1000 + 1000 + 2000 + 2000
+        assert!((stats.capital_weighted_return - (-100.0 / 6000.0)).abs() < 1e-9);
+        assert!((stats.profit_factor.unwrap() - 200.0 / 300.0).abs() < 1e-9);
+        assert!((stats.average_win - 100.0).abs() < 1e-9);
+        assert!((stats.average_loss + 150.0).abs() < 1e-9);
+        assert!((stats.expectancy + 25.0).abs() < 1e-9);
+        assert_eq!(stats.longest_win_streak, 2);
+        assert_eq!(stats.longest_loss_streak, 2);
+        assert!(stats.capital_weighted_annualized_return.unwrap() < 0.0);
+
+        let counts: Vec<usize> = stats.holding_day_histogram.iter().map(|b| b.trades).collect();
+        assert_eq!(counts, vec![1, 1, 1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_summary_filters_and_groups() {
+        let database = crate::db::Database::open_in_memory().unwrap();
+        let conn = database.get().unwrap();
+
+        let closed = [
+            ("组合A", "2025-07-01", "2025-07-15", 11.0),
+            ("组合A", "2025-08-01", "2025-09-30", 9.0),
+            ("组合B", "2025-07-01", "2025-08-20", 12.0),
+            ("组合A", "2024-01-01", "2024-12-31", 10.5),
+        ];
+        for (portfolio, buy_date, sell_date, sell_price) in closed {
+            // 早期记录的代码没有市场前缀
+            let code = if buy_date < "2025-01-01" { "600000" } else { "sh600000" };
+            let mut position = crate::models::Position::new(
+                code.to_string(),
+                "浦发银行".to_string(),
+                10.0,
+                buy_date.to_string(),
+                100,
+                portfolio.to_string(),
+            );
+            position.status = "CLOSE".to_string();
+            position.sell_price = Some(sell_price);
+            position.sell_date = Some(sell_date.to_string());
+            PositionRepository::upsert(&conn, &position).unwrap();
+        }
+
+        // 组合A 在 2025 年第三季度卖出的交易
+        let filter = ClosedTradeFilter {
+            portfolio: Some("组合A".to_string()),
+            sell_date_from: Some("2025-07-01".to_string()),
+            sell_date_to: Some("2025-09-30".to_string()),
+            ..Default::default()
+        };
+        let summary = ClosedTradeService::get_closed_trades_summary(&conn, &filter).unwrap();
+        assert_eq!(summary.trades.len(), 2);
+        assert_eq!(summary.trades[0].sell_date, "2025-09-30");
+        let months: Vec<&str> = summary.by_month.iter().map(|g| g.key.as_str()).collect();
+        assert_eq!(months, vec!["2025-07", "2025-09"]);
+
+        // 不筛选时按组合、股票、年份分组
+        let summary = ClosedTradeService::get_closed_trades_summary(&conn, &ClosedTradeFilter::default()).unwrap();
+        assert_eq!(summary.trades.len(), 4);
+        let portfolios: Vec<(&str, usize)> = summary
+            .by_portfolio
+            .iter()
+            .map(|g| (g.key.as_str(), g.statistics.total_trades))
+            .collect();
+        assert_eq!(portfolios, vec![("组合A", 3), ("组合B", 1)]);
+        assert_eq!(summary.by_code.len(), 1);
+        assert_eq!(summary.by_code[0].key, "sh600000");
+        assert_eq!(summary.by_code[0].name, "浦发银行");
+        let years: Vec<(&str, usize)> = summary
+            .by_year
+            .iter()
+            .map(|g| (g.key.as_str(), g.statistics.total_trades))
+            .collect();
+        assert_eq!(years, vec![("2024", 1), ("2025", 3)]);
+
+        // 按股票筛选时不区分是否带市场前缀
+        let filter = ClosedTradeFilter {
+            code: Some("600000".to_string()),
+            ..Default::default()
+        };
+        let summary = ClosedTradeService::get_closed_trades_summary(&conn, &filter).unwrap();
+        assert_eq!(summary.trades.len(), 4);
+
+        // 日期范围颠倒时报错
+        let invalid = ClosedTradeFilter {
+            buy_date_from: Some("2025-12-31".to_string()),
+            buy_date_to: Some("2025-01-01".to_string()),
+            ..Default::default()
+        };
+        assert!(ClosedTradeService::get_closed_trades_summary(&conn, &invalid).is_err());
+    }
 }
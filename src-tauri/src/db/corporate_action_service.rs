@@ -0,0 +1,638 @@
+/// 公司行为服务
+/// 负责录入分红、送转、拆股、配股，并调整受影响的持仓记录
+
+use rusqlite::{Connection, params, OptionalExtension};
+use std::collections::HashMap;
+use chrono::NaiveDate;
+use crate::{business_error, error::Result};
+use crate::db::{AuditGroup, CashRepository, PortfolioRepository, QuoteService};
+use crate::db::position_repo::{PositionRepository, POSITION_COLUMNS};
+use crate::models::{CashTransaction, FxRates, Position};
+use crate::models::cash::CashTransactionType;
+use crate::models::corporate_action::{
+    CorporateAction, CorporateActionEntry, CorporateActionType, dividend_tax_rate,
+};
+
+/// 公司行为服务
+pub struct CorporateActionService;
+
+impl CorporateActionService {
+    /// 辅助方法：从数据库行映射到 CorporateAction 对象
+    fn map_row_to_action(row: &rusqlite::Row) -> rusqlite::Result<CorporateAction> {
+        let action_type: String = row.get(2)?;
+        Ok(CorporateAction {
+            id: row.get(0)?,
+            code: row.get(1)?,
+            action_type: CorporateActionType::parse(&action_type).ok_or_else(|| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    2,
+                    rusqlite::types::Type::Text,
+                    format!("未知的公司行为类型: {}", action_type).into(),
+                )
+            })?,
+            ex_date: row.get(3)?,
+            cash_per_share: row.get(4)?,
+            share_ratio: row.get(5)?,
+            rights_price: row.get(6)?,
+            note: row.get(7)?,
+        })
+    }
+
+    /// 辅助方法：从数据库行映射到 CorporateActionEntry 对象
+    fn map_row_to_entry(row: &rusqlite::Row) -> rusqlite::Result<CorporateActionEntry> {
+        Ok(CorporateActionEntry {
+            id: row.get(0)?,
+            action_id: row.get(1)?,
+            position_id: row.get(2)?,
+            portfolio: row.get(3)?,
+            code: row.get(4)?,
+            quantity_before: row.get(5)?,
+            quantity_after: row.get(6)?,
+            buy_price_before: row.get(7)?,
+            buy_price_after: row.get(8)?,
+            cash_amount: row.get(9)?,
+            tax: row.get(10)?,
+            net_cash: row.get(11)?,
+        })
+    }
+
+    /// 录入公司行为并应用到受影响的持仓
+    ///
+    /// 处理规则：
+    /// - 除权除息日之前买入的持仓享有权益
+    /// - 分红：差别化红利税按卖出时的持股期限计算，仍在持仓的记录按税前金额计入组合现金，
+    ///   卖出时补扣（见 settle_dividend_tax）；除息日之后才卖出的已平仓记录同样享有分红，
+    ///   按买入到卖出的持股期限直接扣税
+    /// - 送转/拆股：调整持仓中记录的数量和买入价格，总成本不变（零碎股舍去）；
+    ///   合股后不足 1 股的持仓无法记录，拒绝录入
+    /// - 配股：按配股价增加持仓数量和成本，缴款金额从组合现金中扣除
+    /// - 外币持仓的现金按 fx_rates 中交易币种到组合基础货币的汇率折算后入账，
+    ///   没有对应汇率时拒绝录入
+    ///
+    /// 所有改动记录到 audit 分组中，可整体撤销
+    ///
+    /// 注意：调用方应在事务中执行
+    pub fn apply(
+        conn: &Connection,
+        audit: &AuditGroup,
+        action: &CorporateAction,
+        fx_rates: &FxRates,
+    ) -> Result<Vec<CorporateActionEntry>> {
+        action.validate()?;
+
+        let duplicated: Option<String> = conn.query_row(
+            "SELECT id FROM corporate_actions WHERE code = ? AND action_type = ? AND ex_date = ?",
+            params![&action.code, action.action_type.as_str(), &action.ex_date],
+            |row| row.get(0),
+        ).optional()?;
+
+        if duplicated.is_some() {
+            return Err(business_error!(
+                "股票 {} 在 {} 的{}已经录入过",
+                action.code,
+                action.ex_date,
+                action.action_type.as_str()
+            ));
+        }
+
+        Self::upsert_action(conn, action)?;
+        audit.record_corporate_action(conn, None, Some(action))?;
+
+        let lots = Self::find_eligible_lots(conn, action)?;
+        println!(
+            "[CorporateAction] {} {} 除权除息日 {}，受影响持仓 {} 笔",
+            action.code,
+            action.action_type.as_str(),
+            action.ex_date,
+            lots.len()
+        );
+
+        let mut entries = Vec::new();
+        for lot in lots {
+            let entry = Self::build_entry(action, &lot)?;
+
+            if entry.quantity_after != entry.quantity_before
+                || entry.buy_price_after != entry.buy_price_before
+            {
+                PositionRepository::update_quantity_and_price(
+                    conn,
+                    &lot.id,
+                    entry.quantity_after,
+                    entry.buy_price_after,
+                )?;
+                let adjusted = PositionRepository::find_by_id(conn, &lot.id)?;
+                audit.record_position(conn, Some(&lot), adjusted.as_ref())?;
+            }
+
+            if entry.net_cash != 0.0 {
+                let txn_type = match action.action_type {
+                    CorporateActionType::Rights => CashTransactionType::RightsIssue,
+                    _ => CashTransactionType::Dividend,
+                };
+                let fx_rate = Self::trade_rate(conn, &lot, fx_rates)?;
+                let mut txn = CashTransaction::new(
+                    lot.portfolio.clone(),
+                    txn_type,
+                    entry.net_cash * fx_rate.unwrap_or(1.0),
+                    action.ex_date.clone(),
+                );
+                txn.position_id = Some(lot.id.clone());
+                txn.fx_rate = fx_rate;
+                CashRepository::create(conn, &txn)?;
+                audit.record_cash(conn, None, Some(&txn))?;
+            }
+
+            Self::upsert_entry(conn, &entry)?;
+            audit.record_corporate_action_entry(conn, None, Some(&entry))?;
+            entries.push(entry);
+        }
+
+        Ok(entries)
+    }
+
+    /// 持仓交易币种到所在组合基础货币的汇率（币种相同时为 None）
+    fn trade_rate(conn: &Connection, lot: &Position, fx_rates: &FxRates) -> Result<Option<f64>> {
+        let base_currency = PortfolioRepository::base_currency(conn, &lot.portfolio)?;
+        if lot.currency == base_currency {
+            return Ok(None);
+        }
+
+        match fx_rates.rate(&lot.currency, &base_currency) {
+            Some(rate) => Ok(Some(rate)),
+            None => Err(business_error!(
+                "没有 {}/{} 汇率，无法将 {} 的公司行为现金折算为组合基础货币，请先录入手动汇率",
+                lot.currency,
+                base_currency,
+                lot.code
+            )),
+        }
+    }
+
+    /// 查找享有权益的持仓记录
+    fn find_eligible_lots(conn: &Connection, action: &CorporateAction) -> Result<Vec<Position>> {
+        // 分红包含除息日及之后才卖出的记录；股份调整只作用于仍在持仓的记录
+        let status_condition = match action.action_type {
+            CorporateActionType::Dividend => "(status = 'POSITION' OR (status = 'CLOSE' AND sell_date >= ?2))",
+            _ => "status = 'POSITION' AND ?2 IS NOT NULL",
+        };
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM positions
+             WHERE buy_date < ?1 AND {}
+             ORDER BY buy_date",
+            POSITION_COLUMNS,
+            status_condition
+        ))?;
+
+        let target_code = QuoteService::format_stock_code(&action.code);
+        let lots = stmt.query_map(params![&action.ex_date, &action.ex_date], PositionRepository::map_row_to_position)?
+            .collect::<rusqlite::Result<Vec<Position>>>()?
+            .into_iter()
+            .filter(|lot| QuoteService::format_stock_code(&lot.code) == target_code)
+            .collect();
+
+        Ok(lots)
+    }
+
+    /// 计算公司行为对单笔持仓的影响
+    fn build_entry(action: &CorporateAction, lot: &Position) -> Result<CorporateActionEntry> {
+        let quantity_before = lot.quantity;
+        let buy_price_before = lot.buy_price;
+        let mut quantity_after = quantity_before;
+        let mut buy_price_after = buy_price_before;
+        let mut cash_amount = 0.0;
+        let mut tax = 0.0;
+
+        match action.action_type {
+            CorporateActionType::Dividend => {
+                cash_amount = round_to_cent(action.cash_per_share * quantity_before as f64);
+                if let Some(sell_date) = &lot.sell_date {
+                    let holding_days = holding_days(&lot.buy_date, sell_date);
+                    tax = round_to_cent(cash_amount * dividend_tax_rate(holding_days));
+                }
+            }
+            CorporateActionType::Bonus => {
+                let bonus_shares = (quantity_before as f64 * action.share_ratio).floor() as i32;
+                quantity_after = quantity_before + bonus_shares;
+                buy_price_after = buy_price_before * quantity_before as f64 / quantity_after as f64;
+            }
+            CorporateActionType::Split => {
+                quantity_after = (quantity_before as f64 * action.share_ratio).floor() as i32;
+                if quantity_after == 0 {
+                    return Err(business_error!(
+                        "{} 在 {} 买入的 {} 股按比例 {} 合股后不足 1 股，请先卖出该笔持仓",
+                        lot.code,
+                        lot.buy_date,
+                        quantity_before,
+                        action.share_ratio
+                    ));
+                }
+                buy_price_after = buy_price_before * quantity_before as f64 / quantity_after as f64;
+            }
+            CorporateActionType::Rights => {
+                let rights_price = action.rights_price.unwrap_or(0.0);
+                let rights_shares = (quantity_before as f64 * action.share_ratio).floor() as i32;
+                quantity_after = quantity_before + rights_shares;
+                let payment = rights_price * rights_shares as f64;
+                buy_price_after = (buy_price_before * quantity_before as f64 + payment) / quantity_after as f64;
+                cash_amount = -round_to_cent(payment);
+            }
+        }
+
+        Ok(CorporateActionEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            action_id: action.id.clone(),
+            position_id: lot.id.clone(),
+            portfolio: lot.portfolio.clone(),
+            code: lot.code.clone(),
+            quantity_before,
+            quantity_after,
+            buy_price_before,
+            buy_price_after,
+            cash_amount,
+            tax,
+            net_cash: cash_amount - tax,
+        })
+    }
+
+    /// 保存公司行为记录（存在则覆盖，撤销操作时也用于恢复数据）
+    pub fn upsert_action(conn: &Connection, action: &CorporateAction) -> Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO corporate_actions (id, code, action_type, ex_date, cash_per_share, share_ratio, rights_price, note, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, datetime('now', 'localtime'))",
+            params![
+                &action.id,
+                &action.code,
+                action.action_type.as_str(),
+                &action.ex_date,
+                action.cash_per_share,
+                action.share_ratio,
+                action.rights_price,
+                &action.note,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 保存持仓影响明细（存在则覆盖，撤销操作时也用于恢复数据）
+    pub fn upsert_entry(conn: &Connection, entry: &CorporateActionEntry) -> Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO corporate_action_entries (
+                id, action_id, position_id, portfolio, code, quantity_before, quantity_after,
+                buy_price_before, buy_price_after, cash_amount, tax, net_cash
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                &entry.id,
+                &entry.action_id,
+                &entry.position_id,
+                &entry.portfolio,
+                &entry.code,
+                entry.quantity_before,
+                entry.quantity_after,
+                entry.buy_price_before,
+                entry.buy_price_after,
+                entry.cash_amount,
+                entry.tax,
+                entry.net_cash,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 查询公司行为（可按股票代码过滤，按除权除息日倒序）
+    pub fn find_actions(conn: &Connection, code: Option<&str>) -> Result<Vec<CorporateAction>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, code, action_type, ex_date, cash_per_share, share_ratio, rights_price, note
+             FROM corporate_actions
+             WHERE ?1 IS NULL OR code = ?1
+             ORDER BY ex_date DESC"
+        )?;
+
+        let code = code.map(|c| QuoteService::format_stock_code(&c.to_lowercase()));
+        let actions = stmt.query_map([code], Self::map_row_to_action)?
+            .collect::<rusqlite::Result<Vec<CorporateAction>>>()?;
+
+        Ok(actions)
+    }
+
+    /// 根据 ID 查找公司行为
+    pub fn find_action_by_id(conn: &Connection, id: &str) -> Result<Option<CorporateAction>> {
+        let action = conn.query_row(
+            "SELECT id, code, action_type, ex_date, cash_per_share, share_ratio, rights_price, note
+             FROM corporate_actions
+             WHERE id = ?",
+            [id],
+            Self::map_row_to_action,
+        ).optional()?;
+
+        Ok(action)
+    }
+
+    /// 查询某个公司行为对持仓的影响明细
+    pub fn find_entries(conn: &Connection, action_id: &str) -> Result<Vec<CorporateActionEntry>> {
+        Self::query_entries(conn, "WHERE action_id = ?1 ORDER BY portfolio, position_id", Some(action_id))
+    }
+
+    /// 查询某笔持仓的所有公司行为明细
+    pub fn find_entries_by_position(conn: &Connection, position_id: &str) -> Result<Vec<CorporateActionEntry>> {
+        Self::query_entries(conn, "WHERE position_id = ?1", Some(position_id))
+    }
+
+    /// 查询所有公司行为明细
+    pub fn find_all_entries(conn: &Connection) -> Result<Vec<CorporateActionEntry>> {
+        Self::query_entries(conn, "WHERE ?1 IS NULL", None)
+    }
+
+    /// 根据 ID 查找公司行为明细
+    pub fn find_entry_by_id(conn: &Connection, id: &str) -> Result<Option<CorporateActionEntry>> {
+        Ok(Self::query_entries(conn, "WHERE id = ?1", Some(id))?.into_iter().next())
+    }
+
+    /// 辅助方法：按条件查询公司行为明细
+    fn query_entries(conn: &Connection, condition: &str, param: Option<&str>) -> Result<Vec<CorporateActionEntry>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, action_id, position_id, portfolio, code, quantity_before, quantity_after,
+                    buy_price_before, buy_price_after, cash_amount, tax, net_cash
+             FROM corporate_action_entries
+             {}",
+            condition
+        ))?;
+
+        let entries = stmt.query_map([param], Self::map_row_to_entry)?
+            .collect::<rusqlite::Result<Vec<CorporateActionEntry>>>()?;
+
+        Ok(entries)
+    }
+
+    /// 指定日期之后是否发生过送转、拆股或配股（会改变持仓数量）
+    pub fn has_share_adjustments_after(conn: &Connection, code: &str, date: &str) -> Result<bool> {
+        let target_code = QuoteService::format_stock_code(code);
+        let mut stmt = conn.prepare(
+            "SELECT code FROM corporate_actions WHERE action_type != 'DIVIDEND' AND ex_date > ?"
+        )?;
+        let codes = stmt.query_map([date], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+
+        Ok(codes.iter().any(|c| QuoteService::format_stock_code(c) == target_code))
+    }
+
+    /// 每笔持仓累计的税后分红收入（position_id -> 金额）
+    ///
+    /// 卖出时补扣的红利税从分红明细所在的记录中扣除（减仓产生的子记录计入原记录）
+    pub fn dividend_income_by_position(conn: &Connection) -> Result<HashMap<String, f64>> {
+        let mut stmt = conn.prepare(
+            "SELECT position_id, SUM(amount)
+             FROM (
+                 SELECT e.position_id AS position_id, e.net_cash AS amount
+                 FROM corporate_action_entries e
+                 JOIN corporate_actions a ON a.id = e.action_id
+                 WHERE a.action_type = 'DIVIDEND'
+                 UNION ALL
+                 SELECT COALESCE(p.parent_id, t.position_id), t.amount / COALESCE(t.fx_rate, 1)
+                 FROM cash_transactions t
+                 LEFT JOIN positions p ON p.id = t.position_id
+                 WHERE t.txn_type = 'DIVIDEND_TAX' AND t.position_id IS NOT NULL
+             )
+             GROUP BY position_id"
+        )?;
+
+        let income = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+        })?
+        .collect::<rusqlite::Result<HashMap<String, f64>>>()?;
+
+        Ok(income)
+    }
+
+    /// 卖出时补扣持仓期间分红的红利税
+    ///
+    /// 持仓中的记录按税前金额收到分红，卖出的股数按买入日到卖出日的持股期限计算税率。
+    /// 每股未扣税分红按该记录的分红明细累计，送转、拆股、配股后按数量变化折算；
+    /// 部分卖出不改变剩余股份的每股未扣税分红，因此不需要记录已扣税的部分。
+    /// 除息日时已按期限扣过税的明细（旧版本录入的数据）不再重复扣税
+    ///
+    /// 参数：
+    /// - lot: 卖出前的持仓记录（分红明细关联在该记录上）
+    /// - sold_id: 已卖出记录的 ID，补扣的红利税流水关联到该记录
+    /// - quantity: 卖出股数
+    /// - fx_rate: 卖出时交易币种到组合基础货币的汇率（币种相同时为 None）
+    ///
+    /// 注意：调用方应在事务中执行
+    pub fn settle_dividend_tax(
+        conn: &Connection,
+        audit: &AuditGroup,
+        lot: &Position,
+        sold_id: &str,
+        quantity: i32,
+        sell_date: &str,
+        fx_rate: Option<f64>,
+    ) -> Result<Option<CashTransaction>> {
+        let mut stmt = conn.prepare(
+            "SELECT a.action_type, e.quantity_before, e.quantity_after, e.cash_amount, e.tax
+             FROM corporate_action_entries e
+             JOIN corporate_actions a ON a.id = e.action_id
+             WHERE e.position_id = ?
+             ORDER BY a.ex_date"
+        )?;
+        let entries = stmt.query_map([&lot.id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i32>(1)?,
+                row.get::<_, i32>(2)?,
+                row.get::<_, f64>(3)?,
+                row.get::<_, f64>(4)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut untaxed_per_share = 0.0;
+        for (action_type, quantity_before, quantity_after, cash_amount, tax) in entries {
+            if action_type == CorporateActionType::Dividend.as_str() {
+                if tax == 0.0 && quantity_before > 0 {
+                    untaxed_per_share += cash_amount / quantity_before as f64;
+                }
+            } else if quantity_after > 0 {
+                untaxed_per_share *= quantity_before as f64 / quantity_after as f64;
+            }
+        }
+
+        let rate = dividend_tax_rate(holding_days(&lot.buy_date, sell_date));
+        let tax = round_to_cent(untaxed_per_share * quantity as f64 * rate);
+        if tax <= 0.0 {
+            return Ok(None);
+        }
+
+        let mut txn = CashTransaction::new(
+            lot.portfolio.clone(),
+            CashTransactionType::DividendTax,
+            -tax * fx_rate.unwrap_or(1.0),
+            sell_date.to_string(),
+        );
+        txn.position_id = Some(sold_id.to_string());
+        txn.fx_rate = fx_rate;
+        CashRepository::create(conn, &txn)?;
+        audit.record_cash(conn, None, Some(&txn))?;
+
+        Ok(Some(txn))
+    }
+
+    /// 删除与指定持仓记录关联的公司行为明细（删除持仓时同步清理）
+    pub fn delete_entries_by_position(conn: &Connection, position_id: &str) -> Result<usize> {
+        let affected_rows = conn.execute(
+            "DELETE FROM corporate_action_entries WHERE position_id = ?",
+            [position_id],
+        )?;
+        Ok(affected_rows)
+    }
+}
+
+/// 计算持股天数（买入日至除权除息日）
+fn holding_days(buy_date: &str, ex_date: &str) -> i64 {
+    let buy = NaiveDate::parse_from_str(buy_date, "%Y-%m-%d").ok();
+    let ex = NaiveDate::parse_from_str(ex_date, "%Y-%m-%d").ok();
+
+    match (buy, ex) {
+        (Some(b), Some(e)) => e.signed_duration_since(b).num_days(),
+        _ => 0,
+    }
+}
+
+/// 按分四舍五入
+fn round_to_cent(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::models::corporate_action::RecordCorporateActionRequest;
+
+    fn request(action_type: CorporateActionType) -> RecordCorporateActionRequest {
+        RecordCorporateActionRequest {
+            code: "600519".to_string(),
+            action_type,
+            ex_date: "2025-06-20".to_string(),
+            cash_per_share: None,
+            share_ratio: None,
+            rights_price: None,
+            note: None,
+        }
+    }
+
+    #[test]
+    fn test_dividend_and_bonus_adjust_lots() {
+        let database = Database::open_in_memory().unwrap();
+        let conn = database.get().unwrap();
+        let audit = AuditGroup::new("record_corporate_action");
+        let old_lot = Position::new("600519".to_string(), "贵州茅台".to_string(), 100.0, "2024-01-02".to_string(), 100, "组合A".to_string());
+        let new_lot = Position::new("sh600519".to_string(), "贵州茅台".to_string(), 110.0, "2025-06-10".to_string(), 100, "组合A".to_string());
+        let late_lot = Position::new("600519".to_string(), "贵州茅台".to_string(), 120.0, "2025-06-20".to_string(), 100, "组合A".to_string());
+        for lot in [&old_lot, &new_lot, &late_lot] {
+            PositionRepository::create(&conn, lot).unwrap();
+        }
+
+        // 分红：持仓中的记录按税前金额入账，除息日当天买入的不享有
+        let mut dividend = request(CorporateActionType::Dividend);
+        dividend.cash_per_share = Some(2.5);
+        let entries = CorporateActionService::apply(&conn, &audit, &CorporateAction::from(dividend), &FxRates::default()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|entry| entry.tax == 0.0));
+        let income = CorporateActionService::dividend_income_by_position(&conn).unwrap();
+        assert_eq!(income.get(&old_lot.id), Some(&250.0));
+        assert_eq!(income.get(&new_lot.id), Some(&250.0));
+        assert_eq!(CashRepository::balance(&conn, "组合A").unwrap(), 500.0);
+
+        // 同一除息日重复录入会被拒绝，代码是否带市场前缀不影响判断
+        let mut duplicated = request(CorporateActionType::Dividend);
+        duplicated.cash_per_share = Some(2.5);
+        assert!(CorporateActionService::apply(&conn, &audit, &CorporateAction::from(duplicated), &FxRates::default()).is_err());
+        let mut prefixed = request(CorporateActionType::Dividend);
+        prefixed.code = "SH600519".to_string();
+        prefixed.cash_per_share = Some(2.5);
+        assert!(CorporateActionService::apply(&conn, &audit, &CorporateAction::from(prefixed), &FxRates::default()).is_err());
+        assert_eq!(CashRepository::balance(&conn, "组合A").unwrap(), 500.0);
+        assert_eq!(CorporateActionService::find_actions(&conn, Some("600519")).unwrap().len(), 1);
+
+        // 10送3：数量增加，总成本不变
+        let mut bonus = request(CorporateActionType::Bonus);
+        bonus.share_ratio = Some(0.3);
+        CorporateActionService::apply(&conn, &audit, &CorporateAction::from(bonus), &FxRates::default()).unwrap();
+        let adjusted = PositionRepository::find_by_id(&conn, &old_lot.id).unwrap().unwrap();
+        assert_eq!(adjusted.quantity, 130);
+        assert!((adjusted.buy_price * 130.0 - 10000.0).abs() < 1e-6);
+        let untouched = PositionRepository::find_by_id(&conn, &late_lot.id).unwrap().unwrap();
+        assert_eq!(untouched.quantity, 100);
+    }
+
+    #[test]
+    fn test_dividend_tax_is_settled_at_sale() {
+        let database = Database::open_in_memory().unwrap();
+        let conn = database.get().unwrap();
+        let audit = AuditGroup::new("record_corporate_action");
+        let old_lot = Position::new("600519".to_string(), "贵州茅台".to_string(), 100.0, "2024-01-02".to_string(), 100, "组合A".to_string());
+        let new_lot = Position::new("600519".to_string(), "贵州茅台".to_string(), 110.0, "2025-06-10".to_string(), 300, "组合A".to_string());
+        for lot in [&old_lot, &new_lot] {
+            PositionRepository::create(&conn, lot).unwrap();
+        }
+
+        let mut dividend = request(CorporateActionType::Dividend);
+        dividend.cash_per_share = Some(2.5);
+        CorporateActionService::apply(&conn, &audit, &CorporateAction::from(dividend), &FxRates::default()).unwrap();
+
+        // 10送5 后每股未扣税分红摊薄为 2.5 / 1.5
+        let mut bonus = request(CorporateActionType::Bonus);
+        bonus.ex_date = "2025-06-25".to_string();
+        bonus.share_ratio = Some(0.5);
+        CorporateActionService::apply(&conn, &audit, &CorporateAction::from(bonus), &FxRates::default()).unwrap();
+        let new_lot = PositionRepository::find_by_id(&conn, &new_lot.id).unwrap().unwrap();
+        assert_eq!(new_lot.quantity, 450);
+
+        // 持股 21 天卖出 150 股：补扣 150 × 2.5 / 1.5 × 20% = 50
+        let sell = AuditGroup::new("reduce_position");
+        let sold_id = crate::db::TradeService::reduce_lot(&conn, &sell, &new_lot, 150, 120.0, "2025-07-01", 0.0, None).unwrap();
+        let taxes: Vec<f64> = CashRepository::find_by_position(&conn, &sold_id)
+            .unwrap()
+            .iter()
+            .filter(|txn| txn.txn_type == CashTransactionType::DividendTax)
+            .map(|txn| txn.amount)
+            .collect();
+        assert_eq!(taxes, vec![-50.0]);
+        let income = CorporateActionService::dividend_income_by_position(&conn).unwrap();
+        assert_eq!(income.get(&new_lot.id), Some(&700.0));
+
+        // 持股超过 1 年卖出：免税
+        let old_lot = PositionRepository::find_by_id(&conn, &old_lot.id).unwrap().unwrap();
+        crate::db::TradeService::close_lot(&conn, &sell, &old_lot, 120.0, "2025-07-01", 0.0, None).unwrap();
+        assert!(CashRepository::find_by_position(&conn, &old_lot.id)
+            .unwrap()
+            .iter()
+            .all(|txn| txn.txn_type != CashTransactionType::DividendTax));
+
+        // 卖出后才录入、除息日在卖出前的分红：已卖出的记录直接按买入到卖出的持股期限扣税
+        let mut late = request(CorporateActionType::Dividend);
+        late.ex_date = "2025-06-30".to_string();
+        late.cash_per_share = Some(1.0);
+        let entries = CorporateActionService::apply(&conn, &audit, &CorporateAction::from(late), &FxRates::default()).unwrap();
+        let tax_of = |id: &str| entries.iter().find(|entry| entry.position_id == id).unwrap().tax;
+        assert_eq!(tax_of(&sold_id), 30.0);
+        assert_eq!(tax_of(&old_lot.id), 0.0);
+        assert_eq!(tax_of(&new_lot.id), 0.0);
+    }
+
+    #[test]
+    fn test_reverse_split_rejects_lots_below_one_share() {
+        let database = Database::open_in_memory().unwrap();
+        let conn = database.get().unwrap();
+        let audit = AuditGroup::new("record_corporate_action");
+        let lot = Position::new("600519".to_string(), "贵州茅台".to_string(), 100.0, "2024-01-02".to_string(), 5, "组合A".to_string());
+        PositionRepository::create(&conn, &lot).unwrap();
+
+        // 10股合1股：5股合股后不足1股，不能凭空记成1股
+        let mut split = request(CorporateActionType::Split);
+        split.share_ratio = Some(0.1);
+        assert!(CorporateActionService::apply(&conn, &audit, &CorporateAction::from(split), &FxRates::default()).is_err());
+        assert_eq!(PositionRepository::find_by_id(&conn, &lot.id).unwrap().unwrap().quantity, 5);
+    }
+}
@@ -0,0 +1,54 @@
+/// 每日收盘价数据访问层
+/// 负责 daily_prices 表的读写，每个代码每天一条
+
+use rusqlite::{Connection, params};
+use crate::error::Result;
+use crate::models::DailyPrice;
+
+/// 每日收盘价数据仓库
+pub struct DailyPriceRepository;
+
+impl DailyPriceRepository {
+    /// 辅助方法：从数据库行映射到 DailyPrice 对象
+    fn map_row_to_daily_price(row: &rusqlite::Row) -> rusqlite::Result<DailyPrice> {
+        Ok(DailyPrice {
+            code: row.get(0)?,
+            price_date: row.get(1)?,
+            close: row.get(2)?,
+            prev_close: row.get(3)?,
+        })
+    }
+
+    /// 保存收盘价（同一代码同一天存在则覆盖）
+    pub fn upsert(conn: &Connection, daily_price: &DailyPrice) -> Result<()> {
+        conn.execute(
+            "INSERT INTO daily_prices (code, price_date, close, prev_close)
+             VALUES (?, ?, ?, ?)
+             ON CONFLICT(code, price_date) DO UPDATE SET
+                close = excluded.close,
+                prev_close = excluded.prev_close",
+            params![
+                &daily_price.code,
+                &daily_price.price_date,
+                daily_price.close,
+                daily_price.prev_close,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 查询代码在日期范围内（含首尾）的收盘价，按日期升序
+    pub fn find_range(conn: &Connection, code: &str, from: &str, to: &str) -> Result<Vec<DailyPrice>> {
+        let mut stmt = conn.prepare(
+            "SELECT code, price_date, close, prev_close FROM daily_prices
+             WHERE code = ? AND price_date >= ? AND price_date <= ?
+             ORDER BY price_date",
+        )?;
+
+        let daily_prices = stmt
+            .query_map(params![code, from, to], Self::map_row_to_daily_price)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(daily_prices)
+    }
+}
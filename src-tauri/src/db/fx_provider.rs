@@ -0,0 +1,113 @@
+/// 汇率数据源
+/// 每个数据源负责拼接请求地址和解析响应，请求的发送、重试由 FxService 统一处理
+
+use crate::db::quote_provider::QuoteRequest;
+use crate::models::fx::{FxRate, FxRateSource};
+
+/// 汇率数据源
+///
+/// 传入的币种对均为大写币种代码（如 ("USD", "CNY")），源币种和目标币种不同
+pub trait FxRateProvider: Send + Sync {
+    /// 拼接批量汇率请求
+    fn build_request(&self, pairs: &[(String, String)]) -> QuoteRequest;
+
+    /// 解析批量响应，只返回获取成功的汇率
+    fn parse_response(&self, text: &str, pairs: &[(String, String)]) -> Vec<FxRate>;
+}
+
+/// 新浪外汇行情
+pub struct SinaFxProvider {
+    base_url: String,
+}
+
+impl SinaFxProvider {
+    pub fn new() -> Self {
+        Self::with_base_url("https://hq.sinajs.cn")
+    }
+
+    /// 指定接口地址（用于测试时指向本地服务）
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self { base_url: base_url.into() }
+    }
+
+    /// 新浪的币种对代码：fx_s + 小写的源币种和目标币种（如 fx_susdcny）
+    fn pair_code(from_currency: &str, to_currency: &str) -> String {
+        format!("fx_s{}{}", from_currency.to_lowercase(), to_currency.to_lowercase())
+    }
+}
+
+impl Default for SinaFxProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FxRateProvider for SinaFxProvider {
+    fn build_request(&self, pairs: &[(String, String)]) -> QuoteRequest {
+        let codes: Vec<String> = pairs
+            .iter()
+            .map(|(from, to)| Self::pair_code(from, to))
+            .collect();
+
+        QuoteRequest {
+            url: format!("{}/list={}", self.base_url, codes.join(",")),
+            // 不带 Referer 会被拒绝访问
+            referer: Some("https://finance.sina.com.cn"),
+        }
+    }
+
+    /// 返回格式示例（每个币种对一行）:
+    /// var hq_str_fx_susdcny="15:59:58,7.2663,7.2673,7.2650,...,7.2668,在岸人民币,...";
+    /// 字段说明: 0=时间, 1=买入价, 2=卖出价, 3=昨收, 8=最新价, 9=名称
+    /// 最新价为空时使用买入价，不支持的币种对返回空字符串
+    fn parse_response(&self, text: &str, pairs: &[(String, String)]) -> Vec<FxRate> {
+        let mut fx_rates = Vec::new();
+
+        for line in text.split(';') {
+            let Some((key, value)) = line.trim().split_once('=') else {
+                continue;
+            };
+            let Some(code) = key.trim().strip_prefix("var hq_str_") else {
+                continue;
+            };
+            let Some((from, to)) = pairs.iter().find(|(from, to)| Self::pair_code(from, to) == code) else {
+                continue;
+            };
+
+            let fields: Vec<&str> = value.trim().trim_matches('"').split(',').collect();
+            let rate = [8, 1]
+                .iter()
+                .filter_map(|index| fields.get(*index))
+                .find_map(|field| field.trim().parse::<f64>().ok().filter(|rate| *rate > 0.0));
+
+            if let Some(fx_rate) = rate.and_then(|rate| FxRate::new(from, to, rate, FxRateSource::Live).ok()) {
+                fx_rates.push(fx_rate);
+            }
+        }
+
+        fx_rates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recorded_fixture() {
+        let provider = SinaFxProvider::new();
+        let pairs = vec![
+            ("USD".to_string(), "CNY".to_string()),
+            ("HKD".to_string(), "CNY".to_string()),
+            ("EUR".to_string(), "CNY".to_string()),
+        ];
+        assert!(provider.build_request(&pairs).url.ends_with("list=fx_susdcny,fx_shkdcny,fx_seurcny"));
+
+        let fx_rates = provider.parse_response(include_str!("fixtures/fx/sina.txt"), &pairs);
+        assert_eq!(fx_rates.len(), 2);
+        // 有最新价时使用最新价，没有时使用买入价
+        assert_eq!((fx_rates[0].from_currency.as_str(), fx_rates[0].rate), ("USD", 7.2668));
+        assert_eq!((fx_rates[1].from_currency.as_str(), fx_rates[1].rate), ("HKD", 0.9305));
+        assert_eq!(fx_rates[1].source, FxRateSource::Live);
+    }
+}
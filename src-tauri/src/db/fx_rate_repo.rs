@@ -0,0 +1,68 @@
+/// 汇率数据访问层
+/// 负责 fx_rates 表的读写，每个币种对只保存一条（手动汇率或最近一次获取的实时汇率）
+
+use rusqlite::{Connection, params};
+use crate::error::Result;
+use crate::models::fx::{FxRate, FxRateSource};
+
+/// 汇率数据仓库
+pub struct FxRateRepository;
+
+impl FxRateRepository {
+    /// 辅助方法：从数据库行映射到 FxRate 对象（无法识别的来源按实时汇率处理）
+    fn map_row_to_fx_rate(row: &rusqlite::Row) -> rusqlite::Result<FxRate> {
+        Ok(FxRate {
+            from_currency: row.get(0)?,
+            to_currency: row.get(1)?,
+            rate: row.get(2)?,
+            as_of: row.get(3)?,
+            source: FxRateSource::parse(&row.get::<_, String>(4)?).unwrap_or(FxRateSource::Live),
+        })
+    }
+
+    /// 保存汇率（同一币种对存在则覆盖）
+    ///
+    /// 实时汇率不会覆盖手动汇率，手动汇率删除后才恢复使用实时汇率
+    pub fn upsert(conn: &Connection, fx_rate: &FxRate) -> Result<()> {
+        conn.execute(
+            "INSERT INTO fx_rates (from_currency, to_currency, rate, as_of, source)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT(from_currency, to_currency) DO UPDATE SET
+                rate = excluded.rate,
+                as_of = excluded.as_of,
+                source = excluded.source
+             WHERE excluded.source = 'MANUAL' OR fx_rates.source <> 'MANUAL'",
+            params![
+                &fx_rate.from_currency,
+                &fx_rate.to_currency,
+                fx_rate.rate,
+                &fx_rate.as_of,
+                fx_rate.source.as_str(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 查询所有汇率（按币种对排序）
+    pub fn find_all(conn: &Connection) -> Result<Vec<FxRate>> {
+        let mut stmt = conn.prepare(
+            "SELECT from_currency, to_currency, rate, as_of, source FROM fx_rates
+             ORDER BY from_currency, to_currency",
+        )?;
+
+        let fx_rates = stmt
+            .query_map([], Self::map_row_to_fx_rate)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(fx_rates)
+    }
+
+    /// 删除手动汇率，返回是否存在该记录
+    pub fn delete_manual(conn: &Connection, from_currency: &str, to_currency: &str) -> Result<bool> {
+        let affected_rows = conn.execute(
+            "DELETE FROM fx_rates WHERE from_currency = ? AND to_currency = ? AND source = 'MANUAL'",
+            params![from_currency, to_currency],
+        )?;
+        Ok(affected_rows > 0)
+    }
+}
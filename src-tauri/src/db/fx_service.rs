@@ -0,0 +1,116 @@
+/// 汇率服务
+/// 按手动汇率、缓存、实时汇率的优先级提供组合汇总所需的汇率
+
+use crate::db::{Database, FxRateRepository, QuoteService};
+use crate::db::fx_provider::{FxRateProvider, SinaFxProvider};
+use crate::db::quote_cache_repo::AS_OF_FORMAT;
+use crate::{business_error, error::Result};
+use crate::models::fx::{FxRate, FxRateSource};
+use crate::models::FxRates;
+use std::time::Duration;
+
+/// 实时汇率的缓存有效期
+const FX_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// 汇率服务
+pub struct FxService;
+
+impl FxService {
+    /// 使用默认数据源获取汇率
+    pub async fn fetch_configured_rates(database: &Database, pairs: &[(String, String)]) -> Result<FxRates> {
+        Self::fetch_rates(database, &SinaFxProvider::new(), FX_CACHE_TTL, pairs).await
+    }
+
+    /// 获取交易币种到组合基础货币的成交汇率，用于把外币交易的现金流水折算为基础货币
+    ///
+    /// 币种相同时返回 None；没有可用汇率时返回错误（可先录入手动汇率）
+    pub async fn fetch_trade_rate(database: &Database, currency: &str, base_currency: &str) -> Result<Option<f64>> {
+        if currency == base_currency {
+            return Ok(None);
+        }
+
+        let pairs = vec![(currency.to_string(), base_currency.to_string())];
+        let rate = Self::fetch_configured_rates(database, &pairs).await?.rate(currency, base_currency);
+        match rate {
+            Some(rate) => Ok(Some(rate)),
+            None => Err(business_error!(
+                "没有 {}/{} 汇率，无法将交易金额折算为组合基础货币，请先录入手动汇率",
+                currency,
+                base_currency
+            )),
+        }
+    }
+
+    /// 获取币种对的汇率
+    ///
+    /// - 手动汇率优先，不请求网络
+    /// - 实时汇率的缓存未超过有效期时直接使用
+    /// - 其余币种对从数据源获取，成功后写入缓存
+    /// - 获取失败时使用缓存中最近一次的汇率，都没有的币种对在返回的汇率表中查不到
+    pub async fn fetch_rates(
+        database: &Database,
+        provider: &dyn FxRateProvider,
+        cache_ttl: Duration,
+        pairs: &[(String, String)],
+    ) -> Result<FxRates> {
+        let now = chrono::Local::now().naive_local();
+        let stored = FxRateRepository::find_all(&*database.acquire().await?)?;
+
+        let usable = FxRates::new(
+            Self::prefer_manual(&stored)
+                .into_iter()
+                .filter(|fx_rate| fx_rate.source == FxRateSource::Manual || !Self::is_expired(fx_rate, now, cache_ttl)),
+        );
+        let mut to_fetch: Vec<(String, String)> = Vec::new();
+        for (from, to) in pairs {
+            let pair = (from.clone(), to.clone());
+            if usable.rate(from, to).is_none() && !to_fetch.contains(&pair) {
+                to_fetch.push(pair);
+            }
+        }
+
+        if !to_fetch.is_empty() {
+            // 请求网络期间不占用数据库连接
+            let request = provider.build_request(&to_fetch);
+            match QuoteService::fetch_batch_with_retry(&request).await {
+                Ok(text) => {
+                    let fetched = provider.parse_response(&text, &to_fetch);
+                    let conn = database.acquire().await?;
+                    for fx_rate in &fetched {
+                        FxRateRepository::upsert(&conn, fx_rate)?;
+                    }
+                    println!("💱 获取汇率 {} 个，请求 {} 个", fetched.len(), to_fetch.len());
+                }
+                Err(e) => println!("⚠️  获取汇率失败: {}，使用最近一次的汇率", e),
+            }
+        }
+
+        let stored = FxRateRepository::find_all(&*database.acquire().await?)?;
+        Ok(FxRates::new(Self::prefer_manual(&stored)))
+    }
+
+    /// 同一币种对（含反向）有手动汇率时忽略实时汇率
+    fn prefer_manual(fx_rates: &[FxRate]) -> Vec<FxRate> {
+        let is_manual = |from: &str, to: &str| {
+            fx_rates.iter().any(|fx_rate| {
+                fx_rate.source == FxRateSource::Manual
+                    && ((fx_rate.from_currency == from && fx_rate.to_currency == to)
+                        || (fx_rate.from_currency == to && fx_rate.to_currency == from))
+            })
+        };
+
+        fx_rates
+            .iter()
+            .filter(|fx_rate| fx_rate.source == FxRateSource::Manual || !is_manual(&fx_rate.from_currency, &fx_rate.to_currency))
+            .cloned()
+            .collect()
+    }
+
+    /// 缓存是否已超过有效期（时间无法解析时视为过期）
+    fn is_expired(fx_rate: &FxRate, now: chrono::NaiveDateTime, cache_ttl: Duration) -> bool {
+        match chrono::NaiveDateTime::parse_from_str(&fx_rate.as_of, AS_OF_FORMAT) {
+            Ok(as_of) => (now - as_of).to_std().map(|age| age >= cache_ttl).unwrap_or(cache_ttl.is_zero()),
+            Err(_) => true,
+        }
+    }
+}
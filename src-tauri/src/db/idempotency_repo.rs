@@ -0,0 +1,154 @@
+/// 幂等键数据访问层
+/// 记录带幂等键的写操作结果，前端重试时返回首次执行的结果而不重复执行
+
+use rusqlite::{Connection, Transaction, params, OptionalExtension};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use crate::{business_error, error::Result};
+use crate::db::AuditGroup;
+
+/// 幂等键记录（idempotency_keys 表）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IdempotencyRecord {
+    /// 幂等键
+    pub key: String,
+    /// 命令名
+    pub command: String,
+    /// 首次执行的结果（JSON）
+    pub response: String,
+}
+
+/// 幂等键数据仓库
+pub struct IdempotencyRepository;
+
+impl IdempotencyRepository {
+    /// 查找幂等键对应的命令名和已保存的结果
+    pub fn find(conn: &Connection, key: &str) -> Result<Option<IdempotencyRecord>> {
+        let record = conn.query_row(
+            "SELECT key, command, response FROM idempotency_keys WHERE key = ?",
+            [key],
+            |row| Ok(IdempotencyRecord { key: row.get(0)?, command: row.get(1)?, response: row.get(2)? }),
+        ).optional()?;
+
+        Ok(record)
+    }
+
+    /// 保存幂等键及执行结果
+    pub fn save(conn: &Connection, record: &IdempotencyRecord) -> Result<()> {
+        conn.execute(
+            "INSERT INTO idempotency_keys (key, command, response, created_at)
+             VALUES (?, ?, ?, datetime('now', 'localtime'))",
+            params![&record.key, &record.command, &record.response],
+        )?;
+        Ok(())
+    }
+
+    /// 插入或替换幂等键（撤销操作恢复快照时使用）
+    pub fn upsert(conn: &Connection, record: &IdempotencyRecord) -> Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO idempotency_keys (key, command, response, created_at)
+             VALUES (?, ?, ?, datetime('now', 'localtime'))",
+            params![&record.key, &record.command, &record.response],
+        )?;
+        Ok(())
+    }
+
+    /// 查找幂等键对应的首次执行结果（未提供幂等键或幂等键不存在时为 None）
+    ///
+    /// 幂等键已被其他命令使用时返回错误
+    pub fn find_result<T: DeserializeOwned>(conn: &Connection, command: &str, idempotency_key: Option<&str>) -> Result<Option<T>> {
+        let Some(key) = idempotency_key.map(str::trim).filter(|key| !key.is_empty()) else {
+            return Ok(None);
+        };
+        let Some(record) = Self::find(conn, key)? else {
+            return Ok(None);
+        };
+
+        if record.command != command {
+            return Err(business_error!(
+                "幂等键 {} 已被 {} 使用，不能用于 {}",
+                key,
+                record.command,
+                command
+            ));
+        }
+        println!("[Idempotency] {} 命中幂等键 {}，返回首次执行结果", command, key);
+        Ok(Some(serde_json::from_str(&record.response)?))
+    }
+
+    /// 在事务中执行写操作，并按幂等键去重
+    ///
+    /// - 未提供幂等键：仅在事务中执行
+    /// - 幂等键已存在：不再执行，直接返回首次执行的结果
+    /// - 幂等键不存在：执行后在同一事务中保存结果，保证"执行"与"记录"同时生效
+    ///
+    /// 命令名取 audit 分组的操作名称；保存的幂等键也记录到该分组，撤销操作时一并删除，
+    /// 之后使用同一幂等键的请求会重新执行
+    pub fn run_in_transaction<T, F>(
+        conn: &mut Connection,
+        audit: &AuditGroup,
+        idempotency_key: Option<&str>,
+        operation: F,
+    ) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce(&Transaction) -> Result<T>,
+    {
+        let tx = conn.transaction()?;
+
+        if let Some(result) = Self::find_result(&tx, audit.action(), idempotency_key)? {
+            return Ok(result);
+        }
+
+        let result = operation(&tx)?;
+
+        if let Some(key) = idempotency_key.map(str::trim).filter(|key| !key.is_empty()) {
+            let record = IdempotencyRecord {
+                key: key.to_string(),
+                command: audit.action().to_string(),
+                response: serde_json::to_string(&result)?,
+            };
+            Self::save(&tx, &record)?;
+            audit.record_idempotency_key(&tx, None, Some(&record))?;
+        }
+
+        tx.commit()?;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    #[test]
+    fn test_retry_with_same_key_runs_once() {
+        let database = Database::open_in_memory().unwrap();
+        let mut conn = database.get().unwrap();
+        conn.execute("CREATE TABLE counter (n INTEGER NOT NULL)", []).unwrap();
+        conn.execute("INSERT INTO counter (n) VALUES (0)", []).unwrap();
+
+        let increment = |tx: &Transaction| -> Result<i32> {
+            tx.execute("UPDATE counter SET n = n + 1", [])?;
+            Ok(tx.query_row("SELECT n FROM counter", [], |row| row.get(0))?)
+        };
+
+        let first = IdempotencyRepository::run_in_transaction(&mut conn, &AuditGroup::new("reduce_position"), Some("k1"), increment).unwrap();
+        let retried = IdempotencyRepository::run_in_transaction(&mut conn, &AuditGroup::new("reduce_position"), Some("k1"), increment).unwrap();
+        assert_eq!(first, 1);
+        assert_eq!(retried, 1);
+
+        // 不同命令复用同一幂等键会被拒绝
+        assert!(IdempotencyRepository::run_in_transaction(&mut conn, &AuditGroup::new("close_position"), Some("k1"), increment).is_err());
+
+        // 操作失败时事务回滚，幂等键不会被记录
+        let failed = IdempotencyRepository::run_in_transaction(&mut conn, &AuditGroup::new("reduce_position"), Some("k2"), |tx: &Transaction| -> Result<i32> {
+            tx.execute("UPDATE counter SET n = n + 1", [])?;
+            Err(business_error!("模拟失败"))
+        });
+        assert!(failed.is_err());
+        assert!(IdempotencyRepository::find(&conn, "k2").unwrap().is_none());
+        let n: i32 = conn.query_row("SELECT n FROM counter", [], |row| row.get(0)).unwrap();
+        assert_eq!(n, 1);
+    }
+}
@@ -0,0 +1,62 @@
+/// 手动价格数据访问层
+/// 负责 manual_prices 表的读写
+
+use rusqlite::{Connection, params};
+use crate::error::Result;
+use crate::models::quote::ManualPrice;
+
+/// 手动价格数据仓库
+pub struct ManualPriceRepository;
+
+impl ManualPriceRepository {
+    /// 辅助方法：从数据库行映射到 ManualPrice 对象
+    fn map_row_to_manual_price(row: &rusqlite::Row) -> rusqlite::Result<ManualPrice> {
+        Ok(ManualPrice {
+            code: row.get(0)?,
+            price: row.get(1)?,
+            price_date: row.get(2)?,
+            pinned: row.get(3)?,
+            updated_at: row.get(4)?,
+        })
+    }
+
+    /// 保存手动价格（同一代码存在则覆盖）
+    pub fn upsert(conn: &Connection, manual_price: &ManualPrice) -> Result<()> {
+        conn.execute(
+            "INSERT INTO manual_prices (code, price, price_date, pinned, updated_at)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT(code) DO UPDATE SET
+                price = excluded.price,
+                price_date = excluded.price_date,
+                pinned = excluded.pinned,
+                updated_at = excluded.updated_at",
+            params![
+                &manual_price.code,
+                manual_price.price,
+                &manual_price.price_date,
+                manual_price.pinned,
+                &manual_price.updated_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 查询所有手动价格（按代码排序）
+    pub fn find_all(conn: &Connection) -> Result<Vec<ManualPrice>> {
+        let mut stmt = conn.prepare(
+            "SELECT code, price, price_date, pinned, updated_at FROM manual_prices ORDER BY code",
+        )?;
+
+        let manual_prices = stmt
+            .query_map([], Self::map_row_to_manual_price)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(manual_prices)
+    }
+
+    /// 删除手动价格，返回是否存在该记录
+    pub fn delete(conn: &Connection, code: &str) -> Result<bool> {
+        let affected_rows = conn.execute("DELETE FROM manual_prices WHERE code = ?", [code])?;
+        Ok(affected_rows > 0)
+    }
+}
@@ -3,11 +3,49 @@
 
 pub mod position_repo;
 pub mod quote_service;
+pub mod quote_provider;
+pub mod quote_cache_repo;
+pub mod manual_price_repo;
+pub mod fx_rate_repo;
+pub mod fx_provider;
+pub mod fx_service;
+pub mod daily_price_repo;
+pub mod snapshot_repo;
+pub mod snapshot_service;
+pub mod return_service;
+pub mod risk_service;
+pub mod benchmark_service;
 pub mod portfolio_service;
 pub mod closed_trade_service;
+pub mod settings_repo;
+pub mod portfolio_repo;
+pub mod cash_repo;
+pub mod corporate_action_service;
+pub mod trade_service;
+pub mod idempotency_repo;
+pub mod audit_service;
+pub mod pool;
 
 // 重新导出
 pub use position_repo::*;
 pub use quote_service::QuoteService;
+pub use quote_cache_repo::QuoteCacheRepository;
+pub use manual_price_repo::ManualPriceRepository;
+pub use fx_rate_repo::FxRateRepository;
+pub use fx_service::FxService;
+pub use daily_price_repo::DailyPriceRepository;
+pub use snapshot_repo::SnapshotRepository;
+pub use snapshot_service::SnapshotService;
+pub use return_service::ReturnService;
+pub use risk_service::RiskService;
+pub use benchmark_service::BenchmarkService;
 pub use portfolio_service::PortfolioService;
-pub use closed_trade_service::ClosedTradeService;
\ No newline at end of file
+pub use closed_trade_service::ClosedTradeService;
+pub use settings_repo::SettingsRepository;
+pub use portfolio_repo::PortfolioRepository;
+pub use cash_repo::CashRepository;
+pub use corporate_action_service::CorporateActionService;
+pub use trade_service::TradeService;
+pub use idempotency_repo::IdempotencyRepository;
+pub use audit_service::{AuditGroup, AuditService};
+pub use pool::Database;
\ No newline at end of file
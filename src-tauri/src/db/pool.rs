@@ -0,0 +1,344 @@
+/// 数据库连接管理
+/// 应用启动时初始化一次（迁移、建表、索引），之后由连接池向各命令提供连接
+/// 通过 tauri::Builder::manage 注入，命令中以 State<'_, Database> 获取
+
+use rusqlite::Connection;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+use crate::{db_error, error::Result};
+use crate::migration::MigrationReport;
+
+/// 文件数据库的默认连接数上限
+const DEFAULT_POOL_SIZE: usize = 4;
+
+/// 获取连接的最长等待时间
+const ACQUIRE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 数据库被其他连接锁定时的重试等待时间
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 获取数据库路径
+/// 使用平台特定的应用数据目录，确保符合各平台的标准规范
+pub fn default_db_path() -> PathBuf {
+    let app_data_dir = if cfg!(windows) {
+        // Windows: 使用 %APPDATA%\InvestmentTracker
+        // 如果 APPDATA 不存在，回退到 USERPROFILE\AppData\Roaming\InvestmentTracker
+        let appdata = std::env::var("APPDATA")
+            .or_else(|_| {
+                std::env::var("USERPROFILE")
+                    .map(|home| format!("{}\\AppData\\Roaming", home))
+            })
+            .unwrap_or_else(|_| ".".to_string());
+        PathBuf::from(appdata).join("InvestmentTracker")
+    } else if cfg!(target_os = "macos") {
+        // macOS: 使用 ~/Library/Application Support/InvestmentTracker
+        // 优先使用 HOME，如果不存在则回退到 USERPROFILE（某些特殊环境可能只有 USERPROFILE）
+        let home = std::env::var("HOME")
+            .or_else(|_| std::env::var("USERPROFILE"))
+            .unwrap_or_else(|_| ".".to_string());
+        PathBuf::from(home)
+            .join("Library")
+            .join("Application Support")
+            .join("InvestmentTracker")
+    } else {
+        // Linux 和其他 Unix 系统: 使用 ~/.local/share/InvestmentTracker
+        let home = std::env::var("HOME")
+            .unwrap_or_else(|_| ".".to_string());
+        PathBuf::from(home)
+            .join(".local")
+            .join("share")
+            .join("InvestmentTracker")
+    };
+
+    app_data_dir.join("positions.db")
+}
+
+/// 连接池内部状态
+struct PoolState {
+    /// 空闲连接
+    idle: Vec<Connection>,
+    /// 已打开的连接总数（空闲 + 使用中）
+    open: usize,
+}
+
+/// 连接池（借出的连接持有其引用，以便在阻塞线程池中等待连接）
+struct Pool {
+    /// 数据库文件路径，None 表示内存数据库
+    path: Option<PathBuf>,
+    /// 连接数上限
+    max_size: usize,
+    state: Mutex<PoolState>,
+    available: Condvar,
+}
+
+/// 已初始化的数据库句柄
+pub struct Database {
+    /// 启动时的迁移报告
+    migration_report: MigrationReport,
+    pool: Arc<Pool>,
+}
+
+impl Database {
+    /// 打开数据库文件并完成初始化
+    ///
+    /// 启用 WAL 模式，使读操作不会被写事务阻塞
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        println!("[Init DB] 数据库路径: {:?}", path);
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                db_error!("创建数据库目录失败: {:?}, 错误: {}", parent, e)
+            })?;
+        }
+
+        let conn = Self::connect(Some(&path))?;
+        let journal_mode: String = conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+        println!("[Init DB] 日志模式: {}", journal_mode);
+        conn.execute_batch("PRAGMA synchronous = NORMAL")?;
+
+        let migration_report = Self::initialize(&conn)?;
+        Ok(Self::with_connection(Some(path), DEFAULT_POOL_SIZE, conn, migration_report))
+    }
+
+    /// 打开内存数据库并完成初始化（用于测试）
+    ///
+    /// 内存数据库只存在于单个连接中，因此连接池大小固定为 1
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self> {
+        let conn = Self::connect(None)?;
+        let migration_report = Self::initialize(&conn)?;
+        Ok(Self::with_connection(None, 1, conn, migration_report))
+    }
+
+    /// 从连接池获取一个连接，连接用完后自动归还
+    ///
+    /// 连接都在使用中且已达上限时阻塞当前线程等待归还，超时返回错误。
+    /// 异步命令中应使用 acquire，避免占用异步运行时的工作线程
+    pub fn get(&self) -> Result<PooledConnection> {
+        self.pool.get()
+    }
+
+    /// 在异步命令中获取连接
+    ///
+    /// 有空闲连接或未达上限时直接返回；需要等待归还时在阻塞线程池中等待
+    pub async fn acquire(&self) -> Result<PooledConnection> {
+        if let Some(conn) = self.pool.try_get()? {
+            return Ok(conn);
+        }
+
+        let pool = Arc::clone(&self.pool);
+        tokio::task::spawn_blocking(move || pool.get())
+            .await
+            .map_err(|e| db_error!("获取数据库连接失败: {}", e))?
+    }
+
+    /// 启动时的迁移报告
+    pub fn migration_report(&self) -> &MigrationReport {
+        &self.migration_report
+    }
+
+    fn with_connection(path: Option<PathBuf>, max_size: usize, conn: Connection, migration_report: MigrationReport) -> Self {
+        Self {
+            migration_report,
+            pool: Arc::new(Pool {
+                path,
+                max_size,
+                state: Mutex::new(PoolState { idle: vec![conn], open: 1 }),
+                available: Condvar::new(),
+            }),
+        }
+    }
+
+    /// 打开新连接并设置连接级参数
+    fn connect(path: Option<&Path>) -> Result<Connection> {
+        let conn = match path {
+            Some(path) => Connection::open(path),
+            None => Connection::open_in_memory(),
+        }
+        .map_err(|e| db_error!("连接数据库失败: {:?}, 错误: {}", path, e))?;
+
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        Ok(conn)
+    }
+
+    /// 执行迁移、建表并创建索引，只在启动时执行一次
+    fn initialize(conn: &Connection) -> Result<MigrationReport> {
+        println!("[Init DB] 开始初始化数据库...");
+
+        // 执行所有数据库迁移（按 schema_version 记录只执行未执行过的版本）
+        // 注意：对于全新数据库，各迁移会跳过持仓表相关的变更，持仓表随后按最新结构创建
+        let report = crate::migration::run_migrations(conn)
+            .map_err(|e| db_error!("数据库迁移失败: {}", e))?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS positions (
+                id TEXT PRIMARY KEY,
+                code TEXT NOT NULL,
+                name TEXT NOT NULL,
+                buy_price REAL NOT NULL,
+                buy_date TEXT NOT NULL,
+                quantity INTEGER NOT NULL,
+                status TEXT NOT NULL DEFAULT 'POSITION',
+                portfolio TEXT,
+                sell_price REAL,
+                sell_date TEXT,
+                parent_id TEXT,
+                buy_fee REAL NOT NULL DEFAULT 0,
+                sell_fee REAL,
+                market TEXT,
+                currency TEXT NOT NULL DEFAULT 'CNY',
+                buy_fx_rate REAL
+            )",
+            [],
+        ).map_err(|e| db_error!("创建表失败: {}", e))?;
+
+        conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_code ON positions(code);
+             CREATE INDEX IF NOT EXISTS idx_status ON positions(status);
+             CREATE INDEX IF NOT EXISTS idx_parent_id ON positions(parent_id);",
+        ).map_err(|e| db_error!("创建索引失败: {}", e))?;
+
+        println!("[Init DB] ✅ 数据库初始化完成");
+        Ok(report)
+    }
+}
+
+impl Pool {
+    /// 获取连接，需要时阻塞等待归还
+    fn get(self: &Arc<Self>) -> Result<PooledConnection> {
+        let mut state = self.lock_state();
+
+        loop {
+            if let Some(conn) = state.idle.pop() {
+                return Ok(self.lend(conn));
+            }
+
+            if state.open < self.max_size {
+                state.open += 1;
+                drop(state);
+                return self.open_new();
+            }
+
+            let (guard, timeout) = self.available
+                .wait_timeout(state, ACQUIRE_TIMEOUT)
+                .unwrap_or_else(|e| e.into_inner());
+            state = guard;
+
+            if timeout.timed_out() && state.idle.is_empty() {
+                return Err(db_error!("获取数据库连接超时，所有连接均在使用中"));
+            }
+        }
+    }
+
+    /// 不等待地获取连接，连接都在使用中且已达上限时返回 None
+    fn try_get(self: &Arc<Self>) -> Result<Option<PooledConnection>> {
+        let mut state = self.lock_state();
+
+        if let Some(conn) = state.idle.pop() {
+            return Ok(Some(self.lend(conn)));
+        }
+        if state.open < self.max_size {
+            state.open += 1;
+            drop(state);
+            return self.open_new().map(Some);
+        }
+        Ok(None)
+    }
+
+    /// 打开新连接（调用前已计入 open），失败时释放名额
+    fn open_new(self: &Arc<Self>) -> Result<PooledConnection> {
+        match Database::connect(self.path.as_deref()) {
+            Ok(conn) => Ok(self.lend(conn)),
+            Err(e) => {
+                self.lock_state().open -= 1;
+                self.available.notify_one();
+                Err(e)
+            }
+        }
+    }
+
+    fn lend(self: &Arc<Self>, conn: Connection) -> PooledConnection {
+        PooledConnection { pool: Arc::clone(self), conn: Some(conn) }
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn release(&self, conn: Connection) {
+        self.lock_state().idle.push(conn);
+        self.available.notify_one();
+    }
+}
+
+/// 从连接池借出的连接，离开作用域时归还
+pub struct PooledConnection {
+    pool: Arc<Pool>,
+    conn: Option<Connection>,
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("连接已归还")
+    }
+}
+
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.conn.as_mut().expect("连接已归还")
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.release(conn);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_memory_database_is_initialized_and_reused() {
+        let database = Database::open_in_memory().unwrap();
+
+        {
+            let conn = database.get().unwrap();
+            conn.execute(
+                "INSERT INTO positions (id, code, name, buy_price, buy_date, quantity, portfolio)
+                 VALUES ('p1', 'sh600000', '浦发银行', 10.0, '2024-01-02', 100, '默认')",
+                [],
+            ).unwrap();
+        }
+
+        // 归还后再次获取的是同一个内存数据库
+        let conn = database.get().unwrap();
+        let count: i32 = conn.query_row("SELECT COUNT(*) FROM positions", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waits_without_blocking_the_runtime() {
+        let database = Database::open_in_memory().unwrap();
+        let held = database.get().unwrap();
+
+        // 唯一的连接被占用时，acquire 在阻塞线程池中等待，运行时仍能执行归还连接的任务
+        let release = async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            drop(held);
+        };
+        let (acquired, _) = tokio::join!(database.acquire(), release);
+
+        let conn = acquired.unwrap();
+        let count: i32 = conn.query_row("SELECT COUNT(*) FROM positions", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 0);
+    }
+}
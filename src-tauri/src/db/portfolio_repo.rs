@@ -0,0 +1,260 @@
+/// 投资组合设置数据访问层
+/// 负责 portfolios 表的增删改查及重命名级联
+
+use rusqlite::{Connection, params, OptionalExtension};
+use std::collections::HashMap;
+use crate::{db_error, error::Result};
+use crate::models::portfolio::{Portfolio, validate_name, DEFAULT_BASE_CURRENCY};
+
+/// 投资组合数据仓库
+pub struct PortfolioRepository;
+
+impl PortfolioRepository {
+    /// 辅助方法：从数据库行映射到 Portfolio 对象
+    fn map_row_to_portfolio(row: &rusqlite::Row) -> rusqlite::Result<Portfolio> {
+        Ok(Portfolio {
+            name: row.get(0)?,
+            full_position: row.get(1)?,
+            base_currency: row.get(2)?,
+            description: row.get(3)?,
+            created_at: row.get(4)?,
+            archived: row.get::<_, i32>(5)? != 0,
+            benchmark: row.get(6)?,
+        })
+    }
+
+    /// 创建投资组合
+    pub fn create(conn: &Connection, portfolio: &Portfolio) -> Result<()> {
+        portfolio.validate()?;
+
+        let affected_rows = conn.execute(
+            "INSERT INTO portfolios (name, full_position, base_currency, description, created_at, archived, benchmark)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![
+                &portfolio.name,
+                portfolio.full_position,
+                &portfolio.base_currency,
+                &portfolio.description,
+                &portfolio.created_at,
+                portfolio.archived as i32,
+                &portfolio.benchmark,
+            ],
+        )?;
+
+        if affected_rows == 0 {
+            return Err(db_error!("创建投资组合失败"));
+        }
+
+        Ok(())
+    }
+
+    /// 确保组合存在（不存在时按默认设置创建）
+    pub fn ensure_exists(conn: &Connection, name: &str) -> Result<Portfolio> {
+        if let Some(portfolio) = Self::find_by_name(conn, name)? {
+            return Ok(portfolio);
+        }
+
+        let portfolio = Portfolio::new(name.to_string());
+        Self::create(conn, &portfolio)?;
+        println!("✅ 自动创建投资组合: {}", name);
+
+        Ok(portfolio)
+    }
+
+    /// 根据名称查找投资组合
+    pub fn find_by_name(conn: &Connection, name: &str) -> Result<Option<Portfolio>> {
+        let portfolio = conn.query_row(
+            "SELECT name, full_position, base_currency, description, created_at, archived, benchmark
+             FROM portfolios
+             WHERE name = ?",
+            [name],
+            Self::map_row_to_portfolio,
+        ).optional()?;
+
+        Ok(portfolio)
+    }
+
+    /// 投资组合的基础货币（还没有组合设置时为默认的人民币）
+    pub fn base_currency(conn: &Connection, name: &str) -> Result<String> {
+        Ok(Self::find_by_name(conn, name)?
+            .map(|portfolio| portfolio.base_currency)
+            .unwrap_or_else(|| DEFAULT_BASE_CURRENCY.to_string()))
+    }
+
+    /// 获取所有投资组合
+    pub fn find_all(conn: &Connection, include_archived: bool) -> Result<Vec<Portfolio>> {
+        let mut stmt = conn.prepare(
+            "SELECT name, full_position, base_currency, description, created_at, archived, benchmark
+             FROM portfolios
+             WHERE archived = 0 OR ?
+             ORDER BY name"
+        )?;
+
+        let portfolios = stmt.query_map([include_archived], Self::map_row_to_portfolio)?
+            .collect::<rusqlite::Result<Vec<Portfolio>>>()?;
+
+        Ok(portfolios)
+    }
+
+    /// 获取组合名称 -> 组合设置的映射（包含已归档组合）
+    pub fn find_all_as_map(conn: &Connection) -> Result<HashMap<String, Portfolio>> {
+        let portfolios = Self::find_all(conn, true)?;
+        Ok(portfolios.into_iter().map(|p| (p.name.clone(), p)).collect())
+    }
+
+    /// 更新组合设置（不含名称和归档状态）
+    pub fn update(conn: &Connection, portfolio: &Portfolio) -> Result<bool> {
+        portfolio.validate()?;
+
+        let affected_rows = conn.execute(
+            "UPDATE portfolios SET full_position = ?, base_currency = ?, description = ?, benchmark = ? WHERE name = ?",
+            params![
+                portfolio.full_position,
+                &portfolio.base_currency,
+                &portfolio.description,
+                &portfolio.benchmark,
+                &portfolio.name,
+            ],
+        )?;
+
+        Ok(affected_rows > 0)
+    }
+
+    /// 重命名组合，并级联更新 positions、cash_transactions、corporate_action_entries
+    /// 和 portfolio_snapshots 中的组合名称
+    ///
+    /// 注意：调用方应在事务中执行，保证各表同时更新
+    pub fn rename(conn: &Connection, old_name: &str, new_name: &str) -> Result<usize> {
+        validate_name(new_name)?;
+
+        conn.execute(
+            "UPDATE portfolios SET name = ? WHERE name = ?",
+            params![new_name, old_name],
+        )?;
+
+        let affected_positions = conn.execute(
+            "UPDATE positions SET portfolio = ? WHERE portfolio = ?",
+            params![new_name, old_name],
+        )?;
+
+        conn.execute(
+            "UPDATE cash_transactions SET portfolio = ? WHERE portfolio = ?",
+            params![new_name, old_name],
+        )?;
+
+        conn.execute(
+            "UPDATE corporate_action_entries SET portfolio = ? WHERE portfolio = ?",
+            params![new_name, old_name],
+        )?;
+
+        conn.execute(
+            "UPDATE portfolio_snapshots SET portfolio = ? WHERE portfolio = ?",
+            params![new_name, old_name],
+        )?;
+
+        Ok(affected_positions)
+    }
+
+    /// 设置归档状态
+    pub fn set_archived(conn: &Connection, name: &str, archived: bool) -> Result<bool> {
+        let affected_rows = conn.execute(
+            "UPDATE portfolios SET archived = ? WHERE name = ?",
+            params![archived as i32, name],
+        )?;
+
+        Ok(affected_rows > 0)
+    }
+
+    /// 统计组合中仍在持仓的记录数
+    pub fn count_open_positions(conn: &Connection, name: &str) -> Result<i32> {
+        let count: i32 = conn.query_row(
+            "SELECT COUNT(*) FROM positions WHERE portfolio = ? AND status = 'POSITION'",
+            [name],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{CashRepository, Database, PositionRepository};
+    use crate::models::{CashTransaction, Position};
+
+    #[test]
+    fn test_rename_cascades_to_every_portfolio_column() {
+        let database = Database::open_in_memory().unwrap();
+        let conn = database.get().unwrap();
+
+        let mut portfolio = Portfolio::new("价值组合".to_string());
+        portfolio.full_position = 100000.0;
+        PortfolioRepository::create(&conn, &portfolio).unwrap();
+
+        let position = Position::new(
+            "600519".to_string(),
+            "贵州茅台".to_string(),
+            100.0,
+            "2025-01-01".to_string(),
+            100,
+            "价值组合".to_string(),
+        );
+        let other = Position::new(
+            "000001".to_string(),
+            "平安银行".to_string(),
+            10.0,
+            "2025-01-01".to_string(),
+            100,
+            "其他".to_string(),
+        );
+        PositionRepository::create(&conn, &position).unwrap();
+        PositionRepository::create(&conn, &other).unwrap();
+        CashRepository::create(&conn, &CashTransaction::for_buy(&position)).unwrap();
+        conn.execute(
+            "INSERT INTO corporate_action_entries (id, action_id, position_id, portfolio, code, quantity_before, quantity_after, buy_price_before, buy_price_after)
+             VALUES ('e', 'a', ?, '价值组合', 'sh600519', 100, 100, 100.0, 100.0)",
+            [&position.id],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO portfolio_snapshots (portfolio, snapshot_date, base_currency, market_value, position_cost, cash_balance, total_equity, profit_loss, today_profit_loss, created_at)
+             VALUES ('价值组合', '2025-01-02', 'CNY', 0, 0, 0, 0, 0, 0, '2025-01-02 15:00:00')",
+            [],
+        ).unwrap();
+
+        let affected = PortfolioRepository::rename(&conn, "价值组合", "红利组合").unwrap();
+        assert_eq!(affected, 1);
+
+        let renamed = PortfolioRepository::find_by_name(&conn, "红利组合").unwrap().unwrap();
+        assert_eq!(renamed.full_position, 100000.0);
+        assert!(PortfolioRepository::find_by_name(&conn, "价值组合").unwrap().is_none());
+
+        // 所有带 portfolio 字段的表都不再引用旧名称
+        let tables: Vec<String> = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table'")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        let mut checked = 0;
+        for table in tables {
+            let has_portfolio: bool = conn
+                .query_row(&format!("SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = 'portfolio'", table), [], |row| row.get::<_, i32>(0))
+                .unwrap() > 0;
+            if !has_portfolio {
+                continue;
+            }
+            let (old, new): (i32, i32) = conn
+                .query_row(
+                    &format!("SELECT SUM(portfolio = '价值组合'), SUM(portfolio = '红利组合') FROM {}", table),
+                    [],
+                    |row| Ok((row.get::<_, Option<i32>>(0)?.unwrap_or(0), row.get::<_, Option<i32>>(1)?.unwrap_or(0))),
+                )
+                .unwrap();
+            assert_eq!(old, 0, "{} 中仍有旧组合名称", table);
+            assert_eq!(new, 1, "{} 中没有新组合名称", table);
+            checked += 1;
+        }
+        assert_eq!(checked, 4);
+    }
+}
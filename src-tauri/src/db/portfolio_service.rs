@@ -2,28 +2,229 @@
 /// 对应 Java 版本的 PortfolioService
 /// 负责将持仓数据聚合为投资组合视图
 
-use crate::models::{Position, PositionProfitLoss, TargetProfitLoss, PortfolioProfitLoss, RealQuote};
+use crate::db::{Database, PositionRepository, PortfolioRepository, CashRepository, CorporateActionService, ManualPriceRepository, QuoteService, FxService, ReturnService};
+use crate::models::{Position, PositionProfitLoss, TargetProfitLoss, PortfolioProfitLoss, ProfitLossView, RealQuote, Portfolio, FxRates};
+use crate::models::portfolio::{DEFAULT_BASE_CURRENCY, DEFAULT_FULL_POSITION};
+use crate::models::quote::QuoteOutcome;
+use crate::models::returns::ReturnMetrics;
 use crate::error::Result;
 use std::collections::HashMap;
 
-/// 满仓金额常量（50000）
-const FULL_POSITION: f64 = 50000.0;
-
 /// 投资组合聚合服务
 pub struct PortfolioService;
 
 impl PortfolioService {
+    /// 读取持仓并获取行情、汇率，生成所有投资组合的完整盈亏视图
+    ///
+    /// 手动价格优先级低于实时行情，固定（pinned）时高于实时行情（price_source 为 MANUAL）
+    ///
+    /// 没有获取到价格的股票仍会出现在视图中（real_price 为 None，按买入价估值），
+    /// 并在 missing_codes 中列出，is_partial 标记数据不完整
+    ///
+    /// 组合汇总按各组合的基础货币计算，缺少汇率的外币持仓不计入汇总，并在 missing_fx_rates 中列出
+    ///
+    /// 参数：
+    /// - use_mock: 使用模拟行情（不请求网络）
+    pub async fn build_profit_loss_view(database: &Database, use_mock: bool) -> Result<ProfitLossView> {
+        let conn = match database.acquire().await {
+            Ok(conn) => {
+                println!("[PortfolioService] build_profit_loss_view: 数据库连接成功");
+                conn
+            }
+            Err(e) => {
+                eprintln!("[PortfolioService] build_profit_loss_view: 数据库连接失败: {}", e);
+                return Err(e);
+            }
+        };
+
+        // 获取所有未平仓的持仓
+        println!("[PortfolioService] build_profit_loss_view: 开始查询持仓记录...");
+        let positions = match PositionRepository::find_positions(&conn) {
+            Ok(positions) => {
+                println!("[PortfolioService] build_profit_loss_view: 查询到 {} 条持仓记录", positions.len());
+                positions
+            }
+            Err(e) => {
+                eprintln!("[PortfolioService] build_profit_loss_view: 查询持仓记录失败: {}", e);
+                return Err(e);
+            }
+        };
+
+        let positions: Vec<Position> = positions.into_iter()
+            .filter(|p| p.status == "POSITION")
+            .collect();
+
+        println!("[PortfolioService] build_profit_loss_view: 过滤后剩余 {} 条未平仓持仓", positions.len());
+
+        // 如果没有持仓，返回空列表
+        if positions.is_empty() {
+            return Ok(ProfitLossView {
+                portfolios: vec![],
+                is_partial: false,
+                missing_codes: vec![],
+                missing_fx_rates: vec![],
+                overall_returns: ReturnMetrics::default(),
+            });
+        }
+
+        // 读取各组合设置（满仓金额）、现金余额和分红收入
+        let portfolios = PortfolioRepository::find_all_as_map(&conn)?;
+        let cash_balances = CashRepository::balances(&conn)?;
+        let dividends = CorporateActionService::dividend_income_by_position(&conn)?;
+        let manual_prices = ManualPriceRepository::find_all(&conn)?;
+
+        // 获取行情需要等待网络请求，先把连接归还连接池
+        drop(conn);
+
+        // 获取所有需要的股票代码
+        let codes: Vec<String> = positions.iter()
+            .map(|p| p.code.clone())
+            .collect::<std::collections::HashSet<_>>()
+            .into_iter()
+            .collect();
+
+        println!("📦 获取持仓的股票代码列表 (共{}只):", codes.len());
+        for code in &codes {
+            println!("   - {}", code);
+        }
+
+        // 行情代码按持仓记录的市场确定（场外产品和无法识别的代码没有行情，只能使用手动价格）
+        // 固定使用手动价格的股票不需要请求行情
+        let quote_codes: HashMap<String, String> = positions.iter()
+            .filter(|p| !QuoteService::find_manual_price(&manual_prices, &p.code).is_some_and(|m| m.pinned))
+            .filter_map(|p| {
+                p.symbol()
+                    .filter(|symbol| symbol.market.has_quotes())
+                    .map(|symbol| (p.code.clone(), symbol.to_string()))
+            })
+            .collect();
+        let codes_to_fetch: Vec<String> = quote_codes.values()
+            .cloned()
+            .collect::<std::collections::HashSet<_>>()
+            .into_iter()
+            .collect();
+
+        // 获取价格（按行情代码）
+        let fetched = if use_mock {
+            // 强制使用模拟数据
+            println!("使用模拟数据（用户指定）");
+            QuoteService::mock_quotes(codes_to_fetch)
+        } else {
+            // 优先使用缓存，网络失败时使用最后已知价格
+            // 仍没有价格的股票不使用模拟价格代替，在视图中标记为缺少价格
+            println!("尝试获取实时价格...");
+            QuoteService::fetch_configured_quotes(database, &codes_to_fetch)
+                .await?
+                .into_iter()
+                .filter_map(|(code, outcome)| match outcome {
+                    QuoteOutcome::Ok { quote } => Some((code, quote)),
+                    _ => None,
+                })
+                .collect::<HashMap<String, RealQuote>>()
+        };
+
+        // 还原为持仓记录中的代码
+        let mut quotes: HashMap<String, RealQuote> = quote_codes.iter()
+            .filter_map(|(code, quote_code)| {
+                fetched.get(quote_code).map(|quote| (code.clone(), RealQuote { code: code.clone(), ..quote.clone() }))
+            })
+            .collect();
+
+        // 合并手动价格：固定的覆盖行情，其余只补充没有行情的股票
+        QuoteService::apply_manual_prices(&mut quotes, &codes, &manual_prices);
+
+        let mut missing_codes: Vec<String> = codes.into_iter()
+            .filter(|code| !quotes.contains_key(code))
+            .collect();
+        missing_codes.sort();
+
+        if !missing_codes.is_empty() {
+            println!("⚠️  以下股票没有价格，按买入价估值: {:?}", missing_codes);
+        }
+
+        // 获取外币持仓换算为组合基础货币的汇率
+        let mut fx_pairs: Vec<(String, String)> = positions.iter()
+            .map(|p| {
+                let base_currency = portfolios
+                    .get(&p.portfolio)
+                    .map(|portfolio| portfolio.base_currency.clone())
+                    .unwrap_or_else(|| DEFAULT_BASE_CURRENCY.to_string());
+                (p.currency.clone(), base_currency)
+            })
+            .filter(|(currency, base_currency)| currency != base_currency)
+            .collect();
+        fx_pairs.sort();
+        fx_pairs.dedup();
+
+        let fx_rates = if fx_pairs.is_empty() {
+            FxRates::default()
+        } else {
+            FxService::fetch_configured_rates(database, &fx_pairs).await?
+        };
+        let missing_fx_rates: Vec<String> = fx_pairs.iter()
+            .filter(|(currency, base_currency)| fx_rates.rate(currency, base_currency).is_none())
+            .map(|(currency, base_currency)| format!("{}/{}", currency, base_currency))
+            .collect();
+
+        if !missing_fx_rates.is_empty() {
+            println!("⚠️  以下币种对没有汇率，对应持仓不计入组合汇总: {:?}", missing_fx_rates);
+        }
+
+        // 聚合计算
+        println!("[PortfolioService] build_profit_loss_view: 开始聚合计算...");
+        let result = match PortfolioService::aggregate_positions(positions, &quotes, &portfolios, &cash_balances, &dividends, &fx_rates) {
+            Ok(data) => {
+                println!("[PortfolioService] build_profit_loss_view: 聚合计算成功，共 {} 个投资组合", data.len());
+                println!("📊 聚合后的投资组合数据:");
+                for portfolio in &data {
+                    println!("  投资组合: {}", portfolio.portfolio);
+                    for target in &portfolio.target_profit_losses {
+                        println!("    股票: {} {} (当前价: {:?}, 来源: {:?})", target.code, target.name, target.real_price, target.price_source);
+                    }
+                }
+                data
+            }
+            Err(e) => {
+                eprintln!("[PortfolioService] build_profit_loss_view: 聚合计算失败: {}", e);
+                return Err(e);
+            }
+        };
+
+        let mut view = ProfitLossView {
+            portfolios: result,
+            is_partial: !missing_codes.is_empty() || !missing_fx_rates.is_empty(),
+            missing_codes,
+            missing_fx_rates,
+            overall_returns: ReturnMetrics::default(),
+        };
+
+        // 补充组合及合计的收益率（需要已平仓记录、分红流水和历史快照）
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        ReturnService::apply(&*database.acquire().await?, &mut view, &fx_rates, &today)?;
+
+        println!("[PortfolioService] build_profit_loss_view: 成功完成");
+        Ok(view)
+    }
+
     /// 将持仓列表聚合为投资组合汇总
     ///
     /// 参数：
     /// - positions: 所有持仓记录（未平仓）
-    /// - quotes: 实时价格映射表（code -> RealQuote）
+    /// - quotes: 实时价格映射表（code -> RealQuote），没有价格的股票仍会出现在结果中，按买入价估值
+    /// - portfolios: 组合设置映射表（name -> Portfolio），未配置的组合使用默认满仓金额
+    /// - cash_balances: 现金余额映射表（name -> 余额），没有现金流水的组合余额为 0
+    /// - dividends: 分红收入映射表（position_id -> 税后分红累计）
+    /// - fx_rates: 汇率表，外币持仓按组合的基础货币汇总，缺少汇率的股票不计入汇总
     ///
     /// 返回：
     /// - Vec<PortfolioProfitLoss>: 按投资组合分组的汇总数据
     pub fn aggregate_positions(
         positions: Vec<Position>,
         quotes: &HashMap<String, RealQuote>,
+        portfolios: &HashMap<String, Portfolio>,
+        cash_balances: &HashMap<String, f64>,
+        dividends: &HashMap<String, f64>,
+        fx_rates: &FxRates,
     ) -> Result<Vec<PortfolioProfitLoss>> {
         // 按投资组合分组
         let mut portfolio_map: HashMap<String, Vec<Position>> = HashMap::new();
@@ -35,10 +236,23 @@ impl PortfolioService {
                 .push(position);
         }
 
+        // 当日买入的持仓以买入价计算今日盈亏
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+
         // 为每个投资组合生成聚合数据
         let mut result = Vec::new();
 
         for (portfolio_name, positions_in_portfolio) in portfolio_map {
+            let full_position = portfolios
+                .get(&portfolio_name)
+                .map(|p| p.full_position)
+                .unwrap_or(DEFAULT_FULL_POSITION);
+            let base_currency = portfolios
+                .get(&portfolio_name)
+                .map(|p| p.base_currency.clone())
+                .unwrap_or_else(|| DEFAULT_BASE_CURRENCY.to_string());
+            let cash_balance = cash_balances.get(&portfolio_name).copied().unwrap_or(0.0);
+
             // 按股票代码分组
             let mut code_map: HashMap<String, Vec<Position>> = HashMap::new();
 
@@ -53,21 +267,22 @@ impl PortfolioService {
             let mut target_losses = Vec::new();
 
             for (code, positions_by_code) in code_map {
-                println!("🔍 尝试获取股票 '{}' 的价格数据", code);
-                println!("   - quotes HashMap 的所有 keys: {:?}", quotes.keys().collect::<Vec<_>>());
-
-                if let Some(quote) = quotes.get(&code) {
-                    println!("   ✅ 在 HashMap 中找到了价格数据");
-                    let target = Self::create_target_profit_loss(
-                        &code,
-                        quote,
-                        positions_by_code,
-                    )?;
-                    target_losses.push(target);
-                } else {
-                    println!("   ❌ 在 HashMap 中没有找到 '{}' 的价格数据！", code);
-                    println!("   ⚠️  这支股票将被跳过，不会出现在最终结果中");
+                let quote = quotes.get(&code);
+                if quote.is_none() {
+                    println!("   ⚠️  没有股票 '{}' 的价格数据，按买入价估值", code);
                 }
+
+                let target = Self::create_target_profit_loss(
+                    &code,
+                    quote,
+                    positions_by_code,
+                    full_position,
+                    dividends,
+                    &today,
+                    &base_currency,
+                    fx_rates,
+                )?;
+                target_losses.push(target);
             }
 
             // 按持仓成本从高到低排序
@@ -79,7 +294,10 @@ impl PortfolioService {
             // 计算投资组合级汇总
             let portfolio_loss = Self::create_portfolio_profit_loss(
                 portfolio_name,
+                base_currency,
                 target_losses,
+                full_position,
+                cash_balance,
             )?;
 
             result.push(portfolio_loss);
@@ -89,31 +307,82 @@ impl PortfolioService {
     }
 
     /// 为单支股票创建TargetProfitLoss
+    ///
+    /// 没有价格（quote 为 None）时按各笔买入价估值，real_price 和 price_source 为 None
+    ///
+    /// 外币持仓的基础货币金额：成本和价差按各笔买入时汇率折算（没有记录时按当前汇率），
+    /// 市值和分红按当前汇率折算，两种汇率之差带来的市值变化计为汇兑盈亏
+    #[allow(clippy::too_many_arguments)]
     fn create_target_profit_loss(
         code: &str,
-        quote: &RealQuote,
+        quote: Option<&RealQuote>,
         positions: Vec<Position>,
+        full_position: f64,
+        dividends: &HashMap<String, f64>,
+        today: &str,
+        base_currency: &str,
+        fx_rates: &FxRates,
     ) -> Result<TargetProfitLoss> {
+        let real_price = quote.map(|q| q.real_price);
+        let today_quote = quote.filter(|q| q.is_current_on(today));
+        let name = quote
+            .map(|q| q.name.clone())
+            .filter(|name| !name.is_empty())
+            .or_else(|| positions.first().map(|p| p.name.clone()))
+            .unwrap_or_else(|| code.to_string());
+
         println!("📊 聚合股票 {} 的持仓数据:", code);
-        println!("   - 股票名称: {}", quote.name);
-        println!("   - 实时价格: {}", quote.real_price);
+        println!("   - 股票名称: {}", name);
+        println!("   - 实时价格: {:?}", real_price);
         println!("   - 持仓笔数: {}", positions.len());
 
+        let currency = positions
+            .first()
+            .map(|p| p.currency.clone())
+            .unwrap_or_else(|| base_currency.to_string());
+        let fx_rate = fx_rates.rate(&currency, base_currency);
+        if fx_rate.is_none() {
+            println!("   ⚠️  没有 {}/{} 汇率，不计入组合汇总", currency, base_currency);
+        }
+        let current_fx_rate = fx_rate.unwrap_or(0.0);
+
         // 将Position转换为PositionProfitLoss
         let mut position_losses = Vec::new();
         let mut total_cost = 0.0;
-        let mut total_quantity: i32 = 0;
+        let mut current_value = 0.0;
         let mut total_profit_loss = 0.0;
+        let mut total_dividend_income = 0.0;
+        let mut today_profit_loss: Option<f64> = None;
+        let mut base_cost = 0.0;
+        let mut price_profit_loss = 0.0;
+        let mut fx_profit_loss = 0.0;
 
         for position in positions {
+            let dividend_income = dividends.get(&position.id).copied().unwrap_or(0.0);
+            let value = position.quantity as f64 * real_price.unwrap_or(position.buy_price);
+            let buy_fx_rate = match position.buy_fx_rate {
+                Some(rate) if position.currency != base_currency => rate,
+                _ => current_fx_rate,
+            };
+            current_value += value;
             let position_loss = PositionProfitLoss::from_position(
-                position.clone(),
-                quote.real_price,
+                position,
+                real_price,
+                today_quote,
+                dividend_income,
+                today,
             );
 
             total_cost += position_loss.position_cost;
-            total_quantity += position.quantity;
+            total_dividend_income += dividend_income;
             total_profit_loss += position_loss.profit_loss;
+            base_cost += position_loss.position_cost * buy_fx_rate;
+            price_profit_loss += (position_loss.profit_loss - dividend_income) * buy_fx_rate
+                + dividend_income * current_fx_rate;
+            fx_profit_loss += value * (current_fx_rate - buy_fx_rate);
+            if let Some(value) = position_loss.today_profit_loss {
+                today_profit_loss = Some(today_profit_loss.unwrap_or(0.0) + value);
+            }
 
             position_losses.push(position_loss);
         }
@@ -121,17 +390,18 @@ impl PortfolioService {
         // 排序按日期倒序（最新的在前）
         position_losses.sort_by(|a, b| b.buy_date.cmp(&a.buy_date));
 
+        let market_value = current_value * current_fx_rate;
+
         // 计算成本仓位占比
-        let cost_position_rate = if FULL_POSITION != 0.0 {
-            total_cost / FULL_POSITION
+        let cost_position_rate = if full_position != 0.0 {
+            base_cost / full_position
         } else {
             0.0
         };
 
         // 计算当前仓位占比
-        let current_value = quote.real_price * total_quantity as f64;
-        let current_position_rate = if FULL_POSITION != 0.0 {
-            current_value / FULL_POSITION
+        let current_position_rate = if full_position != 0.0 {
+            market_value / full_position
         } else {
             0.0
         };
@@ -154,52 +424,217 @@ impl PortfolioService {
 
         let result = TargetProfitLoss {
             code: code.to_string(),
-            name: quote.name.clone(),
-            real_price: quote.real_price,
+            name,
+            real_price,
+            price_source: quote.map(|q| q.price_source),
+            price_as_of: quote.and_then(|q| q.as_of.clone()),
+            is_stale: quote.map(|q| q.is_stale).unwrap_or(false),
+            prev_close: quote.and_then(|q| q.prev_close),
+            change_percent: quote.and_then(|q| q.change_percent),
+            currency,
+            fx_rate,
             position_profit_losses: position_losses,
             cost_position_rate,
             current_position_rate,
+            target_position_cost: base_cost,
+            target_market_value: market_value,
+            target_price_profit_loss: price_profit_loss,
+            target_fx_profit_loss: fx_profit_loss,
+            target_dividend_income: total_dividend_income,
             target_profit_loss: total_profit_loss,
             target_profit_loss_rate,
+            target_today_profit_loss: today_profit_loss,
+            // 股票收益率需要已平仓记录和分红流水，由 ReturnService 补充
+            returns: ReturnMetrics::default(),
             recommended_buy_in_point,
             recommended_sale_out_point,
         };
 
         println!("   - 聚合结果:");
-        println!("     • 当前价格: {}", result.real_price);
+        println!("     • 当前价格: {:?} ({:?})", result.real_price, result.price_source);
         println!("     • 总盈亏: {}", result.target_profit_loss);
         println!("     • 盈亏比: {:.2}%", result.target_profit_loss_rate * 100.0);
+        println!("     • 今日盈亏: {:?}", result.target_today_profit_loss);
 
         Ok(result)
     }
 
     /// 为投资组合创建PortfolioProfitLoss
+    ///
+    /// 汇总金额均以基础货币计价，缺少汇率的股票不计入
     fn create_portfolio_profit_loss(
         portfolio: String,
+        base_currency: String,
         target_losses: Vec<TargetProfitLoss>,
+        full_position: f64,
+        cash_balance: f64,
     ) -> Result<PortfolioProfitLoss> {
         let mut sum_position_cost = 0.0;
-        let mut sum_profit_losses = 0.0;
+        let mut sum_price_profit_loss = 0.0;
+        let mut sum_fx_profit_loss = 0.0;
+        let mut sum_dividend_income = 0.0;
+        let mut sum_market_value = 0.0;
+        let mut sum_today_profit_loss = 0.0;
 
         for target in &target_losses {
-            // 成本 = 仓位比 × 满仓金额
-            sum_position_cost += target.cost_position_rate * FULL_POSITION;
-            sum_profit_losses += target.target_profit_loss;
+            let Some(fx_rate) = target.fx_rate else {
+                continue;
+            };
+            sum_position_cost += target.target_position_cost;
+            sum_price_profit_loss += target.target_price_profit_loss;
+            sum_fx_profit_loss += target.target_fx_profit_loss;
+            sum_dividend_income += target.target_dividend_income * fx_rate;
+            sum_market_value += target.target_market_value;
+            sum_today_profit_loss += target.target_today_profit_loss.unwrap_or(0.0) * fx_rate;
         }
 
+        let sum_profit_losses = sum_price_profit_loss + sum_fx_profit_loss;
+
         let sum_profit_losses_rate = if sum_position_cost != 0.0 {
             sum_profit_losses / sum_position_cost
         } else {
             0.0
         };
 
+        // 昨日市值 = 当前市值 - 今日盈亏
+        let prev_market_value = sum_market_value - sum_today_profit_loss;
+        let sum_today_profit_loss_rate = if prev_market_value > 0.0 {
+            sum_today_profit_loss / prev_market_value
+        } else {
+            0.0
+        };
+
         Ok(PortfolioProfitLoss {
             portfolio,
-            full_position: FULL_POSITION,
+            base_currency,
+            full_position,
             target_profit_losses: target_losses,
             sum_position_cost,
+            sum_dividend_income,
             sum_profit_losses,
+            sum_price_profit_loss,
+            sum_fx_profit_loss,
             sum_profit_losses_rate,
+            sum_today_profit_loss,
+            sum_today_profit_loss_rate,
+            // 组合收益率需要已平仓记录和历史快照，由 ReturnService 补充
+            returns: ReturnMetrics::default(),
+            sum_market_value,
+            cash_balance,
+            total_equity: cash_balance + sum_market_value,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::quote::PriceSource;
+
+    #[test]
+    fn test_positions_without_price_are_kept() {
+        let positions = vec![
+            Position::new("sh600519".to_string(), "贵州茅台".to_string(), 1800.0, "2025-01-02".to_string(), 100, "组合A".to_string()),
+            Position::new("sz000001".to_string(), "平安银行".to_string(), 10.0, "2025-01-02".to_string(), 1000, "组合A".to_string()),
+        ];
+        let mut quotes = HashMap::new();
+        quotes.insert(
+            "sh600519".to_string(),
+            RealQuote::new("sh600519".to_string(), "贵州茅台".to_string(), 1850.0),
+        );
+
+        let result = PortfolioService::aggregate_positions(positions, &quotes, &HashMap::new(), &HashMap::new(), &HashMap::new(), &FxRates::default()).unwrap();
+        let targets = &result[0].target_profit_losses;
+        assert_eq!(targets.len(), 2);
+
+        let priced = targets.iter().find(|t| t.code == "sh600519").unwrap();
+        assert_eq!(priced.real_price, Some(1850.0));
+        assert_eq!(priced.price_source, Some(PriceSource::Live));
+
+        // 没有价格的股票不再被丢弃，按买入价估值
+        let missing = targets.iter().find(|t| t.code == "sz000001").unwrap();
+        assert_eq!(missing.name, "平安银行");
+        assert_eq!(missing.real_price, None);
+        assert_eq!(missing.price_source, None);
+        assert_eq!(result[0].sum_market_value, 1850.0 * 100.0 + 10.0 * 1000.0);
+    }
+
+    #[test]
+    fn test_today_profit_loss_uses_prev_close_and_buy_price_for_today() {
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let positions = vec![
+            Position::new("sh600519".to_string(), "贵州茅台".to_string(), 1800.0, "2025-01-02".to_string(), 100, "组合A".to_string()),
+            Position::new("sh600519".to_string(), "贵州茅台".to_string(), 1845.0, today, 100, "组合A".to_string()),
+        ];
+        let mut quotes = HashMap::new();
+        quotes.insert(
+            "sh600519".to_string(),
+            RealQuote::new("sh600519".to_string(), "贵州茅台".to_string(), 1850.0).with_prev_close(Some(1840.0)),
+        );
+
+        let result = PortfolioService::aggregate_positions(positions, &quotes, &HashMap::new(), &HashMap::new(), &HashMap::new(), &FxRates::default()).unwrap();
+        let target = &result[0].target_profit_losses[0];
+
+        // 之前买入的按昨收计算：(1850 - 1840) × 100；今天买入的按买入价计算：(1850 - 1845) × 100
+        assert_eq!(target.target_today_profit_loss, Some(1000.0 + 500.0));
+        assert_eq!(result[0].sum_today_profit_loss, 1500.0);
+        assert_eq!(result[0].sum_today_profit_loss_rate, 1500.0 / (1850.0 * 200.0 - 1500.0));
+    }
+
+    #[test]
+    fn test_today_profit_loss_is_none_for_stale_or_old_quotes() {
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let quote = RealQuote::new("sh600519".to_string(), "贵州茅台".to_string(), 1850.0).with_prev_close(Some(1840.0));
+        let stale = RealQuote { is_stale: true, ..quote.clone() };
+        let old = RealQuote { as_of: Some("2025-01-02 15:00:00".to_string()), ..quote.clone() };
+        let current = RealQuote { as_of: Some(format!("{} 10:30:00", today)), ..quote };
+
+        for (quote, expected) in [(stale, None), (old, None), (current, Some(1000.0))] {
+            let positions = vec![
+                Position::new("sh600519".to_string(), "贵州茅台".to_string(), 1800.0, "2025-01-02".to_string(), 100, "组合A".to_string()),
+            ];
+            let quotes = HashMap::from([("sh600519".to_string(), quote)]);
+
+            let result = PortfolioService::aggregate_positions(positions, &quotes, &HashMap::new(), &HashMap::new(), &HashMap::new(), &FxRates::default()).unwrap();
+            assert_eq!(result[0].target_profit_losses[0].target_today_profit_loss, expected);
+        }
+    }
+
+    #[test]
+    fn test_foreign_positions_are_summed_in_base_currency() {
+        let mut aapl = Position::new("usAAPL".to_string(), "苹果".to_string(), 100.0, "2025-01-02".to_string(), 10, "组合A".to_string());
+        aapl.buy_fx_rate = Some(7.0);
+        let positions = vec![
+            aapl,
+            Position::new("sh600519".to_string(), "贵州茅台".to_string(), 1800.0, "2025-01-02".to_string(), 100, "组合A".to_string()),
+            Position::new("hk00700".to_string(), "腾讯控股".to_string(), 300.0, "2025-01-02".to_string(), 100, "组合A".to_string()),
+        ];
+        let mut quotes = HashMap::new();
+        quotes.insert("usAAPL".to_string(), RealQuote::new("usAAPL".to_string(), "苹果".to_string(), 110.0));
+        quotes.insert("sh600519".to_string(), RealQuote::new("sh600519".to_string(), "贵州茅台".to_string(), 1850.0));
+        let fx_rates = FxRates::new(vec![
+            crate::models::FxRate::new("USD", "CNY", 7.2, crate::models::fx::FxRateSource::Live).unwrap(),
+        ]);
+
+        let result = PortfolioService::aggregate_positions(positions, &quotes, &HashMap::new(), &HashMap::new(), &HashMap::new(), &fx_rates).unwrap();
+        let portfolio = &result[0];
+        let aapl = portfolio.target_profit_losses.iter().find(|t| t.code == "usAAPL").unwrap();
+
+        // 价格盈亏按买入汇率折算：(110 - 100) × 10 × 7.0；汇兑盈亏：1100 × (7.2 - 7.0)
+        assert_eq!(aapl.currency, "USD");
+        assert_eq!(aapl.target_profit_loss, 100.0);
+        assert_eq!(aapl.target_position_cost, 7000.0);
+        assert_eq!(aapl.target_price_profit_loss, 700.0);
+        assert!((aapl.target_fx_profit_loss - 220.0).abs() < 1e-9);
+
+        // 没有港币汇率的腾讯不计入组合汇总
+        let tencent = portfolio.target_profit_losses.iter().find(|t| t.code == "hk00700").unwrap();
+        assert_eq!(tencent.fx_rate, None);
+
+        assert_eq!(portfolio.base_currency, "CNY");
+        assert_eq!(portfolio.sum_position_cost, 7000.0 + 180_000.0);
+        assert!((portfolio.sum_market_value - (7920.0 + 185_000.0)).abs() < 1e-9);
+        assert_eq!(portfolio.sum_price_profit_loss, 700.0 + 5000.0);
+        assert!((portfolio.sum_profit_losses - (700.0 + 220.0 + 5000.0)).abs() < 1e-9);
+    }
+}
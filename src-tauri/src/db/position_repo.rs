@@ -4,13 +4,18 @@
 use rusqlite::{Connection, params, OptionalExtension};
 use crate::{db_error, error::{AppError, Result}};
 use crate::models::position::{Position, PositionQuery, PortfolioSummary};
+use crate::models::symbol::Market;
+
+/// 持仓表查询列（顺序与 map_row_to_position 对应）
+pub const POSITION_COLUMNS: &str = "id, code, name, buy_price, buy_date, quantity, status, portfolio,
+                    sell_price, sell_date, parent_id, buy_fee, sell_fee, market, currency, buy_fx_rate";
 
 /// 持仓数据仓库
 pub struct PositionRepository;
 
 impl PositionRepository {
     /// 辅助方法：从数据库行映射到 Position 对象
-    fn map_row_to_position(row: &rusqlite::Row) -> rusqlite::Result<Position> {
+    pub fn map_row_to_position(row: &rusqlite::Row) -> rusqlite::Result<Position> {
         Ok(Position {
             id: row.get(0)?,
             code: row.get(1)?,
@@ -23,6 +28,11 @@ impl PositionRepository {
             sell_price: row.get(8)?,
             sell_date: row.get(9)?,
             parent_id: row.get(10)?,
+            buy_fee: row.get(11)?,
+            sell_fee: row.get(12)?,
+            market: row.get::<_, Option<String>>(13)?.and_then(|market| Market::parse(&market)),
+            currency: row.get(14)?,
+            buy_fx_rate: row.get(15)?,
         })
     }
 
@@ -33,8 +43,8 @@ impl PositionRepository {
 
         let affected_rows = conn.execute(
             "INSERT INTO positions (
-                id, code, name, buy_price, buy_date, quantity, status, portfolio
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                id, code, name, buy_price, buy_date, quantity, status, portfolio, buy_fee, market, currency, buy_fx_rate
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             params![
                 &position.id,
                 &position.code,
@@ -44,6 +54,10 @@ impl PositionRepository {
                 position.quantity,
                 &position.status,
                 &position.portfolio,
+                position.buy_fee,
+                position.market.map(|market| market.as_str()),
+                &position.currency,
+                position.buy_fx_rate,
             ],
         )?;
 
@@ -54,6 +68,36 @@ impl PositionRepository {
         Ok(position.id.clone())
     }
 
+    /// 按完整快照写入持仓记录（存在则覆盖，用于撤销操作时恢复数据）
+    pub fn upsert(conn: &Connection, position: &Position) -> Result<()> {
+        conn.execute(
+            "INSERT OR REPLACE INTO positions (
+                id, code, name, buy_price, buy_date, quantity, status, portfolio,
+                sell_price, sell_date, parent_id, buy_fee, sell_fee, market, currency, buy_fx_rate
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                &position.id,
+                &position.code,
+                &position.name,
+                position.buy_price,
+                &position.buy_date,
+                position.quantity,
+                &position.status,
+                &position.portfolio,
+                position.sell_price,
+                &position.sell_date,
+                &position.parent_id,
+                position.buy_fee,
+                position.sell_fee,
+                position.market.map(|market| market.as_str()),
+                &position.currency,
+                position.buy_fx_rate,
+            ],
+        )?;
+
+        Ok(())
+    }
+
     /// 批量创建持仓记录
     pub fn create_batch(conn: &Connection, positions: &[Position]) -> Result<usize> {
         let mut affected_rows = 0;
@@ -68,12 +112,10 @@ impl PositionRepository {
 
     /// 根据 ID 查找持仓记录
     pub fn find_by_id(conn: &Connection, id: &str) -> Result<Option<Position>> {
-        let mut stmt = conn.prepare(
-            "SELECT id, code, name, buy_price, buy_date, quantity, status, portfolio,
-                    sell_price, sell_date, parent_id
-             FROM positions
-             WHERE id = ?"
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM positions WHERE id = ?",
+            POSITION_COLUMNS
+        ))?;
 
         let position = stmt.query_row(
             [id],
@@ -93,6 +135,56 @@ impl PositionRepository {
         Ok(affected_rows > 0)
     }
 
+    /// 修改持仓记录的基本信息（代码、市场、币种、名称、买入价格、买入日期、数量、组合、买入费用）
+    pub fn update(conn: &Connection, position: &Position) -> Result<bool> {
+        // 验证数据
+        position.validate()?;
+
+        let affected_rows = conn.execute(
+            "UPDATE positions
+             SET code = ?, market = ?, currency = ?, buy_fx_rate = ?, name = ?, buy_price = ?, buy_date = ?, quantity = ?, portfolio = ?, buy_fee = ?
+             WHERE id = ?",
+            params![
+                &position.code,
+                position.market.map(|market| market.as_str()),
+                &position.currency,
+                position.buy_fx_rate,
+                &position.name,
+                position.buy_price,
+                &position.buy_date,
+                position.quantity,
+                &position.portfolio,
+                position.buy_fee,
+                &position.id,
+            ],
+        )?;
+
+        Ok(affected_rows > 0)
+    }
+
+    /// 获取减仓产生的子记录（parent_id 指向指定记录）
+    pub fn find_children(conn: &Connection, parent_id: &str) -> Result<Vec<Position>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM positions WHERE parent_id = ? ORDER BY sell_date",
+            POSITION_COLUMNS
+        ))?;
+
+        let children = stmt.query_map([parent_id], Self::map_row_to_position)?
+            .collect::<rusqlite::Result<Vec<Position>>>()?;
+
+        Ok(children)
+    }
+
+    /// 更新持仓数量和买入价格（送转、拆股、配股后调整）
+    pub fn update_quantity_and_price(conn: &Connection, id: &str, quantity: i32, buy_price: f64) -> Result<bool> {
+        let affected_rows = conn.execute(
+            "UPDATE positions SET quantity = ?, buy_price = ? WHERE id = ?",
+            params![quantity, buy_price, id],
+        )?;
+
+        Ok(affected_rows > 0)
+    }
+
     /// 删除持仓记录
     pub fn delete(conn: &Connection, id: &str) -> Result<bool> {
         let affected_rows = conn.execute("DELETE FROM positions WHERE id = ?", [id])?;
@@ -110,11 +202,11 @@ impl PositionRepository {
         let (where_clause, params) = query.build_where_clause();
 
         let sql = format!(
-            "SELECT id, code, name, buy_price, buy_date, quantity, status, portfolio,
-                    sell_price, sell_date, parent_id
+            "SELECT {}
              FROM positions
              {}
              ORDER BY buy_date DESC",
+            POSITION_COLUMNS,
             where_clause
         );
 
@@ -160,13 +252,13 @@ impl PositionRepository {
 
     /// 获取指定组合中的所有持仓记录（仅状态为 POSITION 的）
     pub fn get_portfolio_positions(conn: &Connection, portfolio: &str) -> Result<Vec<Position>> {
-        let mut stmt = conn.prepare(
-            "SELECT id, code, name, buy_price, buy_date, quantity, status, portfolio,
-                    sell_price, sell_date, parent_id
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
              FROM positions
              WHERE portfolio = ? AND status = 'POSITION'
-             ORDER BY code, buy_date DESC"
-        )?;
+             ORDER BY code, buy_date DESC",
+            POSITION_COLUMNS
+        ))?;
 
         let positions = stmt.query_map([portfolio], |row| {
             Self::map_row_to_position(row)
@@ -215,7 +307,7 @@ impl PositionRepository {
             "SELECT
                 COUNT(*) as record_count,
                 SUM(quantity) as total_quantity,
-                SUM(quantity * buy_price) as total_cost
+                SUM(quantity * buy_price + buy_fee) as total_cost
              FROM positions
              WHERE code = ? AND status = 'POSITION'"
         )?;
@@ -352,7 +444,15 @@ mod tests {
                 buy_date TEXT NOT NULL,
                 quantity INTEGER NOT NULL,
                 status TEXT NOT NULL DEFAULT 'POSITION',
-                portfolio TEXT
+                portfolio TEXT,
+                sell_price REAL,
+                sell_date TEXT,
+                parent_id TEXT,
+                buy_fee REAL NOT NULL DEFAULT 0,
+                sell_fee REAL,
+                market TEXT,
+                currency TEXT NOT NULL DEFAULT 'CNY',
+                buy_fx_rate REAL
             )",
             [],
         ).unwrap();
@@ -365,6 +465,7 @@ mod tests {
             buy_date: "2025-01-01".to_string(),
             quantity: 100,
             portfolio: "股票组合".to_string(),
+            buy_fee: None,
         });
 
         let position2 = Position::from(CreatePositionRequest {
@@ -374,6 +475,7 @@ mod tests {
             buy_date: "2025-01-02".to_string(),
             quantity: 1000,
             portfolio: "股票组合".to_string(),
+            buy_fee: None,
         });
 
         let position3 = Position::from(CreatePositionRequest {
@@ -383,6 +485,7 @@ mod tests {
             buy_date: "2025-01-03".to_string(),
             quantity: 50,
             portfolio: "股票组合".to_string(),
+            buy_fee: None,
         });
 
         // 保存测试数据
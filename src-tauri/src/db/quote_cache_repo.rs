@@ -0,0 +1,94 @@
+/// 行情缓存数据访问层
+/// 按股票代码（带交易所前缀，如 sh600519）保存最近一次获取成功的行情
+
+use rusqlite::{Connection, params, OptionalExtension};
+use crate::error::Result;
+use crate::models::RealQuote;
+use crate::models::quote::PriceSource;
+
+/// 行情时间的保存格式
+pub const AS_OF_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// 行情缓存数据仓库
+pub struct QuoteCacheRepository;
+
+impl QuoteCacheRepository {
+    /// 查询缓存的行情（不存在时返回 None）
+    pub fn find(conn: &Connection, code: &str) -> Result<Option<RealQuote>> {
+        let quote = conn.query_row(
+            "SELECT code, name, last_price, prev_close, as_of, source,
+                    open, high, low, volume, turnover, change_percent, pe_ratio, market_cap
+             FROM quotes WHERE code = ?",
+            [code],
+            |row| {
+                Ok(RealQuote {
+                    code: row.get(0)?,
+                    name: row.get(1)?,
+                    real_price: row.get(2)?,
+                    prev_close: row.get(3)?,
+                    open: row.get(6)?,
+                    high: row.get(7)?,
+                    low: row.get(8)?,
+                    volume: row.get(9)?,
+                    turnover: row.get(10)?,
+                    change_percent: row.get(11)?,
+                    pe_ratio: row.get(12)?,
+                    market_cap: row.get(13)?,
+                    as_of: row.get(4)?,
+                    is_stale: false,
+                    source: row.get(5)?,
+                    price_source: PriceSource::Cached,
+                })
+            },
+        ).optional()?;
+
+        Ok(quote)
+    }
+
+    /// 保存行情（存在则覆盖）
+    ///
+    /// code 为带交易所前缀的代码，与 quote.code 可能不同（quote.code 是调用方传入的原始代码）
+    pub fn upsert(conn: &Connection, code: &str, quote: &RealQuote) -> Result<()> {
+        let as_of = quote
+            .as_of
+            .clone()
+            .unwrap_or_else(|| chrono::Local::now().format(AS_OF_FORMAT).to_string());
+
+        conn.execute(
+            "INSERT INTO quotes (code, name, last_price, prev_close, as_of, source,
+                                 open, high, low, volume, turnover, change_percent, pe_ratio, market_cap)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(code) DO UPDATE SET
+                name = excluded.name,
+                last_price = excluded.last_price,
+                prev_close = excluded.prev_close,
+                as_of = excluded.as_of,
+                source = excluded.source,
+                open = excluded.open,
+                high = excluded.high,
+                low = excluded.low,
+                volume = excluded.volume,
+                turnover = excluded.turnover,
+                change_percent = excluded.change_percent,
+                pe_ratio = excluded.pe_ratio,
+                market_cap = excluded.market_cap",
+            params![
+                code,
+                &quote.name,
+                quote.real_price,
+                quote.prev_close,
+                as_of,
+                &quote.source,
+                quote.open,
+                quote.high,
+                quote.low,
+                quote.volume,
+                quote.turnover,
+                quote.change_percent,
+                quote.pe_ratio,
+                quote.market_cap,
+            ],
+        )?;
+        Ok(())
+    }
+}
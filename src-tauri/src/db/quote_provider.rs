@@ -0,0 +1,567 @@
+/// 行情数据源
+/// 每个数据源负责拼接请求和解析响应，HTTP 请求、重试和故障切换由 QuoteService 统一处理
+
+use crate::models::{DailyPrice, Market, RealQuote, Symbol};
+use crate::models::quote::{QuoteOutcome, QuoteProviderKind};
+use std::collections::HashMap;
+
+/// 行情请求
+pub struct QuoteRequest {
+    /// 请求地址
+    pub url: String,
+    /// Referer 请求头（新浪接口要求）
+    pub referer: Option<&'static str>,
+}
+
+/// 行情数据源
+///
+/// 传入的代码均为带市场前缀的格式（如 sh600519、hk00700），由 QuoteService::format_stock_code 生成，
+/// 且只包含 supports 返回 true 的市场
+pub trait QuoteProvider: Send + Sync {
+    /// 数据源类型
+    fn kind(&self) -> QuoteProviderKind;
+
+    /// 是否支持该市场的行情
+    fn supports(&self, market: Market) -> bool;
+
+    /// 单次请求最多包含的代码数量
+    fn batch_size(&self) -> usize;
+
+    /// 拼接批量行情请求
+    fn build_request(&self, codes: &[String]) -> QuoteRequest;
+
+    /// 解析批量响应，返回每个代码的获取结果
+    ///
+    /// 响应中没有出现的代码视为代码不存在
+    fn parse_response(&self, text: &str, codes: &[String]) -> HashMap<String, QuoteOutcome>;
+
+    /// 拼接单个代码的日线历史请求（不复权，日期为 YYYY-MM-DD，含首尾），不提供历史行情时返回 None
+    fn build_history_request(&self, _code: &str, _from: &str, _to: &str) -> Option<QuoteRequest> {
+        None
+    }
+
+    /// 解析日线历史响应，返回按日期升序的收盘价
+    fn parse_history_response(&self, _text: &str, _code: &str) -> Vec<DailyPrice> {
+        Vec::new()
+    }
+}
+
+/// 解析价格字段：空、"--"、"-" 或 0 视为没有价格
+fn parse_price(value: &str) -> Option<f64> {
+    value.trim().parse::<f64>().ok().filter(|price| *price > 0.0)
+}
+
+/// 解析数值字段（成交量、涨跌幅等，允许为 0 或负数）
+fn parse_number(value: &str) -> Option<f64> {
+    value.trim().parse::<f64>().ok().filter(|number| number.is_finite())
+}
+
+/// 根据价格字段生成获取结果：没有当前价格表示停牌或暂无成交
+///
+/// extend 用于补充各数据源的扩展字段（今开、最高、最低、成交量等），
+/// 数据源没有提供涨跌幅时按昨收计算
+fn outcome_from_price(
+    code: &str,
+    name: &str,
+    price: &str,
+    prev_close: &str,
+    extend: impl FnOnce(RealQuote) -> RealQuote,
+) -> QuoteOutcome {
+    match parse_price(price) {
+        Some(price) => {
+            let mut quote = extend(
+                RealQuote::new(code.to_string(), name.to_string(), price)
+                    .with_prev_close(parse_price(prev_close)),
+            );
+            if quote.change_percent.is_none() {
+                quote.change_percent = quote
+                    .prev_close
+                    .and_then(|prev_close| quote.change().map(|change| change / prev_close * 100.0));
+            }
+            QuoteOutcome::Ok { quote }
+        }
+        _ => QuoteOutcome::Suspended { name: name.to_string() },
+    }
+}
+
+/// 由按日期升序的 (日期, 收盘价) 生成每日收盘价，昨收取前一个交易日的收盘价
+fn daily_prices(code: &str, closes: Vec<(String, f64)>) -> Vec<DailyPrice> {
+    let mut prev_close = None;
+    closes
+        .into_iter()
+        .map(|(price_date, close)| {
+            let price = DailyPrice { code: code.to_string(), price_date, close, prev_close };
+            prev_close = Some(close);
+            price
+        })
+        .collect()
+}
+
+/// 为响应中缺失的代码补充"代码不存在"
+fn fill_unknown(mut outcomes: HashMap<String, QuoteOutcome>, codes: &[String]) -> HashMap<String, QuoteOutcome> {
+    for code in codes {
+        outcomes.entry(code.clone()).or_insert(QuoteOutcome::UnknownCode);
+    }
+    outcomes
+}
+
+/// 腾讯财经：http://qt.gtimg.cn/q=sh600519,sz000001
+/// 日线历史：https://web.ifzq.gtimg.cn/appstock/app/fqkline/get?param=sh000300,day,2025-01-01,2025-06-30,640,
+pub struct TencentProvider {
+    base_url: String,
+    history_base_url: String,
+}
+
+impl TencentProvider {
+    pub fn new() -> Self {
+        Self {
+            base_url: "http://qt.gtimg.cn".to_string(),
+            history_base_url: "https://web.ifzq.gtimg.cn".to_string(),
+        }
+    }
+
+    /// 指定接口地址（用于测试时指向本地服务，行情和历史接口使用同一地址）
+    #[cfg(test)]
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        let base_url = base_url.into();
+        Self { history_base_url: base_url.clone(), base_url }
+    }
+
+    /// 单次日线历史请求最多返回的交易日数
+    const HISTORY_LIMIT: usize = 640;
+}
+
+impl Default for TencentProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuoteProvider for TencentProvider {
+    fn kind(&self) -> QuoteProviderKind {
+        QuoteProviderKind::Tencent
+    }
+
+    /// 沪深京 A 股、港股（hk00700）、美股（usAAPL）
+    fn supports(&self, market: Market) -> bool {
+        market.has_quotes()
+    }
+
+    fn batch_size(&self) -> usize {
+        50
+    }
+
+    fn build_request(&self, codes: &[String]) -> QuoteRequest {
+        QuoteRequest {
+            url: format!("{}/q={}", self.base_url, codes.join(",")),
+            referer: None,
+        }
+    }
+
+    /// 返回格式示例（每只股票一行）:
+    /// v_sh600519="1~贵州茅台~600519~1850.00~...";
+    /// 字段说明: 0=市场, 1=名称, 2=代码, 3=当前价格, 4=昨收, 5=今开, 6=成交量（手）,
+    /// 32=涨跌幅（%）, 33=最高, 34=最低, 37=成交额（万元）, 39=市盈率, 45=总市值（亿元）
+    ///
+    /// 无效代码返回 v_pv_none_match="1";
+    fn parse_response(&self, text: &str, codes: &[String]) -> HashMap<String, QuoteOutcome> {
+        let mut outcomes = HashMap::new();
+
+        for line in text.split(';') {
+            let Some((key, value)) = line.trim().split_once('=') else {
+                continue;
+            };
+            let Some(code) = key.trim().strip_prefix("v_") else {
+                continue;
+            };
+            if !codes.iter().any(|c| c == code) {
+                continue;
+            }
+
+            let fields: Vec<&str> = value.trim().trim_matches('"').split('~').collect();
+            let field = |index: usize| fields.get(index).copied().unwrap_or_default();
+            let outcome = if fields.len() < 5 {
+                QuoteOutcome::UnknownCode
+            } else {
+                outcome_from_price(code, fields[1], fields[3], fields[4], |quote| RealQuote {
+                    open: parse_price(field(5)),
+                    volume: parse_number(field(6)).map(|lots| lots * 100.0),
+                    change_percent: parse_number(field(32)),
+                    high: parse_price(field(33)),
+                    low: parse_price(field(34)),
+                    turnover: parse_number(field(37)).map(|wan| wan * 10_000.0),
+                    pe_ratio: parse_number(field(39)),
+                    market_cap: parse_number(field(45)).map(|yi| yi * 100_000_000.0),
+                    ..quote
+                })
+            };
+            outcomes.insert(code.to_string(), outcome);
+        }
+
+        fill_unknown(outcomes, codes)
+    }
+
+    /// 返回格式示例（JSON，指数和不复权的股票为 day 字段）:
+    /// {"code":0,"data":{"sh000300":{"day":[["2025-06-26","3960.07","3947.15","3968.47","3940.21","1.4e8"]]}}}
+    /// 每行字段: 0=日期, 1=开盘, 2=收盘, 3=最高, 4=最低, 5=成交量
+    ///
+    /// 单次最多返回 HISTORY_LIMIT 个交易日，范围更长时只返回最近的部分
+    fn build_history_request(&self, code: &str, from: &str, to: &str) -> Option<QuoteRequest> {
+        Some(QuoteRequest {
+            url: format!(
+                "{}/appstock/app/fqkline/get?param={},day,{},{},{},",
+                self.history_base_url,
+                code,
+                from,
+                to,
+                Self::HISTORY_LIMIT
+            ),
+            referer: None,
+        })
+    }
+
+    fn parse_history_response(&self, text: &str, code: &str) -> Vec<DailyPrice> {
+        let json: serde_json::Value = match serde_json::from_str(text) {
+            Ok(json) => json,
+            Err(e) => {
+                eprintln!("❌ 解析腾讯日线响应失败: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let rows = json["data"][code]["day"].as_array().cloned().unwrap_or_default();
+        let closes = rows
+            .iter()
+            .filter_map(|row| {
+                let date = row[0].as_str()?;
+                let close = parse_price(row[2].as_str()?)?;
+                Some((date.to_string(), close))
+            })
+            .collect();
+
+        daily_prices(code, closes)
+    }
+}
+
+/// 新浪财经：https://hq.sinajs.cn/list=sh600519,sz000001
+pub struct SinaProvider {
+    base_url: String,
+}
+
+impl SinaProvider {
+    pub fn new() -> Self {
+        Self::with_base_url("https://hq.sinajs.cn")
+    }
+
+    /// 指定接口地址（用于测试时指向本地服务）
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self { base_url: base_url.into() }
+    }
+}
+
+impl Default for SinaProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuoteProvider for SinaProvider {
+    fn kind(&self) -> QuoteProviderKind {
+        QuoteProviderKind::Sina
+    }
+
+    /// 只支持沪深京 A 股（港股、美股的返回格式不同）
+    fn supports(&self, market: Market) -> bool {
+        matches!(market, Market::Sh | Market::Sz | Market::Bj)
+    }
+
+    fn batch_size(&self) -> usize {
+        50
+    }
+
+    fn build_request(&self, codes: &[String]) -> QuoteRequest {
+        QuoteRequest {
+            url: format!("{}/list={}", self.base_url, codes.join(",")),
+            // 不带 Referer 会被拒绝访问
+            referer: Some("https://finance.sina.com.cn"),
+        }
+    }
+
+    /// 返回格式示例（每只股票一行）:
+    /// var hq_str_sh600519="贵州茅台,1845.00,1840.00,1850.00,...";
+    /// 字段说明: 0=名称, 1=今开, 2=昨收, 3=当前价格, 4=最高, 5=最低, 8=成交量（股）, 9=成交额（元）
+    /// 不提供涨跌幅、市盈率和总市值，涨跌幅按昨收计算
+    ///
+    /// 无效代码返回空字符串：var hq_str_sh688999="";
+    fn parse_response(&self, text: &str, codes: &[String]) -> HashMap<String, QuoteOutcome> {
+        let mut outcomes = HashMap::new();
+
+        for line in text.split(';') {
+            let Some((key, value)) = line.trim().split_once('=') else {
+                continue;
+            };
+            let Some(code) = key.trim().strip_prefix("var hq_str_") else {
+                continue;
+            };
+            if !codes.iter().any(|c| c == code) {
+                continue;
+            }
+
+            let fields: Vec<&str> = value.trim().trim_matches('"').split(',').collect();
+            let field = |index: usize| fields.get(index).copied().unwrap_or_default();
+            let outcome = if fields.len() < 4 || fields[0].is_empty() {
+                QuoteOutcome::UnknownCode
+            } else {
+                outcome_from_price(code, fields[0], fields[3], fields[2], |quote| RealQuote {
+                    open: parse_price(field(1)),
+                    high: parse_price(field(4)),
+                    low: parse_price(field(5)),
+                    volume: parse_number(field(8)),
+                    turnover: parse_number(field(9)),
+                    ..quote
+                })
+            };
+            outcomes.insert(code.to_string(), outcome);
+        }
+
+        fill_unknown(outcomes, codes)
+    }
+}
+
+/// 东方财富：https://push2.eastmoney.com/api/qt/ulist.np/get?fltt=2&fields=f2,f3,...&secids=1.600519,0.000001
+/// 日线历史：https://push2his.eastmoney.com/api/qt/stock/kline/get?secid=1.000300&klt=101&fqt=0&beg=20250101&end=20250630
+pub struct EastmoneyProvider {
+    base_url: String,
+    history_base_url: String,
+}
+
+impl EastmoneyProvider {
+    pub fn new() -> Self {
+        Self {
+            base_url: "https://push2.eastmoney.com".to_string(),
+            history_base_url: "https://push2his.eastmoney.com".to_string(),
+        }
+    }
+
+    /// 请求的字段
+    const FIELDS: &'static str = "f2,f3,f5,f6,f9,f12,f13,f14,f15,f16,f17,f18,f20";
+
+    /// sh600519 -> 1.600519，sz000001 -> 0.000001，bj430047 -> 0.430047，hk00700 -> 116.00700
+    fn secid(code: &str) -> String {
+        match Symbol::parse(code) {
+            Ok(Symbol { market: Market::Sh, code }) => format!("1.{}", code),
+            Ok(Symbol { market: Market::Hk, code }) => format!("116.{}", code),
+            Ok(symbol) => format!("0.{}", symbol.code),
+            Err(_) => format!("0.{}", code),
+        }
+    }
+
+    /// 市场编号 + 代码 -> sh600519 / sz000001 / bj430047 / hk00700
+    ///
+    /// 深圳和北京共用市场编号 0，按号段区分
+    fn code_from(market: i64, number: &str) -> String {
+        let market = match market {
+            1 => Market::Sh,
+            116 => Market::Hk,
+            _ => match Symbol::parse(number) {
+                Ok(symbol) if symbol.market == Market::Bj => Market::Bj,
+                _ => Market::Sz,
+            },
+        };
+        Symbol::with_market(market, number)
+            .map(|symbol| symbol.to_string())
+            .unwrap_or_else(|_| format!("{}{}", market.prefix(), number))
+    }
+}
+
+impl Default for EastmoneyProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuoteProvider for EastmoneyProvider {
+    fn kind(&self) -> QuoteProviderKind {
+        QuoteProviderKind::Eastmoney
+    }
+
+    /// 沪深京 A 股和港股（美股的 secid 需要区分交易所，无法仅凭代码确定）
+    fn supports(&self, market: Market) -> bool {
+        matches!(market, Market::Sh | Market::Sz | Market::Bj | Market::Hk)
+    }
+
+    fn batch_size(&self) -> usize {
+        100
+    }
+
+    fn build_request(&self, codes: &[String]) -> QuoteRequest {
+        let secids: Vec<String> = codes.iter().map(|code| Self::secid(code)).collect();
+        QuoteRequest {
+            url: format!(
+                "{}/api/qt/ulist.np/get?fltt=2&fields={}&secids={}",
+                self.base_url,
+                Self::FIELDS,
+                secids.join(",")
+            ),
+            referer: None,
+        }
+    }
+
+    /// 返回格式示例（JSON）:
+    /// {"rc":0,"data":{"total":1,"diff":[{"f2":1850.0,"f12":"600519","f13":1,"f14":"贵州茅台"}]}}
+    /// 字段说明: f2=当前价格（停牌时为 "-"）, f3=涨跌幅（%）, f5=成交量（手）, f6=成交额（元）, f9=市盈率（动态）,
+    /// f12=代码, f13=市场（1=上海, 0=深圳）, f14=名称, f15=最高, f16=最低, f17=今开, f18=昨收, f20=总市值（元）
+    ///
+    /// 无效代码不出现在 diff 中，全部无效时 data 为 null
+    fn parse_response(&self, text: &str, codes: &[String]) -> HashMap<String, QuoteOutcome> {
+        let mut outcomes = HashMap::new();
+
+        let json: serde_json::Value = match serde_json::from_str(text) {
+            Ok(json) => json,
+            Err(e) => {
+                eprintln!("❌ 解析东方财富响应失败: {}", e);
+                return fill_unknown(outcomes, codes);
+            }
+        };
+
+        let items = json["data"]["diff"].as_array().cloned().unwrap_or_default();
+        for item in items {
+            let (Some(number), Some(market)) = (item["f12"].as_str(), item["f13"].as_i64()) else {
+                continue;
+            };
+            let code = Self::code_from(market, number);
+            if !codes.contains(&code) {
+                continue;
+            }
+
+            let name = item["f14"].as_str().unwrap_or_default();
+            let field = |key: &str| match &item[key] {
+                serde_json::Value::Number(n) => n.to_string(),
+                other => other.as_str().unwrap_or_default().to_string(),
+            };
+            let outcome = outcome_from_price(&code, name, &field("f2"), &field("f18"), |quote| RealQuote {
+                change_percent: parse_number(&field("f3")),
+                volume: parse_number(&field("f5")).map(|lots| lots * 100.0),
+                turnover: parse_number(&field("f6")),
+                pe_ratio: parse_number(&field("f9")),
+                high: parse_price(&field("f15")),
+                low: parse_price(&field("f16")),
+                open: parse_price(&field("f17")),
+                market_cap: parse_number(&field("f20")),
+                ..quote
+            });
+            outcomes.insert(code.clone(), outcome);
+        }
+
+        fill_unknown(outcomes, codes)
+    }
+
+    /// 返回格式示例（JSON，klines 每行为 "日期,收盘价"）:
+    /// {"rc":0,"data":{"code":"000300","market":1,"name":"沪深300","klines":["2025-06-26,3947.15"]}}
+    ///
+    /// 代码不存在时 data 为 null
+    fn build_history_request(&self, code: &str, from: &str, to: &str) -> Option<QuoteRequest> {
+        Some(QuoteRequest {
+            url: format!(
+                "{}/api/qt/stock/kline/get?secid={}&fields1=f1,f2,f3&fields2=f51,f53&klt=101&fqt=0&beg={}&end={}",
+                self.history_base_url,
+                Self::secid(code),
+                from.replace('-', ""),
+                to.replace('-', "")
+            ),
+            referer: None,
+        })
+    }
+
+    fn parse_history_response(&self, text: &str, code: &str) -> Vec<DailyPrice> {
+        let json: serde_json::Value = match serde_json::from_str(text) {
+            Ok(json) => json,
+            Err(e) => {
+                eprintln!("❌ 解析东方财富日线响应失败: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let rows = json["data"]["klines"].as_array().cloned().unwrap_or_default();
+        let closes = rows
+            .iter()
+            .filter_map(|row| {
+                let (date, close) = row.as_str()?.split_once(',')?;
+                Some((date.to_string(), parse_price(close)?))
+            })
+            .collect();
+
+        daily_prices(code, closes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes() -> Vec<String> {
+        vec!["sh600519".to_string(), "sz000001".to_string(), "sh688999".to_string()]
+    }
+
+    /// 校验三个数据源对同一组代码的解析结果：茅台正常、平安银行停牌、688999 不存在
+    fn assert_outcomes(outcomes: &HashMap<String, QuoteOutcome>) {
+        match &outcomes["sh600519"] {
+            QuoteOutcome::Ok { quote } => {
+                assert_eq!(quote.name, "贵州茅台");
+                assert_eq!(quote.real_price, 1850.0);
+                assert_eq!(quote.prev_close, Some(1840.0));
+                assert_eq!(quote.open, Some(1845.0));
+                assert_eq!(quote.high, Some(1856.0));
+                assert_eq!(quote.low, Some(1838.0));
+                assert_eq!(quote.volume, Some(2_345_600.0));
+                assert_eq!(quote.change_percent.map(|p| (p * 100.0).round() / 100.0), Some(0.54));
+            }
+            other => panic!("应获取成功: {:?}", other),
+        }
+        assert!(matches!(&outcomes["sz000001"], QuoteOutcome::Suspended { name } if name == "平安银行"));
+        assert!(matches!(outcomes["sh688999"], QuoteOutcome::UnknownCode));
+    }
+
+    #[test]
+    fn test_parse_recorded_fixtures() {
+        let tencent = TencentProvider::new();
+        assert_outcomes(&tencent.parse_response(include_str!("fixtures/quotes/tencent.txt"), &codes()));
+
+        let sina = SinaProvider::new();
+        assert_outcomes(&sina.parse_response(include_str!("fixtures/quotes/sina.txt"), &codes()));
+
+        let eastmoney = EastmoneyProvider::new();
+        assert_outcomes(&eastmoney.parse_response(include_str!("fixtures/quotes/eastmoney.json"), &codes()));
+        assert!(eastmoney.build_request(&codes()).url.ends_with("secids=1.600519,0.000001,1.688999"));
+    }
+
+    #[test]
+    fn test_parse_history_fixtures() {
+        let tencent = TencentProvider::new();
+        let eastmoney = EastmoneyProvider::new();
+        let sina = SinaProvider::new();
+        assert!(sina.build_history_request("sh000300", "2025-06-24", "2025-06-27").is_none());
+        assert!(eastmoney
+            .build_history_request("sh000300", "2025-06-24", "2025-06-27")
+            .unwrap()
+            .url
+            .contains("secid=1.000300&fields1=f1,f2,f3&fields2=f51,f53&klt=101&fqt=0&beg=20250624&end=20250627"));
+
+        let histories = [
+            tencent.parse_history_response(include_str!("fixtures/history/tencent.json"), "sh000300"),
+            eastmoney.parse_history_response(include_str!("fixtures/history/eastmoney.json"), "sh000300"),
+        ];
+        for prices in histories {
+            let closes: Vec<(&str, f64, Option<f64>)> = prices
+                .iter()
+                .map(|price| (price.price_date.as_str(), price.close, price.prev_close))
+                .collect();
+            assert_eq!(closes, vec![
+                ("2025-06-24", 3904.03, None),
+                ("2025-06-25", 3960.07, Some(3904.03)),
+                ("2025-06-26", 3947.15, Some(3960.07)),
+                ("2025-06-27", 3921.76, Some(3947.15)),
+            ]);
+        }
+    }
+}
@@ -1,171 +1,426 @@
 /// 实时行情服务
 /// 对应 Java 版本的 RealQuoteService
-/// 负责从外部 API 获取股票实时价格
+/// 负责从外部 API 获取股票实时价格，按配置的优先级在多个数据源之间故障切换
 
-use crate::models::RealQuote;
+use crate::models::{DailyPrice, RealQuote, Symbol};
+use crate::models::quote::{ManualPrice, PriceSource, QuoteConfig, QuoteOutcome, QuoteProviderKind};
+use crate::db::{Database, QuoteCacheRepository, SettingsRepository};
+use crate::db::quote_cache_repo::AS_OF_FORMAT;
+use crate::db::quote_provider::{QuoteProvider, QuoteRequest, TencentProvider, SinaProvider, EastmoneyProvider};
 use crate::error::{AppError, Result};
+use once_cell::sync::Lazy;
+use rusqlite::Connection;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// 同时进行的批次请求数
+const MAX_CONCURRENT_BATCHES: usize = 4;
+
+/// 网络错误时的最大重试次数
+const MAX_RETRIES: u32 = 2;
+
+/// 首次重试前的等待时间，之后每次翻倍
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(300);
+
+/// 共享的 HTTP 客户端（复用连接池）
+///
+/// 连接超时 3 秒，整个请求（含读取响应）超时 8 秒，避免单个慢请求拖住整个视图
+static HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(3))
+        .timeout(Duration::from_secs(8))
+        .build()
+        .expect("无法创建 HTTP 客户端")
+});
 
 /// 行情服务
 pub struct QuoteService;
 
 impl QuoteService {
-    /// 格式化股票代码为腾讯API格式
+    /// 格式化股票代码为带市场前缀的格式（如 sh600519、bj430047、hk00700、usAAPL）
     ///
-    /// 规则：
-    /// - 6位数字且以6开头：上海股票，添加 sh 前缀
-    /// - 6位数字且以0或3开头：深圳股票，添加 sz 前缀
-    /// - 已有前缀：保持不变
+    /// 解析规则见 Symbol::parse，无法识别的代码保持原样
     pub fn format_stock_code(code: &str) -> String {
-        // 如果已有前缀，直接返回
-        if code.starts_with("sh") || code.starts_with("sz") {
-            return code.to_string();
-        }
+        Symbol::parse(code)
+            .map(|symbol| symbol.to_string())
+            .unwrap_or_else(|_| code.to_string())
+    }
 
-        // 清理代码（只保留数字）
-        let clean_code: String = code.chars().filter(|c| c.is_ascii_digit()).collect();
+    /// 按行情配置的优先级创建数据源
+    pub fn providers(config: &QuoteConfig) -> Vec<Arc<dyn QuoteProvider>> {
+        config.providers
+            .iter()
+            .map(|kind| -> Arc<dyn QuoteProvider> {
+                match kind {
+                    QuoteProviderKind::Tencent => Arc::new(TencentProvider::new()),
+                    QuoteProviderKind::Sina => Arc::new(SinaProvider::new()),
+                    QuoteProviderKind::Eastmoney => Arc::new(EastmoneyProvider::new()),
+                }
+            })
+            .collect()
+    }
 
-        // 如果不是6位数字，返回原代码（可能已经是正确格式）
-        if clean_code.len() != 6 {
-            return code.to_string();
-        }
+    /// 读取已保存的行情配置并创建数据源（不使用缓存）
+    pub fn configured_providers(conn: &Connection) -> Result<Vec<Arc<dyn QuoteProvider>>> {
+        let config = SettingsRepository::get_quote_config(conn)?;
+        Ok(Self::providers(&config))
+    }
 
-        // 根据首位数字添加前缀
-        match clean_code.chars().next() {
-            Some('6') => format!("sh{}", clean_code),  // 上海证券交易所
-            Some('0' | '3') => format!("sz{}", clean_code),  // 深圳证券交易所
-            _ => code.to_string(),  // 其他情况保持原样
-        }
+    /// 按已保存的行情配置获取行情（优先使用缓存）
+    pub async fn fetch_configured_quotes(database: &Database, codes: &[String]) -> Result<HashMap<String, QuoteOutcome>> {
+        let config = SettingsRepository::get_quote_config(&*database.acquire().await?)?;
+        let providers = Self::providers(&config);
+
+        Self::fetch_quotes_cached(database, &providers, Duration::from_secs(config.cache_ttl_secs), codes).await
     }
 
-    /// 批量获取股票实时价格
+    /// 获取行情，优先使用缓存
     ///
-    /// 参数：
-    /// - codes: 股票代码列表
+    /// - 缓存未超过有效期：直接使用缓存，不请求网络
+    /// - 其余代码从数据源获取，成功后写入缓存
+    /// - 网络错误或停牌时，使用缓存中的最后已知价格并标记为过期（is_stale）
     ///
     /// 返回：
-    /// - HashMap<String, RealQuote>: code -> RealQuote 映射
-    pub async fn fetch_real_quotes(codes: Vec<String>) -> Result<HashMap<String, RealQuote>> {
-        let mut quotes = HashMap::new();
+    /// - HashMap<String, QuoteOutcome>: 原始代码 -> 获取结果
+    pub async fn fetch_quotes_cached(
+        database: &Database,
+        providers: &[Arc<dyn QuoteProvider>],
+        cache_ttl: Duration,
+        codes: &[String],
+    ) -> Result<HashMap<String, QuoteOutcome>> {
+        let now = chrono::Local::now().naive_local();
+        let mut result = HashMap::new();
+        let mut cached: HashMap<String, RealQuote> = HashMap::new();
+        let mut to_fetch = Vec::new();
+
+        {
+            let conn = database.acquire().await?;
+            for code in codes {
+                let formatted_code = Self::format_stock_code(code);
+                let quote = match cached.get(&formatted_code) {
+                    Some(quote) => Some(quote.clone()),
+                    None => QuoteCacheRepository::find(&conn, &formatted_code)?,
+                };
+
+                match quote {
+                    Some(quote) if !Self::is_expired(&quote, now, cache_ttl) => {
+                        println!("💾 {} 使用缓存行情（{}）", code, quote.as_of.as_deref().unwrap_or("-"));
+                        result.insert(code.clone(), QuoteOutcome::Ok {
+                            quote: RealQuote { code: code.clone(), ..quote.clone() },
+                        });
+                        cached.insert(formatted_code, quote);
+                    }
+                    Some(quote) => {
+                        cached.insert(formatted_code, quote);
+                        to_fetch.push(code.clone());
+                    }
+                    None => to_fetch.push(code.clone()),
+                }
+            }
+        }
 
-        println!("开始批量获取实时价格，股票代码列表: {:?}", codes);
+        if to_fetch.is_empty() {
+            return Ok(result);
+        }
 
-        for code in codes {
-            match Self::fetch_single_quote(&code).await {
-                Ok(quote) => {
-                    println!("✅ 成功获取股票 {} 的价格数据:", code);
-                    println!("   - 股票代码: {}", quote.code);
-                    println!("   - 股票名称: {}", quote.name);
-                    println!("   - 当前价格: {}", quote.real_price);
-                    quotes.insert(code.clone(), quote);
+        // 请求网络期间不占用数据库连接
+        let outcomes = Self::fetch_quotes(providers, &to_fetch).await;
+
+        let conn = database.acquire().await?;
+        for (code, outcome) in outcomes {
+            let formatted_code = Self::format_stock_code(&code);
+
+            let outcome = match outcome {
+                QuoteOutcome::Ok { quote } => {
+                    QuoteCacheRepository::upsert(&conn, &formatted_code, &quote)?;
+                    QuoteOutcome::Ok { quote }
                 }
-                Err(e) => {
-                    // 记录错误但继续处理其他股票
-                    eprintln!("❌ 获取 {} 价格失败: {}", code, e);
+                QuoteOutcome::Suspended { .. } | QuoteOutcome::NetworkError { .. } if cached.contains_key(&formatted_code) => {
+                    let quote = &cached[&formatted_code];
+                    println!("⚠️  {} 获取行情失败，使用最后已知价格 {}（{}）", code, quote.real_price, quote.as_of.as_deref().unwrap_or("-"));
+                    QuoteOutcome::Ok {
+                        quote: RealQuote { code: code.clone(), is_stale: true, ..quote.clone() },
+                    }
                 }
-            }
+                other => other,
+            };
+
+            result.insert(code, outcome);
         }
 
-        println!("批量获取完成，成功获取 {} 只股票的价格", quotes.len());
+        Ok(result)
+    }
 
-        Ok(quotes)
+    /// 缓存是否已超过有效期（行情时间无法解析时视为过期）
+    fn is_expired(quote: &RealQuote, now: chrono::NaiveDateTime, cache_ttl: Duration) -> bool {
+        let Some(as_of) = quote
+            .as_of
+            .as_deref()
+            .and_then(|as_of| chrono::NaiveDateTime::parse_from_str(as_of, AS_OF_FORMAT).ok())
+        else {
+            return true;
+        };
+
+        match (now - as_of).to_std() {
+            Ok(age) => age >= cache_ttl,
+            // 行情时间晚于当前时间（系统时间被调整过），视为刚获取
+            Err(_) => cache_ttl.is_zero(),
+        }
     }
 
-    /// 获取单个股票的实时价格
+    /// 批量获取股票行情，逐只返回获取结果
     ///
-    /// 使用腾讯财经 API: http://qt.gtimg.cn/q={code}
+    /// 按优先级依次使用各数据源：前一个数据源获取成功或确认停牌的代码不再请求，
+    /// 网络错误或未识别的代码切换到下一个数据源。停牌、代码不存在、网络错误分别返回，不会丢弃
     ///
-    /// 返回格式示例:
-    /// v_sh600519="51~贵州茅台~600519~1850.00~...~3.45~..."
-    /// 字段说明: 0=未知, 1=名称, 2=代码, 3=当前价格...
-    async fn fetch_single_quote(code: &str) -> Result<RealQuote> {
-        // 标准化股票代码格式
-        let formatted_code = Self::format_stock_code(code);
-        let url = format!("http://qt.gtimg.cn/q={}", formatted_code);
+    /// 返回：
+    /// - HashMap<String, QuoteOutcome>: 原始代码 -> 获取结果（以最后一个尝试的数据源为准）
+    pub async fn fetch_quotes(providers: &[Arc<dyn QuoteProvider>], codes: &[String]) -> HashMap<String, QuoteOutcome> {
+        // 统一为带前缀的格式并去重，多个原始代码可能对应同一只股票（如 600519 与 sh600519）
+        let mut code_map: HashMap<String, Vec<String>> = HashMap::new();
+        for code in codes {
+            code_map
+                .entry(Self::format_stock_code(code))
+                .or_default()
+                .push(code.clone());
+        }
 
-        println!("📡 请求股票 {} 的实时价格:", code);
-        println!("   - 原始代码: {}", code);
-        println!("   - 格式化代码: {}", formatted_code);
-        println!("   - 请求URL: {}", url);
+        let mut pending: Vec<String> = code_map.keys().cloned().collect();
+        let mut formatted_outcomes: HashMap<String, QuoteOutcome> = HashMap::new();
 
-        // 使用 reqwest 发送HTTP请求
-        let response = reqwest::get(&url)
-            .await
-            .map_err(|e| AppError::Business(format!("请求API失败: {}", e)))?;
+        for provider in providers {
+            if pending.is_empty() {
+                break;
+            }
 
-        let text = response
-            .text()
-            .await
-            .map_err(|e| AppError::Business(format!("读取响应失败: {}", e)))?;
+            // 数据源不支持的市场留给后面的数据源
+            let (supported, unsupported): (Vec<String>, Vec<String>) = pending
+                .drain(..)
+                .partition(|code| Self::provider_supports(provider.as_ref(), code));
+            pending = unsupported;
+            if supported.is_empty() {
+                continue;
+            }
 
-        println!("📥 收到API响应:");
-        println!("   - 响应内容: {}", text);
+            println!("📡 使用数据源 {:?} 获取 {} 只股票的行情", provider.kind(), supported.len());
+            let outcomes = Self::fetch_from_provider(provider.clone(), &supported).await;
 
-        // 解析响应
-        let result = Self::parse_quote_response(&text, code)?;
+            for (code, outcome) in outcomes {
+                if matches!(outcome, QuoteOutcome::UnknownCode | QuoteOutcome::NetworkError { .. }) {
+                    pending.push(code.clone());
+                }
+                formatted_outcomes.insert(code, outcome);
+            }
 
-        println!("✅ 解析成功:");
-        println!("   - 股票代码: {}", result.code);
-        println!("   - 股票名称: {}", result.name);
-        println!("   - 当前价格: {}", result.real_price);
+            if !pending.is_empty() {
+                println!("⚠️  数据源 {:?} 有 {} 只股票未获取到行情，切换到下一个数据源", provider.kind(), pending.len());
+            }
+        }
 
-        Ok(result)
-    }
+        // 还原为调用方传入的原始代码
+        let mut result = HashMap::new();
+        for (formatted_code, original_codes) in code_map {
+            let outcome = formatted_outcomes
+                .remove(&formatted_code)
+                .unwrap_or_else(|| match Symbol::parse(&formatted_code) {
+                    Ok(symbol) if symbol.market.has_quotes() => QuoteOutcome::NetworkError {
+                        message: format!("没有支持 {} 市场的行情数据源", symbol.market.as_str()),
+                    },
+                    // 无法识别的代码和场外产品没有行情
+                    _ => QuoteOutcome::UnknownCode,
+                });
+
+            for original_code in original_codes {
+                let outcome = match &outcome {
+                    QuoteOutcome::Ok { quote } => QuoteOutcome::Ok {
+                        quote: RealQuote { code: original_code.clone(), ..quote.clone() },
+                    },
+                    other => other.clone(),
+                };
+
+                match &outcome {
+                    QuoteOutcome::Ok { quote } => println!("✅ {} {} 当前价格: {}", original_code, quote.name, quote.real_price),
+                    QuoteOutcome::Suspended { name } => println!("⏸️  {} {} 停牌或暂无价格", original_code, name),
+                    QuoteOutcome::UnknownCode => println!("❓ {} 代码不存在", original_code),
+                    QuoteOutcome::NetworkError { message } => println!("❌ {} 网络错误: {}", original_code, message),
+                }
 
-    /// 解析腾讯行情API响应
-    fn parse_quote_response(text: &str, original_code: &str) -> Result<RealQuote> {
-        println!("🔍 开始解析API响应，原始代码: {}", original_code);
+                result.insert(original_code, outcome);
+            }
+        }
 
-        // 提取引号内的内容
-        let start = text
-            .find('"')
-            .ok_or_else(|| AppError::Business("无效的响应格式".to_string()))?;
+        result
+    }
 
-        let end = text[start + 1..]
-            .find('"')
-            .ok_or_else(|| AppError::Business("无效的响应格式".to_string()))?;
+    /// 获取单个代码的日线历史收盘价（不复权，不使用缓存）
+    ///
+    /// 按优先级依次尝试支持该市场且提供历史行情的数据源，返回第一个取得数据的结果（只保留日期范围内的收盘价）。
+    /// 所有数据源都没有返回数据时，有网络错误则返回最后一个错误，否则返回空列表
+    pub async fn fetch_history(
+        providers: &[Arc<dyn QuoteProvider>],
+        code: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<DailyPrice>> {
+        let code = Self::format_stock_code(code);
+        let mut last_error = None;
+
+        for provider in providers.iter().filter(|provider| Self::provider_supports(provider.as_ref(), &code)) {
+            let Some(request) = provider.build_history_request(&code, from, to) else {
+                continue;
+            };
+
+            println!("📡 使用数据源 {:?} 获取 {} 的历史收盘价（{} ~ {}）", provider.kind(), code, from, to);
+            match Self::fetch_batch_with_retry(&request).await {
+                Ok(text) => {
+                    let mut prices = provider.parse_history_response(&text, &code);
+                    prices.retain(|price| price.price_date.as_str() >= from && price.price_date.as_str() <= to);
+                    if !prices.is_empty() {
+                        return Ok(prices);
+                    }
+                    println!("⚠️  数据源 {:?} 没有返回 {} 的历史收盘价，切换到下一个数据源", provider.kind(), code);
+                }
+                Err(e) => {
+                    eprintln!("❌ 数据源 {:?} 获取 {} 的历史收盘价失败: {}", provider.kind(), code, e);
+                    last_error = Some(e);
+                }
+            }
+        }
 
-        let data = &text[start + 1..start + 1 + end];
+        match last_error {
+            Some(e) => Err(e),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// 数据源是否支持该代码所属的市场（无法识别的代码不请求）
+    fn provider_supports(provider: &dyn QuoteProvider, code: &str) -> bool {
+        Symbol::parse(code).is_ok_and(|symbol| provider.supports(symbol.market))
+    }
 
-        println!("   - 提取的数据: {}", data);
+    /// 从单个数据源获取行情
+    ///
+    /// 代码按数据源的批次大小合并为一次请求，最多 MAX_CONCURRENT_BATCHES 个请求并发
+    async fn fetch_from_provider(provider: Arc<dyn QuoteProvider>, codes: &[String]) -> HashMap<String, QuoteOutcome> {
+        let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_BATCHES));
+        let mut tasks = JoinSet::new();
+
+        for batch in codes.chunks(provider.batch_size().max(1)) {
+            let batch = batch.to_vec();
+            let provider = provider.clone();
+            let semaphore = semaphore.clone();
+
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                match Self::fetch_batch_with_retry(&provider.build_request(&batch)).await {
+                    Ok(text) => provider.parse_response(&text, &batch),
+                    Err(e) => {
+                        eprintln!("❌ 数据源 {:?} 获取批次 {:?} 失败: {}", provider.kind(), batch, e);
+                        batch
+                            .iter()
+                            .map(|code| (code.clone(), QuoteOutcome::NetworkError { message: e.to_string() }))
+                            .collect()
+                    }
+                }
+            });
+        }
 
-        // 按 ~ 分割
-        let fields: Vec<&str> = data.split('~').collect();
+        let mut outcomes = HashMap::new();
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok(batch_outcomes) => outcomes.extend(batch_outcomes),
+                Err(e) => eprintln!("❌ 行情请求任务异常退出: {}", e),
+            }
+        }
 
-        println!("   - 分割后的字段数: {}", fields.len());
-        for (i, field) in fields.iter().take(10).enumerate() {
-            println!("     [{}] = \"{}\"", i, field);
+        // 记录行情时间和数据源
+        let as_of = chrono::Local::now().format(AS_OF_FORMAT).to_string();
+        for outcome in outcomes.values_mut() {
+            if let QuoteOutcome::Ok { quote } = outcome {
+                quote.as_of = Some(as_of.clone());
+                quote.source = Some(provider.kind().as_str().to_string());
+            }
         }
 
-        if fields.len() < 4 {
-            return Err(AppError::Business(format!(
-                "股票 {} 数据字段不足，响应: {}",
-                original_code, data
-            )));
+        // 任务异常退出的批次按网络错误处理
+        for code in codes {
+            outcomes
+                .entry(code.clone())
+                .or_insert_with(|| QuoteOutcome::NetworkError { message: "行情请求未完成".to_string() });
         }
 
-        let name = fields[1].to_string();
-        let price_str = fields[3];
+        outcomes
+    }
 
-        println!("   - 提取的字段:");
-        println!("     [1] 股票名称: {}", name);
-        println!("     [3] 价格字符串: \"{}\"", price_str);
+    /// 请求一个批次，网络错误时按指数退避重试
+    pub(crate) async fn fetch_batch_with_retry(request: &QuoteRequest) -> Result<String> {
+        let mut attempt = 0;
+
+        loop {
+            match Self::fetch_text(request).await {
+                Ok(text) => return Ok(text),
+                Err(e) if attempt < MAX_RETRIES => {
+                    let delay = RETRY_BASE_DELAY * 2u32.pow(attempt);
+                    attempt += 1;
+                    println!("⚠️  请求 {} 失败: {}，{:?} 后第 {} 次重试", request.url, e, delay, attempt);
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
 
-        // 检查价格是否为空或无效
-        if price_str.is_empty() || price_str == "--" {
-            return Err(AppError::Business(format!(
-                "股票 {} 暂停或无价格数据",
-                original_code
-            )));
+    /// 发送 HTTP 请求并读取响应文本
+    async fn fetch_text(request: &QuoteRequest) -> Result<String> {
+        let mut builder = HTTP_CLIENT.get(&request.url);
+        if let Some(referer) = request.referer {
+            builder = builder.header(reqwest::header::REFERER, referer);
         }
 
-        let real_price: f64 = price_str
-            .parse()
-            .map_err(|e| AppError::Business(format!("解析价格失败 '{}': {}", price_str, e)))?;
+        let response = builder
+            .send()
+            .await
+            .map_err(|e| AppError::Business(format!("请求API失败: {}", e)))?
+            .error_for_status()
+            .map_err(|e| AppError::Business(format!("API返回错误状态: {}", e)))?;
 
-        println!("   - 解析后的价格: {}", real_price);
+        response
+            .text()
+            .await
+            .map_err(|e| AppError::Business(format!("读取响应失败: {}", e)))
+    }
 
-        Ok(RealQuote::new(original_code.to_string(), name, real_price))
+    /// 查找代码对应的手动价格（按带交易所前缀的格式比较，600519 与 sh600519 视为同一代码）
+    pub fn find_manual_price<'a>(manual_prices: &'a [ManualPrice], code: &str) -> Option<&'a ManualPrice> {
+        let formatted_code = Self::format_stock_code(code);
+        manual_prices
+            .iter()
+            .find(|manual_price| Self::format_stock_code(&manual_price.code) == formatted_code)
+    }
+
+    /// 将手动价格合并到行情中
+    ///
+    /// - 固定（pinned）的手动价格覆盖实时行情
+    /// - 未固定的手动价格只在没有行情时使用
+    pub fn apply_manual_prices(quotes: &mut HashMap<String, RealQuote>, codes: &[String], manual_prices: &[ManualPrice]) {
+        for code in codes {
+            let Some(manual_price) = Self::find_manual_price(manual_prices, code) else {
+                continue;
+            };
+
+            let name = match quotes.get(code) {
+                Some(_) if !manual_price.pinned => continue,
+                Some(quote) => quote.name.clone(),
+                // 名称为空时由调用方使用持仓记录中的名称
+                None => String::new(),
+            };
+
+            println!("✍️  {} 使用手动价格 {}（{}）", code, manual_price.price, manual_price.price_date);
+            quotes.insert(code.clone(), manual_price.to_quote(code.clone(), name));
+        }
     }
 
     /// 生成模拟数据（用于开发测试）
@@ -179,10 +434,136 @@ impl QuoteService {
 
             quotes.insert(
                 code.clone(),
-                RealQuote::new(code, name, real_price),
+                RealQuote {
+                    price_source: PriceSource::Mock,
+                    ..RealQuote::new(code, name, real_price)
+                },
             );
         }
 
         quotes
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// 启动本地 HTTP 桩服务，对每个请求返回固定的响应内容
+    async fn start_stub_server(body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 4096];
+                let _ = socket.read(&mut buf).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        format!("http://{}", addr)
+    }
+
+    /// 返回一个没有服务监听的地址（请求会被拒绝连接）
+    async fn unreachable_url() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn test_manual_prices_fill_missing_and_override_when_pinned() {
+        let mut quotes = HashMap::new();
+        quotes.insert("600519".to_string(), RealQuote::new("600519".to_string(), "贵州茅台".to_string(), 1850.0));
+        quotes.insert("000001".to_string(), RealQuote::new("000001".to_string(), "平安银行".to_string(), 10.0));
+
+        let manual_prices = vec![
+            ManualPrice::new("sh600519".to_string(), 1800.0, "2024-06-28".to_string(), false).unwrap(),
+            ManualPrice::new("sz000001".to_string(), 9.5, "2024-06-28".to_string(), true).unwrap(),
+            ManualPrice::new("PF0001".to_string(), 1.25, "2024-06-30".to_string(), false).unwrap(),
+        ];
+        let codes = vec!["600519".to_string(), "000001".to_string(), "PF0001".to_string()];
+        QuoteService::apply_manual_prices(&mut quotes, &codes, &manual_prices);
+
+        // 未固定：有实时行情时不使用手动价格
+        assert_eq!(quotes["600519"].real_price, 1850.0);
+        assert_eq!(quotes["600519"].price_source, PriceSource::Live);
+
+        // 固定：覆盖实时行情，保留行情中的名称
+        assert_eq!(quotes["000001"].real_price, 9.5);
+        assert_eq!(quotes["000001"].name, "平安银行");
+        assert_eq!(quotes["000001"].price_source, PriceSource::Manual);
+
+        // 没有行情的场外产品使用手动价格
+        assert_eq!(quotes["PF0001"].real_price, 1.25);
+        assert_eq!(quotes["PF0001"].as_of.as_deref(), Some("2024-06-30"));
+        assert_eq!(quotes["PF0001"].price_source, PriceSource::Manual);
+    }
+
+    #[tokio::test]
+    async fn test_failover_to_next_provider() {
+        let providers: Vec<Arc<dyn QuoteProvider>> = vec![
+            Arc::new(TencentProvider::with_base_url(unreachable_url().await)),
+            Arc::new(SinaProvider::with_base_url(start_stub_server(include_str!("fixtures/quotes/sina.txt")).await)),
+        ];
+
+        let codes = vec!["600519".to_string(), "sz000001".to_string(), "sh688999".to_string()];
+        let outcomes = QuoteService::fetch_quotes(&providers, &codes).await;
+
+        // 腾讯无法连接，自动切换到新浪；结果使用调用方传入的原始代码
+        match &outcomes["600519"] {
+            QuoteOutcome::Ok { quote } => {
+                assert_eq!(quote.code, "600519");
+                assert_eq!(quote.real_price, 1850.0);
+            }
+            other => panic!("应获取成功: {:?}", other),
+        }
+        assert!(matches!(&outcomes["sz000001"], QuoteOutcome::Suspended { .. }));
+        assert!(matches!(outcomes["sh688999"], QuoteOutcome::UnknownCode));
+    }
+
+    #[tokio::test]
+    async fn test_cache_serves_fresh_and_falls_back_to_last_known_price() {
+        let database = Database::open_in_memory().unwrap();
+        let providers: Vec<Arc<dyn QuoteProvider>> = vec![
+            Arc::new(TencentProvider::with_base_url(unreachable_url().await)),
+        ];
+        let codes = vec!["600519".to_string()];
+
+        // 有效期内的缓存直接使用，不请求网络
+        {
+            let conn = database.get().unwrap();
+            let quote = RealQuote::new("sh600519".to_string(), "贵州茅台".to_string(), 1850.0);
+            QuoteCacheRepository::upsert(&conn, "sh600519", &quote).unwrap();
+        }
+        let outcomes = QuoteService::fetch_quotes_cached(&database, &providers, Duration::from_secs(60), &codes).await.unwrap();
+        match &outcomes["600519"] {
+            QuoteOutcome::Ok { quote } => {
+                assert_eq!(quote.real_price, 1850.0);
+                assert!(!quote.is_stale);
+            }
+            other => panic!("应使用缓存: {:?}", other),
+        }
+
+        // 缓存过期且网络失败时，使用最后已知价格并标记为过期
+        let outcomes = QuoteService::fetch_quotes_cached(&database, &providers, Duration::ZERO, &codes).await.unwrap();
+        match &outcomes["600519"] {
+            QuoteOutcome::Ok { quote } => {
+                assert_eq!(quote.code, "600519");
+                assert_eq!(quote.real_price, 1850.0);
+                assert!(quote.is_stale);
+                assert!(quote.as_of.is_some());
+            }
+            other => panic!("应使用最后已知价格: {:?}", other),
+        }
+    }
+}
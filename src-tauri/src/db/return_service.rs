@@ -0,0 +1,369 @@
+/// 收益率服务
+/// 根据全部交易记录、分红流水和每日快照，计算各投资组合及全部组合合计的资金加权（XIRR）
+/// 和时间加权（TWR）收益率
+
+use crate::db::{CashRepository, PositionRepository, SnapshotRepository};
+use crate::error::Result;
+use crate::models::{CashTransaction, FxRates, PortfolioProfitLoss, PortfolioSnapshot, Position, ProfitLossView, ReturnMetrics};
+use crate::models::cash::CashTransactionType;
+use crate::models::returns::{self, CashFlow, TwrPeriod};
+use rusqlite::Connection;
+use std::collections::{BTreeMap, HashSet};
+
+/// 单个组合用于计算收益率的数据（金额均为基础货币）
+#[derive(Debug, Default)]
+struct PortfolioFlows {
+    /// XIRR 现金流：买入为负，卖出回款、分红为正，今天的持仓市值为正
+    cash_flows: Vec<CashFlow>,
+    /// 各日持仓市值（历史快照 + 今天的实时市值）
+    market_values: BTreeMap<String, f64>,
+    /// 各日从持仓中流出的金额：卖出回款 + 分红 - 买入成本
+    payouts: Vec<(String, f64)>,
+}
+
+/// 收益率服务
+pub struct ReturnService;
+
+impl ReturnService {
+    /// 读取全部交易记录、分红流水和快照，补充盈亏视图中各股票、各组合及合计的收益率指标
+    pub fn apply(conn: &Connection, view: &mut ProfitLossView, fx_rates: &FxRates, today: &str) -> Result<()> {
+        let positions = PositionRepository::find_all(conn)?;
+        let cash_transactions = CashRepository::find_all(conn)?;
+        let snapshots = SnapshotRepository::find_all(conn)?;
+
+        view.overall_returns = Self::compute(&mut view.portfolios, &positions, &cash_transactions, &snapshots, fx_rates, today);
+        Ok(())
+    }
+
+    /// 计算各股票和各组合的收益率（写入 TargetProfitLoss::returns、PortfolioProfitLoss::returns），
+    /// 返回全部组合合计的收益率
+    ///
+    /// - XIRR：每笔买入（含已平仓记录）按买入日投入，卖出按卖出日取回，分红按流水日期计入，
+    ///   今天取回当前持仓市值
+    /// - TWR：以相邻两个快照日划分区间，期间的买入、卖出和分红视为期末发生的资金进出
+    ///
+    /// 外币记录的买入成本按买入时汇率折算，卖出回款按卖出流水记录的成交汇率折算（没有时按当前汇率），
+    /// 市值按当前汇率折算，分红按入账时已折算的流水金额计入，缺少汇率的记录不计入。
+    /// 各组合基础货币不同时，合计收益率为空
+    pub fn compute(
+        portfolios: &mut [PortfolioProfitLoss],
+        positions: &[Position],
+        cash_transactions: &[CashTransaction],
+        snapshots: &[PortfolioSnapshot],
+        fx_rates: &FxRates,
+        today: &str,
+    ) -> ReturnMetrics {
+        let mut all_flows = Vec::new();
+
+        for portfolio in portfolios.iter_mut() {
+            Self::apply_target_returns(portfolio, positions, cash_transactions, today);
+            let flows = Self::portfolio_flows(portfolio, positions, cash_transactions, snapshots, fx_rates, today);
+            portfolio.returns = Self::metrics(&flows.cash_flows, &[&flows], today);
+            all_flows.push(flows);
+        }
+
+        let same_base = portfolios.windows(2).all(|pair| pair[0].base_currency == pair[1].base_currency);
+        if portfolios.is_empty() || !same_base {
+            return ReturnMetrics::default();
+        }
+
+        let cash_flows: Vec<CashFlow> = all_flows.iter().flat_map(|flows| flows.cash_flows.iter().copied()).collect();
+        let series: Vec<&PortfolioFlows> = all_flows.iter().collect();
+        Self::metrics(&cash_flows, &series, today)
+    }
+
+    /// 计算组合中各股票的收益率（交易币种）
+    ///
+    /// 包含该股票在组合中的全部记录（未平仓、已平仓及减仓产生的已卖出记录）：
+    /// - XIRR：每笔买入按买入日投入，卖出按卖出日取回，分红按流水日期计入，今天取回未平仓部分的市值
+    /// - TWR：以各成交日的成交均价（买入和卖出）作为当日价格划分区间，剔除买卖的影响，分红计入所在区间
+    fn apply_target_returns(
+        portfolio: &mut PortfolioProfitLoss,
+        positions: &[Position],
+        cash_transactions: &[CashTransaction],
+        today: &str,
+    ) {
+        for target in portfolio.target_profit_losses.iter_mut() {
+            let records: Vec<&Position> = positions
+                .iter()
+                .filter(|p| p.portfolio == portfolio.portfolio && p.code == target.code)
+                .collect();
+            let ids: HashSet<&str> = records.iter().map(|p| p.id.as_str()).collect();
+            let dividends: Vec<(String, f64)> = cash_transactions
+                .iter()
+                .filter(|txn| {
+                    txn.txn_type.is_dividend()
+                        && txn.position_id.as_deref().is_some_and(|id| ids.contains(id))
+                })
+                .map(|txn| (txn.txn_date.clone(), txn.trade_amount()))
+                .collect();
+            let open_value: f64 = target
+                .position_profit_losses
+                .iter()
+                .map(|p| p.quantity as f64 * p.real_price.unwrap_or(p.buy_price))
+                .sum();
+
+            target.returns = Self::target_metrics(&records, &dividends, open_value, today);
+        }
+    }
+
+    /// 单支股票的收益率指标，计算方式见 apply_target_returns
+    ///
+    /// 参数：
+    /// - records: 该股票的全部交易记录
+    /// - dividends: 分红流水 (日期, 金额)
+    /// - open_value: 未平仓部分的当前市值
+    fn target_metrics(records: &[&Position], dividends: &[(String, f64)], open_value: f64, today: &str) -> ReturnMetrics {
+        let mut cash_flows = Vec::new();
+        // 按成交日期汇总：(成交金额, 成交数量, 持股数变化)
+        let mut trades: BTreeMap<&str, (f64, f64, f64)> = BTreeMap::new();
+
+        for record in records {
+            let quantity = record.quantity as f64;
+            cash_flows.extend(CashFlow::on(&record.buy_date, -record.cost()));
+            let trade = trades.entry(record.buy_date.as_str()).or_default();
+            trade.0 += quantity * record.buy_price;
+            trade.1 += quantity;
+            trade.2 += quantity;
+
+            if let (Some(sell_price), Some(sell_date)) = (record.sell_price, &record.sell_date) {
+                cash_flows.extend(CashFlow::on(sell_date, sell_price * quantity - record.sell_fee.unwrap_or(0.0)));
+                let trade = trades.entry(sell_date.as_str()).or_default();
+                trade.0 += quantity * sell_price;
+                trade.1 += quantity;
+                trade.2 -= quantity;
+            }
+        }
+        for (date, amount) in dividends {
+            cash_flows.extend(CashFlow::on(date, *amount));
+        }
+        cash_flows.extend(CashFlow::on(today, open_value));
+
+        let trades: Vec<(&str, f64, f64)> = trades
+            .into_iter()
+            .map(|(date, (amount, traded, change))| (date, if traded != 0.0 { amount / traded } else { 0.0 }, change))
+            .collect();
+
+        let mut periods = Vec::new();
+        let mut held = 0.0;
+        for (index, (date, price, change)) in trades.iter().enumerate() {
+            held += change;
+            let next = trades.get(index + 1);
+            let end_value = match next {
+                Some((_, next_price, _)) => held * next_price,
+                None => open_value,
+            };
+            let payouts: f64 = dividends
+                .iter()
+                .filter(|(dividend_date, _)| {
+                    dividend_date.as_str() > *date && next.map_or(true, |(next_date, _, _)| dividend_date.as_str() <= *next_date)
+                })
+                .map(|(_, amount)| amount)
+                .sum();
+            periods.push(TwrPeriod { start_value: held * price, end_value: end_value + payouts });
+        }
+
+        let since = trades.first().map(|(date, _, _)| date.to_string());
+        let twr = returns::time_weighted_return(&periods);
+        let days = since.as_deref().and_then(|since| returns::days_between(since, today)).unwrap_or(0);
+
+        ReturnMetrics {
+            xirr: returns::xirr(&cash_flows),
+            twr,
+            annualized_twr: twr.and_then(|twr| returns::annualize(twr, days)),
+            since,
+        }
+    }
+
+    /// 整理单个组合的现金流和市值序列
+    fn portfolio_flows(
+        portfolio: &PortfolioProfitLoss,
+        positions: &[Position],
+        cash_transactions: &[CashTransaction],
+        snapshots: &[PortfolioSnapshot],
+        fx_rates: &FxRates,
+        today: &str,
+    ) -> PortfolioFlows {
+        let mut flows = PortfolioFlows::default();
+
+        for (date, amount) in Self::holding_flows(&portfolio.portfolio, &portfolio.base_currency, positions, cash_transactions, fx_rates) {
+            flows.cash_flows.extend(CashFlow::on(&date, amount));
+            flows.payouts.push((date, amount));
+        }
+
+        flows.cash_flows.extend(CashFlow::on(today, portfolio.sum_market_value));
+
+        for snapshot in snapshots.iter().filter(|s| s.portfolio == portfolio.portfolio && s.snapshot_date.as_str() < today) {
+            flows.market_values.insert(snapshot.snapshot_date.clone(), snapshot.market_value);
+        }
+        flows.market_values.insert(today.to_string(), portfolio.sum_market_value);
+
+        flows
+    }
+
+    /// 组合持仓的资金进出（基础货币）：(日期, 金额)，买入成本为负，卖出回款和分红为正
+    ///
+    /// 汇率折算规则见 compute
+    pub(crate) fn holding_flows(
+        portfolio: &str,
+        base_currency: &str,
+        positions: &[Position],
+        cash_transactions: &[CashTransaction],
+        fx_rates: &FxRates,
+    ) -> Vec<(String, f64)> {
+        let mut flows = Vec::new();
+
+        for position in positions.iter().filter(|p| p.portfolio == portfolio) {
+            let Some(current_fx_rate) = fx_rates.rate(&position.currency, base_currency) else {
+                continue;
+            };
+            let buy_fx_rate = match position.buy_fx_rate {
+                Some(rate) if position.currency != base_currency => rate,
+                _ => current_fx_rate,
+            };
+
+            flows.push((position.buy_date.clone(), -position.cost() * buy_fx_rate));
+
+            if let (Some(sell_price), Some(sell_date)) = (position.sell_price, &position.sell_date) {
+                let sell_fx_rate = cash_transactions
+                    .iter()
+                    .find(|txn| txn.txn_type == CashTransactionType::Sell && txn.position_id.as_deref() == Some(position.id.as_str()))
+                    .and_then(|txn| txn.fx_rate)
+                    .filter(|_| position.currency != base_currency)
+                    .unwrap_or(current_fx_rate);
+                let proceeds = (sell_price * position.quantity as f64 - position.sell_fee.unwrap_or(0.0)) * sell_fx_rate;
+                flows.push((sell_date.clone(), proceeds));
+            }
+        }
+
+        // 配股缴款已计入持仓的买入价格（即买入成本），不再单独计入；分红流水已按入账时汇率折算为基础货币
+        for txn in cash_transactions.iter().filter(|txn| {
+            txn.portfolio == portfolio && txn.txn_type.is_dividend()
+        }) {
+            flows.push((txn.txn_date.clone(), txn.amount));
+        }
+
+        flows
+    }
+
+    /// 根据现金流和一个或多个组合的市值序列计算收益率指标
+    ///
+    /// 多个组合合计时，每个区间只统计区间首尾两天都有市值的组合
+    fn metrics(cash_flows: &[CashFlow], series: &[&PortfolioFlows], today: &str) -> ReturnMetrics {
+        let mut dates: Vec<&String> = series.iter().flat_map(|flows| flows.market_values.keys()).collect();
+        dates.sort();
+        dates.dedup();
+
+        let periods: Vec<TwrPeriod> = dates
+            .windows(2)
+            .map(|window| {
+                let (start, end) = (window[0], window[1]);
+                series
+                    .iter()
+                    .filter_map(|flows| {
+                        let start_value = flows.market_values.get(start)?;
+                        let end_value = flows.market_values.get(end)?;
+                        let payouts: f64 = flows.payouts
+                            .iter()
+                            .filter(|(date, _)| date > start && date <= end)
+                            .map(|(_, amount)| amount)
+                            .sum();
+                        Some((*start_value, end_value + payouts))
+                    })
+                    .fold(TwrPeriod { start_value: 0.0, end_value: 0.0 }, |period, (start_value, end_value)| TwrPeriod {
+                        start_value: period.start_value + start_value,
+                        end_value: period.end_value + end_value,
+                    })
+            })
+            .collect();
+
+        let twr = returns::time_weighted_return(&periods);
+        let since = dates.first().filter(|_| twr.is_some()).map(|date| date.to_string());
+        let days = since.as_deref().and_then(|since| returns::days_between(since, today)).unwrap_or(0);
+
+        ReturnMetrics {
+            xirr: returns::xirr(cash_flows),
+            twr,
+            annualized_twr: twr.and_then(|twr| returns::annualize(twr, days)),
+            since,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::PortfolioService;
+    use crate::models::RealQuote;
+    use std::collections::HashMap;
+
+    fn snapshot(date: &str, market_value: f64) -> PortfolioSnapshot {
+        PortfolioSnapshot {
+            portfolio: "组合A".to_string(),
+            snapshot_date: date.to_string(),
+            base_currency: "CNY".to_string(),
+            market_value,
+            position_cost: 0.0,
+            cash_balance: 0.0,
+            total_equity: market_value,
+            profit_loss: 0.0,
+            today_profit_loss: 0.0,
+            is_partial: false,
+            created_at: format!("{} 15:30:00", date),
+        }
+    }
+
+    #[test]
+    fn test_twr_ignores_added_money_and_xirr_weights_it() {
+        // 年初买入 1000，半年上涨 10% 后追加 1100，下半年再上涨 10%
+        let positions = vec![
+            Position::new("sh600000".to_string(), "浦发银行".to_string(), 10.0, "2024-01-01".to_string(), 100, "组合A".to_string()),
+            Position::new("sh600000".to_string(), "浦发银行".to_string(), 11.0, "2024-07-01".to_string(), 100, "组合A".to_string()),
+        ];
+        let mut quotes = HashMap::new();
+        quotes.insert("sh600000".to_string(), RealQuote::new("sh600000".to_string(), "浦发银行".to_string(), 12.1));
+        let mut portfolios = PortfolioService::aggregate_positions(
+            positions.clone(), &quotes, &HashMap::new(), &HashMap::new(), &HashMap::new(), &FxRates::default(),
+        ).unwrap();
+        let snapshots = vec![snapshot("2024-01-01", 1000.0), snapshot("2024-07-01", 2200.0)];
+
+        let overall = ReturnService::compute(&mut portfolios, &positions, &[], &snapshots, &FxRates::default(), "2025-01-01");
+        let returns = &portfolios[0].returns;
+
+        assert!((returns.twr.unwrap() - 0.21).abs() < 1e-9);
+        assert_eq!(returns.since.as_deref(), Some("2024-01-01"));
+        assert!((returns.annualized_twr.unwrap() - (1.21f64.powf(365.0 / 366.0) - 1.0)).abs() < 1e-9);
+
+        // 追加的资金只赚了半年 10%，资金加权收益率介于 10% 与 21% 之间
+        let xirr = returns.xirr.unwrap();
+        assert!(xirr > 0.1 && xirr < 0.21, "xirr = {}", xirr);
+        assert_eq!(&overall, returns);
+    }
+
+    #[test]
+    fn test_target_returns_include_sold_records() {
+        // 年初买入 200 股 @10，年中以 12 卖出 100 股，剩余 100 股年末价格 12.1
+        let parent = Position::new("sh600000".to_string(), "浦发银行".to_string(), 10.0, "2024-01-01".to_string(), 100, "组合A".to_string());
+        let mut sold = Position::new("sh600000".to_string(), "浦发银行".to_string(), 10.0, "2024-01-01".to_string(), 100, "组合A".to_string());
+        sold.status = "CLOSE".to_string();
+        sold.sell_price = Some(12.0);
+        sold.sell_date = Some("2024-07-01".to_string());
+        sold.parent_id = Some(parent.id.clone());
+
+        let mut quotes = HashMap::new();
+        quotes.insert("sh600000".to_string(), RealQuote::new("sh600000".to_string(), "浦发银行".to_string(), 12.1));
+        let mut portfolios = PortfolioService::aggregate_positions(
+            vec![parent.clone()], &quotes, &HashMap::new(), &HashMap::new(), &HashMap::new(), &FxRates::default(),
+        ).unwrap();
+
+        ReturnService::compute(&mut portfolios, &[parent, sold], &[], &[], &FxRates::default(), "2025-01-01");
+        let returns = &portfolios[0].target_profit_losses[0].returns;
+
+        // 股价全年上涨 21%，与买卖时点无关
+        assert!((returns.twr.unwrap() - 0.21).abs() < 1e-9);
+        assert_eq!(returns.since.as_deref(), Some("2024-01-01"));
+        // 上半年投入的 2000 赚了 20%，下半年仅剩 1200 赚了不到 1%，资金加权收益率高于 21%
+        let xirr = returns.xirr.unwrap();
+        assert!(xirr > 0.28 && xirr < 0.29, "xirr = {}", xirr);
+    }
+}
@@ -0,0 +1,114 @@
+/// 风险分析服务
+/// 根据本地保存的每日收盘价和组合快照，计算个股和投资组合的风险指标
+
+use crate::db::{CashRepository, DailyPriceRepository, FxRateRepository, PortfolioRepository, PositionRepository, QuoteService, ReturnService, SnapshotRepository};
+use crate::error::Result;
+use crate::models::{FxRates, PortfolioSnapshot};
+use crate::models::portfolio::DEFAULT_BASE_CURRENCY;
+use crate::models::risk::{self, PeriodReturn, RiskMetrics, RiskQuery};
+use rusqlite::Connection;
+use std::collections::BTreeMap;
+
+/// 风险分析服务
+pub struct RiskService;
+
+impl RiskService {
+    /// 计算个股在日期范围内的风险指标（使用已保存的每日收盘价）
+    pub fn stock_risk(conn: &Connection, code: &str, query: &RiskQuery) -> Result<RiskMetrics> {
+        query.validate()?;
+        let code = QuoteService::format_stock_code(code);
+        let (from, to) = query.date_range();
+
+        let closes: Vec<(String, f64)> = DailyPriceRepository::find_range(conn, &code, from, to)?
+            .into_iter()
+            .map(|price| (price.price_date, price.close))
+            .collect();
+        let returns = risk::price_returns(&closes);
+        println!("[RiskService] {} 共 {} 个收盘价，{} 个收益率区间", code, closes.len(), returns.len());
+
+        Self::compute(conn, &returns, query)
+    }
+
+    /// 计算投资组合在日期范围内的风险指标（使用已保存的组合快照）
+    ///
+    /// 未指定基准时使用组合设置的业绩比较基准
+    pub fn portfolio_risk(conn: &Connection, portfolio: &str, query: &RiskQuery) -> Result<RiskMetrics> {
+        query.validate()?;
+        let (from, to) = query.date_range();
+
+        let settings = PortfolioRepository::find_by_name(conn, portfolio)?;
+        let base_currency = settings
+            .as_ref()
+            .map(|p| p.base_currency.clone())
+            .unwrap_or_else(|| DEFAULT_BASE_CURRENCY.to_string());
+        let returns = Self::portfolio_returns(conn, portfolio, &base_currency, from, to)?;
+
+        let query = RiskQuery {
+            benchmark: query.benchmark.clone().or_else(|| settings.and_then(|p| p.benchmark)),
+            ..query.clone()
+        };
+        Self::compute(conn, &returns, &query)
+    }
+
+    /// 组合在日期范围内相邻两个快照之间的收益率
+    ///
+    /// 收益率只反映持仓本身的表现：相邻两个快照之间的买入、卖出和分红视为期末发生的资金进出，
+    /// 与 ReturnService 的时间加权收益率一致
+    pub(crate) fn portfolio_returns(
+        conn: &Connection,
+        portfolio: &str,
+        base_currency: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<PeriodReturn>> {
+        let snapshots = SnapshotRepository::find_range(conn, portfolio, from, to)?;
+        let positions = PositionRepository::find_by_portfolio(conn, portfolio)?;
+        let cash_transactions = CashRepository::find_by_portfolio(conn, portfolio)?;
+        let fx_rates = FxRates::new(FxRateRepository::find_all(conn)?);
+
+        let flows = ReturnService::holding_flows(portfolio, base_currency, &positions, &cash_transactions, &fx_rates);
+        let returns = Self::snapshot_returns(&snapshots, &flows);
+        println!("[RiskService] 组合 {} 共 {} 个快照，{} 个收益率区间", portfolio, snapshots.len(), returns.len());
+
+        Ok(returns)
+    }
+
+    /// 读取基准指数的收盘价并计算风险指标
+    fn compute(conn: &Connection, returns: &[PeriodReturn], query: &RiskQuery) -> Result<RiskMetrics> {
+        let benchmark = QuoteService::format_stock_code(query.benchmark());
+        let (from, to) = query.date_range();
+
+        let benchmark_closes: BTreeMap<String, f64> = DailyPriceRepository::find_range(conn, &benchmark, from, to)?
+            .into_iter()
+            .map(|price| (price.price_date, price.close))
+            .collect();
+        if benchmark_closes.is_empty() {
+            println!("⚠️ [RiskService] 基准 {} 没有已保存的收盘价，无法计算 Beta / Alpha", benchmark);
+        }
+
+        Ok(RiskMetrics::compute(returns, &benchmark, &benchmark_closes, query.risk_free_rate()))
+    }
+
+    /// 相邻两个快照之间持仓的收益率：(期末市值 + 期间的资金流出 - 期间的资金投入) / 期初市值 - 1
+    ///
+    /// 期初市值不大于 0 的区间（如尚未建仓）跳过
+    fn snapshot_returns(snapshots: &[PortfolioSnapshot], flows: &[(String, f64)]) -> Vec<PeriodReturn> {
+        snapshots
+            .windows(2)
+            .filter(|pair| pair[0].market_value > 0.0)
+            .map(|pair| {
+                let (start, end) = (&pair[0], &pair[1]);
+                let payouts: f64 = flows
+                    .iter()
+                    .filter(|(date, _)| *date > start.snapshot_date && *date <= end.snapshot_date)
+                    .map(|(_, amount)| amount)
+                    .sum();
+                PeriodReturn {
+                    start_date: start.snapshot_date.clone(),
+                    end_date: end.snapshot_date.clone(),
+                    rate: (end.market_value + payouts) / start.market_value - 1.0,
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,64 @@
+/// 应用设置数据访问层
+/// 以 key -> JSON 的形式保存各类可配置项
+
+use rusqlite::{Connection, params, OptionalExtension};
+use serde::{de::DeserializeOwned, Serialize};
+use crate::error::Result;
+use crate::models::FeeConfig;
+use crate::models::quote::QuoteConfig;
+
+/// 交易费用配置的设置键
+const FEE_CONFIG_KEY: &str = "fee_config";
+
+/// 行情配置的设置键
+const QUOTE_CONFIG_KEY: &str = "quote_config";
+
+/// 设置数据仓库
+pub struct SettingsRepository;
+
+impl SettingsRepository {
+    /// 读取设置（不存在时返回 None）
+    pub fn get<T: DeserializeOwned>(conn: &Connection, key: &str) -> Result<Option<T>> {
+        let value: Option<String> = conn
+            .query_row("SELECT value FROM settings WHERE key = ?", [key], |row| row.get(0))
+            .optional()?;
+
+        match value {
+            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 保存设置（存在则覆盖）
+    pub fn set<T: Serialize>(conn: &Connection, key: &str, value: &T) -> Result<()> {
+        let json = serde_json::to_string(value)?;
+        conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?, ?)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, json],
+        )?;
+        Ok(())
+    }
+
+    /// 获取交易费用配置（未配置时使用默认值）
+    pub fn get_fee_config(conn: &Connection) -> Result<FeeConfig> {
+        Ok(Self::get(conn, FEE_CONFIG_KEY)?.unwrap_or_default())
+    }
+
+    /// 保存交易费用配置
+    pub fn save_fee_config(conn: &Connection, config: &FeeConfig) -> Result<()> {
+        config.validate()?;
+        Self::set(conn, FEE_CONFIG_KEY, config)
+    }
+
+    /// 获取行情配置（未配置时使用默认值）
+    pub fn get_quote_config(conn: &Connection) -> Result<QuoteConfig> {
+        Ok(Self::get(conn, QUOTE_CONFIG_KEY)?.unwrap_or_default())
+    }
+
+    /// 保存行情配置
+    pub fn save_quote_config(conn: &Connection, config: &QuoteConfig) -> Result<()> {
+        config.validate()?;
+        Self::set(conn, QUOTE_CONFIG_KEY, config)
+    }
+}
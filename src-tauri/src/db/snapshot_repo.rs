@@ -0,0 +1,107 @@
+/// 组合快照数据访问层
+/// 负责 portfolio_snapshots 表的读写，每个组合每天一条
+
+use rusqlite::{Connection, params};
+use crate::error::Result;
+use crate::models::PortfolioSnapshot;
+
+/// 组合快照数据仓库
+pub struct SnapshotRepository;
+
+impl SnapshotRepository {
+    /// 辅助方法：从数据库行映射到 PortfolioSnapshot 对象
+    fn map_row_to_snapshot(row: &rusqlite::Row) -> rusqlite::Result<PortfolioSnapshot> {
+        Ok(PortfolioSnapshot {
+            portfolio: row.get(0)?,
+            snapshot_date: row.get(1)?,
+            base_currency: row.get(2)?,
+            market_value: row.get(3)?,
+            position_cost: row.get(4)?,
+            cash_balance: row.get(5)?,
+            total_equity: row.get(6)?,
+            profit_loss: row.get(7)?,
+            today_profit_loss: row.get(8)?,
+            is_partial: row.get(9)?,
+            created_at: row.get(10)?,
+        })
+    }
+
+    /// 保存快照（同一组合同一天存在则覆盖，以最后一次记录为准）
+    pub fn upsert(conn: &Connection, snapshot: &PortfolioSnapshot) -> Result<()> {
+        conn.execute(
+            "INSERT INTO portfolio_snapshots (
+                portfolio, snapshot_date, base_currency, market_value, position_cost, cash_balance,
+                total_equity, profit_loss, today_profit_loss, is_partial, created_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(portfolio, snapshot_date) DO UPDATE SET
+                base_currency = excluded.base_currency,
+                market_value = excluded.market_value,
+                position_cost = excluded.position_cost,
+                cash_balance = excluded.cash_balance,
+                total_equity = excluded.total_equity,
+                profit_loss = excluded.profit_loss,
+                today_profit_loss = excluded.today_profit_loss,
+                is_partial = excluded.is_partial,
+                created_at = excluded.created_at",
+            params![
+                &snapshot.portfolio,
+                &snapshot.snapshot_date,
+                &snapshot.base_currency,
+                snapshot.market_value,
+                snapshot.position_cost,
+                snapshot.cash_balance,
+                snapshot.total_equity,
+                snapshot.profit_loss,
+                snapshot.today_profit_loss,
+                snapshot.is_partial,
+                &snapshot.created_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 查询组合在日期范围内（含首尾）的快照，按日期升序
+    pub fn find_range(conn: &Connection, portfolio: &str, from: &str, to: &str) -> Result<Vec<PortfolioSnapshot>> {
+        let mut stmt = conn.prepare(
+            "SELECT portfolio, snapshot_date, base_currency, market_value, position_cost, cash_balance,
+                    total_equity, profit_loss, today_profit_loss, is_partial, created_at
+             FROM portfolio_snapshots
+             WHERE portfolio = ? AND snapshot_date >= ? AND snapshot_date <= ?
+             ORDER BY snapshot_date",
+        )?;
+
+        let snapshots = stmt
+            .query_map(params![portfolio, from, to], Self::map_row_to_snapshot)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(snapshots)
+    }
+
+    /// 查询所有快照，按组合、日期升序
+    pub fn find_all(conn: &Connection) -> Result<Vec<PortfolioSnapshot>> {
+        let mut stmt = conn.prepare(
+            "SELECT portfolio, snapshot_date, base_currency, market_value, position_cost, cash_balance,
+                    total_equity, profit_loss, today_profit_loss, is_partial, created_at
+             FROM portfolio_snapshots
+             ORDER BY portfolio, snapshot_date",
+        )?;
+
+        let snapshots = stmt
+            .query_map([], Self::map_row_to_snapshot)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(snapshots)
+    }
+
+    /// 指定日期的快照最早的记录时间（任一组合），没有快照时为 None
+    ///
+    /// 取最早的时间：只要有组合的快照是收盘前记录的，就需要在收盘后重新记录
+    pub fn earliest_recorded_at(conn: &Connection, snapshot_date: &str) -> Result<Option<String>> {
+        let recorded_at: Option<String> = conn.query_row(
+            "SELECT MIN(created_at) FROM portfolio_snapshots WHERE snapshot_date = ?",
+            [snapshot_date],
+            |row| row.get(0),
+        )?;
+        Ok(recorded_at)
+    }
+}
@@ -0,0 +1,235 @@
+/// 组合快照服务
+/// 每天记录各投资组合的市值、成本、现金和盈亏，以及持仓股票的收盘价
+
+use crate::db::{Database, PortfolioService, PortfolioRepository, QuoteService, DailyPriceRepository, SnapshotRepository};
+use crate::error::Result;
+use crate::models::{DailyPrice, PortfolioSnapshot, ProfitLossView};
+use crate::models::quote::{PriceSource, QuoteOutcome};
+use crate::models::risk::DEFAULT_BENCHMARK;
+use chrono::{Datelike, Local, NaiveDateTime, NaiveTime, Weekday};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+/// 收盘后记录快照的时间（A 股 15:00 收盘，留出行情更新的时间）
+const CLOSE_RECORD_TIME: &str = "15:05:00";
+
+/// 组合快照服务
+pub struct SnapshotService;
+
+impl SnapshotService {
+    /// 按当前行情记录今天的组合快照和收盘价（同一天重复记录时覆盖，以最后一次为准）
+    ///
+    /// 收盘后记录的为当日收盘数据，盘中记录的为记录时的数据。
+    /// 周末和节假日（沪深300 当天没有行情）不记录，返回空列表
+    pub async fn record_today(database: &Database) -> Result<Vec<PortfolioSnapshot>> {
+        let now = Local::now();
+        let today = now.format("%Y-%m-%d").to_string();
+        if matches!(now.weekday(), Weekday::Sat | Weekday::Sun) {
+            println!("📸 {} 是周末，不记录组合快照", today);
+            return Ok(Vec::new());
+        }
+
+        // 基准指数收盘价用于风险分析和业绩基准对比，获取失败不影响快照
+        let benchmark_quotes = match Self::fetch_benchmark_quotes(database).await {
+            Ok(outcomes) => outcomes,
+            Err(e) => {
+                println!("⚠️ 获取基准指数行情失败: {}", e);
+                HashMap::new()
+            }
+        };
+        if let Some(QuoteOutcome::Ok { quote }) = benchmark_quotes.get(DEFAULT_BENCHMARK) {
+            let quote_date = quote.as_of.as_deref().and_then(|as_of| as_of.get(..10));
+            if !quote.is_stale && quote_date.is_some_and(|date| date != today) {
+                println!("📸 {} 休市（沪深300 最新行情日期为 {}），不记录组合快照", today, quote_date.unwrap_or("-"));
+                return Ok(Vec::new());
+            }
+        }
+
+        let view = PortfolioService::build_profit_loss_view(database, false).await?;
+
+        let mut conn = database.acquire().await?;
+        let snapshots = Self::save_view(&mut conn, &view, &today)?;
+        println!("📸 已记录 {} 的组合快照 {} 个", today, snapshots.len());
+        Self::save_benchmark_closes(&conn, &benchmark_quotes, &today)?;
+
+        Ok(snapshots)
+    }
+
+    /// 获取默认基准（沪深300）和各组合设置的业绩比较基准的行情
+    async fn fetch_benchmark_quotes(database: &Database) -> Result<HashMap<String, QuoteOutcome>> {
+        let mut codes = vec![DEFAULT_BENCHMARK.to_string()];
+        for portfolio in PortfolioRepository::find_all(&*database.acquire().await?, false)? {
+            if let Some(benchmark) = portfolio.benchmark.map(|code| QuoteService::format_stock_code(&code)) {
+                if !codes.contains(&benchmark) {
+                    codes.push(benchmark);
+                }
+            }
+        }
+
+        QuoteService::fetch_configured_quotes(database, &codes).await
+    }
+
+    /// 保存基准指数的收盘价（价格日期取行情时间的日期）
+    fn save_benchmark_closes(conn: &Connection, outcomes: &HashMap<String, QuoteOutcome>, today: &str) -> Result<()> {
+        for (code, outcome) in outcomes {
+            let QuoteOutcome::Ok { quote } = outcome else {
+                println!("⚠️ 未获取到基准指数 {} 的行情", code);
+                continue;
+            };
+
+            let price_date = quote
+                .as_of
+                .as_deref()
+                .and_then(|as_of| as_of.get(..10))
+                .filter(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok())
+                .unwrap_or(today);
+            DailyPriceRepository::upsert(conn, &DailyPrice {
+                code: code.clone(),
+                price_date: price_date.to_string(),
+                close: quote.real_price,
+                prev_close: quote.prev_close,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// 需要时记录今天的快照：今天还没有快照，或已有的快照是收盘前记录的而现在已收盘
+    pub async fn record_today_if_needed(database: &Database) -> Result<()> {
+        let now = Local::now().naive_local();
+        let today = now.format("%Y-%m-%d").to_string();
+        let close_time = format!("{} {}", today, CLOSE_RECORD_TIME);
+        let after_close = now.format("%Y-%m-%d %H:%M:%S").to_string() >= close_time;
+
+        match SnapshotRepository::earliest_recorded_at(&*database.acquire().await?, &today)? {
+            Some(recorded_at) if recorded_at >= close_time => {
+                println!("📸 {} 的收盘快照已存在，跳过", today);
+                return Ok(());
+            }
+            Some(_) if !after_close => {
+                println!("📸 {} 的盘中快照已存在，收盘后再记录", today);
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        Self::record_today(database).await?;
+        Ok(())
+    }
+
+    /// 应用运行期间的快照任务：启动时按需记录一次，之后每天收盘后再记录，
+    /// 使盘中记录的快照被当天的收盘数据覆盖
+    pub async fn run_daily(database: &Database) {
+        loop {
+            if let Err(e) = Self::record_today_if_needed(database).await {
+                eprintln!("⚠️  记录组合快照失败: {}", e);
+            }
+            tokio::time::sleep(Self::until_next_close(Local::now().naive_local())).await;
+        }
+    }
+
+    /// 距离下一次收盘后记录时间的时长
+    fn until_next_close(now: NaiveDateTime) -> std::time::Duration {
+        let close = NaiveTime::parse_from_str(CLOSE_RECORD_TIME, "%H:%M:%S").expect("收盘记录时间格式错误");
+        let mut next = now.date().and_time(close);
+        if next <= now {
+            next += chrono::Duration::days(1);
+        }
+        (next - now).to_std().unwrap_or_default()
+    }
+
+    /// 保存盈亏视图中的组合快照和收盘价
+    ///
+    /// 只保存行情数据源的价格（实时或缓存），手动价格和模拟价格不作为收盘价，
+    /// 价格日期取行情时间的日期（过期的最后已知价格记在原来的日期）
+    pub fn save_view(conn: &mut Connection, view: &ProfitLossView, today: &str) -> Result<Vec<PortfolioSnapshot>> {
+        let tx = conn.transaction()?;
+        let mut snapshots = Vec::new();
+
+        for portfolio in &view.portfolios {
+            let snapshot = PortfolioSnapshot::from_profit_loss(portfolio, today);
+            SnapshotRepository::upsert(&tx, &snapshot)?;
+            snapshots.push(snapshot);
+
+            for target in &portfolio.target_profit_losses {
+                let Some(close) = target.real_price else {
+                    continue;
+                };
+                if !matches!(target.price_source, Some(PriceSource::Live | PriceSource::Cached)) {
+                    continue;
+                }
+
+                let price_date = target
+                    .price_as_of
+                    .as_deref()
+                    .and_then(|as_of| as_of.get(..10))
+                    .filter(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok())
+                    .unwrap_or(today);
+                DailyPriceRepository::upsert(&tx, &DailyPrice {
+                    code: QuoteService::format_stock_code(&target.code),
+                    price_date: price_date.to_string(),
+                    close,
+                    prev_close: target.prev_close,
+                })?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(snapshots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Position, RealQuote, FxRates};
+    use crate::models::quote::ManualPrice;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_save_view_records_snapshots_and_market_closes() {
+        let database = Database::open_in_memory().unwrap();
+        let positions = vec![
+            Position::new("600519".to_string(), "贵州茅台".to_string(), 1800.0, "2025-01-02".to_string(), 100, "组合A".to_string()),
+            Position::new("otcPF0001".to_string(), "私募基金".to_string(), 1.0, "2025-01-02".to_string(), 10000, "组合A".to_string()),
+            Position::new("000001".to_string(), "平安银行".to_string(), 12.0, "2025-01-02".to_string(), 1000, "组合B".to_string()),
+        ];
+        let mut quotes = HashMap::new();
+        let mut quote = RealQuote::new("600519".to_string(), "贵州茅台".to_string(), 1850.0).with_prev_close(Some(1840.0));
+        quote.as_of = Some("2025-06-27 15:00:00".to_string());
+        quotes.insert("600519".to_string(), quote);
+        let manual_price = ManualPrice::new("otcPF0001".to_string(), 1.2, "2025-06-27".to_string(), false).unwrap();
+        quotes.insert("otcPF0001".to_string(), manual_price.to_quote("otcPF0001".to_string(), "私募基金".to_string()));
+
+        let portfolios = PortfolioService::aggregate_positions(positions, &quotes, &HashMap::new(), &HashMap::new(), &HashMap::new(), &FxRates::default()).unwrap();
+        let view = ProfitLossView { portfolios, is_partial: true, missing_codes: vec!["000001".to_string()], missing_fx_rates: vec![], overall_returns: Default::default() };
+
+        let mut conn = database.get().unwrap();
+        SnapshotService::save_view(&mut conn, &view, "2025-06-28").unwrap();
+        // 同一天再次记录时覆盖
+        let snapshots = SnapshotService::save_view(&mut conn, &view, "2025-06-28").unwrap();
+        assert_eq!(snapshots.len(), 2);
+
+        let history = SnapshotRepository::find_range(&conn, "组合A", "2025-06-01", "2025-06-30").unwrap();
+        assert_eq!(history.len(), 1);
+        // 只有缺少价格的组合B标记为不完整
+        assert!(!history[0].is_partial);
+        assert!(SnapshotRepository::find_range(&conn, "组合B", "2025-06-01", "2025-06-30").unwrap()[0].is_partial);
+        assert_eq!(history[0].market_value, 1850.0 * 100.0 + 1.2 * 10000.0);
+        assert_eq!(history[0].total_equity, history[0].cash_balance + history[0].market_value);
+
+        // 收盘价按行情日期保存，手动价格不作为收盘价
+        let prices = DailyPriceRepository::find_range(&conn, "sh600519", "2025-06-01", "2025-06-30").unwrap();
+        assert_eq!(prices.len(), 1);
+        assert_eq!((prices[0].price_date.as_str(), prices[0].close, prices[0].prev_close), ("2025-06-27", 1850.0, Some(1840.0)));
+        assert!(DailyPriceRepository::find_range(&conn, "otcPF0001", "2025-06-01", "2025-06-30").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_until_next_close() {
+        let at = |time: &str| NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(SnapshotService::until_next_close(at("2025-06-27 10:00:00")).as_secs(), 5 * 3600 + 5 * 60);
+        // 已过今天的记录时间时等到明天
+        assert_eq!(SnapshotService::until_next_close(at("2025-06-27 15:05:00")).as_secs(), 24 * 3600);
+    }
+}
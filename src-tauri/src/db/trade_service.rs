@@ -0,0 +1,829 @@
+/// 交易服务
+/// 负责平仓、减仓、重新打开、修改持仓以及按股票维度跨批次卖出
+
+use rusqlite::{Connection, params};
+use crate::{not_found, invalid_input, business_error, error::Result};
+use crate::db::{AuditGroup, CashRepository, CorporateActionService, PortfolioRepository, QuoteService, SettingsRepository};
+use crate::db::position_repo::{PositionRepository, POSITION_COLUMNS};
+use crate::models::{CashTransaction, Position};
+use crate::models::cash::CashTransactionType;
+use crate::models::position::UpdatePositionRequest;
+use crate::models::trade::{LotMatchingPolicy, LotSale, SellSharesRequest, SellSharesResult};
+
+/// 交易服务
+pub struct TradeService;
+
+impl TradeService {
+    /// 校验卖出价格和费用
+    pub fn validate_sell(sell_price: f64, sell_fee: Option<f64>) -> Result<()> {
+        if sell_price <= 0.0 {
+            return Err(invalid_input!("卖出价格必须大于0"));
+        }
+        if sell_fee.is_some_and(|fee| fee < 0.0) {
+            return Err(invalid_input!("卖出费用不能为负数"));
+        }
+        Ok(())
+    }
+
+    /// 校验卖出日期：格式为 YYYY-MM-DD，且不早于买入日期
+    pub fn validate_sell_date(sell_date: &str, buy_date: &str) -> Result<()> {
+        if chrono::NaiveDate::parse_from_str(sell_date, "%Y-%m-%d").is_err() {
+            return Err(invalid_input!("卖出日期格式必须为 YYYY-MM-DD"));
+        }
+        if sell_date < buy_date {
+            return Err(invalid_input!("卖出日期({})不能早于买入日期({})", sell_date, buy_date));
+        }
+        Ok(())
+    }
+
+    /// 确定卖出费用（未填写时按费用配置计算）
+    pub fn resolve_sell_fee(
+        conn: &Connection,
+        code: &str,
+        sell_price: f64,
+        quantity: i32,
+        sell_fee: Option<f64>,
+    ) -> Result<f64> {
+        match sell_fee {
+            Some(fee) => Ok(fee),
+            None => Ok(SettingsRepository::get_fee_config(conn)?
+                .calculate_sell_fee(code, sell_price, quantity)),
+        }
+    }
+
+    /// 整笔平仓：更新状态为 CLOSE，记录卖出信息，将回款计入组合现金并补扣红利税
+    ///
+    /// fx_rate: 卖出时交易币种到组合基础货币的汇率（币种相同时为 None），用于折算现金流水
+    pub fn close_lot(
+        conn: &Connection,
+        audit: &AuditGroup,
+        position: &Position,
+        sell_price: f64,
+        sell_date: &str,
+        sell_fee: f64,
+        fx_rate: Option<f64>,
+    ) -> Result<()> {
+        conn.execute(
+            "UPDATE positions SET status = 'CLOSE', sell_price = ?, sell_date = ?, sell_fee = ? WHERE id = ?",
+            params![sell_price, sell_date, sell_fee, &position.id],
+        )?;
+        let closed = PositionRepository::find_by_id(conn, &position.id)?;
+        audit.record_position(conn, Some(position), closed.as_ref())?;
+
+        let txn = CashTransaction::for_sell(
+            position.portfolio.clone(),
+            position.id.clone(),
+            sell_price,
+            position.quantity,
+            sell_fee,
+            sell_date.to_string(),
+            fx_rate,
+        );
+        CashRepository::create(conn, &txn)?;
+        audit.record_cash(conn, None, Some(&txn))?;
+
+        CorporateActionService::settle_dividend_tax(conn, audit, position, &position.id, position.quantity, sell_date, fx_rate)?;
+
+        Ok(())
+    }
+
+    /// 部分卖出：创建已卖出的子记录（parent_id 指向原记录），扣减原记录数量
+    ///
+    /// 买入费用按数量比例拆分到子记录，回款和卖出股数对应的红利税关联到子记录
+    ///
+    /// fx_rate: 卖出时交易币种到组合基础货币的汇率（币种相同时为 None）
+    ///
+    /// 返回：已卖出子记录的 ID
+    #[allow(clippy::too_many_arguments)]
+    pub fn reduce_lot(
+        conn: &Connection,
+        audit: &AuditGroup,
+        position: &Position,
+        reduce_quantity: i32,
+        sell_price: f64,
+        sell_date: &str,
+        sell_fee: f64,
+        fx_rate: Option<f64>,
+    ) -> Result<String> {
+        if reduce_quantity <= 0 || reduce_quantity >= position.quantity {
+            return Err(invalid_input!(
+                "减仓数量({})必须大于0且小于持有数量({})",
+                reduce_quantity,
+                position.quantity
+            ));
+        }
+
+        // 按数量比例拆分买入费用
+        let sold_buy_fee = position.buy_fee * reduce_quantity as f64 / position.quantity as f64;
+        let remaining_buy_fee = position.buy_fee - sold_buy_fee;
+
+        // 生成新记录ID（已卖出部分），使用 UUID 避免同一秒内多次减仓时主键冲突
+        let sold_id = format!("{}-sold-{}", position.id, uuid::Uuid::new_v4().simple());
+
+        // 创建已卖出记录（新记录）
+        conn.execute(
+            "INSERT INTO positions (id, code, name, quantity, buy_price, buy_date, status, portfolio, sell_price, sell_date, parent_id, buy_fee, sell_fee, market, currency, buy_fx_rate)
+             VALUES (?, ?, ?, ?, ?, ?, 'CLOSE', ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                sold_id,
+                position.code,
+                position.name,
+                reduce_quantity,
+                position.buy_price,
+                position.buy_date,
+                position.portfolio,
+                sell_price,
+                sell_date,
+                position.id, // parent_id 指向原记录
+                sold_buy_fee,
+                sell_fee,
+                position.market.map(|market| market.as_str()),
+                &position.currency,
+                position.buy_fx_rate,
+            ],
+        )?;
+
+        // 更新原持仓数量及剩余买入费用
+        conn.execute(
+            "UPDATE positions SET quantity = ?, buy_fee = ? WHERE id = ?",
+            params![position.quantity - reduce_quantity, remaining_buy_fee, &position.id],
+        )?;
+
+        // 记录审计：新建的子记录和被扣减的原记录，撤销时子记录删除、原记录数量恢复
+        let sold = PositionRepository::find_by_id(conn, &sold_id)?;
+        audit.record_position(conn, None, sold.as_ref())?;
+        let remaining = PositionRepository::find_by_id(conn, &position.id)?;
+        audit.record_position(conn, Some(position), remaining.as_ref())?;
+
+        // 卖出回款计入组合现金（关联到已卖出记录）
+        let txn = CashTransaction::for_sell(
+            position.portfolio.clone(),
+            sold_id.clone(),
+            sell_price,
+            reduce_quantity,
+            sell_fee,
+            sell_date.to_string(),
+            fx_rate,
+        );
+        CashRepository::create(conn, &txn)?;
+        audit.record_cash(conn, None, Some(&txn))?;
+
+        CorporateActionService::settle_dividend_tax(conn, audit, position, &sold_id, reduce_quantity, sell_date, fx_rate)?;
+
+        Ok(sold_id)
+    }
+
+    /// 重新打开已平仓记录（撤销一次卖出）
+    ///
+    /// 操作逻辑：
+    /// - 普通记录：清除卖出信息，恢复为持仓状态
+    /// - 减仓产生的子记录：数量和买入费用合并回原记录，删除子记录
+    ///   - 原记录已平仓：先重新打开原记录，再合并
+    ///   - 原记录已删除：按原 ID 重建原记录，删除时退回的买入扣款按子记录的成本重新扣除
+    /// - 删除对应的卖出回款和补扣的红利税流水，分红明细随记录合并
+    ///
+    /// 以下情况拒绝操作：
+    /// - 卖出后发生过送转、拆股或配股（恢复后的数量和成本无法对应）
+    /// - 子记录与原记录的代码、买入价格、买入日期或组合不一致
+    ///
+    /// 注意：调用方应在事务中执行
+    pub fn reopen_position(conn: &Connection, audit: &AuditGroup, id: &str) -> Result<Position> {
+        let closed = PositionRepository::find_by_id(conn, id)?
+            .ok_or_else(|| not_found!("找不到 ID 为 {} 的持仓记录", id))?;
+
+        if closed.status != "CLOSE" {
+            return Err(business_error!("该记录仍在持仓中，无需重新打开"));
+        }
+
+        let sell_date = closed.sell_date.clone().unwrap_or_default();
+        if CorporateActionService::has_share_adjustments_after(conn, &closed.code, &sell_date)? {
+            return Err(business_error!(
+                "{} 在卖出日 {} 之后发生过送转、拆股或配股，重新打开会导致数量和成本不一致",
+                closed.code,
+                sell_date
+            ));
+        }
+
+        // 外层为空表示没有原记录，内层为空表示原记录已删除
+        let parent = match &closed.parent_id {
+            Some(parent_id) => Some(match PositionRepository::find_by_id(conn, parent_id)? {
+                Some(parent) => {
+                    if parent.code != closed.code
+                        || parent.buy_price != closed.buy_price
+                        || parent.buy_date != closed.buy_date
+                        || parent.portfolio != closed.portfolio
+                    {
+                        return Err(business_error!(
+                            "已卖出记录与原持仓记录信息不一致（代码、买入价格、买入日期或组合），无法合并"
+                        ));
+                    }
+                    if parent.status == "POSITION" {
+                        Some(parent)
+                    } else {
+                        Some(Self::reopen_position(conn, audit, parent_id)?)
+                    }
+                }
+                None => None,
+            }),
+            None => None,
+        };
+
+        // 删除卖出回款和卖出时补扣的红利税
+        let is_sale = |txn: &&CashTransaction| {
+            matches!(txn.txn_type, CashTransactionType::Sell | CashTransactionType::DividendTax)
+        };
+        let linked_txns = CashRepository::find_by_position(conn, id)?;
+        for txn in linked_txns.iter().filter(is_sale) {
+            CashRepository::delete(conn, &txn.id)?;
+            audit.record_cash(conn, Some(txn), None)?;
+        }
+
+        let Some(parent) = parent else {
+            // 直接平仓的记录恢复为持仓
+            let mut reopened = closed.clone();
+            reopened.status = "POSITION".to_string();
+            reopened.sell_price = None;
+            reopened.sell_date = None;
+            reopened.sell_fee = None;
+            reopened.parent_id = None;
+            PositionRepository::upsert(conn, &reopened)?;
+            audit.record_position(conn, Some(&closed), Some(&reopened))?;
+            return Ok(reopened);
+        };
+
+        // 合并回原记录（原记录已删除时按原 ID 重建）
+        let mut merged = parent.clone().unwrap_or_else(|| {
+            let mut recreated = closed.clone();
+            recreated.id = closed.parent_id.clone().unwrap_or_default();
+            recreated.status = "POSITION".to_string();
+            recreated.sell_price = None;
+            recreated.sell_date = None;
+            recreated.sell_fee = None;
+            recreated.parent_id = None;
+            recreated.quantity = 0;
+            recreated.buy_fee = 0.0;
+            recreated
+        });
+        merged.quantity += closed.quantity;
+        merged.buy_fee += closed.buy_fee;
+        PositionRepository::upsert(conn, &merged)?;
+        audit.record_position(conn, parent.as_ref(), Some(&merged))?;
+
+        if parent.is_none() {
+            let txn = CashTransaction::for_buy(&merged);
+            CashRepository::create(conn, &txn)?;
+            audit.record_cash(conn, None, Some(&txn))?;
+        }
+
+        // 分红流水和明细转到原记录名下
+        for txn in linked_txns.iter().filter(|txn| !is_sale(txn)) {
+            let mut moved = txn.clone();
+            moved.position_id = Some(merged.id.clone());
+            CashRepository::upsert(conn, &moved)?;
+            audit.record_cash(conn, Some(txn), Some(&moved))?;
+        }
+        for entry in CorporateActionService::find_entries_by_position(conn, id)? {
+            let mut moved = entry.clone();
+            moved.position_id = merged.id.clone();
+            CorporateActionService::upsert_entry(conn, &moved)?;
+            audit.record_corporate_action_entry(conn, Some(&entry), Some(&moved))?;
+        }
+
+        PositionRepository::delete(conn, id)?;
+        audit.record_position(conn, Some(&closed), None)?;
+
+        Ok(merged)
+    }
+
+    /// 修改持仓记录
+    ///
+    /// 操作逻辑：
+    /// 1. 减仓产生的子记录不能单独修改，需修改原持仓记录
+    /// 2. 代码、名称、买入价格、买入日期、组合同步到所有子记录
+    /// 3. 买入扣款流水按成本变化调整；已平仓记录数量变化时重新计算卖出回款
+    /// 4. 修改组合时，关联的现金流水和公司行为明细一并转移
+    /// 5. 交易币种或组合基础货币变化时，原记录和子记录的买入汇率改为 buy_fx_rate，
+    ///    关联的现金流水按原成交汇率还原为交易币种金额后用新汇率重新折算
+    ///
+    /// 参数：
+    /// - buy_fx_rate: 修改后交易币种到组合基础货币的汇率（币种相同时为 None），仅在币种变化时使用
+    ///
+    /// 注意：调用方应在事务中执行
+    pub fn update_position(
+        conn: &Connection,
+        audit: &AuditGroup,
+        request: UpdatePositionRequest,
+        buy_fx_rate: Option<f64>,
+    ) -> Result<Position> {
+        let before = PositionRepository::find_by_id(conn, &request.id)?
+            .ok_or_else(|| not_found!("找不到 ID 为 {} 的持仓记录", request.id))?;
+
+        if before.parent_id.is_some() {
+            return Err(business_error!("减仓产生的已卖出记录不能单独修改，请修改原持仓记录"));
+        }
+
+        let mut updated = before.clone();
+        request.apply_to(&mut updated)?;
+        updated.validate()?;
+
+        if updated.portfolio != before.portfolio {
+            let portfolio = PortfolioRepository::ensure_exists(conn, &updated.portfolio)?;
+            if portfolio.archived {
+                return Err(business_error!("投资组合 {} 已归档，不能转入持仓", portfolio.name));
+            }
+        }
+
+        // 原买入汇率只适用于原来的币种对
+        let fx_changed = Self::trade_currency_changed(conn, &before, &updated)?;
+        if fx_changed {
+            updated.buy_fx_rate = buy_fx_rate;
+        }
+        let rerate = |txn: &CashTransaction, updated_txn: &mut CashTransaction| {
+            if fx_changed {
+                updated_txn.amount = txn.trade_amount() * updated.buy_fx_rate.unwrap_or(1.0);
+                updated_txn.fx_rate = updated.buy_fx_rate;
+            }
+        };
+
+        // 同步到子记录
+        let children = PositionRepository::find_children(conn, &before.id)?;
+        let mut updated_children = Vec::new();
+        for child in &children {
+            let mut updated_child = child.clone();
+            updated_child.code = updated.code.clone();
+            updated_child.market = updated.market;
+            updated_child.currency = updated.currency.clone();
+            updated_child.buy_fx_rate = updated.buy_fx_rate;
+            updated_child.name = updated.name.clone();
+            updated_child.buy_price = updated.buy_price;
+            updated_child.buy_date = updated.buy_date.clone();
+            updated_child.portfolio = updated.portfolio.clone();
+            updated_child.validate()?;
+            updated_children.push(updated_child);
+        }
+
+        PositionRepository::update(conn, &updated)?;
+        audit.record_position(conn, Some(&before), Some(&updated))?;
+        for (child, updated_child) in children.iter().zip(&updated_children) {
+            PositionRepository::update(conn, updated_child)?;
+            audit.record_position(conn, Some(child), Some(updated_child))?;
+        }
+
+        // 买入成本变化（原记录 + 所有子记录，按买入时汇率折算为组合基础货币）
+        let old_cost = (before.cost() + children.iter().map(|c| c.cost()).sum::<f64>()) * before.buy_fx_rate.unwrap_or(1.0);
+        let new_cost = (updated.cost() + updated_children.iter().map(|c| c.cost()).sum::<f64>()) * updated.buy_fx_rate.unwrap_or(1.0);
+
+        // 调整关联的现金流水
+        for txn in CashRepository::find_by_position(conn, &before.id)? {
+            let mut updated_txn = txn.clone();
+            updated_txn.portfolio = updated.portfolio.clone();
+            match txn.txn_type {
+                CashTransactionType::Buy => {
+                    updated_txn.amount = txn.amount - (new_cost - old_cost);
+                    updated_txn.txn_date = updated.buy_date.clone();
+                    updated_txn.fx_rate = updated.buy_fx_rate;
+                }
+                CashTransactionType::Sell if updated.quantity != before.quantity => {
+                    // 币种未变化时沿用原流水的卖出汇率
+                    let fx_rate = if fx_changed { updated.buy_fx_rate } else { txn.fx_rate };
+                    let sell_price = updated.sell_price.unwrap_or(0.0);
+                    updated_txn.amount = (sell_price * updated.quantity as f64 - updated.sell_fee.unwrap_or(0.0))
+                        * fx_rate.unwrap_or(1.0);
+                    updated_txn.fx_rate = fx_rate;
+                }
+                _ => rerate(&txn, &mut updated_txn),
+            }
+            Self::update_cash_leg(conn, audit, &txn, &updated_txn)?;
+        }
+        for child in &children {
+            for txn in CashRepository::find_by_position(conn, &child.id)? {
+                let mut updated_txn = txn.clone();
+                updated_txn.portfolio = updated.portfolio.clone();
+                rerate(&txn, &mut updated_txn);
+                Self::update_cash_leg(conn, audit, &txn, &updated_txn)?;
+            }
+        }
+
+        // 公司行为明细随组合转移
+        if updated.portfolio != before.portfolio {
+            for entry in CorporateActionService::find_entries_by_position(conn, &before.id)? {
+                let mut updated_entry = entry.clone();
+                updated_entry.portfolio = updated.portfolio.clone();
+                CorporateActionService::upsert_entry(conn, &updated_entry)?;
+                audit.record_corporate_action_entry(conn, Some(&entry), Some(&updated_entry))?;
+            }
+        }
+
+        Ok(updated)
+    }
+
+    /// 修改持仓后交易币种或所在组合的基础货币是否变化（变化时需要重新获取买入汇率）
+    pub fn trade_currency_changed(conn: &Connection, before: &Position, updated: &Position) -> Result<bool> {
+        if before.currency != updated.currency {
+            return Ok(true);
+        }
+        if before.portfolio == updated.portfolio {
+            return Ok(false);
+        }
+        Ok(PortfolioRepository::base_currency(conn, &before.portfolio)?
+            != PortfolioRepository::base_currency(conn, &updated.portfolio)?)
+    }
+
+    /// 辅助方法：更新现金流水并记录审计（无变化时跳过）
+    fn update_cash_leg(
+        conn: &Connection,
+        audit: &AuditGroup,
+        before: &CashTransaction,
+        after: &CashTransaction,
+    ) -> Result<()> {
+        if before.portfolio == after.portfolio
+            && before.amount == after.amount
+            && before.txn_date == after.txn_date
+            && before.fx_rate == after.fx_rate
+        {
+            return Ok(());
+        }
+        CashRepository::update(conn, after)?;
+        audit.record_cash(conn, Some(before), Some(after))
+    }
+
+    /// 按股票卖出指定数量，根据匹配策略拆分到各持仓批次
+    ///
+    /// 操作逻辑：
+    /// 1. 查询组合中该股票所有持仓中的批次
+    /// 2. 按匹配策略计算每个批次的卖出数量
+    /// 3. 卖完的批次直接平仓，部分卖出的批次按减仓逻辑生成子记录
+    /// 4. 卖出费用按数量比例分摊到各批次
+    ///
+    /// 注意：调用方应在事务中执行，保证所有批次同时更新
+    pub fn sell_shares(
+        conn: &Connection,
+        audit: &AuditGroup,
+        request: &SellSharesRequest,
+        fx_rate: Option<f64>,
+    ) -> Result<SellSharesResult> {
+        let SellSharesRequest { portfolio, code, quantity, sell_price, sell_date, policy, sell_fee } = request;
+        let (quantity, sell_price, policy) = (*quantity, *sell_price, *policy);
+
+        if quantity <= 0 {
+            return Err(invalid_input!("卖出数量必须大于0"));
+        }
+        Self::validate_sell(sell_price, *sell_fee)?;
+
+        let lots = Self::find_open_lots(conn, portfolio, code)?;
+        let available: i32 = lots.iter().map(|lot| lot.quantity).sum();
+        if lots.is_empty() {
+            return Err(business_error!("投资组合 {} 中没有 {} 的持仓", portfolio, code));
+        }
+        if quantity > available {
+            return Err(business_error!(
+                "卖出数量({})超过持有数量({})",
+                quantity,
+                available
+            ));
+        }
+
+        let allocations = Self::allocate(lots, quantity, policy);
+        let total_fee = Self::resolve_sell_fee(conn, code, sell_price, quantity, *sell_fee)?;
+
+        // 卖出费用按数量分摊，最后一个批次承担舍入误差
+        let mut remaining_fee = total_fee;
+        let mut sales = Vec::new();
+        let last_index = allocations.len().saturating_sub(1);
+
+        for (index, (lot, sell_quantity)) in allocations.into_iter().enumerate() {
+            Self::validate_sell_date(sell_date, &lot.buy_date)?;
+            let lot_fee = if index == last_index {
+                remaining_fee
+            } else {
+                let fee = (total_fee * sell_quantity as f64 / quantity as f64 * 100.0).round() / 100.0;
+                remaining_fee -= fee;
+                fee
+            };
+
+            let closed = sell_quantity == lot.quantity;
+            let sold_id = if closed {
+                Self::close_lot(conn, audit, &lot, sell_price, sell_date, lot_fee, fx_rate)?;
+                lot.id.clone()
+            } else {
+                Self::reduce_lot(conn, audit, &lot, sell_quantity, sell_price, sell_date, lot_fee, fx_rate)?
+            };
+
+            sales.push(LotSale {
+                position_id: lot.id.clone(),
+                sold_id,
+                buy_date: lot.buy_date.clone(),
+                buy_price: lot.buy_price,
+                quantity: sell_quantity,
+                sell_fee: lot_fee,
+                closed,
+            });
+        }
+
+        Ok(SellSharesResult {
+            portfolio: portfolio.clone(),
+            code: code.clone(),
+            policy,
+            quantity,
+            sell_price,
+            sell_fee: total_fee,
+            lots: sales,
+        })
+    }
+
+    /// 查询组合中某支股票所有持仓中的批次（按买入日期升序）
+    pub fn find_open_lots(conn: &Connection, portfolio: &str, code: &str) -> Result<Vec<Position>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM positions
+             WHERE portfolio = ? AND status = 'POSITION'
+             ORDER BY buy_date ASC, rowid ASC",
+            POSITION_COLUMNS
+        ))?;
+
+        let target_code = QuoteService::format_stock_code(code);
+        let lots = stmt.query_map([portfolio], PositionRepository::map_row_to_position)?
+            .collect::<rusqlite::Result<Vec<Position>>>()?
+            .into_iter()
+            .filter(|lot| QuoteService::format_stock_code(&lot.code) == target_code)
+            .collect();
+
+        Ok(lots)
+    }
+
+    /// 按匹配策略计算每个批次的卖出数量，只返回卖出数量大于0的批次
+    fn allocate(mut lots: Vec<Position>, quantity: i32, policy: LotMatchingPolicy) -> Vec<(Position, i32)> {
+        match policy {
+            LotMatchingPolicy::Fifo => {}
+            LotMatchingPolicy::Lifo => lots.reverse(),
+            LotMatchingPolicy::HighestCost => {
+                lots.sort_by(|a, b| {
+                    let cost_a = a.cost() / a.quantity as f64;
+                    let cost_b = b.cost() / b.quantity as f64;
+                    cost_b.partial_cmp(&cost_a).unwrap_or(std::cmp::Ordering::Equal)
+                });
+            }
+            LotMatchingPolicy::AverageCost => return Self::allocate_pro_rata(lots, quantity),
+        }
+
+        let mut remaining = quantity;
+        let mut allocations = Vec::new();
+        for lot in lots {
+            if remaining == 0 {
+                break;
+            }
+            let sell_quantity = remaining.min(lot.quantity);
+            remaining -= sell_quantity;
+            allocations.push((lot, sell_quantity));
+        }
+        allocations
+    }
+
+    /// 平均成本：各批次按持有数量等比例卖出，取整后的余数按买入先后补足
+    fn allocate_pro_rata(lots: Vec<Position>, quantity: i32) -> Vec<(Position, i32)> {
+        let total: i64 = lots.iter().map(|lot| lot.quantity as i64).sum();
+        let mut shares: Vec<i32> = lots
+            .iter()
+            .map(|lot| (lot.quantity as i64 * quantity as i64 / total) as i32)
+            .collect();
+
+        let mut remaining = quantity - shares.iter().sum::<i32>();
+        for (index, lot) in lots.iter().enumerate() {
+            if remaining == 0 {
+                break;
+            }
+            let extra = remaining.min(lot.quantity - shares[index]);
+            shares[index] += extra;
+            remaining -= extra;
+        }
+
+        lots.into_iter()
+            .zip(shares)
+            .filter(|(_, sell_quantity)| *sell_quantity > 0)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    fn lot(buy_price: f64, buy_date: &str, quantity: i32) -> Position {
+        Position::new(
+            "600519".to_string(),
+            "贵州茅台".to_string(),
+            buy_price,
+            buy_date.to_string(),
+            quantity,
+            "组合A".to_string(),
+        )
+    }
+
+    fn quantities(allocations: &[(Position, i32)]) -> Vec<(f64, i32)> {
+        allocations.iter().map(|(lot, qty)| (lot.buy_price, *qty)).collect()
+    }
+
+    #[test]
+    fn test_allocate_by_policy() {
+        let lots = vec![
+            lot(10.0, "2025-01-01", 100),
+            lot(12.0, "2025-02-01", 200),
+            lot(11.0, "2025-03-01", 300),
+        ];
+
+        let fifo = TradeService::allocate(lots.clone(), 250, LotMatchingPolicy::Fifo);
+        assert_eq!(quantities(&fifo), vec![(10.0, 100), (12.0, 150)]);
+
+        let lifo = TradeService::allocate(lots.clone(), 250, LotMatchingPolicy::Lifo);
+        assert_eq!(quantities(&lifo), vec![(11.0, 250)]);
+
+        let highest = TradeService::allocate(lots.clone(), 250, LotMatchingPolicy::HighestCost);
+        assert_eq!(quantities(&highest), vec![(12.0, 200), (11.0, 50)]);
+
+        let average = TradeService::allocate(lots, 300, LotMatchingPolicy::AverageCost);
+        assert_eq!(quantities(&average), vec![(10.0, 50), (12.0, 100), (11.0, 150)]);
+    }
+
+    #[test]
+    fn test_validate_sell_date() {
+        assert!(TradeService::validate_sell_date("2025-02-01", "2025-01-01").is_ok());
+        assert!(TradeService::validate_sell_date("2025-01-01", "2025-01-01").is_ok());
+        assert!(TradeService::validate_sell_date("2024-12-31", "2025-01-01").is_err());
+        assert!(TradeService::validate_sell_date("2025/02/01", "2025-01-01").is_err());
+        assert!(TradeService::validate_sell_date("", "2025-01-01").is_err());
+    }
+
+    #[test]
+    fn test_update_propagates_to_children() {
+        let database = Database::open_in_memory().unwrap();
+        let conn = database.get().unwrap();
+        let audit = AuditGroup::new("test");
+        let parent = lot(10.0, "2025-01-01", 300);
+        PositionRepository::create(&conn, &parent).unwrap();
+        CashRepository::create(&conn, &CashTransaction::for_buy(&parent)).unwrap();
+        let sold_id = TradeService::reduce_lot(&conn, &audit, &parent, 100, 12.0, "2025-02-01", 0.0, None).unwrap();
+
+        // 修正买入价格：子记录同步，买入扣款按全部300股的成本差调整
+        let request = UpdatePositionRequest {
+            id: parent.id.clone(),
+            code: None,
+            name: None,
+            buy_price: Some(9.5),
+            buy_date: None,
+            quantity: None,
+            portfolio: None,
+            buy_fee: None,
+        };
+        let updated = TradeService::update_position(&conn, &audit, request, None).unwrap();
+        assert_eq!(updated.quantity, 200);
+
+        let child = PositionRepository::find_by_id(&conn, &sold_id).unwrap().unwrap();
+        assert_eq!(child.buy_price, 9.5);
+        assert_eq!(CashRepository::balance(&conn, "组合A").unwrap(), -2850.0 + 1200.0);
+
+        // 子记录不能单独修改
+        let request = UpdatePositionRequest {
+            id: sold_id,
+            code: None,
+            name: Some("茅台".to_string()),
+            buy_price: None,
+            buy_date: None,
+            quantity: None,
+            portfolio: None,
+            buy_fee: None,
+        };
+        assert!(TradeService::update_position(&conn, &audit, request, None).is_err());
+
+        // 无法确定市场的代码不能保存
+        let request = UpdatePositionRequest {
+            id: parent.id.clone(),
+            code: Some("AAPL".to_string()),
+            name: None,
+            buy_price: None,
+            buy_date: None,
+            quantity: None,
+            portfolio: None,
+            buy_fee: None,
+        };
+        assert!(TradeService::update_position(&conn, &audit, request, None).is_err());
+        assert_eq!(PositionRepository::find_by_id(&conn, &parent.id).unwrap().unwrap().code, parent.code);
+    }
+
+    #[test]
+    fn test_reopen_merges_child_into_parent() {
+        let database = Database::open_in_memory().unwrap();
+        let conn = database.get().unwrap();
+        let audit = AuditGroup::new("test");
+        let parent = lot(10.0, "2025-01-01", 300);
+        PositionRepository::create(&conn, &parent).unwrap();
+        let sold_id = TradeService::reduce_lot(&conn, &audit, &parent, 100, 12.0, "2025-02-01", 0.0, None).unwrap();
+
+        let merged = TradeService::reopen_position(&conn, &audit, &sold_id).unwrap();
+        assert_eq!(merged.id, parent.id);
+        assert_eq!(merged.quantity, 300);
+        assert!(PositionRepository::find_by_id(&conn, &sold_id).unwrap().is_none());
+        assert_eq!(CashRepository::balance(&conn, "组合A").unwrap(), 0.0);
+
+        // 仍在持仓中的记录不能重新打开
+        assert!(TradeService::reopen_position(&conn, &audit, &parent.id).is_err());
+
+        // 原记录已平仓时，先重新打开原记录再合并
+        let sold_id = TradeService::reduce_lot(&conn, &audit, &merged, 100, 12.0, "2025-02-01", 0.0, None).unwrap();
+        let remaining = PositionRepository::find_by_id(&conn, &parent.id).unwrap().unwrap();
+        TradeService::close_lot(&conn, &audit, &remaining, 13.0, "2025-03-01", 0.0, None).unwrap();
+        let reopened = TradeService::reopen_position(&conn, &audit, &sold_id).unwrap();
+        assert_eq!(reopened.id, parent.id);
+        assert_eq!(reopened.status, "POSITION");
+        assert_eq!(reopened.quantity, 300);
+        assert!(reopened.sell_date.is_none());
+        assert!(PositionRepository::find_by_id(&conn, &sold_id).unwrap().is_none());
+        assert_eq!(CashRepository::balance(&conn, "组合A").unwrap(), 0.0);
+
+        // 原记录已删除时，按原 ID 重建并重新扣除子记录的买入成本
+        let sold_id = TradeService::reduce_lot(&conn, &audit, &reopened, 100, 12.0, "2025-02-01", 0.0, None).unwrap();
+        PositionRepository::delete(&conn, &parent.id).unwrap();
+        CashRepository::delete_by_position(&conn, &parent.id).unwrap();
+        let recreated = TradeService::reopen_position(&conn, &audit, &sold_id).unwrap();
+        assert_eq!(recreated.id, parent.id);
+        assert_eq!(recreated.status, "POSITION");
+        assert_eq!(recreated.quantity, 100);
+        assert!(recreated.parent_id.is_none());
+        assert!(PositionRepository::find_by_id(&conn, &sold_id).unwrap().is_none());
+        assert_eq!(CashRepository::balance(&conn, "组合A").unwrap(), -1000.0);
+    }
+
+    #[test]
+    fn test_foreign_cash_legs_in_base_currency() {
+        let database = Database::open_in_memory().unwrap();
+        let conn = database.get().unwrap();
+        let audit = AuditGroup::new("test");
+        let mut position = lot(100.0, "2025-01-01", 200);
+        position.code = "hk00700".to_string();
+        position.market = Some(crate::models::Market::Hk);
+        position.currency = "HKD".to_string();
+        position.buy_fx_rate = Some(0.9);
+        PositionRepository::create(&conn, &position).unwrap();
+        CashRepository::create(&conn, &CashTransaction::for_buy(&position)).unwrap();
+        assert_eq!(CashRepository::balance(&conn, "组合A").unwrap(), -18000.0);
+
+        // 卖出回款按卖出时的汇率折算，流水保留成交汇率以便还原港元金额
+        let sold_id = TradeService::reduce_lot(&conn, &audit, &position, 100, 110.0, "2025-02-01", 0.0, Some(0.95)).unwrap();
+        let sell = CashRepository::find_by_position(&conn, &sold_id).unwrap().remove(0);
+        assert_eq!(sell.amount, 10450.0);
+        assert_eq!(sell.trade_amount(), 11000.0);
+        assert_eq!(CashRepository::balance(&conn, "组合A").unwrap(), -18000.0 + 10450.0);
+    }
+
+    #[test]
+    fn test_update_refreshes_fx_rate_when_currency_changes() {
+        let database = Database::open_in_memory().unwrap();
+        let conn = database.get().unwrap();
+        let audit = AuditGroup::new("test");
+        let mut position = lot(100.0, "2025-01-01", 200);
+        position.code = "hk00700".to_string();
+        position.market = Some(crate::models::Market::Hk);
+        position.currency = "HKD".to_string();
+        position.buy_fx_rate = Some(0.9);
+        PositionRepository::create(&conn, &position).unwrap();
+        CashRepository::create(&conn, &CashTransaction::for_buy(&position)).unwrap();
+        let sold_id = TradeService::reduce_lot(&conn, &audit, &position, 100, 110.0, "2025-02-01", 0.0, Some(0.95)).unwrap();
+
+        // 代码改为 A 股：币种变为人民币，原记录和子记录都不再有买入汇率，流水按交易币种金额入账
+        let request = UpdatePositionRequest {
+            id: position.id.clone(),
+            code: Some("600519".to_string()),
+            name: None,
+            buy_price: None,
+            buy_date: None,
+            quantity: None,
+            portfolio: None,
+            buy_fee: None,
+        };
+        let updated = TradeService::update_position(&conn, &audit, request, None).unwrap();
+        assert_eq!(updated.currency, "CNY");
+        assert!(updated.buy_fx_rate.is_none());
+        assert!(PositionRepository::find_by_id(&conn, &sold_id).unwrap().unwrap().buy_fx_rate.is_none());
+        assert_eq!(CashRepository::balance(&conn, "组合A").unwrap(), -20000.0 + 11000.0);
+
+        // 转入基础货币为美元的组合：使用新的汇率
+        let mut portfolio = crate::models::Portfolio::new("美元组合".to_string());
+        portfolio.base_currency = "USD".to_string();
+        PortfolioRepository::create(&conn, &portfolio).unwrap();
+        let request = UpdatePositionRequest {
+            id: position.id.clone(),
+            code: None,
+            name: None,
+            buy_price: None,
+            buy_date: None,
+            quantity: None,
+            portfolio: Some("美元组合".to_string()),
+            buy_fee: None,
+        };
+        let moved = TradeService::update_position(&conn, &audit, request, Some(0.14)).unwrap();
+        assert_eq!(moved.buy_fx_rate, Some(0.14));
+        assert_eq!(PositionRepository::find_by_id(&conn, &sold_id).unwrap().unwrap().buy_fx_rate, Some(0.14));
+        assert!((CashRepository::balance(&conn, "美元组合").unwrap() - (-20000.0 + 11000.0) * 0.14).abs() < 1e-6);
+        assert_eq!(CashRepository::balance(&conn, "组合A").unwrap(), 0.0);
+    }
+}
@@ -1,31 +1,224 @@
 use rusqlite::{Connection, Result as SqliteResult, params};
+use serde::Serialize;
 use uuid::Uuid;
+use crate::models::{Market, Symbol};
+
+/// 单个数据库迁移
+pub struct Migration {
+    /// 迁移后的版本号
+    pub version: u32,
+    /// 变更说明
+    pub description: &'static str,
+    /// 迁移函数（在事务中执行）
+    pub up: fn(&Connection) -> SqliteResult<()>,
+}
+
+/// 迁移注册表（按版本号升序）
+///
+/// 新增迁移时在末尾追加，版本号必须连续递增，已发布的迁移不能修改
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, description: "重构表结构，添加 UUID 主键", up: migrate_v0_to_v1 },
+    Migration { version: 2, description: "添加 sell_price 和 sell_date 字段", up: migrate_v1_to_v2 },
+    Migration { version: 3, description: "添加 parent_id 字段（支持减仓功能）", up: migrate_v2_to_v3 },
+    Migration { version: 4, description: "添加 buy_fee / sell_fee 字段及 settings 表", up: migrate_v3_to_v4 },
+    Migration { version: 5, description: "创建 portfolios 表", up: migrate_v4_to_v5 },
+    Migration { version: 6, description: "创建 cash_transactions 表", up: migrate_v5_to_v6 },
+    Migration { version: 7, description: "创建 corporate_actions / corporate_action_entries 表", up: migrate_v6_to_v7 },
+    Migration { version: 8, description: "创建 idempotency_keys 表", up: migrate_v7_to_v8 },
+    Migration { version: 9, description: "创建 position_events 表", up: migrate_v8_to_v9 },
+    Migration { version: 10, description: "删除 v0 -> v1 迁移遗留的 positions_old 表", up: migrate_v9_to_v10 },
+    Migration { version: 11, description: "创建 quotes 表（行情缓存）", up: migrate_v10_to_v11 },
+    Migration { version: 12, description: "创建 manual_prices 表（手动价格）", up: migrate_v11_to_v12 },
+    Migration { version: 13, description: "quotes 表增加今开、最高、最低、成交量等扩展行情字段", up: migrate_v12_to_v13 },
+    Migration { version: 14, description: "positions 表增加 market 字段（交易市场）", up: migrate_v13_to_v14 },
+    Migration { version: 15, description: "positions 表增加 currency / buy_fx_rate 字段，创建 fx_rates 表（汇率）", up: migrate_v14_to_v15 },
+    Migration { version: 16, description: "创建 daily_prices / portfolio_snapshots 表（每日收盘价和组合快照）", up: migrate_v15_to_v16 },
+    Migration { version: 17, description: "portfolios 表增加 benchmark 字段（业绩比较基准）", up: migrate_v16_to_v17 },
+    Migration { version: 18, description: "修正沪深 B 股持仓的交易币种（美元 / 港元）", up: migrate_v17_to_v18 },
+    Migration { version: 19, description: "corporate_actions 表的股票代码统一为带市场前缀的格式", up: migrate_v18_to_v19 },
+    Migration { version: 20, description: "cash_transactions 表增加 fx_rate 字段，外币持仓的现金流水折算为组合基础货币", up: migrate_v19_to_v20 },
+];
+
+/// 已执行的迁移
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedMigration {
+    pub version: u32,
+    pub description: String,
+}
+
+/// 启动时的迁移报告
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MigrationReport {
+    /// 迁移前的版本号（0 表示没有版本记录）
+    pub from_version: u32,
+    /// 迁移后的版本号
+    pub to_version: u32,
+    /// 本次执行的迁移
+    pub applied: Vec<AppliedMigration>,
+    /// 迁移前的数据库备份文件
+    pub backup_path: Option<String>,
+}
+
+impl MigrationReport {
+    /// 打印迁移报告
+    pub fn print(&self) {
+        println!("========================================");
+        println!("📋 数据库迁移报告");
+        println!("   - 迁移前版本: v{}", self.from_version);
+        println!("   - 当前版本: v{}", self.to_version);
+        if let Some(backup_path) = &self.backup_path {
+            println!("   - 迁移前备份: {}", backup_path);
+        }
+        if self.applied.is_empty() {
+            println!("   - 没有需要执行的迁移");
+        }
+        for migration in &self.applied {
+            println!("   ✓ v{}: {}", migration.version, migration.description);
+        }
+        println!("========================================");
+    }
+}
 
 /// 数据库迁移管理器
-/// 
+///
 /// 迁移版本历史：
 /// - v0 -> v1: 重构表结构，添加 UUID 主键
 /// - v1 -> v2: 添加 sell_price 和 sell_date 字段
 /// - v2 -> v3: 添加 parent_id 字段（支持减仓功能）
-pub fn run_migrations(conn: &Connection) -> SqliteResult<()> {
+/// - v3 -> v4: 添加 buy_fee / sell_fee 字段及 settings 表（支持交易费用）
+/// - v4 -> v5: 创建 portfolios 表（每个组合独立的满仓金额等设置）
+/// - v5 -> v6: 创建 cash_transactions 表（组合现金流水）
+/// - v6 -> v7: 创建 corporate_actions / corporate_action_entries 表（分红送转等公司行为）
+/// - v7 -> v8: 创建 idempotency_keys 表（防止前端重试导致重复卖出）
+/// - v8 -> v9: 创建 position_events 表（操作审计，支持撤销和修改历史）
+/// - v9 -> v10: 删除 positions_old 表（迁移前已有整库文件备份）
+/// - v10 -> v11: 创建 quotes 表（行情缓存，离线时使用最后已知价格）
+/// - v11 -> v12: 创建 manual_prices 表（停牌、场外产品等没有行情的持仓的手动价格）
+/// - v12 -> v13: quotes 表增加扩展行情字段（今开、最高、最低、成交量、成交额、涨跌幅、市盈率、总市值）
+/// - v13 -> v14: positions 表增加 market 字段，按已有代码识别交易市场
+/// - v14 -> v15: positions 表增加 currency / buy_fx_rate 字段，创建 fx_rates 表（多币种持仓按基础货币汇总）
+/// - v15 -> v16: 创建 daily_prices / portfolio_snapshots 表（每日收盘价和组合快照，支持收益曲线）
+/// - v16 -> v17: portfolios 表增加 benchmark 字段（每个组合的业绩比较基准，如沪深300）
+/// - v17 -> v18: 沪市 B 股（900 开头）币种改为美元，深市 B 股（200 开头）改为港元
+/// - v18 -> v19: corporate_actions 表的股票代码统一为带市场前缀的格式（600519 -> sh600519），使重复录入检查生效
+/// - v19 -> v20: cash_transactions 表增加 fx_rate 字段，外币持仓的买卖、分红流水按买入汇率折算为组合基础货币
+///
+/// 当前版本记录在 schema_version 表中，只执行版本号更高的迁移，
+/// 每个迁移在独立事务中执行，失败时回滚该迁移并停止后续迁移。
+/// 已有数据的数据库在执行迁移前会先备份为带时间戳的文件。
+///
+/// 引入 schema_version 之前的数据库没有版本记录，会从 v1 开始依次执行，
+/// 各迁移函数自行检查表结构，已完成的变更会被跳过
+pub fn run_migrations(conn: &Connection) -> SqliteResult<MigrationReport> {
     println!("========================================");
     println!("🔄 开始执行数据库迁移检查");
     println!("========================================");
-    
-    // 先运行 v0 -> v1 迁移
-    migrate_v0_to_v1(conn)?;
-    
-    // 再运行 v1 -> v2 迁移
-    migrate_v1_to_v2(conn)?;
-    
-    // 运行 v2 -> v3 迁移
-    migrate_v2_to_v3(conn)?;
-    
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    let current_version = current_version(conn)?;
+    let latest_version = MIGRATIONS.last().map(|m| m.version).unwrap_or(0);
+
+    let mut report = MigrationReport {
+        from_version: current_version,
+        to_version: current_version,
+        ..Default::default()
+    };
+
+    if current_version > latest_version {
+        println!("[迁移] ⚠️  数据库版本 v{} 高于程序支持的 v{}，请升级程序", current_version, latest_version);
+        return Ok(report);
+    }
+
+    let pending: Vec<&Migration> = MIGRATIONS.iter()
+        .filter(|m| m.version > current_version)
+        .collect();
+
+    if pending.is_empty() {
+        println!("[迁移] 数据库已经是最新版本 v{}", current_version);
+        return Ok(report);
+    }
+
+    // 已有数据的数据库先整库备份，迁移出错时可以手动恢复
+    if has_user_tables(conn)? {
+        report.backup_path = backup_database(conn)?;
+    }
+
+    for migration in pending {
+        println!("[迁移] 执行 v{} -> v{}: {}", migration.version - 1, migration.version, migration.description);
+
+        let tx = conn.unchecked_transaction()?;
+        (migration.up)(&tx)?;
+        tx.execute(
+            "INSERT INTO schema_version (version, description, applied_at)
+             VALUES (?, ?, datetime('now', 'localtime'))",
+            params![migration.version, migration.description],
+        )?;
+        tx.commit()?;
+
+        report.to_version = migration.version;
+        report.applied.push(AppliedMigration {
+            version: migration.version,
+            description: migration.description.to_string(),
+        });
+    }
+
     println!("========================================");
     println!("✅ 所有迁移检查完成");
     println!("========================================");
-    
-    Ok(())
+
+    Ok(report)
+}
+
+/// 读取当前数据库版本（没有版本记录时为 0）
+pub fn current_version(conn: &Connection) -> SqliteResult<u32> {
+    conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// 检查数据库中是否已有业务表（全新数据库无需备份）
+fn has_user_tables(conn: &Connection) -> SqliteResult<bool> {
+    let count: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master
+         WHERE type='table' AND name NOT LIKE 'sqlite_%' AND name != 'schema_version'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// 将数据库备份到同目录下带时间戳的文件，例如 positions.db.20250101-093000.bak
+///
+/// 使用 VACUUM INTO 生成一致的副本（包含 WAL 中尚未写回的数据），内存数据库不备份
+fn backup_database(conn: &Connection) -> SqliteResult<Option<String>> {
+    let db_path = match conn.path() {
+        Some(path) if !path.is_empty() => path.to_string(),
+        _ => return Ok(None),
+    };
+
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let mut backup_path = format!("{}.{}.bak", db_path, timestamp);
+    let mut suffix = 1;
+    while std::path::Path::new(&backup_path).exists() {
+        backup_path = format!("{}.{}-{}.bak", db_path, timestamp, suffix);
+        suffix += 1;
+    }
+
+    println!("[迁移] 备份数据库到: {}", backup_path);
+    conn.execute("VACUUM INTO ?", [&backup_path])?;
+    println!("[迁移] ✓ 数据库备份完成");
+
+    Ok(Some(backup_path))
 }
 
 /// 数据库迁移：从旧版本 (v0) 升级到新版本 (v1)
@@ -52,7 +245,7 @@ pub fn migrate_v0_to_v1(conn: &Connection) -> SqliteResult<()> {
 
     if table_exists == 0 {
         println!("[迁移] 旧表不存在，这是全新数据库，跳过迁移");
-        // 注意：表的创建由 get_db_connection() 中的代码处理
+        // 注意：表的创建由 Database::initialize() 在迁移后处理
         return Ok(());
     }
 
@@ -97,10 +290,8 @@ pub fn migrate_v0_to_v1(conn: &Connection) -> SqliteResult<()> {
     println!("[迁移] 步骤 4/4：迁移数据");
     migrate_data(conn)?;
 
-    // 最后可以删除备份表（注释掉以保留备份）
-    // conn.execute("DROP TABLE IF EXISTS positions_old", [])?;
+    // 旧表在 v9 -> v10 迁移中删除（迁移前已有整库文件备份）
     println!("[迁移] ✓ 数据迁移完成");
-    println!("[迁移] ✓ 旧数据备份在 positions_old，可手动删除");
 
     Ok(())
 }
@@ -285,6 +476,816 @@ pub fn migrate_v2_to_v3(conn: &Connection) -> SqliteResult<()> {
     Ok(())
 }
 
+/// 数据库迁移：从 v3 升级到 v4
+///
+/// 变更内容：
+/// - 添加 buy_fee 字段：买入费用（佣金 + 过户费），历史记录默认为 0
+/// - 添加 sell_fee 字段：卖出费用（佣金 + 印花税 + 过户费）
+/// - 创建 settings 表：保存交易费用配置等应用设置
+pub fn migrate_v3_to_v4(conn: &Connection) -> SqliteResult<()> {
+    println!("[迁移] 检查是否需要 v3 -> v4 迁移");
+
+    // settings 表与持仓表无关，总是确保存在
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // 首先检查表是否存在
+    let table_exists: bool = match conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='positions'",
+        [],
+        |row| row.get::<_, i32>(0)
+    ) {
+        Ok(count) => count > 0,
+        Err(_) => false,
+    };
+
+    if !table_exists {
+        println!("[迁移] 表不存在，跳过 v3 -> v4 迁移（表将在初始化时创建）");
+        return Ok(());
+    }
+
+    // 检查是否已经有费用字段
+    let mut stmt = conn.prepare("PRAGMA table_info(positions)")?;
+    let columns: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let has_buy_fee = columns.iter().any(|col| col == "buy_fee");
+    let has_sell_fee = columns.iter().any(|col| col == "sell_fee");
+
+    if has_buy_fee && has_sell_fee {
+        println!("[迁移] 数据库已经是 v4，跳过迁移");
+        return Ok(());
+    }
+
+    println!("[迁移] 开始 v3 -> v4 迁移...");
+
+    if !has_buy_fee {
+        println!("[迁移] 添加 buy_fee 字段");
+        conn.execute("ALTER TABLE positions ADD COLUMN buy_fee REAL NOT NULL DEFAULT 0", [])?;
+        println!("[迁移] ✓ buy_fee 字段添加成功");
+    }
+
+    if !has_sell_fee {
+        println!("[迁移] 添加 sell_fee 字段");
+        conn.execute("ALTER TABLE positions ADD COLUMN sell_fee REAL", [])?;
+        println!("[迁移] ✓ sell_fee 字段添加成功");
+    }
+
+    println!("[迁移] ✓ v3 -> v4 迁移完成");
+
+    Ok(())
+}
+
+/// 数据库迁移：从 v4 升级到 v5
+///
+/// 变更内容：
+/// - 创建 portfolios 表：满仓金额、基础货币、描述、创建日期、归档状态
+/// - 为已有持仓中出现过的组合名称补建默认设置（满仓金额 50000，CNY）
+pub fn migrate_v4_to_v5(conn: &Connection) -> SqliteResult<()> {
+    println!("[迁移] 检查是否需要 v4 -> v5 迁移");
+
+    let table_exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='portfolios'",
+        [],
+        |row| row.get::<_, i32>(0)
+    )? > 0;
+
+    if table_exists {
+        println!("[迁移] 数据库已经是 v5，跳过迁移");
+        return Ok(());
+    }
+
+    println!("[迁移] 开始 v4 -> v5 迁移...");
+
+    conn.execute(
+        "CREATE TABLE portfolios (
+            name TEXT PRIMARY KEY,
+            full_position REAL NOT NULL DEFAULT 50000,
+            base_currency TEXT NOT NULL DEFAULT 'CNY',
+            description TEXT,
+            created_at TEXT NOT NULL,
+            archived INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+    println!("[迁移] ✓ portfolios 表创建成功");
+
+    // 持仓表可能尚未创建（全新数据库），此时无需补建
+    let positions_exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='positions'",
+        [],
+        |row| row.get::<_, i32>(0)
+    )? > 0;
+
+    if positions_exists {
+        let count = conn.execute(
+            "INSERT OR IGNORE INTO portfolios (name, created_at)
+             SELECT portfolio, MIN(buy_date) FROM positions
+             WHERE portfolio IS NOT NULL AND portfolio != ''
+             GROUP BY portfolio",
+            [],
+        )?;
+        println!("[迁移] ✓ 为 {} 个已有组合补建默认设置", count);
+    }
+
+    println!("[迁移] ✓ v4 -> v5 迁移完成");
+
+    Ok(())
+}
+
+/// 数据库迁移：从 v5 升级到 v6
+///
+/// 变更内容：
+/// - 创建 cash_transactions 表：入金、出金、利息及买卖资金流水
+///
+/// 注意：历史买卖记录不补记现金流水，现金账户从迁移后开始记账，
+/// 如需准确的现金余额，请先录入一笔期初入金
+pub fn migrate_v5_to_v6(conn: &Connection) -> SqliteResult<()> {
+    println!("[迁移] 检查是否需要 v5 -> v6 迁移");
+
+    let table_exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='cash_transactions'",
+        [],
+        |row| row.get::<_, i32>(0)
+    )? > 0;
+
+    if table_exists {
+        println!("[迁移] 数据库已经是 v6，跳过迁移");
+        return Ok(());
+    }
+
+    println!("[迁移] 开始 v5 -> v6 迁移...");
+
+    conn.execute(
+        "CREATE TABLE cash_transactions (
+            id TEXT PRIMARY KEY,
+            portfolio TEXT NOT NULL,
+            txn_type TEXT NOT NULL,
+            amount REAL NOT NULL,
+            txn_date TEXT NOT NULL,
+            position_id TEXT,
+            note TEXT,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_cash_portfolio ON cash_transactions(portfolio)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_cash_position_id ON cash_transactions(position_id)", [])?;
+    println!("[迁移] ✓ cash_transactions 表创建成功");
+
+    println!("[迁移] ✓ v5 -> v6 迁移完成");
+
+    Ok(())
+}
+
+/// 数据库迁移：从 v6 升级到 v7
+///
+/// 变更内容：
+/// - 创建 corporate_actions 表：记录分红、送转、拆股、配股
+/// - 创建 corporate_action_entries 表：记录每个公司行为对各持仓记录的调整明细
+///
+/// 同一股票同一除权除息日的同类公司行为只能录入一次
+pub fn migrate_v6_to_v7(conn: &Connection) -> SqliteResult<()> {
+    println!("[迁移] 检查是否需要 v6 -> v7 迁移");
+
+    let table_exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='corporate_actions'",
+        [],
+        |row| row.get::<_, i32>(0)
+    )? > 0;
+
+    if table_exists {
+        println!("[迁移] 数据库已经是 v7，跳过迁移");
+        return Ok(());
+    }
+
+    println!("[迁移] 开始 v6 -> v7 迁移...");
+
+    conn.execute(
+        "CREATE TABLE corporate_actions (
+            id TEXT PRIMARY KEY,
+            code TEXT NOT NULL,
+            action_type TEXT NOT NULL,
+            ex_date TEXT NOT NULL,
+            cash_per_share REAL NOT NULL DEFAULT 0,
+            share_ratio REAL NOT NULL DEFAULT 0,
+            rights_price REAL,
+            note TEXT,
+            created_at TEXT NOT NULL,
+            UNIQUE(code, action_type, ex_date)
+        )",
+        [],
+    )?;
+    println!("[迁移] ✓ corporate_actions 表创建成功");
+
+    conn.execute(
+        "CREATE TABLE corporate_action_entries (
+            id TEXT PRIMARY KEY,
+            action_id TEXT NOT NULL,
+            position_id TEXT NOT NULL,
+            portfolio TEXT NOT NULL,
+            code TEXT NOT NULL,
+            quantity_before INTEGER NOT NULL,
+            quantity_after INTEGER NOT NULL,
+            buy_price_before REAL NOT NULL,
+            buy_price_after REAL NOT NULL,
+            cash_amount REAL NOT NULL DEFAULT 0,
+            tax REAL NOT NULL DEFAULT 0,
+            net_cash REAL NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_ca_entries_action_id ON corporate_action_entries(action_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_ca_entries_position_id ON corporate_action_entries(position_id)", [])?;
+    println!("[迁移] ✓ corporate_action_entries 表创建成功");
+
+    println!("[迁移] ✓ v6 -> v7 迁移完成");
+
+    Ok(())
+}
+
+/// 数据库迁移：从 v7 升级到 v8
+///
+/// 变更内容：
+/// - 创建 idempotency_keys 表：记录已执行的幂等键及其返回结果，
+///   前端重试同一请求时直接返回首次执行的结果
+pub fn migrate_v7_to_v8(conn: &Connection) -> SqliteResult<()> {
+    println!("[迁移] 检查是否需要 v7 -> v8 迁移");
+
+    let table_exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='idempotency_keys'",
+        [],
+        |row| row.get::<_, i32>(0)
+    )? > 0;
+
+    if table_exists {
+        println!("[迁移] 数据库已经是 v8，跳过迁移");
+        return Ok(());
+    }
+
+    println!("[迁移] 开始 v7 -> v8 迁移...");
+
+    conn.execute(
+        "CREATE TABLE idempotency_keys (
+            key TEXT PRIMARY KEY,
+            command TEXT NOT NULL,
+            response TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    println!("[迁移] ✓ idempotency_keys 表创建成功");
+
+    println!("[迁移] ✓ v7 -> v8 迁移完成");
+
+    Ok(())
+}
+
+/// 数据库迁移：从 v8 升级到 v9
+///
+/// 变更内容：
+/// - 创建 position_events 表：只追加的操作审计记录，保存每次写操作前后的数据快照
+///
+/// 注意：迁移前的历史操作没有审计记录，无法撤销
+pub fn migrate_v8_to_v9(conn: &Connection) -> SqliteResult<()> {
+    println!("[迁移] 检查是否需要 v8 -> v9 迁移");
+
+    let table_exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='position_events'",
+        [],
+        |row| row.get::<_, i32>(0)
+    )? > 0;
+
+    if table_exists {
+        println!("[迁移] 数据库已经是 v9，跳过迁移");
+        return Ok(());
+    }
+
+    println!("[迁移] 开始 v8 -> v9 迁移...");
+
+    conn.execute(
+        "CREATE TABLE position_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            group_id TEXT NOT NULL,
+            action TEXT NOT NULL,
+            entity_type TEXT NOT NULL,
+            entity_id TEXT NOT NULL,
+            before_json TEXT,
+            after_json TEXT,
+            undo_of TEXT,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_events_group_id ON position_events(group_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_events_entity_id ON position_events(entity_id)", [])?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_events_undo_of ON position_events(undo_of)", [])?;
+    println!("[迁移] ✓ position_events 表创建成功");
+
+    println!("[迁移] ✓ v8 -> v9 迁移完成");
+
+    Ok(())
+}
+
+/// 数据库迁移：从 v9 升级到 v10
+///
+/// 变更内容：
+/// - 删除 v0 -> v1 迁移时留下的 positions_old 表
+///
+/// 执行迁移前已经整库备份，不再需要在库内保留旧表
+pub fn migrate_v9_to_v10(conn: &Connection) -> SqliteResult<()> {
+    println!("[迁移] 检查是否需要 v9 -> v10 迁移");
+
+    let table_exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='positions_old'",
+        [],
+        |row| row.get::<_, i32>(0)
+    )? > 0;
+
+    if !table_exists {
+        println!("[迁移] 不存在 positions_old 表，跳过迁移");
+        return Ok(());
+    }
+
+    conn.execute("DROP TABLE positions_old", [])?;
+    println!("[迁移] ✓ positions_old 表已删除");
+
+    println!("[迁移] ✓ v9 -> v10 迁移完成");
+
+    Ok(())
+}
+
+/// 数据库迁移：从 v10 升级到 v11
+///
+/// 变更内容：
+/// - 创建 quotes 表：按股票代码（带交易所前缀）缓存最近一次获取的行情
+pub fn migrate_v10_to_v11(conn: &Connection) -> SqliteResult<()> {
+    println!("[迁移] 检查是否需要 v10 -> v11 迁移");
+
+    let table_exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='quotes'",
+        [],
+        |row| row.get::<_, i32>(0)
+    )? > 0;
+
+    if table_exists {
+        println!("[迁移] 数据库已经是 v11，跳过迁移");
+        return Ok(());
+    }
+
+    println!("[迁移] 开始 v10 -> v11 迁移...");
+
+    conn.execute(
+        "CREATE TABLE quotes (
+            code TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            last_price REAL NOT NULL,
+            prev_close REAL,
+            as_of TEXT NOT NULL,
+            source TEXT
+        )",
+        [],
+    )?;
+    println!("[迁移] ✓ quotes 表创建成功");
+
+    println!("[迁移] ✓ v10 -> v11 迁移完成");
+
+    Ok(())
+}
+
+/// 数据库迁移：从 v11 升级到 v12
+///
+/// 变更内容：
+/// - 创建 manual_prices 表：按股票代码保存手动录入的价格，pinned 表示优先于实时行情
+pub fn migrate_v11_to_v12(conn: &Connection) -> SqliteResult<()> {
+    println!("[迁移] 检查是否需要 v11 -> v12 迁移");
+
+    let table_exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='manual_prices'",
+        [],
+        |row| row.get::<_, i32>(0)
+    )? > 0;
+
+    if table_exists {
+        println!("[迁移] 数据库已经是 v12，跳过迁移");
+        return Ok(());
+    }
+
+    println!("[迁移] 开始 v11 -> v12 迁移...");
+
+    conn.execute(
+        "CREATE TABLE manual_prices (
+            code TEXT PRIMARY KEY,
+            price REAL NOT NULL,
+            price_date TEXT NOT NULL,
+            pinned INTEGER NOT NULL DEFAULT 0,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    println!("[迁移] ✓ manual_prices 表创建成功");
+
+    println!("[迁移] ✓ v11 -> v12 迁移完成");
+
+    Ok(())
+}
+
+/// 数据库迁移：从 v12 升级到 v13
+///
+/// 变更内容：
+/// - quotes 表增加扩展行情字段，缓存的行情与实时获取的行情字段一致
+pub fn migrate_v12_to_v13(conn: &Connection) -> SqliteResult<()> {
+    println!("[迁移] 检查是否需要 v12 -> v13 迁移");
+
+    let mut stmt = conn.prepare("PRAGMA table_info(quotes)")?;
+    let columns: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    println!("[迁移] 开始 v12 -> v13 迁移...");
+
+    for column in ["open", "high", "low", "volume", "turnover", "change_percent", "pe_ratio", "market_cap"] {
+        if columns.iter().any(|col| col == column) {
+            continue;
+        }
+        conn.execute(&format!("ALTER TABLE quotes ADD COLUMN {} REAL", column), [])?;
+        println!("[迁移] ✓ {} 字段添加成功", column);
+    }
+
+    println!("[迁移] ✓ v12 -> v13 迁移完成");
+
+    Ok(())
+}
+
+/// 数据库迁移：从 v13 升级到 v14
+///
+/// 变更内容：
+/// - positions 表增加 market 字段（SH/SZ/BJ/HK/US/OTC）
+/// - 按已有代码识别市场并回填，无法识别的旧记录保持为空，行情按代码推断
+pub fn migrate_v13_to_v14(conn: &Connection) -> SqliteResult<()> {
+    println!("[迁移] 检查是否需要 v13 -> v14 迁移");
+
+    let table_exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='positions'",
+        [],
+        |row| row.get::<_, i32>(0)
+    )? > 0;
+
+    if !table_exists {
+        println!("[迁移] 表不存在，跳过 v13 -> v14 迁移（表将在初始化时创建）");
+        return Ok(());
+    }
+
+    let mut stmt = conn.prepare("PRAGMA table_info(positions)")?;
+    let columns: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if columns.iter().any(|col| col == "market") {
+        println!("[迁移] 数据库已经是 v14，跳过迁移");
+        return Ok(());
+    }
+
+    println!("[迁移] 开始 v13 -> v14 迁移...");
+
+    conn.execute("ALTER TABLE positions ADD COLUMN market TEXT", [])?;
+    println!("[迁移] ✓ market 字段添加成功");
+
+    let mut stmt = conn.prepare("SELECT DISTINCT code FROM positions")?;
+    let codes: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut unrecognized = Vec::new();
+    for code in codes {
+        match Symbol::parse(&code) {
+            Ok(symbol) => {
+                conn.execute(
+                    "UPDATE positions SET market = ? WHERE code = ?",
+                    params![symbol.market.as_str(), &code],
+                )?;
+            }
+            Err(_) => unrecognized.push(code),
+        }
+    }
+
+    if !unrecognized.is_empty() {
+        println!("[迁移] ⚠️  以下代码无法识别市场，保持为空: {:?}", unrecognized);
+    }
+
+    println!("[迁移] ✓ v13 -> v14 迁移完成");
+
+    Ok(())
+}
+
+/// 数据库迁移：从 v14 升级到 v15
+///
+/// 变更内容：
+/// - 创建 fx_rates 表（手动汇率及最近一次获取的实时汇率）
+/// - positions 表增加 currency 字段，按市场回填（港股 HKD、美股 USD，其余 CNY）
+/// - positions 表增加 buy_fx_rate 字段，已有记录为空（不计汇兑盈亏）
+pub fn migrate_v14_to_v15(conn: &Connection) -> SqliteResult<()> {
+    println!("[迁移] 检查是否需要 v14 -> v15 迁移");
+
+    let fx_table_exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='fx_rates'",
+        [],
+        |row| row.get::<_, i32>(0)
+    )? > 0;
+
+    if !fx_table_exists {
+        conn.execute(
+            "CREATE TABLE fx_rates (
+                from_currency TEXT NOT NULL,
+                to_currency TEXT NOT NULL,
+                rate REAL NOT NULL,
+                as_of TEXT NOT NULL,
+                source TEXT NOT NULL,
+                PRIMARY KEY (from_currency, to_currency)
+            )",
+            [],
+        )?;
+        println!("[迁移] ✓ fx_rates 表创建成功");
+    }
+
+    let table_exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='positions'",
+        [],
+        |row| row.get::<_, i32>(0)
+    )? > 0;
+
+    if !table_exists {
+        println!("[迁移] 表不存在，跳过 positions 表变更（表将在初始化时创建）");
+        return Ok(());
+    }
+
+    let mut stmt = conn.prepare("PRAGMA table_info(positions)")?;
+    let columns: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if columns.iter().any(|col| col == "currency") {
+        println!("[迁移] 数据库已经是 v15，跳过迁移");
+        return Ok(());
+    }
+
+    println!("[迁移] 开始 v14 -> v15 迁移...");
+
+    conn.execute("ALTER TABLE positions ADD COLUMN currency TEXT NOT NULL DEFAULT 'CNY'", [])?;
+    println!("[迁移] ✓ currency 字段添加成功");
+
+    conn.execute("ALTER TABLE positions ADD COLUMN buy_fx_rate REAL", [])?;
+    println!("[迁移] ✓ buy_fx_rate 字段添加成功");
+
+    for market in [Market::Hk, Market::Us] {
+        let updated = conn.execute(
+            "UPDATE positions SET currency = ? WHERE market = ?",
+            params![market.currency(), market.as_str()],
+        )?;
+        println!("[迁移] ✓ {} 条 {} 持仓币种设为 {}", updated, market.as_str(), market.currency());
+    }
+
+    println!("[迁移] ✓ v14 -> v15 迁移完成");
+
+    Ok(())
+}
+
+/// 数据库迁移：从 v15 升级到 v16
+///
+/// 变更内容：
+/// - 创建 daily_prices 表（每个代码每天一条收盘价）
+/// - 创建 portfolio_snapshots 表（每个组合每天一条快照）
+pub fn migrate_v15_to_v16(conn: &Connection) -> SqliteResult<()> {
+    println!("[迁移] 检查是否需要 v15 -> v16 迁移");
+
+    let table_exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='portfolio_snapshots'",
+        [],
+        |row| row.get::<_, i32>(0)
+    )? > 0;
+
+    if table_exists {
+        println!("[迁移] 数据库已经是 v16，跳过迁移");
+        return Ok(());
+    }
+
+    println!("[迁移] 开始 v15 -> v16 迁移...");
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS daily_prices (
+            code TEXT NOT NULL,
+            price_date TEXT NOT NULL,
+            close REAL NOT NULL,
+            prev_close REAL,
+            PRIMARY KEY (code, price_date)
+        )",
+        [],
+    )?;
+    println!("[迁移] ✓ daily_prices 表创建成功");
+
+    conn.execute(
+        "CREATE TABLE portfolio_snapshots (
+            portfolio TEXT NOT NULL,
+            snapshot_date TEXT NOT NULL,
+            base_currency TEXT NOT NULL,
+            market_value REAL NOT NULL,
+            position_cost REAL NOT NULL,
+            cash_balance REAL NOT NULL,
+            total_equity REAL NOT NULL,
+            profit_loss REAL NOT NULL,
+            today_profit_loss REAL NOT NULL,
+            is_partial INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (portfolio, snapshot_date)
+        )",
+        [],
+    )?;
+    println!("[迁移] ✓ portfolio_snapshots 表创建成功");
+
+    println!("[迁移] ✓ v15 -> v16 迁移完成");
+
+    Ok(())
+}
+
+/// 数据库迁移：从 v16 升级到 v17
+///
+/// 变更内容：
+/// - portfolios 表增加 benchmark 字段（业绩比较基准代码，为空时使用沪深300）
+pub fn migrate_v16_to_v17(conn: &Connection) -> SqliteResult<()> {
+    println!("[迁移] 检查是否需要 v16 -> v17 迁移");
+
+    let mut stmt = conn.prepare("PRAGMA table_info(portfolios)")?;
+    let columns: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if columns.iter().any(|col| col == "benchmark") {
+        println!("[迁移] 数据库已经是 v17，跳过迁移");
+        return Ok(());
+    }
+
+    println!("[迁移] 开始 v16 -> v17 迁移...");
+
+    conn.execute("ALTER TABLE portfolios ADD COLUMN benchmark TEXT", [])?;
+    println!("[迁移] ✓ benchmark 字段添加成功");
+
+    println!("[迁移] ✓ v16 -> v17 迁移完成");
+
+    Ok(())
+}
+
+/// v17 -> v18: 修正沪深 B 股持仓的交易币种
+///
+/// v15 按市场设置币种时 B 股被设为人民币，而沪市 B 股以美元、深市 B 股以港元交易。
+/// 修正的记录清空买入汇率（原汇率是按人民币记录的），汇兑盈亏从当前汇率开始计算
+pub fn migrate_v17_to_v18(conn: &Connection) -> SqliteResult<()> {
+    println!("[迁移] 检查是否需要 v17 -> v18 迁移");
+
+    let table_exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='positions'",
+        [],
+        |row| row.get::<_, i32>(0),
+    )? > 0;
+
+    if !table_exists {
+        println!("[迁移] 表不存在，跳过 positions 表变更（表将在初始化时创建）");
+        return Ok(());
+    }
+
+    println!("[迁移] 开始 v17 -> v18 迁移...");
+
+    for (market, prefix, currency) in [(Market::Sh, "900", "USD"), (Market::Sz, "200", "HKD")] {
+        let updated = conn.execute(
+            "UPDATE positions SET currency = ?, buy_fx_rate = NULL
+             WHERE market = ? AND substr(code, -6) LIKE ? || '%' AND currency != ?",
+            params![currency, market.as_str(), prefix, currency],
+        )?;
+        println!("[迁移] ✓ {} 条 {} B 股持仓币种设为 {}", updated, market.as_str(), currency);
+    }
+
+    println!("[迁移] ✓ v17 -> v18 迁移完成");
+
+    Ok(())
+}
+
+/// v18 -> v19: 统一公司行为的股票代码格式
+///
+/// 之前按录入的原样保存代码，同一公司行为以 600519 和 sh600519 各录入一次时不会被识别为重复。
+/// 已经重复录入的记录保留原代码（违反唯一约束），需要手动撤销其中一条
+pub fn migrate_v18_to_v19(conn: &Connection) -> SqliteResult<()> {
+    println!("[迁移] 检查是否需要 v18 -> v19 迁移");
+
+    let table_exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='corporate_actions'",
+        [],
+        |row| row.get::<_, i32>(0),
+    )? > 0;
+
+    if !table_exists {
+        println!("[迁移] 表不存在，跳过 corporate_actions 表变更（表将在初始化时创建）");
+        return Ok(());
+    }
+
+    println!("[迁移] 开始 v18 -> v19 迁移...");
+
+    let mut stmt = conn.prepare("SELECT id, code FROM corporate_actions")?;
+    let actions: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut updated = 0;
+    for (id, code) in actions {
+        let Ok(symbol) = Symbol::parse(&code) else { continue };
+        let normalized = symbol.to_string();
+        if normalized == code {
+            continue;
+        }
+        let changed = conn.execute(
+            "UPDATE OR IGNORE corporate_actions SET code = ? WHERE id = ?",
+            params![normalized, id],
+        )?;
+        if changed == 0 {
+            println!("[迁移] ⚠️  公司行为 {} ({}) 与已有记录重复，保留原代码", id, code);
+        }
+        updated += changed;
+    }
+    println!("[迁移] ✓ {} 条公司行为的股票代码已统一格式", updated);
+
+    println!("[迁移] ✓ v18 -> v19 迁移完成");
+
+    Ok(())
+}
+
+/// v19 -> v20 迁移：cash_transactions 表增加 fx_rate 字段
+///
+/// 此前外币持仓的现金流水按交易币种记账，与组合基础货币的现金余额直接相加。
+/// 历史流水没有记录卖出和除权日的汇率，统一按持仓的买入汇率折算；没有买入汇率的持仓保持原值
+pub fn migrate_v19_to_v20(conn: &Connection) -> SqliteResult<()> {
+    println!("[迁移] 检查是否需要 v19 -> v20 迁移");
+
+    let table_exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='cash_transactions'",
+        [],
+        |row| row.get::<_, i32>(0),
+    )? > 0;
+
+    if !table_exists {
+        println!("[迁移] 表不存在，跳过 cash_transactions 表变更（表将在初始化时创建）");
+        return Ok(());
+    }
+
+    let mut stmt = conn.prepare("PRAGMA table_info(cash_transactions)")?;
+    let columns: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if columns.iter().any(|col| col == "fx_rate") {
+        println!("[迁移] 数据库已经是 v20，跳过迁移");
+        return Ok(());
+    }
+
+    println!("[迁移] 开始 v19 -> v20 迁移...");
+
+    conn.execute("ALTER TABLE cash_transactions ADD COLUMN fx_rate REAL", [])?;
+    println!("[迁移] ✓ fx_rate 字段添加成功");
+
+    let positions_exist: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='positions'",
+        [],
+        |row| row.get::<_, i32>(0),
+    )? > 0;
+
+    if !positions_exist {
+        println!("[迁移] positions 表不存在，没有需要折算的现金流水");
+        println!("[迁移] ✓ v19 -> v20 迁移完成");
+        return Ok(());
+    }
+
+    let updated = conn.execute(
+        "UPDATE cash_transactions
+         SET fx_rate = (SELECT p.buy_fx_rate FROM positions p WHERE p.id = cash_transactions.position_id),
+             amount = amount * (SELECT p.buy_fx_rate FROM positions p WHERE p.id = cash_transactions.position_id)
+         WHERE position_id IN (
+             SELECT p.id FROM positions p
+             LEFT JOIN portfolios pf ON pf.name = p.portfolio
+             WHERE p.buy_fx_rate IS NOT NULL
+               AND p.currency != COALESCE(pf.base_currency, 'CNY')
+         )",
+        [],
+    )?;
+    println!("[迁移] ✓ {} 条外币持仓的现金流水已折算为组合基础货币", updated);
+
+    println!("[迁移] ✓ v19 -> v20 迁移完成");
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -349,4 +1350,59 @@ mod tests {
         // 清理
         let _ = fs::remove_file("test_migration.db");
     }
+
+    #[test]
+    fn test_run_migrations_records_version_and_backs_up() {
+        let db_path = std::env::temp_dir().join(format!("test_versioned_migration_{}.db", Uuid::new_v4().simple()));
+        let conn = Connection::open(&db_path).unwrap();
+
+        // 没有版本记录的旧版数据库
+        conn.execute(
+            "CREATE TABLE positions (
+                code TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                quantity INTEGER NOT NULL,
+                buy_price REAL NOT NULL,
+                buy_date TEXT NOT NULL,
+                portfolio TEXT
+            )",
+            [],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO positions VALUES ('600519', '贵州茅台', 100, 1680.5, '2025-01-01', 'default')",
+            [],
+        ).unwrap();
+
+        let report = run_migrations(&conn).unwrap();
+        assert_eq!(report.from_version, 0);
+        assert_eq!(report.to_version, MIGRATIONS.last().unwrap().version);
+        assert_eq!(report.applied.len(), MIGRATIONS.len());
+        assert_eq!(current_version(&conn).unwrap(), report.to_version);
+
+        let backup_path = report.backup_path.expect("迁移前应生成备份");
+        let backup = Connection::open(&backup_path).unwrap();
+        let has_id: i32 = backup.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('positions') WHERE name = 'id'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(has_id, 0, "备份应为迁移前的结构");
+
+        let old_table: i32 = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='positions_old'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(old_table, 0);
+
+        // 再次启动时不重复执行，也不再备份
+        let report = run_migrations(&conn).unwrap();
+        assert!(report.applied.is_empty());
+        assert!(report.backup_path.is_none());
+
+        drop(backup);
+        drop(conn);
+        let _ = fs::remove_file(&backup_path);
+        let _ = fs::remove_file(&db_path);
+    }
 }
@@ -0,0 +1,83 @@
+/// 操作审计数据模型
+/// 记录每次持仓相关写操作前后的数据快照，用于撤销和查看修改历史
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 审计记录对应的数据类型
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AuditEntityType {
+    /// 持仓记录（positions 表）
+    Position,
+    /// 现金流水（cash_transactions 表）
+    CashTransaction,
+    /// 公司行为（corporate_actions 表）
+    CorporateAction,
+    /// 公司行为明细（corporate_action_entries 表）
+    CorporateActionEntry,
+    /// 幂等键（idempotency_keys 表，撤销带幂等键的操作时删除）
+    IdempotencyKey,
+}
+
+impl AuditEntityType {
+    /// 数据库中保存的字符串
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Position => "POSITION",
+            Self::CashTransaction => "CASH_TRANSACTION",
+            Self::CorporateAction => "CORPORATE_ACTION",
+            Self::CorporateActionEntry => "CORPORATE_ACTION_ENTRY",
+            Self::IdempotencyKey => "IDEMPOTENCY_KEY",
+        }
+    }
+
+    /// 从数据库字符串解析
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "POSITION" => Some(Self::Position),
+            "CASH_TRANSACTION" => Some(Self::CashTransaction),
+            "CORPORATE_ACTION" => Some(Self::CorporateAction),
+            "CORPORATE_ACTION_ENTRY" => Some(Self::CorporateActionEntry),
+            "IDEMPOTENCY_KEY" => Some(Self::IdempotencyKey),
+            _ => None,
+        }
+    }
+}
+
+/// 单条审计记录（position_events 表，只追加不修改）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PositionEvent {
+    /// 自增序号（决定操作先后顺序）
+    pub id: i64,
+    /// 操作分组ID，同一次命令产生的所有记录共用一个分组，撤销时整组恢复
+    pub group_id: String,
+    /// 操作名称（与命令名一致，例如 reduce_position）
+    pub action: String,
+    /// 数据类型
+    pub entity_type: AuditEntityType,
+    /// 数据ID
+    pub entity_id: String,
+    /// 操作前快照（新建时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<Value>,
+    /// 操作后快照（删除时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<Value>,
+    /// 被撤销的操作分组ID（仅撤销操作产生的记录）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub undo_of: Option<String>,
+    /// 记录时间
+    pub created_at: String,
+}
+
+/// 撤销操作的结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UndoResult {
+    /// 被撤销的操作分组ID
+    pub group_id: String,
+    /// 被撤销的操作名称
+    pub action: String,
+    /// 恢复的数据条数
+    pub restored: usize,
+}
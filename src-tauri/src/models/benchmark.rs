@@ -0,0 +1,196 @@
+/// 业绩基准对比
+/// 将组合的时间加权累计收益率与基准指数在同一日期序列上对比，计算超额收益和跟踪误差
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::{invalid_input, error::Result};
+use crate::models::returns::days_between;
+use crate::models::risk::{annualized_volatility, trading_periods, PeriodReturn, TradingPeriod};
+use crate::models::symbol::Symbol;
+
+/// 基准收盘价的最长有效天数：对比日没有收盘价（周末、节假日）时取之前最近的收盘价，
+/// 超过该天数视为缺少基准价格（长假休市不会超过该间隔）
+pub const MAX_CLOSE_AGE_DAYS: i64 = 14;
+
+/// 对比序列中的一个日期
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BenchmarkPoint {
+    /// 日期 (YYYY-MM-DD)
+    pub date: String,
+    /// 组合累计收益率（时间加权）
+    pub portfolio_return: f64,
+    /// 基准累计收益率，缺少基准价格时为 None
+    pub benchmark_return: Option<f64>,
+    /// 累计超额收益（组合 - 基准）
+    pub excess_return: Option<f64>,
+}
+
+/// 组合与业绩基准的对比
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct BenchmarkComparison {
+    /// 组合名称
+    pub portfolio: String,
+    /// 基准代码
+    pub benchmark: String,
+    /// 累计收益率序列（第一个日期为起点，收益率为 0）
+    pub series: Vec<BenchmarkPoint>,
+    /// 组合区间累计收益率
+    pub portfolio_return: Option<f64>,
+    /// 基准区间累计收益率
+    pub benchmark_return: Option<f64>,
+    /// 区间超额收益（组合 - 基准）
+    pub excess_return: Option<f64>,
+    /// 年化跟踪误差：超额收益率按交易日折算的标准差 × √252
+    pub tracking_error: Option<f64>,
+    /// 基准价格是否不完整（获取历史行情失败，或部分日期缺少基准价格）
+    pub is_partial: bool,
+}
+
+impl BenchmarkComparison {
+    /// 由组合的区间收益率和基准收盘价计算对比结果
+    ///
+    /// 区间收益率的计算方式见 RiskService，跟踪误差与风险指标一样按区间包含的交易日数年化
+    pub fn compute(
+        portfolio: &str,
+        benchmark: &str,
+        returns: &[PeriodReturn],
+        benchmark_closes: &BTreeMap<String, f64>,
+    ) -> Self {
+        let mut comparison = Self {
+            portfolio: portfolio.to_string(),
+            benchmark: benchmark.to_string(),
+            ..Default::default()
+        };
+        let Some(first) = returns.first() else {
+            return comparison;
+        };
+
+        let base_close = close_on_or_before(benchmark_closes, &first.start_date);
+        let mut series = vec![BenchmarkPoint {
+            date: first.start_date.clone(),
+            portfolio_return: 0.0,
+            benchmark_return: base_close.map(|_| 0.0),
+            excess_return: base_close.map(|_| 0.0),
+        }];
+        let mut growth = 1.0;
+        let (mut asset_returns, mut benchmark_returns) = (Vec::new(), Vec::new());
+
+        for r in returns {
+            growth *= 1.0 + r.rate;
+            let portfolio_return = growth - 1.0;
+            let end_close = close_on_or_before(benchmark_closes, &r.end_date);
+            let benchmark_return = base_close.zip(end_close).map(|(base, end)| end / base - 1.0);
+
+            if let (Some(start), Some(end)) = (close_on_or_before(benchmark_closes, &r.start_date), end_close) {
+                asset_returns.push(r.clone());
+                benchmark_returns.push(PeriodReturn { rate: end / start - 1.0, ..r.clone() });
+            }
+
+            series.push(BenchmarkPoint {
+                date: r.end_date.clone(),
+                portfolio_return,
+                benchmark_return,
+                excess_return: benchmark_return.map(|benchmark_return| portfolio_return - benchmark_return),
+            });
+        }
+
+        let last = series.last().cloned();
+        comparison.portfolio_return = last.as_ref().map(|point| point.portfolio_return);
+        comparison.benchmark_return = last.as_ref().and_then(|point| point.benchmark_return);
+        comparison.excess_return = last.as_ref().and_then(|point| point.excess_return);
+        // 跟踪误差按各区间实际包含的交易日数年化，与风险指标一致
+        let active_periods: Vec<TradingPeriod> = trading_periods(&asset_returns)
+            .iter()
+            .zip(trading_periods(&benchmark_returns))
+            .map(|(asset, benchmark)| TradingPeriod { rate: asset.rate - benchmark.rate, ..*asset })
+            .collect();
+        comparison.tracking_error = annualized_volatility(&active_periods);
+        comparison.is_partial = series.iter().any(|point| point.benchmark_return.is_none());
+        comparison.series = series;
+        comparison
+    }
+}
+
+/// 解析业绩比较基准代码
+///
+/// 上证和中证指数（如沪深300 sh000300、中证500 sh000905）与深市 000 号段的股票代码相同，
+/// 不带前缀时会被识别为深市股票，因此 000 开头的 6 位代码必须加上市场前缀
+pub fn parse_benchmark(input: &str) -> Result<Symbol> {
+    let input = input.trim();
+    if input.len() == 6 && input.starts_with("000") && input.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid_input!(
+            "基准代码 {} 既可能是指数也可能是深市股票，请加上市场前缀（指数如 sh{}，股票如 sz{}）",
+            input, input, input
+        ));
+    }
+
+    let symbol = Symbol::parse(input)?;
+    if !symbol.market.has_quotes() {
+        return Err(invalid_input!("业绩比较基准必须是有行情的代码: {}", input));
+    }
+    Ok(symbol)
+}
+
+/// 取日期当天或之前最近的收盘价（不超过 MAX_CLOSE_AGE_DAYS 天）
+pub fn close_on_or_before(closes: &BTreeMap<String, f64>, date: &str) -> Option<f64> {
+    let (close_date, close) = closes.range(..=date.to_string()).next_back()?;
+    let age = days_between(close_date, date)?;
+    (age <= MAX_CLOSE_AGE_DAYS && *close > 0.0).then_some(*close)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn period(start_date: &str, end_date: &str, rate: f64) -> PeriodReturn {
+        PeriodReturn { start_date: start_date.to_string(), end_date: end_date.to_string(), rate }
+    }
+
+    #[test]
+    fn test_compare_with_benchmark() {
+        // 组合：+10%，再 +10%（含周六的快照），周六到下周二不变；
+        // 基准：+5%，周六不交易取周五收盘价，到下周二再 +5%
+        let returns = vec![
+            period("2025-06-26", "2025-06-27", 0.10),
+            period("2025-06-27", "2025-06-28", 0.10),
+            period("2025-06-28", "2025-07-01", 0.0),
+        ];
+        let closes: BTreeMap<String, f64> = [("2025-06-26", 4000.0), ("2025-06-27", 4200.0), ("2025-07-01", 4410.0)]
+            .iter()
+            .map(|(date, close)| (date.to_string(), *close))
+            .collect();
+
+        let comparison = BenchmarkComparison::compute("组合A", "sh000300", &returns, &closes);
+        assert_eq!(comparison.series.len(), 4);
+        assert_eq!(comparison.series[0].benchmark_return, Some(0.0));
+        assert!((comparison.series[2].benchmark_return.unwrap() - 0.05).abs() < 1e-9);
+        assert!((comparison.portfolio_return.unwrap() - 0.21).abs() < 1e-9);
+        assert!((comparison.benchmark_return.unwrap() - 0.1025).abs() < 1e-9);
+        assert!((comparison.excess_return.unwrap() - 0.1075).abs() < 1e-9);
+        // 周六的快照与下一个区间合并：超额收益 5%（1 个交易日）和 5%（周五到下周二，2 个交易日）
+        let daily_mean = 0.10 / 3.0;
+        let daily_variance = (0.05f64 - daily_mean).powi(2) + (0.05f64 - 2.0 * daily_mean).powi(2) / 2.0;
+        let expected = daily_variance.sqrt() * 252f64.sqrt();
+        assert!((comparison.tracking_error.unwrap() - expected).abs() < 1e-9);
+        assert!(!comparison.is_partial);
+
+        // 基准价格过旧时视为缺失
+        assert_eq!(close_on_or_before(&closes, "2025-07-20"), None);
+        let stale = BenchmarkComparison::compute("组合A", "sh000300", &[period("2025-07-20", "2025-07-21", 0.01)], &closes);
+        assert_eq!(stale.benchmark_return, None);
+        assert!(stale.is_partial);
+    }
+
+    #[test]
+    fn test_parse_benchmark() {
+        // 沪深300、中证500 不带前缀时不能当作深市股票
+        for code in ["000300", "000905"] {
+            assert!(parse_benchmark(code).is_err(), "代码: {}", code);
+            assert_eq!(parse_benchmark(&format!("sh{}", code)).unwrap().to_string(), format!("sh{}", code));
+            assert_eq!(parse_benchmark(&format!("{}.SH", code)).unwrap().to_string(), format!("sh{}", code));
+        }
+        assert_eq!(parse_benchmark("sz000001").unwrap().to_string(), "sz000001");
+        assert_eq!(parse_benchmark("510300").unwrap().to_string(), "sh510300");
+        assert!(parse_benchmark("otcPF0001").is_err());
+    }
+}
@@ -0,0 +1,191 @@
+/// 现金流水数据模型
+/// 记录每个投资组合的入金、出金、利息以及买卖对应的资金变动
+
+use serde::{Deserialize, Serialize};
+use crate::{invalid_input, error::Result};
+
+/// 现金流水类型
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CashTransactionType {
+    /// 入金
+    Deposit,
+    /// 出金
+    Withdraw,
+    /// 利息
+    Interest,
+    /// 买入扣款（由买入操作自动生成）
+    Buy,
+    /// 卖出回款（由平仓/减仓操作自动生成）
+    Sell,
+    /// 现金分红（由公司行为自动生成；持仓中的记录为税前金额，已卖出的记录为税后金额）
+    Dividend,
+    /// 红利税（卖出时按持股期限补扣，由卖出操作自动生成）
+    DividendTax,
+    /// 配股缴款（由公司行为自动生成）
+    RightsIssue,
+}
+
+impl CashTransactionType {
+    /// 数据库中保存的字符串
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Deposit => "DEPOSIT",
+            Self::Withdraw => "WITHDRAW",
+            Self::Interest => "INTEREST",
+            Self::Buy => "BUY",
+            Self::Sell => "SELL",
+            Self::Dividend => "DIVIDEND",
+            Self::DividendTax => "DIVIDEND_TAX",
+            Self::RightsIssue => "RIGHTS_ISSUE",
+        }
+    }
+
+    /// 从数据库字符串解析
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "DEPOSIT" => Some(Self::Deposit),
+            "WITHDRAW" => Some(Self::Withdraw),
+            "INTEREST" => Some(Self::Interest),
+            "BUY" => Some(Self::Buy),
+            "SELL" => Some(Self::Sell),
+            "DIVIDEND" => Some(Self::Dividend),
+            "DIVIDEND_TAX" => Some(Self::DividendTax),
+            "RIGHTS_ISSUE" => Some(Self::RightsIssue),
+            _ => None,
+        }
+    }
+
+    /// 是否为分红收入（现金分红及卖出时补扣的红利税）
+    pub fn is_dividend(&self) -> bool {
+        matches!(self, Self::Dividend | Self::DividendTax)
+    }
+
+    /// 是否可以由用户手工录入（买卖流水由持仓操作自动生成）
+    pub fn is_manual(&self) -> bool {
+        matches!(self, Self::Deposit | Self::Withdraw | Self::Interest)
+    }
+}
+
+/// 单条现金流水
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CashTransaction {
+    /// UUID，唯一主键
+    pub id: String,
+    /// 所属投资组合
+    pub portfolio: String,
+    /// 流水类型
+    pub txn_type: CashTransactionType,
+    /// 金额（组合基础货币，流入为正，流出为负）
+    pub amount: f64,
+    /// 发生日期 (YYYY-MM-DD)
+    pub txn_date: String,
+    /// 关联的持仓记录ID（买卖流水）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position_id: Option<String>,
+    /// 备注
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// 外币交易的成交汇率（交易币种 -> 组合基础货币），amount 已按该汇率折算；币种相同时为 None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fx_rate: Option<f64>,
+}
+
+impl CashTransaction {
+    /// 创建新的现金流水
+    pub fn new(
+        portfolio: String,
+        txn_type: CashTransactionType,
+        amount: f64,
+        txn_date: String,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            portfolio,
+            txn_type,
+            amount,
+            txn_date,
+            position_id: None,
+            note: None,
+            fx_rate: None,
+        }
+    }
+
+    /// 交易币种金额（按成交汇率折回）
+    pub fn trade_amount(&self) -> f64 {
+        self.amount / self.fx_rate.unwrap_or(1.0)
+    }
+
+    /// 买入扣款流水（成交金额 + 买入费用）
+    ///
+    /// 外币持仓按买入时汇率（buy_fx_rate）折算为组合基础货币
+    pub fn for_buy(position: &crate::models::Position) -> Self {
+        let mut txn = Self::new(
+            position.portfolio.clone(),
+            CashTransactionType::Buy,
+            -position.cost() * position.buy_fx_rate.unwrap_or(1.0),
+            position.buy_date.clone(),
+        );
+        txn.position_id = Some(position.id.clone());
+        txn.fx_rate = position.buy_fx_rate;
+        txn
+    }
+
+    /// 卖出回款流水（成交金额 - 卖出费用）
+    ///
+    /// fx_rate: 卖出时交易币种到组合基础货币的汇率，币种相同时为 None
+    pub fn for_sell(
+        portfolio: String,
+        position_id: String,
+        sell_price: f64,
+        quantity: i32,
+        sell_fee: f64,
+        sell_date: String,
+        fx_rate: Option<f64>,
+    ) -> Self {
+        let mut txn = Self::new(
+            portfolio,
+            CashTransactionType::Sell,
+            (sell_price * quantity as f64 - sell_fee) * fx_rate.unwrap_or(1.0),
+            sell_date,
+        );
+        txn.position_id = Some(position_id);
+        txn.fx_rate = fx_rate;
+        txn
+    }
+}
+
+/// 手工录入现金流水的请求参数
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateCashTransactionRequest {
+    pub portfolio: String,
+    pub txn_type: CashTransactionType,
+    /// 金额（正数，方向由类型决定）
+    pub amount: f64,
+    pub txn_date: String,
+    pub note: Option<String>,
+}
+
+impl CreateCashTransactionRequest {
+    /// 验证并转换为现金流水
+    pub fn into_transaction(self) -> Result<CashTransaction> {
+        if !self.txn_type.is_manual() {
+            return Err(invalid_input!("买卖及公司行为流水由系统自动生成，不能手工录入"));
+        }
+        if self.amount <= 0.0 {
+            return Err(invalid_input!("金额必须大于0"));
+        }
+        if self.portfolio.trim().is_empty() {
+            return Err(invalid_input!("投资组合不能为空"));
+        }
+
+        let amount = match self.txn_type {
+            CashTransactionType::Withdraw => -self.amount,
+            _ => self.amount,
+        };
+
+        let mut txn = CashTransaction::new(self.portfolio, self.txn_type, amount, self.txn_date);
+        txn.note = self.note;
+        Ok(txn)
+    }
+}
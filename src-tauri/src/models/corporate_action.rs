@@ -0,0 +1,183 @@
+/// 公司行为数据模型
+/// 现金分红（派息）、送转股、拆股、配股
+
+use serde::{Deserialize, Serialize};
+use crate::{invalid_input, error::Result};
+
+/// 公司行为类型
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CorporateActionType {
+    /// 现金分红（每股派息）
+    Dividend,
+    /// 送转股（每股送转数量，例如 10送3 即 0.3）
+    Bonus,
+    /// 拆股（拆分倍数，例如 1拆2 即 2.0）
+    Split,
+    /// 配股（每股配股数量 + 配股价）
+    Rights,
+}
+
+impl CorporateActionType {
+    /// 数据库中保存的字符串
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dividend => "DIVIDEND",
+            Self::Bonus => "BONUS",
+            Self::Split => "SPLIT",
+            Self::Rights => "RIGHTS",
+        }
+    }
+
+    /// 从数据库字符串解析
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "DIVIDEND" => Some(Self::Dividend),
+            "BONUS" => Some(Self::Bonus),
+            "SPLIT" => Some(Self::Split),
+            "RIGHTS" => Some(Self::Rights),
+            _ => None,
+        }
+    }
+}
+
+/// 公司行为记录
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CorporateAction {
+    /// UUID，唯一主键
+    pub id: String,
+    /// 股票代码
+    pub code: String,
+    /// 行为类型
+    pub action_type: CorporateActionType,
+    /// 除权除息日 (YYYY-MM-DD)，该日之前买入的持仓享有权益
+    pub ex_date: String,
+    /// 每股派息（税前，仅现金分红）
+    pub cash_per_share: f64,
+    /// 股份比例（送转：每股送转数；拆股：拆分倍数；配股：每股配股数）
+    pub share_ratio: f64,
+    /// 配股价（仅配股）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rights_price: Option<f64>,
+    /// 备注
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl CorporateAction {
+    /// 验证数据有效性
+    pub fn validate(&self) -> Result<()> {
+        if self.code.is_empty() {
+            return Err(invalid_input!("股票代码不能为空"));
+        }
+        if chrono::NaiveDate::parse_from_str(&self.ex_date, "%Y-%m-%d").is_err() {
+            return Err(invalid_input!("除权除息日格式必须为 YYYY-MM-DD"));
+        }
+        match self.action_type {
+            CorporateActionType::Dividend => {
+                if self.cash_per_share <= 0.0 {
+                    return Err(invalid_input!("每股派息必须大于0"));
+                }
+            }
+            CorporateActionType::Bonus => {
+                if self.share_ratio <= 0.0 {
+                    return Err(invalid_input!("每股送转数量必须大于0"));
+                }
+            }
+            CorporateActionType::Split => {
+                if self.share_ratio <= 0.0 || self.share_ratio == 1.0 {
+                    return Err(invalid_input!("拆股倍数必须大于0且不等于1"));
+                }
+            }
+            CorporateActionType::Rights => {
+                if self.share_ratio <= 0.0 {
+                    return Err(invalid_input!("每股配股数量必须大于0"));
+                }
+                if !self.rights_price.is_some_and(|price| price > 0.0) {
+                    return Err(invalid_input!("配股价必须大于0"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 录入公司行为的请求参数
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordCorporateActionRequest {
+    pub code: String,
+    pub action_type: CorporateActionType,
+    pub ex_date: String,
+    pub cash_per_share: Option<f64>,
+    pub share_ratio: Option<f64>,
+    pub rights_price: Option<f64>,
+    pub note: Option<String>,
+}
+
+impl From<RecordCorporateActionRequest> for CorporateAction {
+    fn from(req: RecordCorporateActionRequest) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            code: crate::db::QuoteService::format_stock_code(&req.code.to_lowercase()),
+            action_type: req.action_type,
+            ex_date: req.ex_date,
+            cash_per_share: req.cash_per_share.unwrap_or(0.0),
+            share_ratio: req.share_ratio.unwrap_or(0.0),
+            rights_price: req.rights_price,
+            note: req.note,
+        }
+    }
+}
+
+/// 公司行为对单笔持仓的影响明细
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CorporateActionEntry {
+    /// UUID，唯一主键
+    pub id: String,
+    /// 公司行为ID
+    pub action_id: String,
+    /// 持仓记录ID
+    pub position_id: String,
+    /// 所属投资组合
+    pub portfolio: String,
+    /// 股票代码
+    pub code: String,
+    /// 调整前数量
+    pub quantity_before: i32,
+    /// 调整后数量
+    pub quantity_after: i32,
+    /// 调整前买入价格
+    pub buy_price_before: f64,
+    /// 调整后买入价格
+    pub buy_price_after: f64,
+    /// 现金金额（分红为税前金额，配股为负的缴款金额）
+    pub cash_amount: f64,
+    /// 红利税（除息日时已卖出的记录按持股期限计算；仍在持仓的记录为 0，卖出时补扣）
+    pub tax: f64,
+    /// 实际到账现金（cash_amount - tax）
+    pub net_cash: f64,
+}
+
+/// 录入公司行为的结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CorporateActionResult {
+    /// 公司行为记录
+    pub action: CorporateAction,
+    /// 受影响的持仓明细
+    pub entries: Vec<CorporateActionEntry>,
+}
+
+/// A股差别化红利税率（按卖出时的持股期限计算，从买入日到卖出日）
+///
+/// - 持股 1 个月以内（含）：20%
+/// - 持股 1 个月以上至 1 年（含）：10%
+/// - 持股超过 1 年：免税
+pub fn dividend_tax_rate(holding_days: i64) -> f64 {
+    if holding_days <= 30 {
+        0.20
+    } else if holding_days <= 365 {
+        0.10
+    } else {
+        0.0
+    }
+}
@@ -0,0 +1,114 @@
+/// 交易费用模型
+/// 佣金（含最低收费）、印花税（仅卖出收取）、过户费（按交易所区分）
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::{invalid_input, error::Result};
+
+/// 交易费用配置
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FeeConfig {
+    /// 佣金费率（买卖双向，例如 0.00025 即万分之二点五）
+    pub commission_rate: f64,
+    /// 单笔最低佣金（元）
+    pub min_commission: f64,
+    /// 印花税率（仅卖出收取）
+    pub stamp_duty_rate: f64,
+    /// 过户费率（买卖双向，按交易所前缀区分，例如 sh -> 0.00001）
+    pub transfer_fee_rates: HashMap<String, f64>,
+}
+
+impl Default for FeeConfig {
+    fn default() -> Self {
+        let mut transfer_fee_rates = HashMap::new();
+        transfer_fee_rates.insert("sh".to_string(), 0.00001);
+        transfer_fee_rates.insert("sz".to_string(), 0.0);
+
+        Self {
+            commission_rate: 0.00025,
+            min_commission: 5.0,
+            stamp_duty_rate: 0.0005,
+            transfer_fee_rates,
+        }
+    }
+}
+
+impl FeeConfig {
+    /// 计算买入费用（佣金 + 过户费）
+    pub fn calculate_buy_fee(&self, code: &str, price: f64, quantity: i32) -> f64 {
+        let amount = price * quantity as f64;
+        round_to_cent(self.commission(amount) + self.transfer_fee(code, amount))
+    }
+
+    /// 计算卖出费用（佣金 + 印花税 + 过户费）
+    pub fn calculate_sell_fee(&self, code: &str, price: f64, quantity: i32) -> f64 {
+        let amount = price * quantity as f64;
+        let stamp_duty = amount * self.stamp_duty_rate;
+        round_to_cent(self.commission(amount) + stamp_duty + self.transfer_fee(code, amount))
+    }
+
+    /// 佣金（不足最低收费时按最低收费）
+    fn commission(&self, amount: f64) -> f64 {
+        if amount <= 0.0 {
+            return 0.0;
+        }
+        (amount * self.commission_rate).max(self.min_commission)
+    }
+
+    /// 过户费（根据代码所属交易所查找费率）
+    fn transfer_fee(&self, code: &str, amount: f64) -> f64 {
+        let formatted = crate::db::QuoteService::format_stock_code(code);
+        let rate = self
+            .transfer_fee_rates
+            .iter()
+            .find(|(exchange, _)| formatted.starts_with(exchange.as_str()))
+            .map(|(_, rate)| *rate)
+            .unwrap_or(0.0);
+        amount * rate
+    }
+
+    /// 验证配置有效性
+    pub fn validate(&self) -> Result<()> {
+        if self.commission_rate < 0.0 || self.commission_rate >= 1.0 {
+            return Err(invalid_input!("佣金费率必须在 0 到 1 之间"));
+        }
+        if self.min_commission < 0.0 {
+            return Err(invalid_input!("最低佣金不能为负数"));
+        }
+        if self.stamp_duty_rate < 0.0 || self.stamp_duty_rate >= 1.0 {
+            return Err(invalid_input!("印花税率必须在 0 到 1 之间"));
+        }
+        if let Some((exchange, _)) = self
+            .transfer_fee_rates
+            .iter()
+            .find(|(_, rate)| **rate < 0.0 || **rate >= 1.0)
+        {
+            return Err(invalid_input!("交易所 {} 的过户费率必须在 0 到 1 之间", exchange));
+        }
+        Ok(())
+    }
+}
+
+/// 按分四舍五入
+fn round_to_cent(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_calculation() {
+        let config = FeeConfig::default();
+
+        // 上海：佣金 max(10000 × 0.00025, 5) = 5，过户费 10000 × 0.00001 = 0.1
+        assert_eq!(config.calculate_buy_fee("600519", 100.0, 100), 5.1);
+        // 卖出额外收取印花税 10000 × 0.0005 = 5
+        assert_eq!(config.calculate_sell_fee("600519", 100.0, 100), 10.1);
+
+        // 深圳：无过户费，佣金 100000 × 0.00025 = 25
+        assert_eq!(config.calculate_buy_fee("000001", 10.0, 10000), 25.0);
+        assert_eq!(config.calculate_sell_fee("000001", 10.0, 10000), 75.0);
+    }
+}
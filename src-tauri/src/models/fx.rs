@@ -0,0 +1,145 @@
+/// 汇率数据模型
+/// 持仓按交易币种记录，组合汇总时换算为组合的基础货币
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::{invalid_input, error::Result};
+use crate::models::portfolio::DEFAULT_BASE_CURRENCY;
+
+/// 汇率来源
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FxRateSource {
+    /// 从汇率数据源获取（缓存最近一次的值）
+    Live,
+    /// 用户手工录入（优先于实时汇率）
+    Manual,
+}
+
+impl FxRateSource {
+    /// 数据库中保存的字符串
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Live => "LIVE",
+            Self::Manual => "MANUAL",
+        }
+    }
+
+    /// 从数据库字符串解析
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "LIVE" => Some(Self::Live),
+            "MANUAL" => Some(Self::Manual),
+            _ => None,
+        }
+    }
+}
+
+/// 汇率：1 单位 from_currency 兑换 rate 单位 to_currency
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FxRate {
+    /// 源币种（如 USD）
+    pub from_currency: String,
+    /// 目标币种（如 CNY）
+    pub to_currency: String,
+    /// 汇率
+    pub rate: f64,
+    /// 汇率时间（本地时间 YYYY-MM-DD HH:MM:SS）
+    pub as_of: String,
+    /// 汇率来源
+    pub source: FxRateSource,
+}
+
+impl FxRate {
+    /// 创建汇率并验证参数
+    pub fn new(from_currency: &str, to_currency: &str, rate: f64, source: FxRateSource) -> Result<Self> {
+        let from_currency = normalize_currency(from_currency)?;
+        let to_currency = normalize_currency(to_currency)?;
+        if from_currency == to_currency {
+            return Err(invalid_input!("源币种和目标币种不能相同"));
+        }
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(invalid_input!("汇率必须大于0"));
+        }
+
+        Ok(Self {
+            from_currency,
+            to_currency,
+            rate,
+            as_of: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            source,
+        })
+    }
+}
+
+/// 规范化币种代码：3 位字母，统一为大写
+pub fn normalize_currency(currency: &str) -> Result<String> {
+    let currency = currency.trim().to_uppercase();
+    if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(invalid_input!("币种代码必须为 3 位字母（如 CNY、HKD、USD）: {}", currency));
+    }
+    Ok(currency)
+}
+
+/// 汇率表，用于币种换算
+#[derive(Debug, Clone, Default)]
+pub struct FxRates {
+    rates: HashMap<(String, String), f64>,
+}
+
+impl FxRates {
+    pub fn new(rates: impl IntoIterator<Item = FxRate>) -> Self {
+        Self {
+            rates: rates
+                .into_iter()
+                .map(|rate| ((rate.from_currency, rate.to_currency), rate.rate))
+                .collect(),
+        }
+    }
+
+    /// 查询 1 单位 from 兑换多少 to
+    ///
+    /// 依次尝试：相同币种、直接汇率、反向汇率、经人民币交叉换算，都没有时返回 None
+    pub fn rate(&self, from: &str, to: &str) -> Option<f64> {
+        if from == to {
+            return Some(1.0);
+        }
+        if let Some(rate) = self.direct(from, to) {
+            return Some(rate);
+        }
+        if from != DEFAULT_BASE_CURRENCY && to != DEFAULT_BASE_CURRENCY {
+            return self
+                .direct(from, DEFAULT_BASE_CURRENCY)
+                .zip(self.direct(DEFAULT_BASE_CURRENCY, to))
+                .map(|(from_rate, to_rate)| from_rate * to_rate);
+        }
+        None
+    }
+
+    /// 直接汇率或反向汇率
+    fn direct(&self, from: &str, to: &str) -> Option<f64> {
+        self.rates
+            .get(&(from.to_string(), to.to_string()))
+            .copied()
+            .or_else(|| self.rates.get(&(to.to_string(), from.to_string())).map(|rate| 1.0 / rate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_lookup_direct_inverse_and_cross() {
+        let rates = FxRates::new(vec![
+            FxRate::new("usd", "CNY", 7.2, FxRateSource::Live).unwrap(),
+            FxRate::new("HKD", "CNY", 0.92, FxRateSource::Manual).unwrap(),
+        ]);
+
+        assert_eq!(rates.rate("CNY", "CNY"), Some(1.0));
+        assert_eq!(rates.rate("USD", "CNY"), Some(7.2));
+        assert_eq!(rates.rate("CNY", "USD"), Some(1.0 / 7.2));
+        assert_eq!(rates.rate("USD", "HKD"), Some(7.2 * (1.0 / 0.92)));
+        assert_eq!(rates.rate("EUR", "CNY"), None);
+    }
+}
@@ -3,7 +3,29 @@
 
 pub mod position;
 pub mod quote;
+pub mod fee;
+pub mod portfolio;
+pub mod cash;
+pub mod corporate_action;
+pub mod trade;
+pub mod audit;
+pub mod symbol;
+pub mod fx;
+pub mod snapshot;
+pub mod returns;
+pub mod risk;
+pub mod benchmark;
 
 // 重新导出
 pub use position::*;
-pub use quote::{RealQuote, PositionProfitLoss, TargetProfitLoss, PortfolioProfitLoss};
\ No newline at end of file
+pub use quote::{RealQuote, PositionProfitLoss, TargetProfitLoss, PortfolioProfitLoss, ProfitLossView};
+pub use fee::FeeConfig;
+pub use portfolio::Portfolio;
+pub use cash::CashTransaction;
+pub use corporate_action::CorporateAction;
+pub use symbol::{Market, Symbol};
+pub use fx::{FxRate, FxRates};
+pub use snapshot::{DailyPrice, PortfolioSnapshot};
+pub use returns::ReturnMetrics;
+pub use risk::{RiskMetrics, RiskQuery};
+pub use benchmark::BenchmarkComparison;
\ No newline at end of file
@@ -0,0 +1,138 @@
+/// 投资组合设置数据模型
+/// 每个投资组合独立的满仓金额、基础货币等配置
+
+use serde::{Deserialize, Serialize};
+use crate::{invalid_input, error::Result};
+use crate::models::benchmark::parse_benchmark;
+
+/// 默认满仓金额（未单独配置的组合使用）
+pub const DEFAULT_FULL_POSITION: f64 = 50000.0;
+
+/// 默认基础货币
+pub const DEFAULT_BASE_CURRENCY: &str = "CNY";
+
+/// 投资组合设置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Portfolio {
+    /// 组合名称（唯一，对应 positions.portfolio）
+    pub name: String,
+    /// 满仓金额（用于计算仓位占比）
+    pub full_position: f64,
+    /// 基础货币
+    pub base_currency: String,
+    /// 组合描述
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// 业绩比较基准代码（如 sh000300、sh000905、sh510300），为空时使用沪深300
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub benchmark: Option<String>,
+    /// 创建日期 (YYYY-MM-DD)
+    pub created_at: String,
+    /// 是否已归档
+    pub archived: bool,
+}
+
+impl Portfolio {
+    /// 使用默认设置创建组合
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            full_position: DEFAULT_FULL_POSITION,
+            base_currency: DEFAULT_BASE_CURRENCY.to_string(),
+            description: None,
+            benchmark: None,
+            created_at: chrono::Local::now().format("%Y-%m-%d").to_string(),
+            archived: false,
+        }
+    }
+
+    /// 验证数据有效性
+    pub fn validate(&self) -> Result<()> {
+        validate_name(&self.name)?;
+        if self.full_position <= 0.0 {
+            return Err(invalid_input!("满仓金额必须大于0"));
+        }
+        if self.base_currency.trim().is_empty() {
+            return Err(invalid_input!("基础货币不能为空"));
+        }
+        if let Some(benchmark) = &self.benchmark {
+            parse_benchmark(benchmark)?;
+        }
+        Ok(())
+    }
+}
+
+/// 规范化业绩比较基准代码：空字符串表示使用默认基准，可识别的代码统一为带市场前缀的格式
+fn normalize_benchmark(benchmark: String) -> Option<String> {
+    let benchmark = benchmark.trim();
+    if benchmark.is_empty() {
+        return None;
+    }
+    Some(
+        parse_benchmark(benchmark)
+            .map(|symbol| symbol.to_string())
+            .unwrap_or_else(|_| benchmark.to_string()),
+    )
+}
+
+/// 验证组合名称
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        return Err(invalid_input!("投资组合名称不能为空"));
+    }
+    Ok(())
+}
+
+/// 创建投资组合的请求参数
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatePortfolioRequest {
+    pub name: String,
+    pub full_position: Option<f64>,
+    pub base_currency: Option<String>,
+    pub description: Option<String>,
+    pub benchmark: Option<String>,
+}
+
+impl From<CreatePortfolioRequest> for Portfolio {
+    fn from(req: CreatePortfolioRequest) -> Self {
+        let mut portfolio = Self::new(req.name.trim().to_string());
+        if let Some(full_position) = req.full_position {
+            portfolio.full_position = full_position;
+        }
+        if let Some(base_currency) = req.base_currency {
+            portfolio.base_currency = base_currency.to_uppercase();
+        }
+        portfolio.description = req.description;
+        portfolio.benchmark = req.benchmark.and_then(normalize_benchmark);
+        portfolio
+    }
+}
+
+/// 更新投资组合设置的请求参数（未填写的字段保持不变）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdatePortfolioRequest {
+    pub name: String,
+    pub full_position: Option<f64>,
+    pub base_currency: Option<String>,
+    pub description: Option<String>,
+    /// 业绩比较基准，传空字符串时恢复为默认基准
+    pub benchmark: Option<String>,
+}
+
+impl UpdatePortfolioRequest {
+    /// 将修改应用到已有设置上
+    pub fn apply_to(self, portfolio: &mut Portfolio) {
+        if let Some(full_position) = self.full_position {
+            portfolio.full_position = full_position;
+        }
+        if let Some(base_currency) = self.base_currency {
+            portfolio.base_currency = base_currency.to_uppercase();
+        }
+        if let Some(description) = self.description {
+            portfolio.description = Some(description);
+        }
+        if let Some(benchmark) = self.benchmark {
+            portfolio.benchmark = normalize_benchmark(benchmark);
+        }
+    }
+}
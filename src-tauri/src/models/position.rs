@@ -3,6 +3,8 @@
 
 use serde::{Deserialize, Serialize};
 use crate::{invalid_input, error::{AppError, Result}};
+use crate::models::symbol::{Market, Symbol};
+use crate::models::returns::annualize;
 
 /// 数据库中的持仓记录
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -32,6 +34,25 @@ pub struct Position {
     /// 父记录ID（减仓时关联原记录）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parent_id: Option<String>,
+    /// 买入费用（佣金 + 过户费）
+    #[serde(default)]
+    pub buy_fee: f64,
+    /// 卖出费用（佣金 + 印花税 + 过户费，平仓时）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sell_fee: Option<f64>,
+    /// 交易市场（无法识别代码的旧记录为 None）
+    #[serde(default)]
+    pub market: Option<Market>,
+    /// 交易币种（价格、费用均以该币种计价）
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    /// 买入时交易币种兑组合基础货币的汇率（相同币种或买入时没有汇率时为 None，此时不计汇兑盈亏）
+    #[serde(default)]
+    pub buy_fx_rate: Option<f64>,
+}
+
+fn default_currency() -> String {
+    crate::models::portfolio::DEFAULT_BASE_CURRENCY.to_string()
 }
 
 impl Position {
@@ -44,6 +65,8 @@ impl Position {
         quantity: i32,
         portfolio: String,
     ) -> Self {
+        let symbol = Symbol::parse(&code).ok();
+        let market = symbol.as_ref().map(|symbol| symbol.market);
         Self {
             id: uuid::Uuid::new_v4().to_string(),
             code,
@@ -56,14 +79,33 @@ impl Position {
             sell_price: None,
             sell_date: None,
             parent_id: None,
+            buy_fee: 0.0,
+            sell_fee: None,
+            market,
+            currency: symbol.map(|symbol| symbol.currency().to_string()).unwrap_or_else(default_currency),
+            buy_fx_rate: None,
+        }
+    }
+
+    /// 证券代码（按记录的市场解析，没有市场时根据代码推断）
+    pub fn symbol(&self) -> Option<Symbol> {
+        match self.market {
+            Some(market) => Symbol::with_market(market, &self.code).ok(),
+            None => Symbol::parse(&self.code).ok(),
         }
     }
 
-    /// 计算已实现盈亏（仅对已平仓记录有效）
+    /// 持仓成本（买入价格 × 数量 + 买入费用）
+    pub fn cost(&self) -> f64 {
+        self.buy_price * self.quantity as f64 + self.buy_fee
+    }
+
+    /// 计算已实现盈亏（仅对已平仓记录有效，已扣除买卖费用）
     pub fn realized_profit_loss(&self) -> Option<f64> {
         if self.is_closed() {
             self.sell_price.map(|sell_price| {
-                (sell_price - self.buy_price) * self.quantity as f64
+                let proceeds = sell_price * self.quantity as f64 - self.sell_fee.unwrap_or(0.0);
+                proceeds - self.cost()
             })
         } else {
             None
@@ -73,7 +115,7 @@ impl Position {
     /// 计算已实现盈亏率（仅对已平仓记录有效）
     pub fn realized_profit_loss_rate(&self) -> Option<f64> {
         if self.is_closed() {
-            let cost = self.buy_price * self.quantity as f64;
+            let cost = self.cost();
             self.realized_profit_loss().map(|pnl| {
                 if cost != 0.0 {
                     pnl / cost
@@ -100,9 +142,18 @@ impl Position {
         if self.quantity <= 0 {
             return Err(invalid_input!("买入数量必须大于0"));
         }
+        if self.buy_fee < 0.0 || self.sell_fee.is_some_and(|fee| fee < 0.0) {
+            return Err(invalid_input!("交易费用不能为负数"));
+        }
         if self.status != "POSITION" && self.status != "CLOSE" {
             return Err(invalid_input!("状态必须是 POSITION 或 CLOSE"));
         }
+        if chrono::NaiveDate::parse_from_str(&self.buy_date, "%Y-%m-%d").is_err() {
+            return Err(invalid_input!("买入日期格式必须为 YYYY-MM-DD"));
+        }
+        if self.sell_date.as_deref().is_some_and(|sell_date| sell_date < self.buy_date.as_str()) {
+            return Err(invalid_input!("卖出日期不能早于买入日期"));
+        }
         Ok(())
     }
 
@@ -123,6 +174,8 @@ impl Position {
 }
 
 /// 创建持仓记录的请求参数
+///
+/// 代码需要能确定所属市场：号段无法确定市场的代码需要加前缀（如 sh、sz、bj、hk、us、otc）
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreatePositionRequest {
     pub code: String,
@@ -131,18 +184,79 @@ pub struct CreatePositionRequest {
     pub buy_date: String,
     pub quantity: i32,
     pub portfolio: String,
+    /// 实际买入费用（可选，不填时按费用配置计算）
+    #[serde(default)]
+    pub buy_fee: Option<f64>,
+}
+
+impl CreatePositionRequest {
+    /// 解析证券代码，无法识别或无法确定市场时返回错误
+    pub fn symbol(&self) -> Result<Symbol> {
+        Symbol::parse(&self.code)
+    }
 }
 
 impl From<CreatePositionRequest> for Position {
     fn from(req: CreatePositionRequest) -> Self {
-        Self::new(
+        let mut position = Self::new(
             req.code.to_lowercase(),  // 统一转为小写
             req.name,
             req.buy_price,
             req.buy_date,
             req.quantity,
             req.portfolio,
-        )
+        );
+        position.buy_fee = req.buy_fee.unwrap_or(0.0);
+        position
+    }
+}
+
+/// 修改持仓记录的请求参数（只修改传入的字段）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdatePositionRequest {
+    pub id: String,
+    pub code: Option<String>,
+    pub name: Option<String>,
+    pub buy_price: Option<f64>,
+    pub buy_date: Option<String>,
+    pub quantity: Option<i32>,
+    pub portfolio: Option<String>,
+    pub buy_fee: Option<f64>,
+}
+
+impl UpdatePositionRequest {
+    /// 将修改应用到已有记录上
+    ///
+    /// 修改代码时需要能确定所属市场，无法识别或有歧义的代码返回错误
+    pub fn apply_to(self, position: &mut Position) -> Result<()> {
+        if let Some(code) = self.code {
+            let code = code.to_lowercase();
+            if code != position.code {
+                let symbol = Symbol::parse(&code)?;
+                position.market = Some(symbol.market);
+                position.currency = symbol.currency().to_string();
+            }
+            position.code = code;
+        }
+        if let Some(name) = self.name {
+            position.name = name;
+        }
+        if let Some(buy_price) = self.buy_price {
+            position.buy_price = buy_price;
+        }
+        if let Some(buy_date) = self.buy_date {
+            position.buy_date = buy_date;
+        }
+        if let Some(quantity) = self.quantity {
+            position.quantity = quantity;
+        }
+        if let Some(portfolio) = self.portfolio {
+            position.portfolio = portfolio.trim().to_string();
+        }
+        if let Some(buy_fee) = self.buy_fee {
+            position.buy_fee = buy_fee;
+        }
+        Ok(())
     }
 }
 
@@ -174,7 +288,7 @@ impl PortfolioSummary {
     pub fn new(portfolio: String, positions: Vec<Position>) -> Result<Self> {
         let total_cost = positions
             .iter()
-            .map(|p| p.buy_price * p.quantity as f64)
+            .map(|p| p.cost())
             .sum();
 
         Ok(Self {
@@ -244,6 +358,77 @@ impl PositionQuery {
     }
 }
 
+/// 已平仓交易查询条件，所有条件均可选，日期范围两端都包含
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ClosedTradeFilter {
+    /// 按组合筛选
+    pub portfolio: Option<String>,
+    /// 按股票代码筛选
+    pub code: Option<String>,
+    /// 卖出日期起始 (YYYY-MM-DD)
+    pub sell_date_from: Option<String>,
+    /// 卖出日期截止 (YYYY-MM-DD)
+    pub sell_date_to: Option<String>,
+    /// 买入日期起始 (YYYY-MM-DD)
+    pub buy_date_from: Option<String>,
+    /// 买入日期截止 (YYYY-MM-DD)
+    pub buy_date_to: Option<String>,
+}
+
+impl ClosedTradeFilter {
+    /// 验证日期格式和日期范围
+    pub fn validate(&self) -> Result<()> {
+        let dates = [
+            &self.sell_date_from,
+            &self.sell_date_to,
+            &self.buy_date_from,
+            &self.buy_date_to,
+        ];
+        for date in dates.into_iter().flatten() {
+            if chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+                return Err(invalid_input!("日期格式错误，应为 YYYY-MM-DD: {}", date));
+            }
+        }
+
+        if let (Some(from), Some(to)) = (&self.sell_date_from, &self.sell_date_to) {
+            if from > to {
+                return Err(invalid_input!("卖出开始日期不能晚于截止日期"));
+            }
+        }
+        if let (Some(from), Some(to)) = (&self.buy_date_from, &self.buy_date_to) {
+            if from > to {
+                return Err(invalid_input!("买入开始日期不能晚于截止日期"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 构建查询 SQL（始终只查询已平仓记录）
+    ///
+    /// 股票代码的写法不统一（如 600519 与 sh600519），不在 SQL 中筛选，由调用方规范化后比较
+    pub fn build_where_clause(&self) -> (String, Vec<rusqlite::types::Value>) {
+        let mut conditions = vec!["status = 'CLOSE'".to_string()];
+        let mut params = Vec::new();
+
+        let filters = [
+            ("portfolio = ?", &self.portfolio),
+            ("sell_date >= ?", &self.sell_date_from),
+            ("sell_date <= ?", &self.sell_date_to),
+            ("buy_date >= ?", &self.buy_date_from),
+            ("buy_date <= ?", &self.buy_date_to),
+        ];
+        for (condition, value) in filters {
+            if let Some(value) = value {
+                conditions.push(condition.to_string());
+                params.push(value.clone().into());
+            }
+        }
+
+        (format!("WHERE {}", conditions.join(" AND ")), params)
+    }
+}
+
 /// 已平仓交易统计总览
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ClosedTradesSummary {
@@ -251,6 +436,25 @@ pub struct ClosedTradesSummary {
     pub trades: Vec<ClosedTrade>,
     /// 总统计
     pub statistics: ClosedTradesStatistics,
+    /// 按组合分组的统计
+    pub by_portfolio: Vec<ClosedTradeGroup>,
+    /// 按股票分组的统计
+    pub by_code: Vec<ClosedTradeGroup>,
+    /// 按卖出月份 (YYYY-MM) 分组的统计
+    pub by_month: Vec<ClosedTradeGroup>,
+    /// 按卖出年份 (YYYY) 分组的统计
+    pub by_year: Vec<ClosedTradeGroup>,
+}
+
+/// 一组已平仓交易的统计
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClosedTradeGroup {
+    /// 分组键（组合名称、股票代码、YYYY-MM 或 YYYY）
+    pub key: String,
+    /// 显示名称（按股票分组时为股票名称，其余与分组键相同）
+    pub name: String,
+    /// 该组的统计
+    pub statistics: ClosedTradesStatistics,
 }
 
 /// 单笔已平仓交易
@@ -272,7 +476,13 @@ pub struct ClosedTrade {
     pub sell_price: f64,
     /// 数量
     pub quantity: i32,
-    /// 盈亏金额
+    /// 买入费用
+    pub buy_fee: f64,
+    /// 卖出费用
+    pub sell_fee: f64,
+    /// 持有期间的税后分红收入
+    pub dividend_income: f64,
+    /// 盈亏金额（已扣除买卖费用，含分红收入）
     pub profit_loss: f64,
     /// 盈亏率
     pub profit_loss_rate: f64,
@@ -280,15 +490,18 @@ pub struct ClosedTrade {
     pub portfolio: String,
     /// 持有天数
     pub holding_days: i32,
+    /// 年化收益率（当日买卖或亏损超过 100% 时为空）
+    pub annualized_return: Option<f64>,
 }
 
 impl From<Position> for ClosedTrade {
     fn from(pos: Position) -> Self {
         let sell_price = pos.sell_price.unwrap_or(0.0);
-        let sell_date = pos.sell_date.unwrap_or_default();
-        let cost = pos.buy_price * pos.quantity as f64;
-        let profit_loss = (sell_price - pos.buy_price) * pos.quantity as f64;
+        let sell_fee = pos.sell_fee.unwrap_or(0.0);
+        let cost = pos.cost();
+        let profit_loss = sell_price * pos.quantity as f64 - sell_fee - cost;
         let profit_loss_rate = if cost != 0.0 { profit_loss / cost } else { 0.0 };
+        let sell_date = pos.sell_date.unwrap_or_default();
 
         // 计算持有天数
         let holding_days = calculate_holding_days(&pos.buy_date, &sell_date);
@@ -302,16 +515,37 @@ impl From<Position> for ClosedTrade {
             sell_date,
             sell_price,
             quantity: pos.quantity,
+            buy_fee: pos.buy_fee,
+            sell_fee,
+            dividend_income: 0.0,
             profit_loss,
             profit_loss_rate,
             portfolio: pos.portfolio,
             holding_days,
+            annualized_return: annualize(profit_loss_rate, holding_days as i64),
         }
     }
 }
 
+impl ClosedTrade {
+    /// 计入持有期间的分红收入，并重新计算盈亏率
+    pub fn with_dividend_income(mut self, dividend_income: f64) -> Self {
+        let cost = self.cost();
+        self.dividend_income = dividend_income;
+        self.profit_loss += dividend_income;
+        self.profit_loss_rate = if cost != 0.0 { self.profit_loss / cost } else { 0.0 };
+        self.annualized_return = annualize(self.profit_loss_rate, self.holding_days as i64);
+        self
+    }
+
+    /// 买入成本（含买入费用）
+    pub fn cost(&self) -> f64 {
+        self.buy_price * self.quantity as f64 + self.buy_fee
+    }
+}
+
 /// 已平仓交易总统计
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct ClosedTradesStatistics {
     /// 总交易笔数
     pub total_trades: usize,
@@ -331,6 +565,71 @@ pub struct ClosedTradesStatistics {
     pub max_loss: f64,
     /// 平均持有天数
     pub average_holding_days: f64,
+    /// 资金加权收益率（总盈亏 / 总买入成本）
+    pub capital_weighted_return: f64,
+    /// 资金时间加权的年化收益率（总盈亏 / Σ 成本×持有天数 × 365），持有天数全为 0 时为空
+    pub capital_weighted_annualized_return: Option<f64>,
+    /// 盈亏比因子（总盈利 / 总亏损），没有亏损交易时为空
+    pub profit_factor: Option<f64>,
+    /// 平均每笔盈利金额
+    pub average_win: f64,
+    /// 平均每笔亏损金额（负数）
+    pub average_loss: f64,
+    /// 每笔交易的期望盈亏（成功率 × 平均盈利 + 亏损率 × 平均亏损）
+    pub expectancy: f64,
+    /// 最长连续盈利笔数（按卖出日期先后）
+    pub longest_win_streak: usize,
+    /// 最长连续亏损笔数（按卖出日期先后）
+    pub longest_loss_streak: usize,
+    /// 持有天数分布
+    pub holding_day_histogram: Vec<HoldingDayBucket>,
+}
+
+/// 持有天数分布中的一个区间
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HoldingDayBucket {
+    /// 区间名称
+    pub label: String,
+    /// 最少持有天数（含）
+    pub min_days: i32,
+    /// 最多持有天数（含），为空表示不设上限
+    pub max_days: Option<i32>,
+    /// 交易笔数
+    pub trades: usize,
+    /// 其中盈利笔数
+    pub profitable_trades: usize,
+    /// 区间内总盈亏
+    pub total_profit_loss: f64,
+}
+
+impl HoldingDayBucket {
+    fn new(label: &str, min_days: i32, max_days: Option<i32>) -> Self {
+        Self {
+            label: label.to_string(),
+            min_days,
+            max_days,
+            trades: 0,
+            profitable_trades: 0,
+            total_profit_loss: 0.0,
+        }
+    }
+
+    /// 持有天数分布的默认区间
+    pub fn default_buckets() -> Vec<Self> {
+        vec![
+            Self::new("1周内", 0, Some(7)),
+            Self::new("1周-1月", 8, Some(30)),
+            Self::new("1-3月", 31, Some(90)),
+            Self::new("3-6月", 91, Some(180)),
+            Self::new("6-12月", 181, Some(365)),
+            Self::new("1年以上", 366, None),
+        ]
+    }
+
+    /// 持有天数是否落在该区间
+    pub fn contains(&self, days: i32) -> bool {
+        days >= self.min_days && self.max_days.map_or(true, |max| days <= max)
+    }
 }
 
 /// 计算持有天数
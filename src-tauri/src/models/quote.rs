@@ -2,6 +2,23 @@
 /// 对应 Java 版本的 RealQuote
 
 use serde::{Deserialize, Serialize};
+use crate::{invalid_input, error::Result};
+use crate::models::returns::{self, ReturnMetrics};
+
+/// 价格来源
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PriceSource {
+    /// 本次从行情数据源实时获取
+    #[default]
+    Live,
+    /// 来自行情缓存（有效期内的缓存，或获取失败时的最后已知价格）
+    Cached,
+    /// 用户手工录入
+    Manual,
+    /// 模拟价格（仅用于开发测试）
+    Mock,
+}
 
 /// 实时行情数据
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -12,6 +29,45 @@ pub struct RealQuote {
     pub name: String,
     /// 实时价格
     pub real_price: f64,
+    /// 昨收价
+    #[serde(default)]
+    pub prev_close: Option<f64>,
+    /// 今开
+    #[serde(default)]
+    pub open: Option<f64>,
+    /// 最高
+    #[serde(default)]
+    pub high: Option<f64>,
+    /// 最低
+    #[serde(default)]
+    pub low: Option<f64>,
+    /// 成交量（股）
+    #[serde(default)]
+    pub volume: Option<f64>,
+    /// 成交额（元）
+    #[serde(default)]
+    pub turnover: Option<f64>,
+    /// 涨跌幅（百分数，如 0.54 表示上涨 0.54%）
+    #[serde(default)]
+    pub change_percent: Option<f64>,
+    /// 市盈率
+    #[serde(default)]
+    pub pe_ratio: Option<f64>,
+    /// 总市值（元）
+    #[serde(default)]
+    pub market_cap: Option<f64>,
+    /// 行情时间（本地时间 YYYY-MM-DD HH:MM:SS）
+    #[serde(default)]
+    pub as_of: Option<String>,
+    /// 是否已过期（超过缓存有效期，或获取失败时使用的最后已知价格）
+    #[serde(default)]
+    pub is_stale: bool,
+    /// 行情数据源（如 TENCENT）
+    #[serde(default)]
+    pub source: Option<String>,
+    /// 价格来源
+    #[serde(default)]
+    pub price_source: PriceSource,
 }
 
 impl RealQuote {
@@ -20,10 +76,173 @@ impl RealQuote {
             code,
             name,
             real_price,
+            prev_close: None,
+            open: None,
+            high: None,
+            low: None,
+            volume: None,
+            turnover: None,
+            change_percent: None,
+            pe_ratio: None,
+            market_cap: None,
+            as_of: None,
+            is_stale: false,
+            source: None,
+            price_source: PriceSource::Live,
+        }
+    }
+
+    /// 设置昨收价
+    pub fn with_prev_close(mut self, prev_close: Option<f64>) -> Self {
+        self.prev_close = prev_close;
+        self
+    }
+
+    /// 涨跌额（实时价格 - 昨收，没有昨收时为 None）
+    pub fn change(&self) -> Option<f64> {
+        self.prev_close.map(|prev_close| self.real_price - prev_close)
+    }
+
+    /// 是否为今天的有效行情（未过期，且行情日期为今天；没有行情时间时视为今天）
+    ///
+    /// 过期的行情或停牌、休市时的最后已知价格不能用来计算今日盈亏
+    pub fn is_current_on(&self, today: &str) -> bool {
+        !self.is_stale
+            && self
+                .as_of
+                .as_deref()
+                .map_or(true, |as_of| as_of.get(..10) == Some(today))
+    }
+}
+
+/// 手动价格
+///
+/// 用于停牌股票、私募基金、理财产品等获取不到行情的持仓
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ManualPrice {
+    /// 股票代码（与持仓记录中的代码一致）
+    pub code: String,
+    /// 价格
+    pub price: f64,
+    /// 价格日期 (YYYY-MM-DD)
+    pub price_date: String,
+    /// 是否固定使用手动价格（true 时优先于实时行情，false 时只在没有行情时使用）
+    pub pinned: bool,
+    /// 最后修改时间
+    pub updated_at: String,
+}
+
+impl ManualPrice {
+    /// 创建手动价格并验证参数
+    pub fn new(code: String, price: f64, price_date: String, pinned: bool) -> Result<Self> {
+        let code = code.trim().to_string();
+        if code.is_empty() {
+            return Err(invalid_input!("股票代码不能为空"));
+        }
+        if !price.is_finite() || price <= 0.0 {
+            return Err(invalid_input!("价格必须大于0"));
+        }
+        if chrono::NaiveDate::parse_from_str(&price_date, "%Y-%m-%d").is_err() {
+            return Err(invalid_input!("价格日期格式错误，应为 YYYY-MM-DD: {}", price_date));
+        }
+
+        Ok(Self {
+            code,
+            price,
+            price_date,
+            pinned,
+            updated_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        })
+    }
+
+    /// 转换为行情（行情时间为价格日期）
+    pub fn to_quote(&self, code: String, name: String) -> RealQuote {
+        RealQuote {
+            as_of: Some(self.price_date.clone()),
+            price_source: PriceSource::Manual,
+            ..RealQuote::new(code, name, self.price)
+        }
+    }
+}
+
+/// 行情数据源类型
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum QuoteProviderKind {
+    /// 腾讯财经
+    Tencent,
+    /// 新浪财经
+    Sina,
+    /// 东方财富
+    Eastmoney,
+}
+
+impl QuoteProviderKind {
+    /// 数据库中保存的字符串
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Tencent => "TENCENT",
+            Self::Sina => "SINA",
+            Self::Eastmoney => "EASTMONEY",
         }
     }
 }
 
+/// 默认行情缓存有效期（秒）
+pub const DEFAULT_CACHE_TTL_SECS: u64 = 60;
+
+fn default_cache_ttl_secs() -> u64 {
+    DEFAULT_CACHE_TTL_SECS
+}
+
+/// 行情配置
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct QuoteConfig {
+    /// 数据源优先级（靠前的优先使用，失败时依次切换到后面的数据源）
+    pub providers: Vec<QuoteProviderKind>,
+    /// 行情缓存有效期（秒），有效期内直接使用缓存，0 表示每次都重新获取
+    #[serde(default = "default_cache_ttl_secs")]
+    pub cache_ttl_secs: u64,
+}
+
+impl Default for QuoteConfig {
+    fn default() -> Self {
+        Self {
+            providers: vec![QuoteProviderKind::Tencent, QuoteProviderKind::Sina, QuoteProviderKind::Eastmoney],
+            cache_ttl_secs: DEFAULT_CACHE_TTL_SECS,
+        }
+    }
+}
+
+impl QuoteConfig {
+    /// 验证配置：至少一个数据源，且不能重复
+    pub fn validate(&self) -> Result<()> {
+        if self.providers.is_empty() {
+            return Err(invalid_input!("至少需要配置一个行情数据源"));
+        }
+        for (i, provider) in self.providers.iter().enumerate() {
+            if self.providers[..i].contains(provider) {
+                return Err(invalid_input!("行情数据源 {:?} 重复配置", provider));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 单只股票的行情获取结果
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "status", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum QuoteOutcome {
+    /// 获取成功
+    Ok { quote: RealQuote },
+    /// 停牌或暂无成交价格
+    Suspended { name: String },
+    /// 代码不存在
+    UnknownCode,
+    /// 网络错误（重试后仍失败）
+    NetworkError { message: String },
+}
+
 /// 单笔交易的盈亏计算结果
 /// 对应 Java 版本的 PositionProfitLoss
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -40,14 +259,24 @@ pub struct PositionProfitLoss {
     pub buy_price: f64,
     /// 买入数量
     pub quantity: i32,
-    /// 实时价格
-    pub real_price: f64,
-    /// 持仓成本（买入价格 × 数量）
+    /// 买入费用
+    pub buy_fee: f64,
+    /// 实时价格（没有价格时为 None）
+    pub real_price: Option<f64>,
+    /// 持仓成本（买入价格 × 数量 + 买入费用）
     pub position_cost: f64,
-    /// 盈亏金额（(实时价格 - 买入价格) × 数量）
+    /// 累计税后分红收入
+    pub dividend_income: f64,
+    /// 盈亏金额（实时价格 × 数量 - 持仓成本 + 分红收入，没有价格时按买入价估值）
     pub profit_loss: f64,
     /// 盈亏比（盈亏 / 成本）
     pub profit_loss_rate: f64,
+    /// 今日盈亏（(实时价格 - 昨收) × 数量，当日买入的按买入价计算；没有今天的有效行情或昨收时为 None）
+    pub today_profit_loss: Option<f64>,
+    /// 持有天数
+    pub holding_days: i64,
+    /// 年化收益率（单笔持仓没有中途资金进出，资金加权与时间加权收益率相同；持有不满一天时为 None）
+    pub annualized_return: Option<f64>,
     /// 状态
     pub status: String,
     /// 投资组合
@@ -55,14 +284,32 @@ pub struct PositionProfitLoss {
 }
 
 impl PositionProfitLoss {
-    pub fn from_position(position: crate::models::Position, real_price: f64) -> Self {
-        let position_cost = position.buy_price * position.quantity as f64;
-        let profit_loss = (real_price - position.buy_price) * position.quantity as f64;
+    /// 参数：
+    /// - today_quote: 今天的有效行情（见 RealQuote::is_current_on），用于计算今日盈亏
+    /// - today: 今天的日期 (YYYY-MM-DD)，当日买入的持仓以买入价作为今日盈亏的基准
+    pub fn from_position(
+        position: crate::models::Position,
+        real_price: Option<f64>,
+        today_quote: Option<&RealQuote>,
+        dividend_income: f64,
+        today: &str,
+    ) -> Self {
+        let position_cost = position.cost();
+        let today_profit_loss = today_quote.and_then(|quote| {
+            let today_base = if position.buy_date == today {
+                Some(position.buy_price)
+            } else {
+                quote.prev_close
+            };
+            today_base.map(|base| position.quantity as f64 * (quote.real_price - base))
+        });
+        let profit_loss = position.quantity as f64 * real_price.unwrap_or(position.buy_price) - position_cost + dividend_income;
         let profit_loss_rate = if position_cost != 0.0 {
             profit_loss / position_cost
         } else {
             0.0
         };
+        let holding_days = returns::days_between(&position.buy_date, today).unwrap_or(0).max(0);
 
         Self {
             id: position.id,
@@ -71,10 +318,15 @@ impl PositionProfitLoss {
             buy_date: position.buy_date,
             buy_price: position.buy_price,
             quantity: position.quantity,
+            buy_fee: position.buy_fee,
             real_price,
             position_cost,
+            dividend_income,
             profit_loss,
             profit_loss_rate,
+            today_profit_loss,
+            holding_days,
+            annualized_return: returns::annualize(profit_loss_rate, holding_days),
             status: position.status,
             portfolio: position.portfolio,
         }
@@ -89,18 +341,46 @@ pub struct TargetProfitLoss {
     pub code: String,
     /// 股票名称
     pub name: String,
-    /// 实时价格
-    pub real_price: f64,
+    /// 实时价格（没有价格时为 None，此时按买入价估值）
+    pub real_price: Option<f64>,
+    /// 价格来源（没有价格时为 None）
+    pub price_source: Option<PriceSource>,
+    /// 行情时间
+    pub price_as_of: Option<String>,
+    /// 价格是否已过期
+    pub is_stale: bool,
+    /// 昨收价
+    pub prev_close: Option<f64>,
+    /// 涨跌幅（百分数）
+    pub change_percent: Option<f64>,
+    /// 交易币种（价格、成本、盈亏、分红均以该币种计价）
+    pub currency: String,
+    /// 交易币种兑组合基础货币的当前汇率（没有汇率时为 None，不计入组合汇总）
+    pub fx_rate: Option<f64>,
     /// 所有交易记录
     pub position_profit_losses: Vec<PositionProfitLoss>,
-    /// 成本仓位占比（总成本 / 满仓金额）
+    /// 成本仓位占比（基础货币总成本 / 满仓金额）
     pub cost_position_rate: f64,
-    /// 当前仓位占比（当前价值 / 满仓金额）
+    /// 当前仓位占比（基础货币市值 / 满仓金额）
     pub current_position_rate: f64,
-    /// 该股票总盈亏
+    /// 持仓成本（基础货币，按各笔买入时汇率折算）
+    pub target_position_cost: f64,
+    /// 持仓市值（基础货币，按当前汇率折算）
+    pub target_market_value: f64,
+    /// 价格盈亏（基础货币：价差按买入时汇率折算，分红按当前汇率折算）
+    pub target_price_profit_loss: f64,
+    /// 汇兑盈亏（基础货币：市值 × (当前汇率 - 买入时汇率)）
+    pub target_fx_profit_loss: f64,
+    /// 该股票累计分红收入
+    pub target_dividend_income: f64,
+    /// 该股票总盈亏（含分红）
     pub target_profit_loss: f64,
     /// 该股票盈亏比
     pub target_profit_loss_rate: f64,
+    /// 该股票今日盈亏（各笔今日盈亏之和，都无法计算时为 None）
+    pub target_today_profit_loss: Option<f64>,
+    /// 收益率指标（交易币种，含已平仓记录、卖出回款和分红）
+    pub returns: ReturnMetrics,
     /// 建议买入点（最近买入价 × 0.9）
     pub recommended_buy_in_point: f64,
     /// 建议卖出点（最近买入价 × 1.1）
@@ -113,14 +393,58 @@ pub struct TargetProfitLoss {
 pub struct PortfolioProfitLoss {
     /// 投资组合名称
     pub portfolio: String,
+    /// 基础货币（以下汇总金额均以基础货币计价，缺少汇率的股票不计入）
+    pub base_currency: String,
     /// 满仓金额
     pub full_position: f64,
     /// 该组合下所有股票的汇总
     pub target_profit_losses: Vec<TargetProfitLoss>,
     /// 总持仓成本
     pub sum_position_cost: f64,
-    /// 总盈亏
+    /// 总分红收入
+    pub sum_dividend_income: f64,
+    /// 总盈亏（含分红，价格盈亏 + 汇兑盈亏）
     pub sum_profit_losses: f64,
+    /// 价格盈亏（含分红）
+    pub sum_price_profit_loss: f64,
+    /// 汇兑盈亏
+    pub sum_fx_profit_loss: f64,
     /// 总盈亏比
     pub sum_profit_losses_rate: f64,
+    /// 今日总盈亏（没有昨收的股票不计入）
+    pub sum_today_profit_loss: f64,
+    /// 今日盈亏比（今日总盈亏 / 昨日持仓市值）
+    pub sum_today_profit_loss_rate: f64,
+    /// 收益率指标（含已平仓记录和分红，时间加权收益率按每日快照计算）
+    pub returns: ReturnMetrics,
+    /// 持仓总市值
+    pub sum_market_value: f64,
+    /// 现金余额
+    pub cash_balance: f64,
+    /// 总权益（现金余额 + 持仓总市值）
+    pub total_equity: f64,
+}
+
+impl PortfolioProfitLoss {
+    /// 该组合的数据是否不完整（有股票没有价格，或外币持仓缺少汇率）
+    pub fn is_partial(&self) -> bool {
+        self.target_profit_losses
+            .iter()
+            .any(|target| target.real_price.is_none() || target.fx_rate.is_none())
+    }
+}
+
+/// 盈亏视图（get_portfolio_profit_loss_view 的返回结果）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProfitLossView {
+    /// 按投资组合分组的汇总
+    pub portfolios: Vec<PortfolioProfitLoss>,
+    /// 是否为不完整数据（部分股票没有获取到任何价格，按买入价估值）
+    pub is_partial: bool,
+    /// 没有价格的股票代码
+    pub missing_codes: Vec<String>,
+    /// 缺少汇率的币种对（如 USD/CNY），对应股票不计入组合汇总
+    pub missing_fx_rates: Vec<String>,
+    /// 所有投资组合合计的收益率指标（各组合基础货币不同时为空）
+    pub overall_returns: ReturnMetrics,
 }
@@ -0,0 +1,170 @@
+/// 收益率计算
+/// 资金加权收益率（XIRR）考虑每笔资金投入、取出的时间，
+/// 时间加权收益率（TWR）剔除资金进出的影响，只反映持仓本身的表现
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// 一年的天数（年化和 XIRR 按自然日计算）
+pub const DAYS_PER_YEAR: f64 = 365.0;
+
+/// 现金流（从投资者角度：投入为负，取回为正）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CashFlow {
+    /// 发生日期
+    pub date: NaiveDate,
+    /// 金额
+    pub amount: f64,
+}
+
+impl CashFlow {
+    /// 从 YYYY-MM-DD 格式的日期创建，日期无法解析时返回 None
+    pub fn on(date: &str, amount: f64) -> Option<Self> {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .ok()
+            .map(|date| Self { date, amount })
+    }
+}
+
+/// 时间加权收益率的一个子区间
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TwrPeriod {
+    /// 期初市值（已包含期初发生的资金进出）
+    pub start_value: f64,
+    /// 期末市值（含期间分红，已扣除期间新投入的资金）
+    pub end_value: f64,
+}
+
+/// 收益率指标
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ReturnMetrics {
+    /// 资金加权年化收益率（XIRR），现金流不足或持有不满一天时为 None
+    pub xirr: Option<f64>,
+    /// 时间加权累计收益率
+    pub twr: Option<f64>,
+    /// 时间加权年化收益率
+    pub annualized_twr: Option<f64>,
+    /// 计算起始日期 (YYYY-MM-DD)
+    pub since: Option<String>,
+}
+
+/// 资金加权年化收益率（XIRR）：使各现金流按年化收益率折现后净值为 0 的收益率
+///
+/// 现金流必须同时有投入和取回，且跨越至少一天，否则返回 None。
+/// 使用二分法求解，收益率范围为 -99.9999% 到 1,000,000,000%
+pub fn xirr(flows: &[CashFlow]) -> Option<f64> {
+    let first = flows.iter().map(|flow| flow.date).min()?;
+    let last = flows.iter().map(|flow| flow.date).max()?;
+    if first == last
+        || !flows.iter().any(|flow| flow.amount > 0.0)
+        || !flows.iter().any(|flow| flow.amount < 0.0)
+    {
+        return None;
+    }
+
+    let npv = |rate: f64| -> f64 {
+        flows
+            .iter()
+            .map(|flow| {
+                let years = (flow.date - first).num_days() as f64 / DAYS_PER_YEAR;
+                flow.amount / (1.0 + rate).powf(years)
+            })
+            .sum()
+    };
+
+    let mut low = -0.999999;
+    let mut high = 1.0;
+    while npv(low).signum() == npv(high).signum() {
+        high *= 10.0;
+        if high > 1e7 {
+            return None;
+        }
+    }
+
+    for _ in 0..200 {
+        let mid = (low + high) / 2.0;
+        if npv(mid).signum() == npv(low).signum() {
+            low = mid;
+        } else {
+            high = mid;
+        }
+        if high - low < 1e-10 {
+            break;
+        }
+    }
+
+    Some((low + high) / 2.0)
+}
+
+/// 时间加权累计收益率：各子区间收益率连乘
+///
+/// 期初市值不大于 0 的区间（如尚未建仓）跳过，没有有效区间时返回 None
+pub fn time_weighted_return(periods: &[TwrPeriod]) -> Option<f64> {
+    let mut growth = 1.0;
+    let mut has_period = false;
+
+    for period in periods.iter().filter(|period| period.start_value > 0.0) {
+        growth *= period.end_value / period.start_value;
+        has_period = true;
+    }
+
+    has_period.then_some(growth - 1.0)
+}
+
+/// 将持有期累计收益率换算为年化收益率
+///
+/// 持有不满一天或亏损超过 100% 时返回 None
+pub fn annualize(rate: f64, days: i64) -> Option<f64> {
+    if days <= 0 || rate <= -1.0 {
+        return None;
+    }
+    Some((1.0 + rate).powf(DAYS_PER_YEAR / days as f64) - 1.0)
+}
+
+/// 两个 YYYY-MM-DD 日期之间的天数，日期无法解析时返回 None
+pub fn days_between(from: &str, to: &str) -> Option<i64> {
+    let from = NaiveDate::parse_from_str(from, "%Y-%m-%d").ok()?;
+    let to = NaiveDate::parse_from_str(to, "%Y-%m-%d").ok()?;
+    Some((to - from).num_days())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xirr_twr_and_annualize() {
+        // 投入 1000，一年后取回 1100：年化 10%
+        let flows = vec![
+            CashFlow::on("2023-01-01", -1000.0).unwrap(),
+            CashFlow::on("2024-01-01", 1100.0).unwrap(),
+        ];
+        assert!((xirr(&flows).unwrap() - 0.1).abs() < 1e-6);
+
+        // 半年后追加投入 1000，年末共取回 2150：资金加权收益率低于 10%
+        let flows = vec![
+            CashFlow::on("2023-01-01", -1000.0).unwrap(),
+            CashFlow::on("2023-07-02", -1000.0).unwrap(),
+            CashFlow::on("2024-01-01", 2150.0).unwrap(),
+        ];
+        let rate = xirr(&flows).unwrap();
+        assert!(rate > 0.09 && rate < 0.11);
+
+        // 只有投入、或所有现金流在同一天时无法计算
+        assert_eq!(xirr(&flows[..2]), None);
+        assert_eq!(xirr(&[CashFlow::on("2023-01-01", -1000.0).unwrap(), CashFlow::on("2023-01-01", 1000.0).unwrap()]), None);
+
+        // 上涨 10% 后追加资金再上涨 10%：时间加权收益率 21%，不受追加资金影响
+        let periods = vec![
+            TwrPeriod { start_value: 0.0, end_value: 0.0 },
+            TwrPeriod { start_value: 1000.0, end_value: 1100.0 },
+            TwrPeriod { start_value: 2100.0, end_value: 2310.0 },
+        ];
+        assert!((time_weighted_return(&periods).unwrap() - 0.21).abs() < 1e-9);
+        assert_eq!(time_weighted_return(&periods[..1]), None);
+
+        assert!((annualize(0.21, 730).unwrap() - 0.1).abs() < 1e-9);
+        assert_eq!(annualize(0.1, 0), None);
+        assert_eq!(days_between("2024-01-01", "2024-03-01"), Some(60));
+    }
+}
@@ -0,0 +1,496 @@
+/// 风险指标计算
+/// 根据每日收盘价或组合快照得到的区间收益率，计算年化波动率、最大回撤、夏普比率、
+/// 索提诺比率，以及相对基准指数的 Beta / Alpha
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::{invalid_input, error::Result};
+use crate::models::benchmark::parse_benchmark;
+
+/// 一年的交易日数（日收益率年化使用）
+pub const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+
+/// 默认基准指数（沪深300）
+pub const DEFAULT_BENCHMARK: &str = "sh000300";
+
+/// 默认无风险年利率
+pub const DEFAULT_RISK_FREE_RATE: f64 = 0.02;
+
+/// 风险指标查询条件，所有条件均可选
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RiskQuery {
+    /// 开始日期 (YYYY-MM-DD)，不填时从最早的记录开始
+    pub from: Option<String>,
+    /// 结束日期 (YYYY-MM-DD)，不填时到最新的记录为止
+    pub to: Option<String>,
+    /// 基准指数代码，默认沪深300（sh000300）
+    pub benchmark: Option<String>,
+    /// 无风险年利率，默认 2%
+    pub risk_free_rate: Option<f64>,
+}
+
+impl RiskQuery {
+    /// 验证日期范围、基准代码和无风险利率
+    pub fn validate(&self) -> Result<()> {
+        for date in [&self.from, &self.to].into_iter().flatten() {
+            if chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+                return Err(invalid_input!("日期格式错误，应为 YYYY-MM-DD: {}", date));
+            }
+        }
+        if let (Some(from), Some(to)) = (&self.from, &self.to) {
+            if from > to {
+                return Err(invalid_input!("开始日期不能晚于结束日期"));
+            }
+        }
+        if let Some(benchmark) = &self.benchmark {
+            if benchmark.trim().is_empty() {
+                return Err(invalid_input!("基准指数代码不能为空"));
+            }
+            parse_benchmark(benchmark)?;
+        }
+        if let Some(rate) = self.risk_free_rate {
+            if !rate.is_finite() || rate <= -1.0 || rate >= 1.0 {
+                return Err(invalid_input!("无风险利率应在 -100% 到 100% 之间"));
+            }
+        }
+        Ok(())
+    }
+
+    /// 查询的日期范围（含首尾），未指定的一端不设限制
+    pub fn date_range(&self) -> (&str, &str) {
+        (
+            self.from.as_deref().unwrap_or("0000-01-01"),
+            self.to.as_deref().unwrap_or("9999-12-31"),
+        )
+    }
+
+    /// 基准指数代码
+    pub fn benchmark(&self) -> &str {
+        self.benchmark.as_deref().map(str::trim).unwrap_or(DEFAULT_BENCHMARK)
+    }
+
+    /// 无风险年利率
+    pub fn risk_free_rate(&self) -> f64 {
+        self.risk_free_rate.unwrap_or(DEFAULT_RISK_FREE_RATE)
+    }
+}
+
+/// 相邻两个观测日之间的收益率
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeriodReturn {
+    /// 区间开始日期 (YYYY-MM-DD)
+    pub start_date: String,
+    /// 区间结束日期 (YYYY-MM-DD)
+    pub end_date: String,
+    /// 区间收益率
+    pub rate: f64,
+}
+
+impl PeriodReturn {
+    /// 区间包含的交易日数，日期无法解析时按一个交易日计算
+    pub fn trading_days(&self) -> i64 {
+        trading_days_between(&self.start_date, &self.end_date).unwrap_or(1)
+    }
+}
+
+/// 按交易日数计量的区间收益率
+///
+/// 观测日不连续时（快照只在打开应用的日子记录），一个区间可能跨多个交易日，
+/// 年化时按区间实际包含的交易日数折算
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TradingPeriod {
+    /// 区间收益率
+    pub rate: f64,
+    /// 区间包含的交易日数（不小于 1）
+    pub trading_days: i64,
+}
+
+/// 最大回撤
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MaxDrawdown {
+    /// 回撤幅度（不大于 0，如 -0.25 表示从高点下跌 25%）
+    pub rate: f64,
+    /// 回撤开始的高点日期
+    pub peak_date: String,
+    /// 回撤的最低点日期
+    pub trough_date: String,
+    /// 恢复到前高的日期，尚未恢复时为 None
+    pub recovery_date: Option<String>,
+}
+
+/// 风险指标
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RiskMetrics {
+    /// 第一个观测日 (YYYY-MM-DD)
+    pub start_date: Option<String>,
+    /// 最后一个观测日 (YYYY-MM-DD)
+    pub end_date: Option<String>,
+    /// 收益率区间数
+    pub periods: usize,
+    /// 年化波动率，区间数少于 2 时为 None
+    pub annualized_volatility: Option<f64>,
+    /// 最大回撤，没有收益率区间时为 None
+    pub max_drawdown: Option<MaxDrawdown>,
+    /// 夏普比率（年化）
+    pub sharpe_ratio: Option<f64>,
+    /// 索提诺比率（年化，只以下行波动作为风险）
+    pub sortino_ratio: Option<f64>,
+    /// 基准指数代码
+    pub benchmark: String,
+    /// 有基准价格、参与 Beta / Alpha 计算的区间数
+    pub benchmark_periods: usize,
+    /// 相对基准的 Beta
+    pub beta: Option<f64>,
+    /// 相对基准的年化 Alpha（詹森 Alpha）
+    pub alpha: Option<f64>,
+    /// 使用的无风险年利率
+    pub risk_free_rate: f64,
+}
+
+impl RiskMetrics {
+    /// 由区间收益率计算风险指标
+    ///
+    /// 每个区间按其包含的交易日数年化（见 trading_periods）。
+    /// 基准收益率取区间首尾两天的基准收盘价计算（非交易日取之前最近的收盘价），
+    /// 缺少基准价格的区间不参与 Beta / Alpha
+    pub fn compute(
+        returns: &[PeriodReturn],
+        benchmark: &str,
+        benchmark_closes: &BTreeMap<String, f64>,
+        risk_free_rate: f64,
+    ) -> Self {
+        let periods = trading_periods(returns);
+
+        let (asset_returns, benchmark_returns): (Vec<PeriodReturn>, Vec<PeriodReturn>) = returns
+            .iter()
+            .filter_map(|r| {
+                let start = trading_close(benchmark_closes, &r.start_date).filter(|close| *close > 0.0)?;
+                let end = trading_close(benchmark_closes, &r.end_date)?;
+                Some((r.clone(), PeriodReturn { rate: end / start - 1.0, ..r.clone() }))
+            })
+            .unzip();
+        // 两组区间的日期相同，合并非交易日后仍一一对应
+        let asset_periods = trading_periods(&asset_returns);
+        let benchmark_periods = trading_periods(&benchmark_returns);
+        let beta_alpha = beta_alpha(&asset_periods, &benchmark_periods, risk_free_rate);
+
+        Self {
+            start_date: returns.first().map(|r| r.start_date.clone()),
+            end_date: returns.last().map(|r| r.end_date.clone()),
+            periods: returns.len(),
+            annualized_volatility: annualized_volatility(&periods),
+            max_drawdown: max_drawdown(returns),
+            sharpe_ratio: sharpe_ratio(&periods, risk_free_rate),
+            sortino_ratio: sortino_ratio(&periods, risk_free_rate),
+            benchmark: benchmark.to_string(),
+            benchmark_periods: asset_periods.len(),
+            beta: beta_alpha.map(|(beta, _)| beta),
+            alpha: beta_alpha.map(|(_, alpha)| alpha),
+            risk_free_rate,
+        }
+    }
+}
+
+/// 由按日期升序的收盘价计算相邻观测日之间的收益率，前一日价格不大于 0 的区间跳过
+pub fn price_returns(closes: &[(String, f64)]) -> Vec<PeriodReturn> {
+    closes
+        .windows(2)
+        .filter(|pair| pair[0].1 > 0.0)
+        .map(|pair| PeriodReturn {
+            start_date: pair[0].0.clone(),
+            end_date: pair[1].0.clone(),
+            rate: pair[1].1 / pair[0].1 - 1.0,
+        })
+        .collect()
+}
+
+/// 开始日期之后到结束日期（含）之间的交易日数
+///
+/// 按周一至周五计算，不扣除法定节假日；结束日期不晚于开始日期时为 0
+pub fn trading_days_between(start: &str, end: &str) -> Option<i64> {
+    let start = NaiveDate::parse_from_str(start, "%Y-%m-%d").ok()?;
+    let end = NaiveDate::parse_from_str(end, "%Y-%m-%d").ok()?;
+    let days = (end - start).num_days();
+    if days <= 0 {
+        return Some(0);
+    }
+
+    let weekdays = (1..=days % 7)
+        .map(|offset| (start + Duration::days(days - days % 7 + offset)).weekday())
+        .filter(|weekday| !matches!(weekday, Weekday::Sat | Weekday::Sun))
+        .count() as i64;
+    Some(days / 7 * 5 + weekdays)
+}
+
+/// 日期对应的收盘价：当天的收盘价，非交易日取之前最近的收盘价（中间没有交易日）
+fn trading_close(closes: &BTreeMap<String, f64>, date: &str) -> Option<f64> {
+    let (close_date, close) = closes.range(..=date.to_string()).next_back()?;
+    (trading_days_between(close_date, date)? == 0).then_some(*close)
+}
+
+/// 将区间收益率转换为按交易日计量的区间
+///
+/// 不包含交易日的区间（如周五到周六的快照）与下一个区间合并，
+/// 末尾不包含交易日的区间并入前一个区间
+pub fn trading_periods(returns: &[PeriodReturn]) -> Vec<TradingPeriod> {
+    let mut periods: Vec<TradingPeriod> = Vec::with_capacity(returns.len());
+    let mut pending_growth = 1.0;
+
+    for r in returns {
+        pending_growth *= 1.0 + r.rate;
+        let trading_days = r.trading_days();
+        if trading_days > 0 {
+            periods.push(TradingPeriod { rate: pending_growth - 1.0, trading_days });
+            pending_growth = 1.0;
+        }
+    }
+    if let Some(last) = periods.last_mut() {
+        last.rate = (1.0 + last.rate) * pending_growth - 1.0;
+    }
+
+    periods
+}
+
+/// 年化波动率：日收益率的样本标准差 × √252
+pub fn annualized_volatility(periods: &[TradingPeriod]) -> Option<f64> {
+    daily_variance(periods).map(|variance| variance.sqrt() * TRADING_DAYS_PER_YEAR.sqrt())
+}
+
+/// 最大回撤：按区间收益率连乘得到净值，取净值从前高下跌的最大幅度
+pub fn max_drawdown(returns: &[PeriodReturn]) -> Option<MaxDrawdown> {
+    let first = returns.first()?;
+
+    let mut levels = vec![(first.start_date.as_str(), 1.0)];
+    let mut value = 1.0;
+    for r in returns {
+        value *= 1.0 + r.rate;
+        levels.push((r.end_date.as_str(), value));
+    }
+
+    let mut peak = 0;
+    let (mut worst_rate, mut worst_peak, mut worst_trough) = (0.0, 0, 0);
+    for (index, (_, level)) in levels.iter().enumerate() {
+        if *level > levels[peak].1 {
+            peak = index;
+        }
+        if levels[peak].1 <= 0.0 {
+            continue;
+        }
+        let drawdown = level / levels[peak].1 - 1.0;
+        if drawdown < worst_rate {
+            (worst_rate, worst_peak, worst_trough) = (drawdown, peak, index);
+        }
+    }
+
+    let recovery_date = (worst_rate < 0.0)
+        .then(|| {
+            levels[worst_trough..]
+                .iter()
+                .find(|(_, level)| *level >= levels[worst_peak].1)
+                .map(|(date, _)| date.to_string())
+        })
+        .flatten();
+
+    Some(MaxDrawdown {
+        rate: worst_rate,
+        peak_date: levels[worst_peak].0.to_string(),
+        trough_date: levels[worst_trough].0.to_string(),
+        recovery_date,
+    })
+}
+
+/// 夏普比率：日超额收益率均值 / 日收益率标准差 × √252
+pub fn sharpe_ratio(periods: &[TradingPeriod], risk_free_rate: f64) -> Option<f64> {
+    let excess = excess_periods(periods, risk_free_rate);
+    let std = daily_variance(&excess).map(f64::sqrt).filter(|std| *std > 0.0)?;
+    Some(daily_mean(&excess)? / std * TRADING_DAYS_PER_YEAR.sqrt())
+}
+
+/// 索提诺比率：日超额收益率均值 / 日下行偏差 × √252，没有下行波动时为 None
+///
+/// 下行偏差由各区间的负超额收益按交易日数折算为日方差后取平均
+pub fn sortino_ratio(periods: &[TradingPeriod], risk_free_rate: f64) -> Option<f64> {
+    if periods.len() < 2 {
+        return None;
+    }
+    let excess = excess_periods(periods, risk_free_rate);
+    let downside = (excess
+        .iter()
+        .map(|p| p.rate.min(0.0).powi(2) / p.trading_days as f64)
+        .sum::<f64>()
+        / excess.len() as f64)
+        .sqrt();
+    if downside <= 0.0 {
+        return None;
+    }
+    Some(daily_mean(&excess)? / downside * TRADING_DAYS_PER_YEAR.sqrt())
+}
+
+/// 相对基准的 Beta 和年化 Alpha：(Beta, Alpha)
+///
+/// Beta = Cov(资产, 基准) / Var(基准)，Alpha = (资产日超额收益 - Beta × 基准日超额收益) × 252。
+/// 两组区间一一对应（日期和交易日数相同）
+pub fn beta_alpha(asset: &[TradingPeriod], benchmark: &[TradingPeriod], risk_free_rate: f64) -> Option<(f64, f64)> {
+    if asset.len() < 2 || asset.len() != benchmark.len() {
+        return None;
+    }
+
+    let asset_mean = daily_mean(asset)?;
+    let benchmark_mean = daily_mean(benchmark)?;
+    let covariance = daily_covariance(asset, benchmark)?;
+    let variance = daily_variance(benchmark)?;
+    if variance <= 0.0 {
+        return None;
+    }
+
+    let beta = covariance / variance;
+    let daily_risk_free = risk_free_rate / TRADING_DAYS_PER_YEAR;
+    let alpha = (asset_mean - daily_risk_free - beta * (benchmark_mean - daily_risk_free)) * TRADING_DAYS_PER_YEAR;
+    Some((beta, alpha))
+}
+
+/// 扣除区间内无风险收益（日无风险利率 × 交易日数）后的超额收益率
+fn excess_periods(periods: &[TradingPeriod], risk_free_rate: f64) -> Vec<TradingPeriod> {
+    let daily_risk_free = risk_free_rate / TRADING_DAYS_PER_YEAR;
+    periods
+        .iter()
+        .map(|p| TradingPeriod { rate: p.rate - daily_risk_free * p.trading_days as f64, ..*p })
+        .collect()
+}
+
+/// 日均收益率：区间收益率之和 / 交易日数之和
+fn daily_mean(periods: &[TradingPeriod]) -> Option<f64> {
+    let days: i64 = periods.iter().map(|p| p.trading_days).sum();
+    (days > 0).then(|| periods.iter().map(|p| p.rate).sum::<f64>() / days as f64)
+}
+
+/// 日收益率的样本方差，少于 2 个区间时为 None
+fn daily_variance(periods: &[TradingPeriod]) -> Option<f64> {
+    daily_covariance(periods, periods)
+}
+
+/// 日收益率的样本协方差
+///
+/// 跨 n 个交易日的区间，收益率偏离（收益率 - 日均收益率 × n）的方差是日方差的 n 倍，
+/// 因此先除以 n 再求平均；每个区间都是一个交易日时即为普通的样本协方差
+fn daily_covariance(a: &[TradingPeriod], b: &[TradingPeriod]) -> Option<f64> {
+    if a.len() < 2 || a.len() != b.len() {
+        return None;
+    }
+    let (a_mean, b_mean) = (daily_mean(a)?, daily_mean(b)?);
+    let sum: f64 = a
+        .iter()
+        .zip(b)
+        .map(|(a, b)| {
+            let days = a.trading_days as f64;
+            (a.rate - a_mean * days) * (b.rate - b_mean * days) / days
+        })
+        .sum();
+    Some(sum / (a.len() - 1) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn closes(values: &[(&str, f64)]) -> Vec<(String, f64)> {
+        values.iter().map(|(date, close)| (date.to_string(), *close)).collect()
+    }
+
+    #[test]
+    fn test_drawdown_beta_and_ratios() {
+        let asset = closes(&[
+            ("2025-06-02", 10.0),
+            ("2025-06-03", 12.0),
+            ("2025-06-04", 9.0),
+            ("2025-06-05", 10.8),
+            ("2025-06-06", 12.6),
+        ]);
+        let returns = price_returns(&asset);
+        assert_eq!(returns.len(), 4);
+
+        // 从 12 跌到 9：回撤 25%，6 月 6 日回到 12 以上
+        let drawdown = max_drawdown(&returns).unwrap();
+        assert!((drawdown.rate + 0.25).abs() < 1e-9);
+        assert_eq!(drawdown.peak_date, "2025-06-03");
+        assert_eq!(drawdown.trough_date, "2025-06-04");
+        assert_eq!(drawdown.recovery_date.as_deref(), Some("2025-06-06"));
+
+        // 基准每天的涨跌幅正好是资产的一半：Beta = 2；缺少 6 月 6 日的基准价格，该区间不计入
+        let benchmark: BTreeMap<String, f64> = [
+            ("2025-06-02", 100.0),
+            ("2025-06-03", 110.0),
+            ("2025-06-04", 96.25),
+            ("2025-06-05", 105.875),
+        ]
+        .iter()
+        .map(|(date, close)| (date.to_string(), *close))
+        .collect();
+        let metrics = RiskMetrics::compute(&returns, DEFAULT_BENCHMARK, &benchmark, 0.0);
+        assert_eq!(metrics.benchmark_periods, 3);
+        assert!((metrics.beta.unwrap() - 2.0).abs() < 1e-9);
+        assert!(metrics.alpha.unwrap().abs() < 1e-9);
+        assert!(metrics.annualized_volatility.unwrap() > 0.0);
+        assert!(metrics.sortino_ratio.unwrap() > metrics.sharpe_ratio.unwrap());
+
+        // 只有一个区间时无法计算波动率
+        let single = RiskMetrics::compute(&returns[..1], DEFAULT_BENCHMARK, &benchmark, 0.0);
+        assert_eq!(single.annualized_volatility, None);
+        assert_eq!(single.beta, None);
+    }
+
+    #[test]
+    fn test_annualize_by_trading_days() {
+        assert_eq!(trading_days_between("2025-06-06", "2025-06-09"), Some(1)); // 周五 -> 周一
+        assert_eq!(trading_days_between("2025-06-06", "2025-06-07"), Some(0)); // 周五 -> 周六
+        assert_eq!(trading_days_between("2025-06-02", "2025-06-16"), Some(10));
+        assert_eq!(trading_days_between("2025-06-04", "2025-06-14"), Some(7));
+
+        // 每周一记录一次：每个区间 5 个交易日，周收益率 ±5%
+        let weekly = closes(&[
+            ("2025-06-02", 100.0),
+            ("2025-06-09", 105.0),
+            ("2025-06-16", 99.75),
+            ("2025-06-23", 104.7375),
+            ("2025-06-30", 99.500625),
+        ]);
+        let returns = price_returns(&weekly);
+        let periods = trading_periods(&returns);
+        assert!(periods.iter().all(|p| p.trading_days == 5));
+
+        // 年化波动率按周波动率 × √(252 / 5)，而不是 × √252
+        let rates: Vec<f64> = returns.iter().map(|r| r.rate).collect();
+        let mean = rates.iter().sum::<f64>() / 4.0;
+        let weekly_std = (rates.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / 3.0).sqrt();
+        let volatility = annualized_volatility(&periods).unwrap();
+        assert!((volatility - weekly_std * (TRADING_DAYS_PER_YEAR / 5.0).sqrt()).abs() < 1e-9);
+
+        // 周末的快照（周五 -> 周六 -> 周一）合并为一个交易日，不计为额外的零收益交易日
+        let weekend = closes(&[
+            ("2025-06-05", 10.0),
+            ("2025-06-06", 11.0),
+            ("2025-06-07", 11.0),
+            ("2025-06-09", 9.9),
+            ("2025-06-10", 10.89),
+        ]);
+        let periods = trading_periods(&price_returns(&weekend));
+        assert_eq!(periods.len(), 3);
+        assert!((periods[1].rate + 0.1).abs() < 1e-9);
+        assert_eq!(periods[1].trading_days, 1);
+
+        // 基准周末没有收盘价时取周五的收盘价：资产涨跌幅是基准的两倍
+        let benchmark: BTreeMap<String, f64> = [
+            ("2025-06-05", 100.0),
+            ("2025-06-06", 105.0),
+            ("2025-06-09", 99.75),
+            ("2025-06-10", 104.7375),
+        ]
+        .iter()
+        .map(|(date, close)| (date.to_string(), *close))
+        .collect();
+        let metrics = RiskMetrics::compute(&price_returns(&weekend), DEFAULT_BENCHMARK, &benchmark, 0.0);
+        assert_eq!(metrics.benchmark_periods, 3);
+        assert!((metrics.beta.unwrap() - 2.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,64 @@
+/// 历史数据模型
+/// 每日收盘价和投资组合的每日快照，为收益曲线、收益率和风险指标提供时间维度
+
+use serde::{Deserialize, Serialize};
+use crate::models::PortfolioProfitLoss;
+
+/// 每日收盘价（同一代码每天一条）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DailyPrice {
+    /// 股票代码（带交易所前缀，如 sh600519）
+    pub code: String,
+    /// 交易日期 (YYYY-MM-DD)
+    pub price_date: String,
+    /// 收盘价（盘中记录时为记录时的最新价，收盘后再次记录时覆盖）
+    pub close: f64,
+    /// 昨收价
+    pub prev_close: Option<f64>,
+}
+
+/// 投资组合每日快照（同一组合每天一条，金额以组合的基础货币计价）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PortfolioSnapshot {
+    /// 投资组合名称
+    pub portfolio: String,
+    /// 快照日期 (YYYY-MM-DD)
+    pub snapshot_date: String,
+    /// 基础货币
+    pub base_currency: String,
+    /// 持仓总市值
+    pub market_value: f64,
+    /// 总持仓成本
+    pub position_cost: f64,
+    /// 现金余额
+    pub cash_balance: f64,
+    /// 总权益（现金余额 + 持仓总市值）
+    pub total_equity: f64,
+    /// 持仓总盈亏（含分红）
+    pub profit_loss: f64,
+    /// 当日盈亏
+    pub today_profit_loss: f64,
+    /// 是否为不完整数据（部分股票没有价格或汇率）
+    pub is_partial: bool,
+    /// 记录时间
+    pub created_at: String,
+}
+
+impl PortfolioSnapshot {
+    /// 根据当前的组合汇总生成快照
+    pub fn from_profit_loss(portfolio: &PortfolioProfitLoss, snapshot_date: &str) -> Self {
+        Self {
+            portfolio: portfolio.portfolio.clone(),
+            snapshot_date: snapshot_date.to_string(),
+            base_currency: portfolio.base_currency.clone(),
+            market_value: portfolio.sum_market_value,
+            position_cost: portfolio.sum_position_cost,
+            cash_balance: portfolio.cash_balance,
+            total_equity: portfolio.total_equity,
+            profit_loss: portfolio.sum_profit_losses,
+            today_profit_loss: portfolio.sum_today_profit_loss,
+            is_partial: portfolio.is_partial(),
+            created_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+}
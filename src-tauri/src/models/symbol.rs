@@ -0,0 +1,246 @@
+/// 证券代码数据模型
+/// 统一解析沪深京 A 股、港股、美股及场外产品的代码，并识别所属市场
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::{invalid_input, error::Result};
+
+/// 交易市场
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Market {
+    /// 上海证券交易所
+    Sh,
+    /// 深圳证券交易所
+    Sz,
+    /// 北京证券交易所
+    Bj,
+    /// 香港交易所
+    Hk,
+    /// 美国市场
+    Us,
+    /// 场外产品（私募基金、理财产品等，没有行情）
+    Otc,
+}
+
+impl Market {
+    /// 数据库中保存的字符串
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sh => "SH",
+            Self::Sz => "SZ",
+            Self::Bj => "BJ",
+            Self::Hk => "HK",
+            Self::Us => "US",
+            Self::Otc => "OTC",
+        }
+    }
+
+    /// 从数据库字符串解析
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "SH" => Some(Self::Sh),
+            "SZ" => Some(Self::Sz),
+            "BJ" => Some(Self::Bj),
+            "HK" => Some(Self::Hk),
+            "US" => Some(Self::Us),
+            "OTC" => Some(Self::Otc),
+            _ => None,
+        }
+    }
+
+    /// 代码前缀（如 sh600519 中的 sh）
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            Self::Sh => "sh",
+            Self::Sz => "sz",
+            Self::Bj => "bj",
+            Self::Hk => "hk",
+            Self::Us => "us",
+            Self::Otc => "otc",
+        }
+    }
+
+    /// 交易币种（场外产品默认人民币，沪深 B 股的币种按号段确定，见 Symbol::currency）
+    pub fn currency(&self) -> &'static str {
+        match self {
+            Self::Hk => "HKD",
+            Self::Us => "USD",
+            _ => "CNY",
+        }
+    }
+
+    /// 是否有行情（场外产品只能使用手动价格）
+    pub fn has_quotes(&self) -> bool {
+        *self != Self::Otc
+    }
+
+    /// 根据 6 位数字代码的号段推断 A 股市场，无法确定时返回 None
+    ///
+    /// - 上海：60/68（主板、科创板）、90（B 股）、50/51/52/56/58（基金、ETF）、10/11（债券、可转债）
+    /// - 深圳：00/30（主板、创业板）、20（B 股）、15/16/18（基金、ETF）、12（债券、可转债）
+    /// - 北京：4/8 开头、92 开头
+    fn infer_a_share(digits: &str) -> Option<Self> {
+        match &digits[..2] {
+            "60" | "68" | "90" | "50" | "51" | "52" | "56" | "58" | "10" | "11" => Some(Self::Sh),
+            "00" | "30" | "20" | "15" | "16" | "18" | "12" => Some(Self::Sz),
+            "92" => Some(Self::Bj),
+            prefix if prefix.starts_with('4') || prefix.starts_with('8') => Some(Self::Bj),
+            _ => None,
+        }
+    }
+}
+
+/// 证券代码（市场 + 交易所内代码）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct Symbol {
+    /// 交易市场
+    pub market: Market,
+    /// 交易所内代码（A 股 6 位数字，港股 5 位数字，美股大写代码）
+    pub code: String,
+}
+
+impl Symbol {
+    /// 解析证券代码
+    ///
+    /// 支持的格式（不区分大小写）：
+    /// - 带前缀：sh600519、sz000001、bj430047、hk00700、usAAPL、otcPF0001
+    /// - 带后缀：600519.SH、00700.HK、AAPL.US
+    /// - 6 位数字：按号段推断沪深京市场，号段无法确定市场时报错，需要加前缀
+    /// - 5 位数字：港股
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err(invalid_input!("股票代码不能为空"));
+        }
+
+        // 后缀格式：600519.SH
+        if let Some((code, suffix)) = input.rsplit_once('.') {
+            if let Some(market) = Market::parse(&suffix.to_uppercase()) {
+                return Self::with_market(market, code);
+            }
+        }
+
+        // 前缀格式：sh600519（按字节比较 ASCII 前缀，避免小写后字节长度变化的字符导致切片越界）
+        for market in [Market::Otc, Market::Sh, Market::Sz, Market::Bj, Market::Hk, Market::Us] {
+            let prefix_len = market.prefix().len();
+            let has_prefix = input.get(..prefix_len).is_some_and(|prefix| prefix.eq_ignore_ascii_case(market.prefix()));
+            if has_prefix && input.len() > prefix_len {
+                let code = &input[prefix_len..];
+                // 前缀后不是该市场的代码格式时继续尝试其他格式
+                if let Ok(symbol) = Self::with_market(market, code) {
+                    return Ok(symbol);
+                }
+            }
+        }
+
+        if input.chars().all(|c| c.is_ascii_digit()) {
+            match input.len() {
+                6 => {
+                    return Market::infer_a_share(input)
+                        .map(|market| Self { market, code: input.to_string() })
+                        .ok_or_else(|| invalid_input!(
+                            "无法识别代码 {} 所属的市场，请加上市场前缀（如 sh{}、sz{}、bj{}）",
+                            input, input, input, input
+                        ));
+                }
+                5 => return Self::with_market(Market::Hk, input),
+                _ => {}
+            }
+        }
+
+        Err(invalid_input!(
+            "无法识别的股票代码: {}（A 股为 6 位数字，港股加 hk 前缀，美股加 us 前缀，场外产品加 otc 前缀）",
+            input
+        ))
+    }
+
+    /// 按指定市场解析代码（代码可以带或不带该市场的前缀）
+    pub fn with_market(market: Market, code: &str) -> Result<Self> {
+        let code = code.trim();
+        let code = match code.get(..market.prefix().len()) {
+            Some(prefix) if prefix.eq_ignore_ascii_case(market.prefix()) => &code[market.prefix().len()..],
+            _ => code,
+        };
+
+        let is_digits = !code.is_empty() && code.chars().all(|c| c.is_ascii_digit());
+        let code = match market {
+            Market::Sh | Market::Sz | Market::Bj if is_digits && code.len() == 6 => code.to_string(),
+            // 港股代码不足 5 位时补零：700 -> 00700
+            Market::Hk if is_digits && code.len() <= 5 => format!("{:0>5}", code),
+            Market::Us if code.len() <= 10
+                && code.starts_with(|c: char| c.is_ascii_alphabetic())
+                && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-') =>
+            {
+                code.to_uppercase()
+            }
+            Market::Otc if !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') => {
+                code.to_uppercase()
+            }
+            _ => return Err(invalid_input!("{} 不是有效的 {} 市场代码", code, market.as_str())),
+        };
+
+        Ok(Self { market, code })
+    }
+}
+
+impl Symbol {
+    /// 交易币种：沪市 B 股（900 开头）以美元交易，深市 B 股（200 开头）以港元交易，其余按市场
+    pub fn currency(&self) -> &'static str {
+        match self.market {
+            Market::Sh if self.code.starts_with("900") => "USD",
+            Market::Sz if self.code.starts_with("200") => "HKD",
+            market => market.currency(),
+        }
+    }
+}
+
+impl fmt::Display for Symbol {
+    /// 带前缀的格式（如 sh600519、hk00700、usAAPL），与行情数据源的代码格式一致
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.market.prefix(), self.code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_symbols() {
+        let cases = [
+            ("600519", "sh600519"),
+            ("SZ000001", "sz000001"),
+            ("300750", "sz300750"),
+            ("510300", "sh510300"),
+            ("159915", "sz159915"),
+            ("113050", "sh113050"),
+            ("128136", "sz128136"),
+            ("430047", "bj430047"),
+            ("830799", "bj830799"),
+            ("920118", "bj920118"),
+            ("hk700", "hk00700"),
+            ("00700.HK", "hk00700"),
+            ("usaapl", "usAAPL"),
+            ("BRK.B.US", "usBRK.B"),
+            ("otcpf0001", "otcPF0001"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(Symbol::parse(input).unwrap().to_string(), expected, "输入: {}", input);
+        }
+
+        // 号段无法确定市场、位数不对的代码需要加前缀
+        assert!(Symbol::parse("700001").is_err());
+        assert!(Symbol::parse("1234567").is_err());
+        assert!(Symbol::parse("AAPL").is_err());
+        assert!(Symbol::parse("sh00700").is_err());
+        // 开尔文符号小写后是 1 字节的 k，不能按小写后的长度切片
+        assert!(Symbol::parse("h\u{212A}700").is_err());
+
+        // B 股按号段确定交易币种
+        assert_eq!(Symbol::parse("900901").unwrap().currency(), "USD");
+        assert_eq!(Symbol::parse("200002").unwrap().currency(), "HKD");
+        assert_eq!(Symbol::parse("600519").unwrap().currency(), "CNY");
+        assert_eq!(Symbol::parse("hk00700").unwrap().currency(), "HKD");
+    }
+}
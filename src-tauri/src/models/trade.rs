@@ -0,0 +1,71 @@
+/// 卖出撮合数据模型
+/// 按股票维度卖出时，决定从哪些持仓批次中扣减数量
+
+use serde::{Deserialize, Serialize};
+
+/// 批次匹配策略
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LotMatchingPolicy {
+    /// 先进先出：优先卖出最早买入的批次
+    #[default]
+    Fifo,
+    /// 后进先出：优先卖出最近买入的批次
+    Lifo,
+    /// 成本最高优先：优先卖出单位成本最高的批次
+    HighestCost,
+    /// 平均成本：按各批次持有数量等比例卖出
+    AverageCost,
+}
+
+/// 按股票卖出的请求参数
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SellSharesRequest {
+    pub portfolio: String,
+    pub code: String,
+    pub quantity: i32,
+    pub sell_price: f64,
+    pub sell_date: String,
+    #[serde(default)]
+    pub policy: LotMatchingPolicy,
+    /// 实际卖出总费用（可选，不填时按费用配置计算）
+    pub sell_fee: Option<f64>,
+}
+
+/// 单个批次的卖出明细
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LotSale {
+    /// 被卖出的持仓记录ID
+    pub position_id: String,
+    /// 已卖出记录ID（整笔卖出时与 position_id 相同，部分卖出时为新建的子记录）
+    pub sold_id: String,
+    /// 买入日期
+    pub buy_date: String,
+    /// 买入价格
+    pub buy_price: f64,
+    /// 本批次卖出数量
+    pub quantity: i32,
+    /// 本批次分摊的卖出费用
+    pub sell_fee: f64,
+    /// 是否整笔卖出（平仓）
+    pub closed: bool,
+}
+
+/// 按股票卖出的结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SellSharesResult {
+    /// 投资组合
+    pub portfolio: String,
+    /// 股票代码
+    pub code: String,
+    /// 使用的匹配策略
+    pub policy: LotMatchingPolicy,
+    /// 卖出总数量
+    pub quantity: i32,
+    /// 卖出价格
+    pub sell_price: f64,
+    /// 卖出总费用
+    pub sell_fee: f64,
+    /// 各批次的卖出明细
+    pub lots: Vec<LotSale>,
+}
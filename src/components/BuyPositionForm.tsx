@@ -137,7 +137,7 @@ export function BuyPositionForm({ onBuy }: BuyPositionFormProps) {
                   fetchStockName(code);
                 }
               }}
-              placeholder="例如: 600519 或 sh600519"
+              placeholder="例如: 600519、bj430047、hk00700、usAAPL"
               required
             />
             {isFetchingName && (
@@ -1,7 +1,12 @@
 "use client";
 
 import { useEffect, useState } from "react";
-import { ClosedTradesSummary, ClosedTradesStatistics } from "@/lib/types";
+import {
+  ClosedTradeFilter,
+  ClosedTradeGroup,
+  ClosedTradesSummary,
+  ClosedTradesStatistics,
+} from "@/lib/types";
 import { db } from "@/lib/db";
 import {
   Table,
@@ -13,17 +18,41 @@ import {
 } from "@/components/ui/table";
 import { Card } from "@/components/ui/card";
 import { Spinner } from "@/components/ui/spinner";
+import { Input } from "@/components/ui/input";
+import { Button } from "@/components/ui/button";
+
+const EMPTY_FILTER: ClosedTradeFilter = {
+  portfolio: "",
+  code: "",
+  sell_date_from: "",
+  sell_date_to: "",
+  buy_date_from: "",
+  buy_date_to: "",
+};
+
+// 去掉空白的筛选条件，空字符串不传给后端
+function normalizeFilter(filter: ClosedTradeFilter): ClosedTradeFilter {
+  const normalized: ClosedTradeFilter = {};
+  (Object.keys(filter) as (keyof ClosedTradeFilter)[]).forEach((key) => {
+    const value = filter[key]?.trim();
+    if (value) {
+      normalized[key] = value;
+    }
+  });
+  return normalized;
+}
 
 export function ClosedTradesView() {
   const [summary, setSummary] = useState<ClosedTradesSummary | null>(null);
   const [loading, setLoading] = useState(true);
   const [error, setError] = useState<string | null>(null);
+  const [filter, setFilter] = useState<ClosedTradeFilter>(EMPTY_FILTER);
 
-  const fetchData = async () => {
+  const fetchData = async (current: ClosedTradeFilter = filter) => {
     try {
       setLoading(true);
       setError(null);
-      const data = await db.getClosedTradesSummary();
+      const data = await db.getClosedTradesSummary(normalizeFilter(current));
       setSummary(data);
     } catch (err) {
       const errorMsg = err instanceof Error ? err.message : "加载数据失败";
@@ -38,26 +67,49 @@ export function ClosedTradesView() {
     fetchData();
   }, []);
 
+  const resetFilter = () => {
+    setFilter(EMPTY_FILTER);
+    fetchData(EMPTY_FILTER);
+  };
+
+  const filterBar = (
+    <FilterBar
+      filter={filter}
+      onChange={setFilter}
+      onApply={() => fetchData()}
+      onReset={resetFilter}
+    />
+  );
+
   if (loading) {
     return (
-      <div className="flex justify-center items-center h-96">
-        <Spinner />
+      <div className="space-y-6">
+        {filterBar}
+        <div className="flex justify-center items-center h-96">
+          <Spinner />
+        </div>
       </div>
     );
   }
 
   if (error) {
     return (
-      <div className="p-6 bg-red-50 border border-red-200 rounded-lg">
-        <p className="text-red-800">错误: {error}</p>
+      <div className="space-y-6">
+        {filterBar}
+        <div className="p-6 bg-red-50 border border-red-200 rounded-lg">
+          <p className="text-red-800">错误: {error}</p>
+        </div>
       </div>
     );
   }
 
   if (!summary || summary.trades.length === 0) {
     return (
-      <div className="p-6 bg-gray-50 border border-gray-200 rounded-lg text-center">
-        <p className="text-gray-600">暂无已平仓的交易记录</p>
+      <div className="space-y-6">
+        {filterBar}
+        <div className="p-6 bg-gray-50 border border-gray-200 rounded-lg text-center">
+          <p className="text-gray-600">暂无符合条件的已平仓交易记录</p>
+        </div>
       </div>
     );
   }
@@ -66,9 +118,14 @@ export function ClosedTradesView() {
 
   return (
     <div className="space-y-6">
+      {filterBar}
+
       {/* 总统计卡片 */}
       <StatisticsCards stats={statistics} />
 
+      {/* 分组统计 */}
+      <GroupedStatistics summary={summary} />
+
       {/* 已平仓交易表格 */}
       <Card className="p-6">
         <h3 className="text-lg font-semibold mb-4">已平仓交易列表</h3>
@@ -85,6 +142,7 @@ export function ClosedTradesView() {
                 <TableHead className="text-right">盈亏金额</TableHead>
                 <TableHead className="text-right">盈亏率</TableHead>
                 <TableHead className="text-right">持有天数</TableHead>
+                <TableHead className="text-right">年化收益率</TableHead>
               </TableRow>
             </TableHeader>
             <TableBody>
@@ -129,6 +187,11 @@ export function ClosedTradesView() {
                   <TableCell className="text-right text-sm">
                     {trade.holding_days}天
                   </TableCell>
+                  <TableCell className="text-right text-sm">
+                    {trade.annualized_return === null
+                      ? "-"
+                      : `${(trade.annualized_return * 100).toFixed(2)}%`}
+                  </TableCell>
                 </TableRow>
               ))}
             </TableBody>
@@ -215,8 +278,216 @@ function StatisticsCards({ stats }: StatisticsCardsProps) {
         </p>
       </Card>
 
-      {/* 占位符 */}
-      <div className="hidden md:block"></div>
+      {/* 资金加权收益率 */}
+      <Card className="p-4">
+        <p className="text-sm text-gray-600 mb-1">资金加权收益率</p>
+        <p
+          className={`text-2xl font-bold ${
+            stats.capital_weighted_return >= 0
+              ? "text-red-600"
+              : "text-green-600"
+          }`}
+        >
+          {(stats.capital_weighted_return * 100).toFixed(2)}%
+        </p>
+        <p className="text-xs text-gray-500 mt-1">
+          年化{" "}
+          {stats.capital_weighted_annualized_return === null
+            ? "-"
+            : `${(stats.capital_weighted_annualized_return * 100).toFixed(2)}%`}
+        </p>
+      </Card>
+
+      {/* 平均盈利 / 平均亏损 */}
+      <Card className="p-4">
+        <p className="text-sm text-gray-600 mb-1">平均盈利 / 平均亏损</p>
+        <p className="text-lg font-bold">
+          <span className="text-red-600">¥{stats.average_win.toFixed(2)}</span>
+          {" / "}
+          <span className="text-green-600">
+            ¥{Math.abs(stats.average_loss).toFixed(2)}
+          </span>
+        </p>
+        <p className="text-xs text-gray-500 mt-1">
+          盈亏比因子{" "}
+          {stats.profit_factor === null ? "-" : stats.profit_factor.toFixed(2)}
+        </p>
+      </Card>
+
+      {/* 期望盈亏 */}
+      <Card className="p-4">
+        <p className="text-sm text-gray-600 mb-1">每笔期望盈亏</p>
+        <p
+          className={`text-2xl font-bold ${
+            stats.expectancy >= 0 ? "text-red-600" : "text-green-600"
+          }`}
+        >
+          ¥{stats.expectancy.toFixed(2)}
+        </p>
+      </Card>
+
+      {/* 最长连续盈亏 */}
+      <Card className="p-4">
+        <p className="text-sm text-gray-600 mb-1">最长连盈 / 连亏</p>
+        <p className="text-2xl font-bold">
+          <span className="text-red-600">{stats.longest_win_streak}</span>
+          {" / "}
+          <span className="text-green-600">{stats.longest_loss_streak}</span>
+        </p>
+      </Card>
+
+      {/* 持有天数分布 */}
+      <Card className="p-4 col-span-2 md:col-span-4">
+        <p className="text-sm text-gray-600 mb-2">持有天数分布</p>
+        <div className="grid grid-cols-3 md:grid-cols-6 gap-2">
+          {stats.holding_day_histogram.map((bucket) => (
+            <div key={bucket.label} className="text-center">
+              <p className="text-xs text-gray-500">{bucket.label}</p>
+              <p className="text-lg font-bold">{bucket.trades}</p>
+              <p
+                className={`text-xs ${
+                  bucket.total_profit_loss >= 0
+                    ? "text-red-600"
+                    : "text-green-600"
+                }`}
+              >
+                ¥{bucket.total_profit_loss.toFixed(2)}
+              </p>
+            </div>
+          ))}
+        </div>
+      </Card>
     </div>
   );
 }
+
+interface FilterBarProps {
+  filter: ClosedTradeFilter;
+  onChange: (filter: ClosedTradeFilter) => void;
+  onApply: () => void;
+  onReset: () => void;
+}
+
+function FilterBar({ filter, onChange, onApply, onReset }: FilterBarProps) {
+  const field = (key: keyof ClosedTradeFilter) => ({
+    value: filter[key] ?? "",
+    onChange: (e: React.ChangeEvent<HTMLInputElement>) =>
+      onChange({ ...filter, [key]: e.target.value }),
+  });
+
+  return (
+    <Card className="p-4">
+      <div className="grid grid-cols-2 md:grid-cols-4 gap-3">
+        <Input placeholder="投资组合" {...field("portfolio")} />
+        <Input placeholder="股票代码" {...field("code")} />
+        <div className="flex items-center gap-1">
+          <span className="text-xs text-gray-500 whitespace-nowrap">卖出</span>
+          <Input type="date" {...field("sell_date_from")} />
+          <span className="text-gray-400">-</span>
+          <Input type="date" {...field("sell_date_to")} />
+        </div>
+        <div className="flex items-center gap-1">
+          <span className="text-xs text-gray-500 whitespace-nowrap">买入</span>
+          <Input type="date" {...field("buy_date_from")} />
+          <span className="text-gray-400">-</span>
+          <Input type="date" {...field("buy_date_to")} />
+        </div>
+      </div>
+      <div className="flex justify-end gap-2 mt-3">
+        <Button variant="outline" onClick={onReset}>
+          重置
+        </Button>
+        <Button onClick={onApply}>筛选</Button>
+      </div>
+    </Card>
+  );
+}
+
+type GroupBy = "by_portfolio" | "by_code" | "by_month" | "by_year";
+
+const GROUP_TABS: { key: GroupBy; label: string }[] = [
+  { key: "by_portfolio", label: "按组合" },
+  { key: "by_code", label: "按股票" },
+  { key: "by_month", label: "按月份" },
+  { key: "by_year", label: "按年份" },
+];
+
+interface GroupedStatisticsProps {
+  summary: ClosedTradesSummary;
+}
+
+function GroupedStatistics({ summary }: GroupedStatisticsProps) {
+  const [groupBy, setGroupBy] = useState<GroupBy>("by_portfolio");
+  const groups: ClosedTradeGroup[] = summary[groupBy];
+
+  return (
+    <Card className="p-6">
+      <div className="flex items-center justify-between mb-4">
+        <h3 className="text-lg font-semibold">分组统计</h3>
+        <div className="flex gap-2">
+          {GROUP_TABS.map((tab) => (
+            <Button
+              key={tab.key}
+              size="sm"
+              variant={groupBy === tab.key ? "primary" : "outline"}
+              onClick={() => setGroupBy(tab.key)}
+            >
+              {tab.label}
+            </Button>
+          ))}
+        </div>
+      </div>
+      <div className="overflow-x-auto">
+        <Table>
+          <TableHeader>
+            <TableRow>
+              <TableHead>分组</TableHead>
+              <TableHead className="text-right">笔数</TableHead>
+              <TableHead className="text-right">成功率</TableHead>
+              <TableHead className="text-right">总盈亏</TableHead>
+              <TableHead className="text-right">资金加权收益率</TableHead>
+              <TableHead className="text-right">盈亏比因子</TableHead>
+              <TableHead className="text-right">平均持有天数</TableHead>
+            </TableRow>
+          </TableHeader>
+          <TableBody>
+            {groups.map((group) => (
+              <TableRow key={group.key}>
+                <TableCell className="font-medium">
+                  {group.key}
+                  {group.name !== group.key ? ` ${group.name}` : ""}
+                </TableCell>
+                <TableCell className="text-right text-sm">
+                  {group.statistics.total_trades}
+                </TableCell>
+                <TableCell className="text-right text-sm">
+                  {(group.statistics.win_rate * 100).toFixed(1)}%
+                </TableCell>
+                <TableCell
+                  className={`text-right text-sm font-medium ${
+                    group.statistics.total_profit_loss >= 0
+                      ? "text-red-600"
+                      : "text-green-600"
+                  }`}
+                >
+                  ¥{group.statistics.total_profit_loss.toFixed(2)}
+                </TableCell>
+                <TableCell className="text-right text-sm">
+                  {(group.statistics.capital_weighted_return * 100).toFixed(2)}%
+                </TableCell>
+                <TableCell className="text-right text-sm">
+                  {group.statistics.profit_factor === null
+                    ? "-"
+                    : group.statistics.profit_factor.toFixed(2)}
+                </TableCell>
+                <TableCell className="text-right text-sm">
+                  {Math.round(group.statistics.average_holding_days)}
+                </TableCell>
+              </TableRow>
+            ))}
+          </TableBody>
+        </Table>
+      </div>
+    </Card>
+  );
+}
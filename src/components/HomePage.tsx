@@ -21,9 +21,9 @@ export default function HomePage() {
     try {
       setIsLoading(true);
       // 使用实时价格数据（后端会自动处理降级逻辑）
-      const data = await db.getPortfolioProfitLossView(false);
-      setPortfolios(data);
-      console.log('📊 主页数据已刷新，持仓组合数:', data.length);
+      const view = await db.getPortfolioProfitLossView(false);
+      setPortfolios(view.portfolios);
+      console.log('📊 主页数据已刷新，持仓组合数:', view.portfolios.length);
     } catch (err) {
       console.error('Error fetching portfolio data:', err);
       setError(err as Error);
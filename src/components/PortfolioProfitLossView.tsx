@@ -11,6 +11,8 @@ export default function PortfolioProfitLossView() {
   const [portfolios, setPortfolios] = useState<PortfolioProfitLoss[]>([]);
   const [isLoading, setIsLoading] = useState(true);
   const [error, setError] = useState<string | null>(null);
+  const [missingCodes, setMissingCodes] = useState<string[]>([]);
+  const [missingFxRates, setMissingFxRates] = useState<string[]>([]);
   const [selectedStocks, setSelectedStocks] = useState<Set<string>>(new Set());
 
   const toggleStock = (code: string) => {
@@ -35,7 +37,10 @@ export default function PortfolioProfitLossView() {
       let data;
       try {
         console.log("尝试获取实时价格...");
-        data = await db.getPortfolioProfitLossView(false);
+        const view = await db.getPortfolioProfitLossView(false);
+        data = view.portfolios;
+        setMissingCodes(view.missing_codes);
+        setMissingFxRates(view.missing_fx_rates);
         console.log("✅ 实时价格获取成功");
       } catch (error) {
         console.warn("⚠️  获取数据失败:", error);
@@ -112,6 +117,11 @@ export default function PortfolioProfitLossView() {
     return `${(value * 100).toFixed(2)}%`;
   };
 
+  // 格式化可能为空的收益率
+  const formatRate = (value: number | null) => {
+    return value != null ? formatPercentage(value) : "--";
+  };
+
   // 获取盈亏颜色样式（Java版逻辑：>0红色盈利，<0绿色亏损，=0默认颜色）
   const getPnLStyle = (value: number) => {
     if (value > 0) return "text-red-600 font-semibold";    // 盈利显示红色
@@ -159,12 +169,25 @@ export default function PortfolioProfitLossView() {
         <Button onClick={loadData} variant="outline">刷新数据</Button>
       </div>
 
+      {missingCodes.length > 0 && (
+        <div className="p-3 text-amber-700 bg-amber-50 border border-amber-200 rounded-md text-sm">
+          以下股票暂未获取到价格，已按买入价估值：{missingCodes.join("、")}
+        </div>
+      )}
+
+      {missingFxRates.length > 0 && (
+        <div className="p-3 text-amber-700 bg-amber-50 border border-amber-200 rounded-md text-sm">
+          以下币种暂未获取到汇率，对应持仓未计入组合汇总：{missingFxRates.join("、")}
+        </div>
+      )}
+
       {portfolios.map((portfolio) => (
         <Card key={portfolio.portfolio} className="shadow-lg">
           <CardHeader className="bg-gradient-to-r from-blue-50 to-blue-100">
             <div className="flex justify-between items-start">
               <div>
                 <CardTitle className="text-xl">{portfolio.portfolio}</CardTitle>
+                <div className="text-sm text-gray-500 mt-1">基础货币 {portfolio.base_currency}</div>
               </div>
               <div className="text-right space-y-1">
                 <div className="grid grid-cols-2 gap-4 text-right">
@@ -184,6 +207,17 @@ export default function PortfolioProfitLossView() {
                 <div className={`text-xl font-bold mt-2 ${getPnLStyle(portfolio.sum_profit_losses)}`}>
                   {formatCurrency(portfolio.sum_profit_losses)} ({formatPercentage(portfolio.sum_profit_losses_rate)})
                 </div>
+                {portfolio.sum_fx_profit_loss !== 0 && (
+                  <div className="text-sm text-gray-600">
+                    价格 {formatCurrency(portfolio.sum_price_profit_loss)} / 汇兑 {formatCurrency(portfolio.sum_fx_profit_loss)}
+                  </div>
+                )}
+                <div className={`text-sm ${getPnLStyle(portfolio.sum_today_profit_loss)}`}>
+                  今日 {formatCurrency(portfolio.sum_today_profit_loss)} ({formatPercentage(portfolio.sum_today_profit_loss_rate)})
+                </div>
+                <div className="text-sm text-gray-600">
+                  年化 XIRR {formatRate(portfolio.returns.xirr)} / TWR {formatRate(portfolio.returns.annualized_twr)}
+                </div>
               </div>
             </div>
           </CardHeader>
@@ -201,10 +235,34 @@ export default function PortfolioProfitLossView() {
                     <div>
                       <h3 className="text-xl font-bold text-gray-800 mb-1">
                         {target.code} - <span className="text-gray-600">{target.name}</span>
+                        {target.currency !== portfolio.base_currency && (
+                          <span className="ml-2 text-xs font-normal text-gray-500">
+                            {target.currency}
+                            {target.fx_rate != null ? ` @ ${target.fx_rate.toFixed(4)}` : "（缺少汇率）"}
+                          </span>
+                        )}
                       </h3>
                       <div className="flex items-baseline gap-2 mt-1">
                         <span className="text-gray-500 text-sm">当前价</span>
-                        <span className="text-3xl font-bold text-blue-600">{formatCurrency(target.real_price)}</span>
+                        <span className="text-3xl font-bold text-blue-600">
+                          {target.real_price != null ? formatCurrency(target.real_price) : "暂无价格"}
+                        </span>
+                        {target.is_stale && (
+                          <span className="text-xs text-amber-600">
+                            {target.price_source === "CACHED" ? "缓存价格" : "价格已过期"}
+                            {target.price_as_of ? `（${target.price_as_of}）` : ""}
+                          </span>
+                        )}
+                        {target.change_percent != null && (
+                          <span className={`text-sm ${getPnLStyle(target.change_percent)}`}>
+                            {target.change_percent > 0 ? "+" : ""}{target.change_percent.toFixed(2)}%
+                          </span>
+                        )}
+                        {target.price_source === "MANUAL" && (
+                          <span className="text-xs text-purple-600">
+                            手动价格{target.price_as_of ? `（${target.price_as_of}）` : ""}
+                          </span>
+                        )}
                       </div>
                     </div>
                     <div className="text-center bg-blue-50 px-4 py-2 rounded-lg">
@@ -215,8 +273,8 @@ export default function PortfolioProfitLossView() {
                     </div>
                   </div>
 
-                  {/* 第二行：成本仓位 / 当前仓位 / 盈亏 / 盈亏比 */}
-                  <div className="grid grid-cols-2 lg:grid-cols-4 gap-4 mb-4">
+                  {/* 第二行：成本仓位 / 当前仓位 / 盈亏 / 盈亏比 / 今日盈亏 */}
+                  <div className="grid grid-cols-2 lg:grid-cols-5 gap-4 mb-4">
                     <div className="bg-gray-50 p-3 rounded-lg">
                       <div className="text-xs text-gray-500 mb-2">成本仓位</div>
                       <div className="text-lg font-bold text-gray-800">{formatPercentage(target.cost_position_rate)}</div>
@@ -236,6 +294,15 @@ export default function PortfolioProfitLossView() {
                       <div className={`text-lg font-bold ${getPnLStyle(target.target_profit_loss)}`}>
                         {formatPercentage(target.target_profit_loss_rate)}
                       </div>
+                      <div className="text-xs text-gray-500 mt-1">
+                        年化 {formatRate(target.returns.xirr)}
+                      </div>
+                    </div>
+                    <div className="bg-gray-50 p-3 rounded-lg">
+                      <div className="text-xs text-gray-500 mb-2">今日盈亏</div>
+                      <div className={`text-lg font-bold ${getPnLStyle(target.target_today_profit_loss ?? 0)}`}>
+                        {target.target_today_profit_loss != null ? formatCurrency(target.target_today_profit_loss) : "--"}
+                      </div>
                     </div>
                   </div>
 
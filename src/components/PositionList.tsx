@@ -99,7 +99,7 @@ export default function PositionList({ onDataChange }: PositionListProps) {
   useEffect(() => {
     const loadPnLData = async () => {
       try {
-        const data = await db.getPortfolioProfitLossView(false);
+        const { portfolios: data } = await db.getPortfolioProfitLossView(false);
         const pnlMap = new Map<string, PositionProfitLoss>();
 
         // 遍历所有投资组合的所有股票的所有持仓记录
@@ -395,7 +395,7 @@ export default function PositionList({ onDataChange }: PositionListProps) {
                   {pnlData ? formatPercentage(pnlData.profit_loss_rate) : '待计算'}
                 </TableCell>
                 <TableCell>{position.quantity}</TableCell>
-                <TableCell className="text-amber-500">{pnlData ? formatCurrency(pnlData.real_price) : '待获取'}</TableCell>
+                <TableCell className="text-amber-500">{pnlData?.real_price != null ? formatCurrency(pnlData.real_price) : '待获取'}</TableCell>
                 <TableCell>{position.buy_price.toFixed(4)}</TableCell>
                 <TableCell>{(position.buy_price * 1.1).toFixed(4)}</TableCell>
                 <TableCell>{(position.buy_price * 1.2).toFixed(4)}</TableCell>
@@ -4,10 +4,26 @@ import {
   CreatePositionRequest,
   ClosePositionRequest,
   ReducePositionRequest,
+  UpdatePositionRequest,
+  SellSharesRequest,
+  SellSharesResult,
   PortfolioSummary,
   PositionStats,
-  PortfolioProfitLoss,
-  ClosedTradesSummary
+  ProfitLossView,
+  ClosedTradesSummary,
+  ClosedTradeFilter,
+  PositionEvent,
+  UndoResult,
+  MigrationReport,
+  QuoteOutcome,
+  QuoteConfig,
+  ManualPrice,
+  FxRate,
+  PortfolioSnapshot,
+  DailyPrice,
+  RiskMetrics,
+  RiskQuery,
+  BenchmarkComparison
 } from "@/lib/types";
 
 // Tauri 错误对象类型定义
@@ -19,15 +35,18 @@ interface TauriError {
 }
 
 export const db = {
-  // 保存新的持仓记录
-  async savePosition(request: CreatePositionRequest): Promise<Position> {
+  // 保存新的持仓记录（idempotencyKey：重试同一请求时传入相同的值，避免重复买入）
+  async savePosition(request: CreatePositionRequest, idempotencyKey?: string): Promise<Position> {
     if (typeof window === "undefined") {
       throw new Error("savePosition can only be called in the browser");
     }
 
     try {
       console.log("Saving position to database:", request);
-      const result = await invoke<Position>("save_position", { request });
+      const result = await invoke<Position>("save_position", {
+        request,
+        idempotencyKey: idempotencyKey ?? null
+      });
 
       if (!result) {
         throw new Error("保存失败：服务器未返回数据");
@@ -127,7 +146,7 @@ export const db = {
   },
 
   // 平仓（卖出）
-  async closePosition(request: ClosePositionRequest): Promise<void> {
+  async closePosition(request: ClosePositionRequest, idempotencyKey?: string): Promise<void> {
     if (typeof window === "undefined") {
       throw new Error("closePosition can only be called in the browser");
     }
@@ -136,7 +155,9 @@ export const db = {
       await invoke("close_position", { 
         id: request.id,
         sellPrice: request.sell_price,
-        sellDate: request.sell_date
+        sellDate: request.sell_date,
+        sellFee: request.sell_fee ?? null,
+        idempotencyKey: idempotencyKey ?? null
       });
       console.log("平仓成功:", request);
     } catch (err) {
@@ -146,7 +167,7 @@ export const db = {
   },
 
   // 减仓（部分卖出）
-  async reducePosition(request: ReducePositionRequest): Promise<void> {
+  async reducePosition(request: ReducePositionRequest, idempotencyKey?: string): Promise<void> {
     if (typeof window === "undefined") {
       throw new Error("reducePosition can only be called in the browser");
     }
@@ -156,7 +177,9 @@ export const db = {
         id: request.id,
         reduceQuantity: request.reduce_quantity,
         sellPrice: request.sell_price,
-        sellDate: request.sell_date
+        sellDate: request.sell_date,
+        sellFee: request.sell_fee ?? null,
+        idempotencyKey: idempotencyKey ?? null
       });
       console.log("减仓成功:", request);
     } catch (err) {
@@ -165,6 +188,87 @@ export const db = {
     }
   },
 
+  // 按股票卖出（按匹配策略拆分到多个批次）
+  async sellShares(request: SellSharesRequest, idempotencyKey?: string): Promise<SellSharesResult> {
+    if (typeof window === "undefined") {
+      throw new Error("sellShares can only be called in the browser");
+    }
+
+    try {
+      const result = await invoke<SellSharesResult>("sell_shares", {
+        request,
+        idempotencyKey: idempotencyKey ?? null
+      });
+      console.log("卖出成功:", result);
+      return result;
+    } catch (err) {
+      console.error("Sell shares error:", err);
+      throw new Error(err instanceof Error ? err.message : "卖出失败");
+    }
+  },
+
+  // 重新打开已平仓记录（撤销卖出）
+  async reopenPosition(id: string): Promise<Position> {
+    if (typeof window === "undefined") {
+      throw new Error("reopenPosition can only be called in the browser");
+    }
+
+    try {
+      const result = await invoke<Position>("reopen_position", { id });
+      console.log("重新打开成功:", result);
+      return result;
+    } catch (err) {
+      console.error("Reopen position error:", err);
+      throw new Error(err instanceof Error ? err.message : "重新打开失败");
+    }
+  },
+
+  // 修改持仓记录
+  async updatePosition(request: UpdatePositionRequest): Promise<Position> {
+    if (typeof window === "undefined") {
+      throw new Error("updatePosition can only be called in the browser");
+    }
+
+    try {
+      const result = await invoke<Position>("update_position", { request });
+      console.log("修改持仓成功:", result);
+      return result;
+    } catch (err) {
+      console.error("Update position error:", err);
+      throw new Error(err instanceof Error ? err.message : "修改持仓失败");
+    }
+  },
+
+  // 撤销最近一次操作
+  async undoLastAction(): Promise<UndoResult> {
+    if (typeof window === "undefined") {
+      throw new Error("undoLastAction can only be called in the browser");
+    }
+
+    try {
+      const result = await invoke<UndoResult>("undo_last_action");
+      console.log("撤销成功:", result);
+      return result;
+    } catch (err) {
+      console.error("Undo last action error:", err);
+      throw new Error(err instanceof Error ? err.message : "撤销失败");
+    }
+  },
+
+  // 获取持仓记录的修改历史
+  async getPositionHistory(id: string): Promise<PositionEvent[]> {
+    if (typeof window === "undefined") {
+      throw new Error("getPositionHistory can only be called in the browser");
+    }
+
+    try {
+      return await invoke<PositionEvent[]>("get_position_history", { id });
+    } catch (err) {
+      console.error("Get position history error:", err);
+      throw new Error(err instanceof Error ? err.message : "获取修改历史失败");
+    }
+  },
+
   // 删除记录
   async deletePosition(id: string): Promise<void> {
     if (typeof window === "undefined") {
@@ -268,26 +372,250 @@ export const db = {
     }
   },
 
+  // 批量获取行情，逐只返回获取结果（成功、停牌、代码不存在或网络错误）
+  async getQuotes(codes: string[]): Promise<Record<string, QuoteOutcome>> {
+    if (typeof window === "undefined") {
+      throw new Error("getQuotes can only be called in the browser");
+    }
+
+    try {
+      return await invoke<Record<string, QuoteOutcome>>("get_quotes", { codes });
+    } catch (err) {
+      console.error("Get quotes error:", err);
+      throw new Error(err instanceof Error ? err.message : "获取行情失败");
+    }
+  },
+
+  // 获取行情配置（数据源优先级）
+  async getQuoteConfig(): Promise<QuoteConfig> {
+    if (typeof window === "undefined") {
+      throw new Error("getQuoteConfig can only be called in the browser");
+    }
+
+    try {
+      return await invoke<QuoteConfig>("get_quote_config");
+    } catch (err) {
+      console.error("Get quote config error:", err);
+      throw new Error(err instanceof Error ? err.message : "获取行情配置失败");
+    }
+  },
+
+  // 更新行情配置
+  async updateQuoteConfig(config: QuoteConfig): Promise<QuoteConfig> {
+    if (typeof window === "undefined") {
+      throw new Error("updateQuoteConfig can only be called in the browser");
+    }
+
+    try {
+      return await invoke<QuoteConfig>("update_quote_config", { config });
+    } catch (err) {
+      console.error("Update quote config error:", err);
+      throw new Error(err instanceof Error ? err.message : "更新行情配置失败");
+    }
+  },
+
+  // 设置手动价格（pinned 为 true 时优先于实时行情）
+  async setManualPrice(code: string, price: number, date: string, pinned: boolean = false): Promise<ManualPrice> {
+    if (typeof window === "undefined") {
+      throw new Error("setManualPrice can only be called in the browser");
+    }
+
+    try {
+      return await invoke<ManualPrice>("set_manual_price", { code, price, date, pinned });
+    } catch (err) {
+      console.error("Set manual price error:", err);
+      throw new Error(err instanceof Error ? err.message : "设置手动价格失败");
+    }
+  },
+
+  // 获取所有手动价格
+  async getManualPrices(): Promise<ManualPrice[]> {
+    if (typeof window === "undefined") {
+      throw new Error("getManualPrices can only be called in the browser");
+    }
+
+    try {
+      return await invoke<ManualPrice[]>("get_manual_prices");
+    } catch (err) {
+      console.error("Get manual prices error:", err);
+      throw new Error(err instanceof Error ? err.message : "获取手动价格失败");
+    }
+  },
+
+  // 删除手动价格（恢复使用实时行情）
+  async deleteManualPrice(code: string): Promise<void> {
+    if (typeof window === "undefined") {
+      throw new Error("deleteManualPrice can only be called in the browser");
+    }
+
+    try {
+      await invoke("delete_manual_price", { code });
+    } catch (err) {
+      console.error("Delete manual price error:", err);
+      throw new Error(err instanceof Error ? err.message : "删除手动价格失败");
+    }
+  },
+
+  // 设置手动汇率（1 单位 fromCurrency 兑换 rate 单位 toCurrency，优先于实时汇率）
+  async setFxRate(fromCurrency: string, toCurrency: string, rate: number): Promise<FxRate> {
+    if (typeof window === "undefined") {
+      throw new Error("setFxRate can only be called in the browser");
+    }
+
+    try {
+      return await invoke<FxRate>("set_fx_rate", { fromCurrency, toCurrency, rate });
+    } catch (err) {
+      console.error("Set fx rate error:", err);
+      throw new Error(err instanceof Error ? err.message : "设置汇率失败");
+    }
+  },
+
+  // 获取所有汇率（手动汇率及缓存的实时汇率）
+  async getFxRates(): Promise<FxRate[]> {
+    if (typeof window === "undefined") {
+      throw new Error("getFxRates can only be called in the browser");
+    }
+
+    try {
+      return await invoke<FxRate[]>("get_fx_rates");
+    } catch (err) {
+      console.error("Get fx rates error:", err);
+      throw new Error(err instanceof Error ? err.message : "获取汇率失败");
+    }
+  },
+
+  // 删除手动汇率（恢复使用实时汇率）
+  async deleteFxRate(fromCurrency: string, toCurrency: string): Promise<void> {
+    if (typeof window === "undefined") {
+      throw new Error("deleteFxRate can only be called in the browser");
+    }
+
+    try {
+      await invoke("delete_fx_rate", { fromCurrency, toCurrency });
+    } catch (err) {
+      console.error("Delete fx rate error:", err);
+      throw new Error(err instanceof Error ? err.message : "删除汇率失败");
+    }
+  },
+
+  // 按当前行情记录今天的组合快照和收盘价（今天已有快照时覆盖）
+  async recordPortfolioSnapshots(): Promise<PortfolioSnapshot[]> {
+    if (typeof window === "undefined") {
+      throw new Error("recordPortfolioSnapshots can only be called in the browser");
+    }
+
+    try {
+      return await invoke<PortfolioSnapshot[]>("record_portfolio_snapshots");
+    } catch (err) {
+      console.error("Record portfolio snapshots error:", err);
+      throw new Error(err instanceof Error ? err.message : "记录组合快照失败");
+    }
+  },
+
+  // 获取组合在日期范围内的每日快照（权益曲线，按日期升序）
+  async getPortfolioHistory(portfolio: string, from: string, to: string): Promise<PortfolioSnapshot[]> {
+    if (typeof window === "undefined") {
+      throw new Error("getPortfolioHistory can only be called in the browser");
+    }
+
+    try {
+      return await invoke<PortfolioSnapshot[]>("get_portfolio_history", { portfolio, from, to });
+    } catch (err) {
+      console.error("Get portfolio history error:", err);
+      throw new Error(err instanceof Error ? err.message : "获取组合历史失败");
+    }
+  },
+
+  // 获取股票在日期范围内的每日收盘价（按日期升序）
+  async getDailyPrices(code: string, from: string, to: string): Promise<DailyPrice[]> {
+    if (typeof window === "undefined") {
+      throw new Error("getDailyPrices can only be called in the browser");
+    }
+
+    try {
+      return await invoke<DailyPrice[]>("get_daily_prices", { code, from, to });
+    } catch (err) {
+      console.error("Get daily prices error:", err);
+      throw new Error(err instanceof Error ? err.message : "获取每日收盘价失败");
+    }
+  },
+
+  // 对比投资组合与业绩基准的累计收益率（基准未指定时使用组合设置的基准）
+  async getBenchmarkComparison(portfolio: string, query?: RiskQuery): Promise<BenchmarkComparison> {
+    if (typeof window === "undefined") {
+      throw new Error("getBenchmarkComparison can only be called in the browser");
+    }
+
+    try {
+      return await invoke<BenchmarkComparison>("get_benchmark_comparison", { portfolio, query: query ?? null });
+    } catch (err) {
+      console.error("Get benchmark comparison error:", err);
+      throw new Error(err instanceof Error ? err.message : "获取业绩基准对比失败");
+    }
+  },
+
+  // 获取投资组合的风险指标（基于已保存的组合快照）
+  async getPortfolioRisk(portfolio: string, query?: RiskQuery): Promise<RiskMetrics> {
+    if (typeof window === "undefined") {
+      throw new Error("getPortfolioRisk can only be called in the browser");
+    }
+
+    try {
+      return await invoke<RiskMetrics>("get_portfolio_risk", { portfolio, query: query ?? null });
+    } catch (err) {
+      console.error("Get portfolio risk error:", err);
+      throw new Error(err instanceof Error ? err.message : "获取组合风险指标失败");
+    }
+  },
+
+  // 获取个股的风险指标（基于已保存的每日收盘价）
+  async getStockRisk(code: string, query?: RiskQuery): Promise<RiskMetrics> {
+    if (typeof window === "undefined") {
+      throw new Error("getStockRisk can only be called in the browser");
+    }
+
+    try {
+      return await invoke<RiskMetrics>("get_stock_risk", { code, query: query ?? null });
+    } catch (err) {
+      console.error("Get stock risk error:", err);
+      throw new Error(err instanceof Error ? err.message : "获取个股风险指标失败");
+    }
+  },
+
+  // 获取应用启动时的数据库迁移报告
+  async getMigrationReport(): Promise<MigrationReport> {
+    if (typeof window === "undefined") {
+      throw new Error("getMigrationReport can only be called in the browser");
+    }
+
+    try {
+      return await invoke<MigrationReport>("get_migration_report");
+    } catch (err) {
+      console.error("Get migration report error:", err);
+      throw new Error(err instanceof Error ? err.message : "获取数据库迁移报告失败");
+    }
+  },
+
   // 获取所有投资组合的完整盈亏视图（优先使用实时价格）
   // 对应后端的 get_portfolio_profit_loss_view
   // @param useMock - 是否使用模拟数据（仅用于开发测试，默认为 false 优先使用实时价格）
-  async getPortfolioProfitLossView(useMock: boolean = false): Promise<PortfolioProfitLoss[]> {
+  async getPortfolioProfitLossView(useMock: boolean = false): Promise<ProfitLossView> {
     if (typeof window === "undefined") {
       throw new Error("getPortfolioProfitLossView can only be called in the browser");
     }
 
     try {
-      const portfolios = await invoke<PortfolioProfitLoss[]>(
+      const view = await invoke<ProfitLossView>(
         "get_portfolio_profit_loss_view",
         { use_mock: useMock }
       );
 
-      if (!Array.isArray(portfolios)) {
+      if (!view || !Array.isArray(view.portfolios)) {
         throw new Error("获取数据格式错误");
       }
 
-      console.log("Retrieved portfolio profit loss view:", portfolios);
-      return portfolios;
+      console.log("Retrieved portfolio profit loss view:", view);
+      return view;
     } catch (err) {
       // 改进错误处理，更好地显示错误信息
       // Tauri 2 会将错误序列化为包含 message, display, code 的对象
@@ -329,12 +657,14 @@ export const db = {
   },
 
   // 获取已平仓交易统计
-  async getClosedTradesSummary(): Promise<ClosedTradesSummary> {
+  async getClosedTradesSummary(
+    filter?: ClosedTradeFilter
+  ): Promise<ClosedTradesSummary> {
     try {
-      console.log("[DB API] 获取已平仓交易统计...");
+      console.log("[DB API] 获取已平仓交易统计...", filter);
       const result = await invoke<ClosedTradesSummary>(
         "get_closed_trades_summary",
-        {}
+        { filter: filter ?? null }
       );
 
       if (!result || typeof result !== "object") {
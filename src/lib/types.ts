@@ -18,9 +18,17 @@ export interface Position {
   portfolio: string;        // 所属投资组合
   sell_price?: number;      // 卖出价格（可选，仅 status=CLOSE 时有值）
   sell_date?: string;       // 卖出日期（可选，仅 status=CLOSE 时有值）
+  buy_fee: number;          // 买入费用（佣金 + 过户费）
+  sell_fee?: number;        // 卖出费用（可选，仅 status=CLOSE 时有值）
+  market: Market | null;    // 交易市场（无法识别代码的旧记录为 null）
+  currency: string;         // 交易币种（CNY / HKD / USD）
+  buy_fx_rate: number | null; // 买入时交易币种兑组合基础货币的汇率（相同币种时为 null）
   current_price?: number;   // 当前价格（前端附加，用于显示）
 }
 
+// 交易市场：沪、深、京、港、美、场外产品（没有行情，使用手动价格）
+export type Market = "SH" | "SZ" | "BJ" | "HK" | "US" | "OTC";
+
 // 创建持仓记录请求参数
 export interface CreatePositionRequest {
   code: string;             // 股票代码
@@ -29,6 +37,19 @@ export interface CreatePositionRequest {
   buy_date: string;         // 买入日期 (YYYY-MM-DD)
   quantity: number;         // 买入数量
   portfolio: string;        // 所属投资组合
+  buy_fee?: number;         // 实际买入费用（可选，不填时按费用配置计算）
+}
+
+// 修改持仓请求参数（只修改传入的字段，修改会同步到减仓产生的子记录）
+export interface UpdatePositionRequest {
+  id: string;               // 持仓记录ID
+  code?: string;            // 股票代码
+  name?: string;            // 股票名称
+  buy_price?: number;       // 买入价格
+  buy_date?: string;        // 买入日期 (YYYY-MM-DD)
+  quantity?: number;        // 持有数量
+  portfolio?: string;       // 所属投资组合
+  buy_fee?: number;         // 买入费用
 }
 
 // 卖出/平仓请求参数
@@ -36,6 +57,7 @@ export interface ClosePositionRequest {
   id: string;               // 持仓记录ID
   sell_price: number;       // 卖出价格
   sell_date: string;        // 卖出日期 (YYYY-MM-DD)
+  sell_fee?: number;        // 实际卖出费用（可选，不填时按费用配置计算）
 }
 
 // 减仓请求参数
@@ -44,6 +66,51 @@ export interface ReducePositionRequest {
   reduce_quantity: number;  // 减仓数量
   sell_price: number;       // 卖出价格
   sell_date: string;        // 卖出日期 (YYYY-MM-DD)
+  sell_fee?: number;        // 实际卖出费用（可选，不填时按费用配置计算）
+}
+
+// 批次匹配策略：先进先出 / 后进先出 / 成本最高优先 / 平均成本
+export type LotMatchingPolicy = "FIFO" | "LIFO" | "HIGHEST_COST" | "AVERAGE_COST";
+
+// 按股票卖出请求参数（跨批次）
+export interface SellSharesRequest {
+  portfolio: string;        // 投资组合
+  code: string;             // 股票代码
+  quantity: number;         // 卖出总数量
+  sell_price: number;       // 卖出价格
+  sell_date: string;        // 卖出日期 (YYYY-MM-DD)
+  policy?: LotMatchingPolicy; // 匹配策略（默认 FIFO）
+  sell_fee?: number;        // 实际卖出总费用（可选，不填时按费用配置计算）
+}
+
+// 单个批次的卖出明细
+export interface LotSale {
+  position_id: string;      // 被卖出的持仓记录ID
+  sold_id: string;          // 已卖出记录ID（部分卖出时为新建的子记录）
+  buy_date: string;         // 买入日期
+  buy_price: number;        // 买入价格
+  quantity: number;         // 本批次卖出数量
+  sell_fee: number;         // 本批次分摊的卖出费用
+  closed: boolean;          // 是否整笔卖出
+}
+
+// 按股票卖出的结果
+export interface SellSharesResult {
+  portfolio: string;
+  code: string;
+  policy: LotMatchingPolicy;
+  quantity: number;
+  sell_price: number;
+  sell_fee: number;
+  lots: LotSale[];
+}
+
+// 交易费用配置
+export interface FeeConfig {
+  commission_rate: number;                      // 佣金费率（买卖双向）
+  min_commission: number;                       // 单笔最低佣金
+  stamp_duty_rate: number;                      // 印花税率（仅卖出）
+  transfer_fee_rates: Record<string, number>;   // 过户费率（按交易所前缀，如 sh）
 }
 
 // 持仓统计信息
@@ -114,6 +181,99 @@ export interface RealQuote {
   code: string;              // 股票代码
   name: string;              // 股票名称
   real_price: number;        // 实时价格
+  prev_close: number | null; // 昨收价
+  open: number | null;       // 今开
+  high: number | null;       // 最高
+  low: number | null;        // 最低
+  volume: number | null;     // 成交量（股）
+  turnover: number | null;   // 成交额（元）
+  change_percent: number | null; // 涨跌幅（百分数，如 0.54 表示上涨 0.54%）
+  pe_ratio: number | null;   // 市盈率
+  market_cap: number | null; // 总市值（元）
+  as_of: string | null;      // 行情时间（本地时间 YYYY-MM-DD HH:MM:SS）
+  is_stale: boolean;         // 是否已过期（超过缓存有效期，或获取失败时使用的最后已知价格）
+  source: string | null;     // 行情数据源（如 TENCENT）
+  price_source: PriceSource; // 价格来源
+}
+
+// 价格来源：实时行情、缓存、手动录入、模拟数据
+export type PriceSource = "LIVE" | "CACHED" | "MANUAL" | "MOCK";
+
+// 手动价格 - 对应后端 ManualPrice（停牌股票、私募基金、理财产品等没有行情的持仓）
+export interface ManualPrice {
+  code: string;              // 股票代码
+  price: number;             // 价格
+  price_date: string;        // 价格日期 (YYYY-MM-DD)
+  pinned: boolean;           // 是否固定使用（true 时优先于实时行情，false 时只在没有行情时使用）
+  updated_at: string;        // 最后修改时间
+}
+
+// 汇率来源
+export type FxRateSource = "LIVE" | "MANUAL";
+
+// 汇率 - 对应后端 FxRate（1 单位 from_currency 兑换 rate 单位 to_currency）
+export interface FxRate {
+  from_currency: string;     // 源币种
+  to_currency: string;       // 目标币种
+  rate: number;              // 汇率
+  as_of: string;             // 汇率时间
+  source: FxRateSource;      // 汇率来源（手动汇率优先于实时汇率）
+}
+
+// 行情数据源类型
+export type QuoteProviderKind = "TENCENT" | "SINA" | "EASTMONEY";
+
+// 行情配置
+export interface QuoteConfig {
+  providers: QuoteProviderKind[];  // 数据源优先级（失败时依次切换到后面的数据源）
+  cache_ttl_secs: number;          // 行情缓存有效期（秒），0 表示每次都重新获取
+}
+
+// 单只股票的行情获取结果
+export type QuoteOutcome =
+  | { status: "OK"; quote: RealQuote }
+  | { status: "SUSPENDED"; name: string }       // 停牌或暂无成交价格
+  | { status: "UNKNOWN_CODE" }                  // 代码不存在
+  | { status: "NETWORK_ERROR"; message: string }; // 网络错误（重试后仍失败）
+
+// 收益率指标 - 对应后端 ReturnMetrics
+export interface ReturnMetrics {
+  xirr: number | null;             // 资金加权年化收益率（XIRR）
+  twr: number | null;              // 时间加权累计收益率
+  annualized_twr: number | null;   // 时间加权年化收益率
+  since: string | null;            // 计算起始日期
+}
+
+// 风险指标查询条件（均可选）- 对应后端 RiskQuery
+export interface RiskQuery {
+  from?: string | null;            // 开始日期 (YYYY-MM-DD)，不填时从最早的记录开始
+  to?: string | null;              // 结束日期 (YYYY-MM-DD)，不填时到最新的记录为止
+  benchmark?: string | null;       // 基准指数代码，默认沪深300（sh000300）
+  risk_free_rate?: number | null;  // 无风险年利率，默认 2%
+}
+
+// 最大回撤
+export interface MaxDrawdown {
+  rate: number;                    // 回撤幅度（不大于 0）
+  peak_date: string;               // 回撤开始的高点日期
+  trough_date: string;             // 回撤的最低点日期
+  recovery_date: string | null;    // 恢复到前高的日期，尚未恢复时为空
+}
+
+// 风险指标 - 对应后端 RiskMetrics
+export interface RiskMetrics {
+  start_date: string | null;               // 第一个观测日
+  end_date: string | null;                 // 最后一个观测日
+  periods: number;                         // 收益率区间数
+  annualized_volatility: number | null;    // 年化波动率
+  max_drawdown: MaxDrawdown | null;        // 最大回撤
+  sharpe_ratio: number | null;             // 夏普比率
+  sortino_ratio: number | null;            // 索提诺比率
+  benchmark: string;                       // 基准指数代码
+  benchmark_periods: number;               // 参与 Beta / Alpha 计算的区间数
+  beta: number | null;                     // 相对基准的 Beta
+  alpha: number | null;                    // 相对基准的年化 Alpha
+  risk_free_rate: number;                  // 使用的无风险年利率
 }
 
 // 单笔交易的盈亏计算
@@ -124,10 +284,15 @@ export interface PositionProfitLoss {
   buy_date: string;          // 买入日期
   buy_price: number;         // 买入价格
   quantity: number;          // 买入数量
-  real_price: number;        // 实时价格
-  position_cost: number;     // 持仓成本（买入价格 × 数量）
-  profit_loss: number;       // 盈亏金额（(实时价格 - 买入价格) × 数量）
+  buy_fee: number;           // 买入费用
+  real_price: number | null; // 实时价格（暂无价格时为 null，按买入价估值）
+  position_cost: number;     // 持仓成本（买入价格 × 数量 + 买入费用）
+  dividend_income: number;   // 累计税后分红收入
+  profit_loss: number;       // 盈亏金额（实时价格 × 数量 - 持仓成本 + 分红收入）
   profit_loss_rate: number;  // 盈亏比（盈亏 / 成本）
+  today_profit_loss: number | null; // 今日盈亏（没有价格或昨收时为 null）
+  holding_days: number;      // 持有天数
+  annualized_return: number | null; // 年化收益率（持有不满一天时为 null）
   status: string;            // 状态
   portfolio: string;         // 投资组合
 }
@@ -136,24 +301,214 @@ export interface PositionProfitLoss {
 export interface TargetProfitLoss {
   code: string;                          // 股票代码
   name: string;                          // 股票名称
-  real_price: number;                    // 实时价格
+  real_price: number | null;             // 实时价格（暂无价格时为 null，按买入价估值）
+  price_source: PriceSource | null;      // 价格来源
+  price_as_of: string | null;            // 价格时间
+  is_stale: boolean;                     // 价格是否已过期
+  prev_close: number | null;             // 昨收价
+  change_percent: number | null;         // 涨跌幅（百分数）
+  currency: string;                      // 交易币种（价格、成本、盈亏、分红均以该币种计价）
+  fx_rate: number | null;                // 兑组合基础货币的当前汇率（没有汇率时为 null，不计入组合汇总）
   position_profit_losses: PositionProfitLoss[];  // 所有交易记录
-  cost_position_rate: number;            // 成本仓位占比（总成本 / 满仓金额）
-  current_position_rate: number;         // 当前仓位占比（当前价值 / 满仓金额）
-  target_profit_loss: number;            // 该股票总盈亏
+  cost_position_rate: number;            // 成本仓位占比（基础货币总成本 / 满仓金额）
+  current_position_rate: number;         // 当前仓位占比（基础货币市值 / 满仓金额）
+  target_position_cost: number;          // 持仓成本（基础货币，按买入时汇率折算）
+  target_market_value: number;           // 持仓市值（基础货币，按当前汇率折算）
+  target_price_profit_loss: number;      // 价格盈亏（基础货币）
+  target_fx_profit_loss: number;         // 汇兑盈亏（基础货币）
+  target_dividend_income: number;        // 该股票累计分红收入
+  target_profit_loss: number;            // 该股票总盈亏（含分红）
   target_profit_loss_rate: number;       // 该股票盈亏比
+  target_today_profit_loss: number | null; // 该股票今日盈亏
+  returns: ReturnMetrics;                // 收益率指标（交易币种）
   recommended_buy_in_point: number;      // 建议买入点（最近买入价 × 0.9）
   recommended_sale_out_point: number;    // 建议卖出点（最近买入价 × 1.1）
 }
 
+// 投资组合设置 - 对应后端 Portfolio（portfolios 表）
+export interface PortfolioSettings {
+  name: string;                          // 组合名称
+  full_position: number;                 // 满仓金额
+  base_currency: string;                 // 基础货币
+  benchmark?: string;                    // 业绩比较基准代码（未设置时使用沪深300）
+  description?: string;                  // 组合描述
+  created_at: string;                    // 创建日期
+  archived: boolean;                     // 是否已归档
+}
+
 // 投资组合级汇总
 export interface PortfolioProfitLoss {
   portfolio: string;                     // 投资组合名称
+  base_currency: string;                 // 基础货币（汇总金额均以基础货币计价）
   full_position: number;                 // 单标满仓成本
   target_profit_losses: TargetProfitLoss[];  // 该组合下所有股票的汇总
   sum_position_cost: number;             // 总持仓成本
-  sum_profit_losses: number;             // 总盈亏
+  sum_dividend_income: number;           // 总分红收入
+  sum_profit_losses: number;             // 总盈亏（含分红，价格盈亏 + 汇兑盈亏）
+  sum_price_profit_loss: number;         // 价格盈亏（含分红）
+  sum_fx_profit_loss: number;            // 汇兑盈亏
   sum_profit_losses_rate: number;        // 总盈亏比
+  sum_today_profit_loss: number;         // 今日总盈亏
+  sum_today_profit_loss_rate: number;    // 今日盈亏比（今日总盈亏 / 昨日持仓市值）
+  returns: ReturnMetrics;                // 收益率指标（含已平仓记录和分红）
+  sum_market_value: number;              // 持仓总市值
+  cash_balance: number;                  // 现金余额
+  total_equity: number;                  // 总权益（现金余额 + 持仓总市值）
+}
+
+// 盈亏视图
+export interface ProfitLossView {
+  portfolios: PortfolioProfitLoss[];     // 各投资组合汇总
+  is_partial: boolean;                   // 是否有股票未获取到价格（按买入价估值）
+  missing_codes: string[];               // 未获取到价格的股票代码
+  missing_fx_rates: string[];            // 缺少汇率的币种对（如 USD/CNY），对应股票不计入组合汇总
+  overall_returns: ReturnMetrics;        // 所有投资组合合计的收益率指标
+}
+
+// 投资组合每日快照 - 对应后端 PortfolioSnapshot（金额以组合的基础货币计价）
+export interface PortfolioSnapshot {
+  portfolio: string;                     // 投资组合名称
+  snapshot_date: string;                 // 快照日期 (YYYY-MM-DD)
+  base_currency: string;                 // 基础货币
+  market_value: number;                  // 持仓总市值
+  position_cost: number;                 // 总持仓成本
+  cash_balance: number;                  // 现金余额
+  total_equity: number;                  // 总权益（现金余额 + 持仓总市值）
+  profit_loss: number;                   // 持仓总盈亏（含分红）
+  today_profit_loss: number;             // 当日盈亏
+  is_partial: boolean;                   // 是否为不完整数据（部分股票没有价格或汇率）
+  created_at: string;                    // 记录时间
+}
+
+// 业绩基准对比序列中的一个日期
+export interface BenchmarkPoint {
+  date: string;                          // 日期 (YYYY-MM-DD)
+  portfolio_return: number;              // 组合累计收益率（时间加权）
+  benchmark_return: number | null;       // 基准累计收益率，缺少基准价格时为空
+  excess_return: number | null;          // 累计超额收益（组合 - 基准）
+}
+
+// 组合与业绩基准的对比 - 对应后端 BenchmarkComparison
+export interface BenchmarkComparison {
+  portfolio: string;                     // 组合名称
+  benchmark: string;                     // 基准代码
+  series: BenchmarkPoint[];              // 累计收益率序列（第一个日期为起点）
+  portfolio_return: number | null;       // 组合区间累计收益率
+  benchmark_return: number | null;       // 基准区间累计收益率
+  excess_return: number | null;          // 区间超额收益
+  tracking_error: number | null;         // 年化跟踪误差
+  is_partial: boolean;                   // 基准价格是否不完整
+}
+
+// 每日收盘价 - 对应后端 DailyPrice
+export interface DailyPrice {
+  code: string;                          // 股票代码（带交易所前缀）
+  price_date: string;                    // 交易日期 (YYYY-MM-DD)
+  close: number;                         // 收盘价
+  prev_close: number | null;             // 昨收价
+}
+
+// 现金流水类型
+export type CashTransactionType =
+  | "DEPOSIT"
+  | "WITHDRAW"
+  | "INTEREST"
+  | "BUY"
+  | "SELL"
+  | "DIVIDEND"
+  | "DIVIDEND_TAX"
+  | "RIGHTS_ISSUE";
+
+// 现金流水 - 对应后端 CashTransaction
+export interface CashTransaction {
+  id: string;                            // 流水ID
+  portfolio: string;                     // 所属投资组合
+  txn_type: CashTransactionType;         // 流水类型
+  amount: number;                        // 金额（组合基础货币，流入为正，流出为负）
+  txn_date: string;                      // 发生日期
+  position_id?: string;                  // 关联的持仓记录ID（买卖流水）
+  note?: string;                         // 备注
+  fx_rate?: number;                      // 外币交易的成交汇率（交易币种 -> 基础货币）
+}
+
+// ============= 公司行为相关类型 =============
+
+// 公司行为类型：现金分红 / 送转股 / 拆股 / 配股
+export type CorporateActionType = "DIVIDEND" | "BONUS" | "SPLIT" | "RIGHTS";
+
+// 公司行为 - 对应后端 CorporateAction
+export interface CorporateAction {
+  id: string;                            // 公司行为ID
+  code: string;                          // 股票代码
+  action_type: CorporateActionType;      // 行为类型
+  ex_date: string;                       // 除权除息日
+  cash_per_share: number;                // 每股派息（税前）
+  share_ratio: number;                   // 股份比例（送转数 / 拆分倍数 / 配股数）
+  rights_price?: number;                 // 配股价
+  note?: string;                         // 备注
+}
+
+// 录入公司行为的请求参数
+export interface RecordCorporateActionRequest {
+  code: string;
+  action_type: CorporateActionType;
+  ex_date: string;
+  cash_per_share?: number;
+  share_ratio?: number;
+  rights_price?: number;
+  note?: string;
+}
+
+// 公司行为对单笔持仓的影响明细
+export interface CorporateActionEntry {
+  id: string;                            // 明细ID
+  action_id: string;                     // 公司行为ID
+  position_id: string;                   // 持仓记录ID
+  portfolio: string;                     // 所属投资组合
+  code: string;                          // 股票代码
+  quantity_before: number;               // 调整前数量
+  quantity_after: number;                // 调整后数量
+  buy_price_before: number;              // 调整前买入价格
+  buy_price_after: number;               // 调整后买入价格
+  cash_amount: number;                   // 现金金额（分红为税前，配股为负的缴款）
+  tax: number;                           // 红利税
+  net_cash: number;                      // 实际到账现金
+}
+
+// 录入公司行为的结果
+export interface CorporateActionResult {
+  action: CorporateAction;
+  entries: CorporateActionEntry[];
+}
+
+// ============= 操作历史相关类型 =============
+
+// 审计记录对应的数据类型
+export type AuditEntityType =
+  | "POSITION"
+  | "CASH_TRANSACTION"
+  | "CORPORATE_ACTION"
+  | "CORPORATE_ACTION_ENTRY"
+  | "IDEMPOTENCY_KEY";
+
+// 单条审计记录 - 对应后端 PositionEvent
+export interface PositionEvent {
+  id: number;                            // 自增序号
+  group_id: string;                      // 操作分组ID（同一次命令共用）
+  action: string;                        // 操作名称（命令名）
+  entity_type: AuditEntityType;          // 数据类型
+  entity_id: string;                     // 数据ID
+  before?: Record<string, unknown>;      // 操作前快照（新建时为空）
+  after?: Record<string, unknown>;       // 操作后快照（删除时为空）
+  undo_of?: string;                      // 被撤销的操作分组ID
+  created_at: string;                    // 记录时间
+}
+
+// 撤销操作的结果
+export interface UndoResult {
+  group_id: string;                      // 被撤销的操作分组ID
+  action: string;                        // 被撤销的操作名称
+  restored: number;                      // 恢复的数据条数
 }
 
 // ============= 已平仓交易相关类型 =============
@@ -168,10 +523,24 @@ export interface ClosedTrade {
   sell_date: string;       // 卖出日期
   sell_price: number;      // 卖出价格
   quantity: number;        // 数量
-  profit_loss: number;     // 盈亏金额
+  buy_fee: number;         // 买入费用
+  sell_fee: number;        // 卖出费用
+  dividend_income: number; // 持有期间的税后分红收入
+  profit_loss: number;     // 盈亏金额（已扣除买卖费用，含分红）
   profit_loss_rate: number;// 盈亏率
   portfolio: string;       // 所属投资组合
   holding_days: number;    // 持有天数
+  annualized_return: number | null; // 年化收益率（当日买卖或亏损超过 100% 时为空）
+}
+
+// 持有天数分布中的一个区间
+export interface HoldingDayBucket {
+  label: string;             // 区间名称
+  min_days: number;          // 最少持有天数（含）
+  max_days: number | null;   // 最多持有天数（含），为空表示不设上限
+  trades: number;            // 交易笔数
+  profitable_trades: number; // 其中盈利笔数
+  total_profit_loss: number; // 区间内总盈亏
 }
 
 // 已平仓交易总统计
@@ -185,10 +554,54 @@ export interface ClosedTradesStatistics {
   max_profit: number;            // 最大盈利
   max_loss: number;              // 最大亏损
   average_holding_days: number;   // 平均持有天数
+  capital_weighted_return: number; // 资金加权收益率（总盈亏 / 总买入成本）
+  capital_weighted_annualized_return: number | null; // 资金时间加权的年化收益率
+  profit_factor: number | null;   // 盈亏比因子（总盈利 / 总亏损），没有亏损时为空
+  average_win: number;            // 平均每笔盈利金额
+  average_loss: number;           // 平均每笔亏损金额（负数）
+  expectancy: number;             // 每笔交易的期望盈亏
+  longest_win_streak: number;     // 最长连续盈利笔数
+  longest_loss_streak: number;    // 最长连续亏损笔数
+  holding_day_histogram: HoldingDayBucket[]; // 持有天数分布
+}
+
+// 已平仓交易查询条件（均可选，日期范围两端都包含）
+export interface ClosedTradeFilter {
+  portfolio?: string | null;      // 按组合筛选
+  code?: string | null;           // 按股票代码筛选
+  sell_date_from?: string | null; // 卖出日期起始 (YYYY-MM-DD)
+  sell_date_to?: string | null;   // 卖出日期截止 (YYYY-MM-DD)
+  buy_date_from?: string | null;  // 买入日期起始 (YYYY-MM-DD)
+  buy_date_to?: string | null;    // 买入日期截止 (YYYY-MM-DD)
+}
+
+// 一组已平仓交易的统计
+export interface ClosedTradeGroup {
+  key: string;                        // 分组键（组合名称、股票代码、YYYY-MM 或 YYYY）
+  name: string;                       // 显示名称（按股票分组时为股票名称）
+  statistics: ClosedTradesStatistics; // 该组的统计
 }
 
 // 已平仓交易统计总览
 export interface ClosedTradesSummary {
   trades: ClosedTrade[];           // 已平仓交易列表（按卖出时间倒序）
   statistics: ClosedTradesStatistics; // 总统计
+  by_portfolio: ClosedTradeGroup[];   // 按组合分组的统计
+  by_code: ClosedTradeGroup[];        // 按股票分组的统计
+  by_month: ClosedTradeGroup[];       // 按卖出月份分组的统计
+  by_year: ClosedTradeGroup[];        // 按卖出年份分组的统计
+}
+
+// 已执行的数据库迁移
+export interface AppliedMigration {
+  version: number;      // 迁移后的版本号
+  description: string;  // 变更说明
+}
+
+// 启动时的数据库迁移报告
+export interface MigrationReport {
+  from_version: number;         // 迁移前的版本号（0 表示没有版本记录）
+  to_version: number;           // 迁移后的版本号
+  applied: AppliedMigration[];  // 本次执行的迁移
+  backup_path: string | null;   // 迁移前的数据库备份文件
 }